@@ -105,6 +105,11 @@ impl Messages {
     }
 
     /// The next message in the ring.
+    ///
+    /// Not an `Iterator`: each message borrows the ring mutably, so no
+    /// two can be held at once, which the trait cannot express. Clippy's
+    /// `should_implement_trait` flags the name all the same.
+    #[allow(clippy::should_implement_trait)]
     #[inline(always)]
     pub fn next(&mut self) -> &mut [u8] {
         let at = self.cursor;
@@ -7,8 +7,12 @@
 // compiled out and the measurement machinery it drives goes unused. That
 // is the point rather than an oversight, so it is not worth warning
 // about.
-#![cfg_attr(not(openssl_available), allow(dead_code, unused_imports))]
+#![cfg_attr(
+    not(openssl_available),
+    allow(dead_code, unused_imports, unused_macros)
+)]
 
+use std::hint::black_box;
 use std::process::ExitCode;
 
 use scytale_bench::harness::{
@@ -22,6 +26,57 @@ const BLOCK: usize = 16;
 
 const SIZES: [usize; 6] = [16, 64, 256, 1024, 8 * 1024, 16 * 1024];
 
+/// Message sizes for the key agility rows: short enough that expanding a
+/// fresh key per message is a large share of the work.
+const AGILE_SIZES: [usize; 3] = [16, 256, 1024];
+
+/// Key setup rows: expanding an encryption key alone, then expanding one
+/// and encrypting a single message under it, as a caller that keys every
+/// connection afresh does.
+///
+/// Each call builds a schedule and drops it. Ours is wiped on the way
+/// out and OpenSSL's is not; that is counted, since callers pay it too.
+/// The setup row is per byte of key rather than of message.
+macro_rules! setup_ladder {
+    (
+        $meter:expr, $rows:expr, $bits:expr, $len:expr,
+        |$k:ident| $ours:expr, |$k2:ident| $theirs:expr
+    ) => {{
+        let key = [0x2bu8; $len];
+        let ours = |$k: &[u8; $len]| $ours;
+        let theirs = |$k2: &[u8; $len]| $theirs;
+        $rows.push(compare(
+            $meter,
+            concat!("aes", $bits, "-setup"),
+            $len,
+            || {
+                black_box(ours(black_box(&key)));
+            },
+            || {
+                black_box(theirs(black_box(&key)));
+            },
+        ));
+        for bytes in AGILE_SIZES {
+            let mut left = Messages::new(bytes);
+            let mut right = Messages::new(bytes);
+            $rows.push(compare(
+                $meter,
+                &format!(concat!("aes", $bits, "-setup+enc/{}"), bytes),
+                bytes,
+                || {
+                    ours(black_box(&key)).encrypt(left.next());
+                },
+                || {
+                    theirs(black_box(&key)).encrypt(right.next());
+                },
+            ));
+        }
+    }};
+}
+
+/// Every key here is one of the three AES lengths.
+const ANY_KEY: &str = "OpenSSL takes every AES key length";
+
 type Tier = (&'static str, &'static str, &'static str, Verdict, Vec<Row>);
 
 /// Without OpenSSL there is nothing of the same kind to measure
@@ -38,7 +93,7 @@ fn cases(_meter: &Meter, _tier: &str) -> Result<Tier, String> {
 
 #[cfg(openssl_available)]
 fn cases(meter: &Meter, tier: &str) -> Result<Tier, String> {
-    use scytale::symmetric::aes::arch::portable::ttable;
+    use scytale::symmetric::aes::arch::portable::{bitsliced, ttable};
     use scytale_bench::openssl::OpensslAes;
 
     let bad = |e| format!("OpenSSL rejected the key: {e:?}");
@@ -121,19 +176,35 @@ fn cases(meter: &Meter, tier: &str) -> Result<Tier, String> {
         }};
     }
 
+    // Every ladder for one portable module, each against OpenSSL's C AES.
+    // Both sides are named explicitly: the dispatching types would pick
+    // the accelerated backend and make this a different comparison.
+    macro_rules! portable {
+        ($rows:expr, $module:ident) => {{
+            ladder!($rows, $module::Aes128Enc, $module::Aes128Dec,
+                    OpensslAes, OpensslAes, "128", 16);
+            ladder!($rows, $module::Aes192Enc, $module::Aes192Dec,
+                    OpensslAes, OpensslAes, "192", 24);
+            ladder!($rows, $module::Aes256Enc, $module::Aes256Dec,
+                    OpensslAes, OpensslAes, "256", 32);
+            ctr_ladder!($rows, $module::Aes128Enc, "128", 16);
+            ctr_ladder!($rows, $module::Aes192Enc, "192", 24);
+            ctr_ladder!($rows, $module::Aes256Enc, "256", 32);
+            // AES_set_encrypt_key, the C key schedule.
+            setup_ladder!(meter, $rows, "128", 16,
+                          |k| $module::Aes128Enc::new(k),
+                          |k| OpensslAes::try_new_encrypt(k).expect(ANY_KEY));
+            setup_ladder!(meter, $rows, "192", 24,
+                          |k| $module::Aes192Enc::new(k),
+                          |k| OpensslAes::try_new_encrypt(k).expect(ANY_KEY));
+            setup_ladder!(meter, $rows, "256", 32,
+                          |k| $module::Aes256Enc::new(k),
+                          |k| OpensslAes::try_new_encrypt(k).expect(ANY_KEY));
+        }};
+    }
+
     if tier == "portable" {
-        // Scytale's T-table cipher against OpenSSL's C AES. Both sides are
-        // named explicitly: the dispatching types would pick the
-        // accelerated backend and make this a different comparison.
-        ladder!(rows, ttable::Aes128Enc, ttable::Aes128Dec,
-                OpensslAes, OpensslAes, "128", 16);
-        ladder!(rows, ttable::Aes192Enc, ttable::Aes192Dec,
-                OpensslAes, OpensslAes, "192", 24);
-        ladder!(rows, ttable::Aes256Enc, ttable::Aes256Dec,
-                OpensslAes, OpensslAes, "256", 32);
-        ctr_ladder!(rows, ttable::Aes128Enc, "128", 16);
-        ctr_ladder!(rows, ttable::Aes192Enc, "192", 24);
-        ctr_ladder!(rows, ttable::Aes256Enc, "256", 32);
+        portable!(rows, ttable);
         return Ok((
             "portable: scytale T-table against OpenSSL C",
             "scytale",
@@ -143,10 +214,27 @@ fn cases(meter: &Meter, tier: &str) -> Result<Tier, String> {
         ));
     }
 
+    if tier == "bitsliced" {
+        // A circuit against tables is not the same kind of code, and
+        // OpenSSL's C has no constant-time cipher to pair it with, so
+        // the ratio is reported as what constant time costs and gates
+        // nothing.
+        portable!(rows, bitsliced);
+        return Ok((
+            "portable: scytale bitsliced against OpenSSL C\n\
+             (a constant-time circuit against key dependent tables, so the \
+             ratio is the price of constant time rather than a verdict)",
+            "bitsliced",
+            "openssl",
+            Verdict::Speedup,
+            rows,
+        ));
+    }
+
     if tier == "vector" {
         vector(meter, &mut rows)?;
         return Ok((
-            "vector: scytale VAES against scytale AES-NI \n\
+            "vector: scytale VAES against scytale AES-NI\n\
              (OpenSSL has no VAES kernel for ECB, so there is nothing of \
              the same kind to compare against)",
             "VAES",
@@ -156,6 +244,30 @@ fn cases(meter: &Meter, tier: &str) -> Result<Tier, String> {
         ));
     }
 
+    if tier == "wide" {
+        wide(meter, &mut rows)?;
+        return Ok((
+            "wide: scytale 512 bit VAES against scytale 256 bit VAES\n\
+             (OpenSSL has no VAES kernel for ECB, so there is nothing of \
+             the same kind to compare against)",
+            "VAES-512",
+            "VAES",
+            Verdict::Speedup,
+            rows,
+        ));
+    }
+
+    if tier == "simd" {
+        simd(meter, &mut rows, &bad)?;
+        return Ok((
+            "simd: scytale vpaes against OpenSSL vpaes",
+            "scytale",
+            "openssl",
+            Verdict::Parity,
+            rows,
+        ));
+    }
+
     accelerated(meter, &mut rows, &bad)?;
     Ok((
         "accelerated: scytale AES-NI against OpenSSL AES-NI",
@@ -304,9 +416,101 @@ fn accelerated(
         ctr_ladder!(aesni::Aes192Enc, "192", 24);
         ctr_ladder!(aesni::Aes256Enc, "256", 32);
     }
+    // aesni_set_encrypt_key, OpenSSL's AES-NI key schedule.
+    setup_ladder!(meter, rows, "128", 16,
+                  |k| aesni::Aes128Enc::new(k),
+                  |k| OpensslAesni::try_new_encrypt(k).expect(ANY_KEY));
+    setup_ladder!(meter, rows, "192", 24,
+                  |k| aesni::Aes192Enc::new(k),
+                  |k| OpensslAesni::try_new_encrypt(k).expect(ANY_KEY));
+    setup_ladder!(meter, rows, "256", 32,
+                  |k| aesni::Aes256Enc::new(k),
+                  |k| OpensslAesni::try_new_encrypt(k).expect(ANY_KEY));
     Ok(())
 }
 
+/// One of our backends against the next narrower one it falls back to,
+/// ECB, fused CTR and key setup at every key size. Both share a schedule,
+/// so the setup rows differ only in the message, and the ratio is what
+/// the wider registers buy.
+macro_rules! kind_ladders {
+    ($meter:expr, $rows:expr, $wide:ident, $narrow:ident) => {{
+        macro_rules! ladder {
+            ($v:ty, $a:ty, $bits:expr, $len:expr, $op:ident) => {{
+                let key = [0x2bu8; $len];
+                let wide = <$v>::new(&key);
+                let narrow = <$a>::new(&key);
+                for bytes in SIZES {
+                    let mut ours = Messages::new(bytes);
+                    let mut theirs = Messages::new(bytes);
+                    $rows.push(compare(
+                        $meter,
+                        &format!(
+                            concat!(
+                                "aes", $bits, "-", stringify!($op), "/{}"
+                            ),
+                            bytes
+                        ),
+                        bytes,
+                        || {
+                            wide.$op(ours.next());
+                        },
+                        || {
+                            narrow.$op(theirs.next());
+                        },
+                    ));
+                }
+            }};
+        }
+
+        // The fused CTR entries of the two backends, the same comparison
+        // of kinds as the ECB rows above.
+        macro_rules! ctr_ladder {
+            ($v:ty, $a:ty, $bits:expr, $len:expr) => {{
+                let key = [0x2bu8; $len];
+                let wide = <$v>::new(&key);
+                let narrow = <$a>::new(&key);
+                for bytes in SIZES {
+                    let mut ours = Messages::new(bytes);
+                    let mut theirs = Messages::new(bytes);
+                    let mut wc = [0u8; 16];
+                    let mut nc = [0u8; 16];
+                    $rows.push(compare(
+                        $meter,
+                        &format!(concat!("aes", $bits, "-ctr/{}"), bytes),
+                        bytes,
+                        || {
+                            wide.ctr(&mut wc, ours.next());
+                        },
+                        || {
+                            narrow.ctr(&mut nc, theirs.next());
+                        },
+                    ));
+                }
+            }};
+        }
+
+        ladder!($wide::Aes128Enc, $narrow::Aes128Enc, "128", 16, encrypt);
+        ladder!($wide::Aes128Dec, $narrow::Aes128Dec, "128", 16, decrypt);
+        ladder!($wide::Aes192Enc, $narrow::Aes192Enc, "192", 24, encrypt);
+        ladder!($wide::Aes192Dec, $narrow::Aes192Dec, "192", 24, decrypt);
+        ladder!($wide::Aes256Enc, $narrow::Aes256Enc, "256", 32, encrypt);
+        ladder!($wide::Aes256Dec, $narrow::Aes256Dec, "256", 32, decrypt);
+        ctr_ladder!($wide::Aes128Enc, $narrow::Aes128Enc, "128", 16);
+        ctr_ladder!($wide::Aes192Enc, $narrow::Aes192Enc, "192", 24);
+        ctr_ladder!($wide::Aes256Enc, $narrow::Aes256Enc, "256", 32);
+        setup_ladder!($meter, $rows, "128", 16,
+                      |k| $wide::Aes128Enc::new(k),
+                      |k| $narrow::Aes128Enc::new(k));
+        setup_ladder!($meter, $rows, "192", 24,
+                      |k| $wide::Aes192Enc::new(k),
+                      |k| $narrow::Aes192Enc::new(k));
+        setup_ladder!($meter, $rows, "256", 32,
+                      |k| $wide::Aes256Enc::new(k),
+                      |k| $narrow::Aes256Enc::new(k));
+    }};
+}
+
 /// VAES against AES-NI, both ours.
 #[cfg(all(openssl_available, target_arch = "x86_64"))]
 fn vector(meter: &Meter, rows: &mut Vec<Row>) -> Result<(), String> {
@@ -315,72 +519,158 @@ fn vector(meter: &Meter, rows: &mut Vec<Row>) -> Result<(), String> {
     if !vaes::supported() {
         return Err("this CPU has no VAES".to_string());
     }
+    // From 256 bytes up, a message fills a VAES group.
+    kind_ladders!(meter, rows, vaes, aesni);
+    Ok(())
+}
+
+/// 512 bit VAES against 256 bit VAES, both ours.
+#[cfg(all(openssl_available, target_arch = "x86_64"))]
+fn wide(meter: &Meter, rows: &mut Vec<Row>) -> Result<(), String> {
+    use scytale::symmetric::aes::arch::x86_64::{vaes, vaes512};
+
+    if !vaes512::supported() {
+        return Err("this CPU has no VAES on AVX-512".to_string());
+    }
+    // From 512 bytes up, a message fills a 512 bit group; below that
+    // both sides run the same 256 bit code.
+    kind_ladders!(meter, rows, vaes512, vaes);
+    Ok(())
+}
+
+/// The vector permute ladder, against OpenSSL's `vpaes_*`.
+///
+/// OpenSSL's EVP layer runs that cipher a block at a time, for ECB and
+/// under its generic CTR loop alike, so its side here does the same;
+/// ours runs the bulk entries and the fused counter kernel, which is the
+/// difference a caller of either library sees.
+#[cfg(all(openssl_available, target_arch = "x86_64"))]
+fn simd(
+    meter: &Meter,
+    rows: &mut Vec<Row>,
+    bad: &dyn Fn(scytale_bench::openssl::BadKeyLength) -> String,
+) -> Result<(), String> {
+    use scytale::symmetric::aes::arch::x86_64::vpaes;
+    use scytale_bench::openssl::{OpensslVpaes, OpensslVpaesCtr};
+
+    if !vpaes::supported() {
+        return Err("this CPU has no SSSE3".to_string());
+    }
 
     macro_rules! ladder {
-        ($v:ty, $a:ty, $bits:expr, $len:expr, $op:ident) => {{
+        (
+            $ty:ty, $new:ident, $bits:expr, $len:expr, $what:expr,
+            $op:ident, $op_block:ident
+        ) => {{
             let key = [0x2bu8; $len];
-            let wide = <$v>::new(&key);
-            let narrow = <$a>::new(&key);
+            let ours_key = <$ty>::new(&key);
+            let theirs_key = OpensslVpaes::$new(&key).map_err(bad)?;
             for bytes in SIZES {
                 let mut ours = Messages::new(bytes);
                 let mut theirs = Messages::new(bytes);
-                rows.push(compare(
-                    meter,
-                    &format!(
-                        concat!("aes", $bits, "-", stringify!($op), "/{}"),
-                        bytes
-                    ),
-                    bytes,
-                    || {
-                        wide.$op(ours.next());
-                    },
-                    || {
-                        narrow.$op(theirs.next());
-                    },
-                ));
+                let name = format!(concat!("aes", $bits, $what, "/{}"), bytes);
+                // One block through each side's single block entry, as
+                // in the accelerated tier.
+                if bytes == BLOCK {
+                    rows.push(compare(
+                        meter,
+                        &name,
+                        bytes,
+                        || {
+                            ours_key.$op_block(ours.next_block());
+                        },
+                        || {
+                            theirs_key.$op_block(theirs.next_block());
+                        },
+                    ));
+                } else {
+                    rows.push(compare(
+                        meter,
+                        &name,
+                        bytes,
+                        || {
+                            ours_key.$op(ours.next());
+                        },
+                        || {
+                            theirs_key.$op(theirs.next());
+                        },
+                    ));
+                }
             }
         }};
     }
 
-    // The fused CTR entries of the two backends, the same comparison
-    // of kinds as the ECB rows above.
     macro_rules! ctr_ladder {
-        ($v:ty, $a:ty, $bits:expr, $len:expr) => {{
+        ($enc:ty, $bits:expr, $len:expr) => {{
             let key = [0x2bu8; $len];
-            let wide = <$v>::new(&key);
-            let narrow = <$a>::new(&key);
+            let iv = [0u8; 16];
             for bytes in SIZES {
                 let mut ours = Messages::new(bytes);
                 let mut theirs = Messages::new(bytes);
-                let mut wc = [0u8; 16];
-                let mut nc = [0u8; 16];
+                let enc = <$enc>::new(&key);
+                let mut counter = iv;
+                let mut openssl =
+                    OpensslVpaesCtr::try_new(&key, &iv).map_err(bad)?;
                 rows.push(compare(
                     meter,
                     &format!(concat!("aes", $bits, "-ctr/{}"), bytes),
                     bytes,
                     || {
-                        wide.ctr(&mut wc, ours.next());
+                        enc.ctr(&mut counter, ours.next());
                     },
                     || {
-                        narrow.ctr(&mut nc, theirs.next());
+                        openssl.apply_keystream(theirs.next());
                     },
                 ));
             }
         }};
     }
 
-    ladder!(vaes::Aes128Enc, aesni::Aes128Enc, "128", 16, encrypt);
-    ladder!(vaes::Aes128Dec, aesni::Aes128Dec, "128", 16, decrypt);
-    ladder!(vaes::Aes192Enc, aesni::Aes192Enc, "192", 24, encrypt);
-    ladder!(vaes::Aes192Dec, aesni::Aes192Dec, "192", 24, decrypt);
-    ladder!(vaes::Aes256Enc, aesni::Aes256Enc, "256", 32, encrypt);
-    ladder!(vaes::Aes256Dec, aesni::Aes256Dec, "256", 32, decrypt);
-    ctr_ladder!(vaes::Aes128Enc, aesni::Aes128Enc, "128", 16);
-    ctr_ladder!(vaes::Aes192Enc, aesni::Aes192Enc, "192", 24);
-    ctr_ladder!(vaes::Aes256Enc, aesni::Aes256Enc, "256", 32);
+    ladder!(vpaes::Aes128Enc, try_new_encrypt, "128", 16, "-encrypt",
+            encrypt, encrypt_block);
+    ladder!(vpaes::Aes128Dec, try_new_decrypt, "128", 16, "-decrypt",
+            decrypt, decrypt_block);
+    ladder!(vpaes::Aes192Enc, try_new_encrypt, "192", 24, "-encrypt",
+            encrypt, encrypt_block);
+    ladder!(vpaes::Aes192Dec, try_new_decrypt, "192", 24, "-decrypt",
+            decrypt, decrypt_block);
+    ladder!(vpaes::Aes256Enc, try_new_encrypt, "256", 32, "-encrypt",
+            encrypt, encrypt_block);
+    ladder!(vpaes::Aes256Dec, try_new_decrypt, "256", 32, "-decrypt",
+            decrypt, decrypt_block);
+    ctr_ladder!(vpaes::Aes128Enc, "128", 16);
+    ctr_ladder!(vpaes::Aes192Enc, "192", 24);
+    ctr_ladder!(vpaes::Aes256Enc, "256", 32);
+    // vpaes_set_encrypt_key, OpenSSL's vector permute key schedule.
+    setup_ladder!(meter, rows, "128", 16,
+                  |k| vpaes::Aes128Enc::new(k),
+                  |k| OpensslVpaes::try_new_encrypt(k).expect(ANY_KEY));
+    setup_ladder!(meter, rows, "192", 24,
+                  |k| vpaes::Aes192Enc::new(k),
+                  |k| OpensslVpaes::try_new_encrypt(k).expect(ANY_KEY));
+    setup_ladder!(meter, rows, "256", 32,
+                  |k| vpaes::Aes256Enc::new(k),
+                  |k| OpensslVpaes::try_new_encrypt(k).expect(ANY_KEY));
     Ok(())
 }
 
+#[cfg(all(openssl_available, not(target_arch = "x86_64")))]
+fn simd(
+    _meter: &Meter,
+    _rows: &mut Vec<Row>,
+    _bad: &dyn Fn(scytale_bench::openssl::BadKeyLength) -> String,
+) -> Result<(), String> {
+    Err("no vector permute backend on this target".to_string())
+}
+
+#[cfg(all(openssl_available, not(target_arch = "x86_64")))]
+fn wide(
+    _meter: &Meter,
+    _rows: &mut Vec<Row>,
+) -> Result<(), String> {
+    Err("no vector backend on this target".to_string())
+}
+
 #[cfg(all(openssl_available, not(target_arch = "x86_64")))]
 fn vector(
     _meter: &Meter,
@@ -451,6 +741,11 @@ fn describe_machine(meter: &Meter) {
         "{cpus} cpus, {allowed} allowed, governor {governor}, turbo {turbo}"
     );
     println!("measuring {}", meter.describe());
+    // Which kernels the library itself would pick here, so a run on the
+    // wrong machine, or one whose features are masked, shows at a glance.
+    println!("cpu {}", scytale::cpu::Features::detect());
+    println!("aes {}", scytale::symmetric::aes::dispatch_report());
+    println!("aes-ctr {}", scytale::symmetric::aes::ctr::dispatch_report());
     if !pinned {
         println!("not pinned: run under `taskset -c <cpu>` for stable ratios");
     }
@@ -514,8 +809,14 @@ fn main() -> ExitCode {
     let verbose = args.iter().any(|a| a == "--verbose" || a == "-v");
     let tier = if args.iter().any(|a| a == "--portable") {
         "portable"
+    } else if args.iter().any(|a| a == "--bitsliced") {
+        "bitsliced"
+    } else if args.iter().any(|a| a == "--wide") {
+        "wide"
     } else if args.iter().any(|a| a == "--vector") {
         "vector"
+    } else if args.iter().any(|a| a == "--simd") {
+        "simd"
     } else {
         "accelerated"
     };
@@ -150,17 +150,32 @@ pub struct OpensslCtr {
     ivec: [u8; 16],
     ecount: [u8; 16],
     num: c_uint,
+    block: Block128,
 }
 
+/// The shape of every `*_set_encrypt_key`.
+type SetKey =
+    unsafe extern "C" fn(*const c_uchar, c_int, *mut AesKey) -> c_int;
+
 impl OpensslCtr {
     /// Build the schedule and set the initial counter block.
     pub fn try_new(key: &[u8], iv: &[u8; 16]) -> Result<Self, BadKeyLength> {
+        Self::with(key, iv, AES_set_encrypt_key, AES_encrypt)
+    }
+
+    /// The mode loop over any cipher whose schedule `set_key` builds and
+    /// whose blocks `block` encrypts.
+    fn with(
+        key: &[u8],
+        iv: &[u8; 16],
+        set_key: SetKey,
+        block: Block128,
+    ) -> Result<Self, BadKeyLength> {
         let mut schedule = AesKey::default();
         let bits = (key.len() * 8) as c_int;
         // SAFETY: key points to key.len() bytes and bits describes
         // exactly that length; schedule is a valid AES_KEY.
-        let rc =
-            unsafe { AES_set_encrypt_key(key.as_ptr(), bits, &mut schedule) };
+        let rc = unsafe { set_key(key.as_ptr(), bits, &mut schedule) };
         if rc != 0 {
             return Err(BadKeyLength(key.len()));
         }
@@ -169,6 +184,7 @@ impl OpensslCtr {
             ivec: *iv,
             ecount: [0u8; 16],
             num: 0,
+            block,
         })
     }
 
@@ -179,7 +195,8 @@ impl OpensslCtr {
         // SAFETY: input and output are the same buffer, which CTR
         // permits since each byte is read before it is written; ivec and
         // ecount are the 16-byte blocks the function expects and num is
-        // its offset into ecount; AES_encrypt matches the schedule.
+        // its offset into ecount; the block function was paired with the
+        // schedule's builder at construction.
         unsafe {
             CRYPTO_ctr128_encrypt(
                 ptr,
@@ -189,7 +206,7 @@ impl OpensslCtr {
                 self.ivec.as_mut_ptr(),
                 self.ecount.as_mut_ptr(),
                 &mut self.num,
-                AES_encrypt,
+                self.block,
             );
         }
     }
@@ -392,3 +409,132 @@ impl OpensslAesniCtr {
         blocks * 16
     }
 }
+
+#[cfg(target_arch = "x86_64")]
+unsafe extern "C" {
+    fn vpaes_set_encrypt_key(
+        user_key: *const c_uchar,
+        bits: c_int,
+        key: *mut AesKey,
+    ) -> c_int;
+
+    fn vpaes_set_decrypt_key(
+        user_key: *const c_uchar,
+        bits: c_int,
+        key: *mut AesKey,
+    ) -> c_int;
+
+    fn vpaes_encrypt(
+        input: *const c_uchar,
+        out: *mut c_uchar,
+        key: *const AesKey,
+    );
+
+    fn vpaes_decrypt(
+        input: *const c_uchar,
+        out: *mut c_uchar,
+        key: *const AesKey,
+    );
+}
+
+/// OpenSSL's SSSE3 vector permute AES, Hamburg's construction as ours is.
+///
+/// This is the counterpart to scytale's `vpaes` backend. OpenSSL has no
+/// bulk ECB entry for it: its EVP layer calls the single block function
+/// in a loop, and so does this.
+#[cfg(target_arch = "x86_64")]
+pub struct OpensslVpaes {
+    key: AesKey,
+    encrypting: bool,
+}
+
+#[cfg(target_arch = "x86_64")]
+impl OpensslVpaes {
+    /// Build an encryption schedule. `key` must be 16, 24 or 32 bytes.
+    pub fn try_new_encrypt(key: &[u8]) -> Result<Self, BadKeyLength> {
+        let mut schedule = AesKey::default();
+        let bits = (key.len() * 8) as c_int;
+        // SAFETY: key points to key.len() bytes and bits describes exactly
+        // that length; schedule is a valid, correctly sized AES_KEY.
+        let rc =
+            unsafe { vpaes_set_encrypt_key(key.as_ptr(), bits, &mut schedule) };
+        if rc != 0 {
+            return Err(BadKeyLength(key.len()));
+        }
+        Ok(Self { key: schedule, encrypting: true })
+    }
+
+    /// Build a decryption schedule.
+    pub fn try_new_decrypt(key: &[u8]) -> Result<Self, BadKeyLength> {
+        let mut schedule = AesKey::default();
+        let bits = (key.len() * 8) as c_int;
+        // SAFETY: as for try_new_encrypt.
+        let rc =
+            unsafe { vpaes_set_decrypt_key(key.as_ptr(), bits, &mut schedule) };
+        if rc != 0 {
+            return Err(BadKeyLength(key.len()));
+        }
+        Ok(Self { key: schedule, encrypting: false })
+    }
+
+    /// Encrypt whole blocks in place, returning bytes consumed.
+    pub fn encrypt(&self, data: &mut [u8]) -> usize {
+        debug_assert!(self.encrypting, "schedule is for decryption");
+        let (blocks, _tail) = data.as_chunks_mut::<16>();
+        for block in blocks.iter_mut() {
+            self.encrypt_block(block);
+        }
+        blocks.len() * 16
+    }
+
+    /// Decrypt whole blocks in place, returning bytes consumed.
+    pub fn decrypt(&self, data: &mut [u8]) -> usize {
+        debug_assert!(!self.encrypting, "schedule is for encryption");
+        let (blocks, _tail) = data.as_chunks_mut::<16>();
+        for block in blocks.iter_mut() {
+            self.decrypt_block(block);
+        }
+        blocks.len() * 16
+    }
+
+    /// Encrypt exactly one block in place.
+    pub fn encrypt_block(&self, block: &mut [u8; 16]) {
+        let src = *block;
+        // SAFETY: both pointers are to sixteen bytes, the size this
+        // reads and writes, and self.key is an initialized schedule.
+        unsafe {
+            vpaes_encrypt(src.as_ptr(), block.as_mut_ptr(), &self.key);
+        }
+    }
+
+    /// Decrypt exactly one block in place.
+    pub fn decrypt_block(&self, block: &mut [u8; 16]) {
+        let src = *block;
+        // SAFETY: as for encrypt_block.
+        unsafe {
+            vpaes_decrypt(src.as_ptr(), block.as_mut_ptr(), &self.key);
+        }
+    }
+}
+
+/// OpenSSL's CTR over its vector permute AES.
+///
+/// The counterpart to scytale's fused `vpaes` counter kernel. OpenSSL has
+/// no fused kernel of this kind, and its EVP layer runs exactly this: the
+/// generic mode loop over `vpaes_encrypt`.
+#[cfg(target_arch = "x86_64")]
+pub struct OpensslVpaesCtr(OpensslCtr);
+
+#[cfg(target_arch = "x86_64")]
+impl OpensslVpaesCtr {
+    /// Build the schedule and set the initial counter block.
+    pub fn try_new(key: &[u8], iv: &[u8; 16]) -> Result<Self, BadKeyLength> {
+        OpensslCtr::with(key, iv, vpaes_set_encrypt_key, vpaes_encrypt)
+            .map(Self)
+    }
+
+    /// XOR the keystream into `data`, advancing the stream.
+    pub fn apply_keystream(&mut self, data: &mut [u8]) {
+        self.0.apply_keystream(data);
+    }
+}
@@ -0,0 +1,293 @@
+//! What the CPU the code is running on can do.
+//!
+//! The dispatching types consult this once, when a key is expanded, to
+//! decide which implementation to use. It is public so that a caller who
+//! was refused an implementation can be told which instructions were
+//! missing, rather than only that something was, and so that telemetry
+//! can record what the machine offered with [`Features::detect`].
+
+use core::fmt;
+
+/// An instruction set extension one of the implementations needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Feature {
+    /// The AES round instructions: AES-NI on x86 and x86_64, the
+    /// Cryptographic Extension's `aes` on aarch64.
+    Aes,
+    /// SSSE3, whose `pshufb` the x86 counter kernels byte swap with,
+    /// and which the vector permute cipher is built on.
+    Ssse3,
+    /// AVX2, the 256 bit integer instructions.
+    Avx2,
+    /// VAES, the AES round instructions on 256 and, with AVX-512, 512
+    /// bit registers.
+    Vaes,
+    /// AVX-512 Foundation, the 512 bit registers and instructions.
+    Avx512f,
+    /// AVX-512 Vector Length, the EVEX encoded forms on 128 and 256 bit
+    /// registers.
+    Avx512vl,
+}
+
+impl Feature {
+    /// Every feature this module knows, in the order they are reported.
+    const ALL: [Feature; 6] = [
+        Feature::Aes,
+        Feature::Ssse3,
+        Feature::Avx2,
+        Feature::Vaes,
+        Feature::Avx512f,
+        Feature::Avx512vl,
+    ];
+
+    /// The name the target's feature detection macro uses.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Feature::Aes => "aes",
+            Feature::Ssse3 => "ssse3",
+            Feature::Avx2 => "avx2",
+            Feature::Vaes => "vaes",
+            Feature::Avx512f => "avx512f",
+            Feature::Avx512vl => "avx512vl",
+        }
+    }
+
+    /// Whether the CPU the code is running on has this feature.
+    ///
+    /// Always false for a feature that does not exist on this target.
+    pub fn detected(self) -> bool {
+        #[cfg(target_arch = "x86_64")]
+        {
+            match self {
+                Feature::Aes => is_x86_feature_detected!("aes"),
+                Feature::Ssse3 => is_x86_feature_detected!("ssse3"),
+                Feature::Avx2 => is_x86_feature_detected!("avx2"),
+                Feature::Vaes => is_x86_feature_detected!("vaes"),
+                Feature::Avx512f => is_x86_feature_detected!("avx512f"),
+                Feature::Avx512vl => is_x86_feature_detected!("avx512vl"),
+            }
+        }
+        #[cfg(target_arch = "x86")]
+        {
+            match self {
+                Feature::Aes => is_x86_feature_detected!("aes"),
+                Feature::Ssse3 => is_x86_feature_detected!("ssse3"),
+                _ => false,
+            }
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            match self {
+                Feature::Aes => std::arch::is_aarch64_feature_detected!("aes"),
+                _ => false,
+            }
+        }
+        #[cfg(not(any(
+            target_arch = "x86_64",
+            target_arch = "x86",
+            target_arch = "aarch64"
+        )))]
+        {
+            false
+        }
+    }
+
+    const fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+/// The features this target probes for, in the order they are reported.
+///
+/// The rest could never be detected here, so listing them would only say
+/// something about the target, not the machine.
+#[cfg(target_arch = "x86_64")]
+const PROBED: &[Feature] = &[
+    Feature::Aes,
+    Feature::Ssse3,
+    Feature::Avx2,
+    Feature::Vaes,
+    Feature::Avx512f,
+    Feature::Avx512vl,
+];
+
+/// Only AES-NI is built for 32-bit x86, so only it and the SSSE3 its
+/// counter kernels need are worth asking about.
+#[cfg(target_arch = "x86")]
+const PROBED: &[Feature] = &[Feature::Aes, Feature::Ssse3];
+
+#[cfg(target_arch = "aarch64")]
+const PROBED: &[Feature] = &[Feature::Aes];
+
+#[cfg(not(any(
+    target_arch = "x86_64",
+    target_arch = "x86",
+    target_arch = "aarch64"
+)))]
+const PROBED: &[Feature] = &[];
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// What the CPU had when it was asked, feature by feature.
+///
+/// A snapshot: it is taken once, by [`Features::detect`], and is then a
+/// plain value that can be stored, compared and printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Features {
+    /// The detected features, one bit each.
+    detected: u32,
+}
+
+impl Features {
+    /// Probe every feature this target knows how to probe for.
+    pub fn detect() -> Self {
+        let detected = PROBED
+            .iter()
+            .filter(|f| f.detected())
+            .fold(0, |set, f| set | f.bit());
+        Self { detected }
+    }
+
+    /// Whether the CPU had `feature` when the snapshot was taken.
+    pub fn has(&self, feature: Feature) -> bool {
+        self.detected & feature.bit() != 0
+    }
+
+    /// Every feature this target probes for, with whether it was found.
+    pub fn probes(&self) -> impl Iterator<Item = (Feature, bool)> + use<> {
+        let this = *self;
+        PROBED.iter().map(move |&f| (f, this.has(f)))
+    }
+}
+
+/// The probed features, each marked `+` if found and `-` if not.
+impl fmt::Display for Features {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if PROBED.is_empty() {
+            return f.write_str("nothing to probe on this target");
+        }
+        for (i, (feature, found)) in self.probes().enumerate() {
+            let sep = if i == 0 { "" } else { " " };
+            let mark = if found { '+' } else { '-' };
+            write!(f, "{sep}{mark}{feature}")?;
+        }
+        Ok(())
+    }
+}
+
+/// An implementation was refused because the CPU cannot run it.
+///
+/// Names the features that were missing. An empty list means there is no
+/// implementation of the kind asked for on this target at all, so no CPU
+/// could have satisfied the request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsupportedCpu {
+    /// The missing features, one bit each.
+    missing: u32,
+}
+
+impl UnsupportedCpu {
+    /// The error for an implementation needing `required`, naming those
+    /// this CPU does not have.
+    pub(crate) fn lacking(required: &[Feature]) -> Self {
+        let missing = required
+            .iter()
+            .filter(|f| !f.detected())
+            .fold(0, |set, f| set | f.bit());
+        Self { missing }
+    }
+
+    /// The features the CPU would need and does not have.
+    pub fn missing(&self) -> impl Iterator<Item = Feature> + use<> {
+        let missing = self.missing;
+        Feature::ALL
+            .into_iter()
+            .filter(move |f| missing & f.bit() != 0)
+    }
+}
+
+impl fmt::Display for UnsupportedCpu {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.missing == 0 {
+            return f.write_str("no implementation for this target");
+        }
+        f.write_str("CPU lacks")?;
+        for (i, feature) in self.missing().enumerate() {
+            let sep = if i == 0 { " " } else { ", " };
+            write!(f, "{sep}{feature}")?;
+        }
+        Ok(())
+    }
+}
+
+impl core::error::Error for UnsupportedCpu {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_only_what_is_missing() {
+        let e = UnsupportedCpu {
+            missing: Feature::Aes.bit() | Feature::Ssse3.bit(),
+        };
+        assert_eq!(
+            e.missing().collect::<Vec<_>>(),
+            [Feature::Aes, Feature::Ssse3]
+        );
+        assert_eq!(e.to_string(), "CPU lacks aes, ssse3");
+    }
+
+    #[test]
+    fn nothing_missing_means_nothing_exists() {
+        let e = UnsupportedCpu { missing: 0 };
+        assert_eq!(e.missing().count(), 0);
+        assert_eq!(e.to_string(), "no implementation for this target");
+    }
+
+    #[test]
+    fn features_display_marks_each_probe() {
+        let features = Features {
+            detected: Feature::Aes.bit() | Feature::Avx2.bit(),
+        };
+        let expected: Vec<String> = PROBED
+            .iter()
+            .map(|&f| {
+                let found = f == Feature::Aes || f == Feature::Avx2;
+                format!("{}{f}", if found { '+' } else { '-' })
+            })
+            .collect();
+        if !PROBED.is_empty() {
+            assert_eq!(features.to_string(), expected.join(" "));
+        }
+    }
+
+    /// The snapshot agrees with asking feature by feature, and says
+    /// nothing about features this target cannot probe.
+    #[test]
+    fn snapshot_matches_detection() {
+        let features = Features::detect();
+        for feature in Feature::ALL {
+            assert_eq!(features.has(feature), feature.detected(), "{feature}");
+        }
+        assert_eq!(features.probes().count(), PROBED.len());
+    }
+
+    /// A feature the CPU has is never reported as missing.
+    #[test]
+    fn lacking_filters_through_detection() {
+        let e = UnsupportedCpu::lacking(&Feature::ALL);
+        for feature in Feature::ALL {
+            assert_eq!(
+                e.missing().any(|f| f == feature),
+                !feature.detected(),
+                "{feature}"
+            );
+        }
+    }
+}
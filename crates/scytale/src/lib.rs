@@ -17,8 +17,37 @@
 //! Selection happens at run time, so a binary built for a baseline target
 //! still uses whatever the silicon it lands on supports. To pin one exact
 //! implementation instead, name it through its `arch` path.
+//!
+//! Keys and IVs should come from the operating system: on Linux, the AES
+//! types' `generate` and the counter modes' `with_random_iv` read it
+//! through [`rand`], and each takes any [`rand::RandomSource`] in its
+//! `_from` form instead.
+//!
+//! Every implementation the CPU can run passes known-answer tests before
+//! the first key is expanded, and a failure stops all further
+//! construction; see [`self_test`].
+//!
+//! # Features
+//!
+//! - `constant-time`: the dispatching types never fall back to an
+//!   implementation whose timing depends on the key. Where the CPU has no
+//!   AES instructions they use the bitsliced portable cipher instead of
+//!   the T-table one. The compact portable cipher computes its S-box
+//!   rather than looking it up.
+//! - `hazmat`: the AES `Enc` and `Dec` types gain `round_keys` and
+//!   `from_round_keys`, exposing the expanded key schedule in one fixed
+//!   layout. See [`symmetric::aes`](symmetric::aes#round-keys).
+//! - `scrub`: the hardware and vector permute AES implementations clear
+//!   every vector register they wrote before returning, and the stack their key
+//!   schedules ran on. Without it, the last round key and blocks a kernel
+//!   held stay in the registers until something else overwrites them.
 
 #![forbid(unsafe_op_in_unsafe_fn)]
 #![warn(missing_docs)]
 
+pub mod cpu;
+pub mod rand;
+#[cfg(target_os = "linux")]
+pub mod secret;
+pub mod self_test;
 pub mod symmetric;
@@ -0,0 +1,266 @@
+//! Randomness for keys and IVs.
+//!
+//! [`OsRandom`] reads the kernel's generator through the Linux `getrandom`
+//! system call. That blocks until the generator has been seeded once after
+//! boot, and never again, so early in boot it waits rather than hand out
+//! predictable bytes, and every later call returns at once.
+//!
+//! Everything that consumes randomness takes it through [`RandomSource`],
+//! so a test can substitute a deterministic source and get reproducible
+//! keys and IVs. The constructors that read the operating system directly,
+//! such as `Aes128Enc::generate`, are shorthand for their `_from` forms
+//! given an [`OsRandom`].
+//!
+//! Where entropy comes from elsewhere, a hardware source say, and has to
+//! be stretched, [`ctr_drbg`] has the SP 800-90A generator; it is itself a
+//! [`RandomSource`].
+//!
+//! ```
+//! # #[cfg(target_os = "linux")] {
+//! use scytale::symmetric::aes::{Aes256Ctr, Aes256Enc};
+//!
+//! // A key that never leaves the schedule, for data only this process
+//! // will read back.
+//! let cipher = Aes256Enc::generate();
+//!
+//! // A fresh counter block to send alongside the ciphertext.
+//! let key = [0x42u8; 32];
+//! let (mut stream, iv) = Aes256Ctr::with_random_iv(&key);
+//! # let _ = (cipher, iv, stream.apply_keystream(&mut [0u8; 4]));
+//! # }
+//! ```
+
+pub mod ctr_drbg;
+
+pub use ctr_drbg::{Aes128CtrDrbg, Aes192CtrDrbg, Aes256CtrDrbg, DrbgError};
+
+use core::fmt;
+
+/// A source of random bytes.
+///
+/// The operating system is [`OsRandom`]. Implement this to substitute
+/// another, such as a deterministic one in tests. Nothing here checks the
+/// quality of what a source returns.
+pub trait RandomSource {
+    /// Fill all of `buf` with random bytes.
+    ///
+    /// On failure the contents of `buf` are unspecified and must not be
+    /// used.
+    fn fill(&mut self, buf: &mut [u8]) -> Result<(), RandError>;
+}
+
+impl<R: RandomSource + ?Sized> RandomSource for &mut R {
+    fn fill(&mut self, buf: &mut [u8]) -> Result<(), RandError> {
+        (**self).fill(buf)
+    }
+}
+
+/// A random source could not supply bytes.
+///
+/// Either a system call failed, in which case it carries the `errno`, or
+/// a [CTR_DRBG](ctr_drbg) used as a source refused, in which case it
+/// carries the [`DrbgError`] saying why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RandError {
+    repr: Repr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Repr {
+    /// The `errno` the failing call left, or 0 if there was none.
+    Os(i32),
+    /// Boxed, since a DRBG's own error can hold its source's.
+    Drbg(Box<DrbgError>),
+}
+
+impl RandError {
+    /// A failure carrying an OS error number.
+    ///
+    /// For a [`RandomSource`] built on some other system interface to
+    /// report what went wrong in the same terms.
+    pub fn from_raw_os_error(errno: i32) -> Self {
+        Self { repr: Repr::Os(errno) }
+    }
+
+    /// The `errno` value behind the failure, or 0 if there was none.
+    ///
+    /// A DRBG whose own entropy source failed reports that source's.
+    pub fn raw_os_error(&self) -> i32 {
+        match &self.repr {
+            Repr::Os(errno) => *errno,
+            Repr::Drbg(e) => match **e {
+                DrbgError::Source(ref e) => e.raw_os_error(),
+                _ => 0,
+            },
+        }
+    }
+
+    /// The DRBG's refusal behind the failure, if a DRBG was the source.
+    pub fn drbg_error(&self) -> Option<&DrbgError> {
+        match &self.repr {
+            Repr::Drbg(e) => Some(e),
+            Repr::Os(_) => None,
+        }
+    }
+}
+
+impl From<DrbgError> for RandError {
+    fn from(e: DrbgError) -> Self {
+        Self { repr: Repr::Drbg(Box::new(e)) }
+    }
+}
+
+impl fmt::Display for RandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let errno = match &self.repr {
+            Repr::Os(errno) => *errno,
+            Repr::Drbg(_) => return f.write_str("random generator refused"),
+        };
+        if errno == 0 {
+            return f.write_str("random source made no progress");
+        }
+        let e = std::io::Error::from_raw_os_error(errno);
+        write!(f, "random source failed: {e}")
+    }
+}
+
+impl core::error::Error for RandError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match &self.repr {
+            Repr::Drbg(e) => Some(&**e),
+            Repr::Os(_) => None,
+        }
+    }
+}
+
+/// The operating system's generator, through `getrandom`.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsRandom;
+
+#[cfg(target_os = "linux")]
+impl RandomSource for OsRandom {
+    fn fill(&mut self, buf: &mut [u8]) -> Result<(), RandError> {
+        fill_with(buf, getrandom)
+    }
+}
+
+/// Fill `buf` from the operating system's generator.
+#[cfg(target_os = "linux")]
+pub fn fill(buf: &mut [u8]) -> Result<(), RandError> {
+    OsRandom.fill(buf)
+}
+
+/// One `getrandom` call with no flags, returning the bytes written or the
+/// `errno`.
+#[cfg(target_os = "linux")]
+fn getrandom(buf: &mut [u8]) -> Result<usize, i32> {
+    // SAFETY: the kernel writes at most buf.len() bytes to buf.
+    let n = unsafe {
+        libc::syscall(
+            libc::SYS_getrandom,
+            buf.as_mut_ptr(),
+            buf.len(),
+            0 as libc::c_uint,
+        )
+    };
+    if n < 0 {
+        let e = std::io::Error::last_os_error();
+        return Err(e.raw_os_error().unwrap_or(0));
+    }
+    Ok(n as usize)
+}
+
+/// Drive a `getrandom`-shaped call until `buf` is full.
+///
+/// The system call returns short counts for large requests and can be
+/// interrupted by a signal before writing anything; both just mean call
+/// again for the rest. The call is a parameter so that the loop can be
+/// tested against every way it can answer, which the kernel will not
+/// produce on demand.
+#[cfg(target_os = "linux")]
+fn fill_with(
+    mut buf: &mut [u8],
+    mut call: impl FnMut(&mut [u8]) -> Result<usize, i32>,
+) -> Result<(), RandError> {
+    while !buf.is_empty() {
+        match call(buf) {
+            Ok(0) => return Err(RandError::from_raw_os_error(0)),
+            Ok(n) => buf = &mut core::mem::take(&mut buf)[n..],
+            Err(libc::EINTR) => {}
+            Err(errno) => return Err(RandError::from_raw_os_error(errno)),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    /// Answers in turn from a script, then fills whatever is left.
+    fn scripted(
+        mut script: Vec<Result<usize, i32>>,
+    ) -> impl FnMut(&mut [u8]) -> Result<usize, i32> {
+        script.reverse();
+        let mut next = 1u8;
+        move |buf: &mut [u8]| {
+            let answer = script.pop().unwrap_or(Ok(buf.len()));
+            if let Ok(n) = answer {
+                for b in &mut buf[..n] {
+                    *b = next;
+                    next = next.wrapping_add(1);
+                }
+            }
+            answer
+        }
+    }
+
+    #[test]
+    fn short_reads_and_interruptions_are_retried() {
+        let mut buf = [0u8; 10];
+        let call = scripted(vec![
+            Ok(3),
+            Err(libc::EINTR),
+            Ok(4),
+            Err(libc::EINTR),
+        ]);
+        assert_eq!(fill_with(&mut buf, call), Ok(()));
+        assert_eq!(buf, [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+    }
+
+    #[test]
+    fn errors_and_stalls_are_reported() {
+        let mut buf = [0u8; 10];
+        let call = scripted(vec![Ok(3), Err(libc::ENOSYS)]);
+        let e = fill_with(&mut buf, call).unwrap_err();
+        assert_eq!(e.raw_os_error(), libc::ENOSYS);
+
+        let call = scripted(vec![Ok(0)]);
+        assert_eq!(fill_with(&mut buf, call), Err(RandError::from_raw_os_error(0)));
+    }
+
+    #[test]
+    fn empty_requests_never_call() {
+        let call = |_: &mut [u8]| -> Result<usize, i32> {
+            panic!("called for nothing")
+        };
+        assert_eq!(fill_with(&mut [], call), Ok(()));
+    }
+
+    #[test]
+    fn the_kernel_fills_large_requests() {
+        // Past the 256 byte size a single call is guaranteed to fill, so
+        // the loop may have to go round.
+        let mut a = vec![0u8; 1 << 20];
+        let mut b = vec![0u8; 1 << 20];
+        fill(&mut a).unwrap();
+        OsRandom.fill(&mut b).unwrap();
+        assert_ne!(a, b);
+        // A megabyte of zeros would be vanishingly unlikely, and so
+        // would any one byte value being missing from it.
+        let mut seen = [false; 256];
+        a.iter().for_each(|&x| seen[x as usize] = true);
+        assert!(seen.iter().all(|&s| s));
+    }
+}
@@ -0,0 +1,620 @@
+//! CTR_DRBG, the block cipher generator of NIST SP 800-90A.
+//!
+//! A deterministic random bit generator stretches a seed of real entropy
+//! into as many bytes as are asked of it, and is only as unpredictable as
+//! that seed. CTR_DRBG keeps an AES key and a counter block; each request
+//! is the keystream of AES-CTR under them, after which both are replaced
+//! by more of that keystream, so a later compromise of the state does not
+//! reveal output already handed out.
+//!
+//! Every AES key size is here, each with or without the derivation
+//! function. With it, entropy, nonce and personalization may be any
+//! length and are condensed through AES into a seed. Without it, the
+//! entropy must already be a full seed of full entropy, as from a
+//! conditioned hardware source, and no nonce is used.
+//!
+//! The generator does not fetch entropy by itself. It is handed entropy
+//! at instantiation and at every reseed, either as bytes or through a
+//! [`RandomSource`], and asks for a reseed by failing once its reseed
+//! interval has run out. Prediction resistance is a reseed folded into
+//! the request that needs it.
+//!
+//! Output and state updates run through the fused counter kernels of
+//! [`Aes128Ctr`] and its siblings, so a large request costs what
+//! encrypting as much data would. They are built with
+//! `new_constant_time`, since the key and every input are secret: where
+//! the CPU has no constant-time AES, the generator runs on the bitsliced
+//! cipher rather than the T-table one, more slowly.
+
+use core::fmt;
+
+use zeroize::{Zeroize, Zeroizing};
+
+use super::{RandError, RandomSource};
+use crate::self_test::{self, SelfTestError};
+use crate::symmetric::aes::{
+    Aes128Ctr, Aes128Enc, Aes192Ctr, Aes192Enc, Aes256Ctr, Aes256Enc,
+    BLOCK_SIZE,
+};
+
+/// The most bytes one request may ask for: the standard's 2^19 bits.
+pub const MAX_REQUEST: usize = 1 << 16;
+
+/// The most requests allowed between reseeds, and the default: the
+/// standard's 2^48.
+pub const MAX_RESEED_INTERVAL: u64 = 1 << 48;
+
+/// Why a DRBG refused a call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DrbgError {
+    /// The entropy input was too short, or, without the derivation
+    /// function, not exactly one seed long.
+    EntropyLength {
+        /// The length that was supplied, in bytes.
+        got: usize,
+    },
+    /// A personalization string or additional input was longer than
+    /// this configuration accepts.
+    InputTooLong {
+        /// The length that was supplied, in bytes.
+        got: usize,
+    },
+    /// A request was for more than [`MAX_REQUEST`] bytes.
+    RequestTooLarge {
+        /// The length that was asked for, in bytes.
+        got: usize,
+    },
+    /// The reseed interval has run out; reseed before generating more.
+    ReseedRequired,
+    /// The entropy source failed.
+    Source(RandError),
+    /// The library failed its [self tests](crate::self_test), so no
+    /// generator may be instantiated or used.
+    SelfTest(SelfTestError),
+}
+
+impl fmt::Display for DrbgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EntropyLength { got } => {
+                write!(f, "invalid entropy input length: {got} bytes")
+            }
+            Self::InputTooLong { got } => {
+                write!(f, "DRBG input too long: {got} bytes")
+            }
+            Self::RequestTooLarge { got } => {
+                write!(f, "DRBG request too large: {got} bytes")
+            }
+            Self::ReseedRequired => f.write_str("DRBG must be reseeded"),
+            Self::Source(e) => e.fmt(f),
+            Self::SelfTest(e) => e.fmt(f),
+        }
+    }
+}
+
+impl core::error::Error for DrbgError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Source(e) => Some(e),
+            Self::SelfTest(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<RandError> for DrbgError {
+    fn from(e: RandError) -> Self {
+        Self::Source(e)
+    }
+}
+
+impl From<SelfTestError> for DrbgError {
+    fn from(e: SelfTestError) -> Self {
+        Self::SelfTest(e)
+    }
+}
+
+/// The counter block after `v`, wrapping at the full 128 bits.
+fn successor(v: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+    u128::from_be_bytes(*v).wrapping_add(1).to_be_bytes()
+}
+
+fn xor_into(data: &mut [u8], other: &[u8]) {
+    for (d, o) in data.iter_mut().zip(other) {
+        *d ^= *o;
+    }
+}
+
+macro_rules! define_drbg {
+    (
+        $name:ident, $ctr:ident, $enc:ident, $key_size:expr, $doc:expr
+    ) => {
+        #[doc = $doc]
+        pub struct $name {
+            key: [u8; $key_size],
+            /// The counter block, big endian.
+            v: [u8; BLOCK_SIZE],
+            /// Requests served since the last (re)seed, plus one.
+            reseed_counter: u64,
+            reseed_interval: u64,
+            df: bool,
+        }
+
+        impl $name {
+            /// The AES key length in bytes.
+            pub const KEY_SIZE: usize = $key_size;
+            /// The seed length in bytes: a key and a counter block.
+            pub const SEED_SIZE: usize = $key_size + BLOCK_SIZE;
+            /// The security strength in bytes, which is also the least
+            /// entropy input the derivation function accepts.
+            pub const SECURITY_STRENGTH: usize = $key_size;
+
+            /// Instantiate with the derivation function.
+            ///
+            /// `entropy` must hold at least [`Self::SECURITY_STRENGTH`]
+            /// bytes of entropy. `nonce` should be unique to this
+            /// instantiation and at least half that long;
+            /// `personalization` may be empty.
+            pub fn instantiate(
+                entropy: &[u8],
+                nonce: &[u8],
+                personalization: &[u8],
+            ) -> Result<Self, DrbgError> {
+                self_test::check()?;
+                Self::check_entropy(entropy, true)?;
+                let seed = Self::df(&[entropy, nonce, personalization])?;
+                Ok(Self::seeded(&seed, true))
+            }
+
+            /// Instantiate without the derivation function.
+            ///
+            /// `entropy` must be exactly [`Self::SEED_SIZE`] bytes of
+            /// full entropy. `personalization` may be up to that long.
+            pub fn instantiate_without_df(
+                entropy: &[u8],
+                personalization: &[u8],
+            ) -> Result<Self, DrbgError> {
+                self_test::check()?;
+                Self::check_entropy(entropy, false)?;
+                let mut seed = Self::padded(personalization)?;
+                xor_into(&mut seed[..], entropy);
+                Ok(Self::seeded(&seed, false))
+            }
+
+            /// Instantiate with the derivation function, drawing the
+            /// entropy input and nonce from `source`.
+            pub fn instantiate_from(
+                source: &mut impl RandomSource,
+                personalization: &[u8],
+            ) -> Result<Self, DrbgError> {
+                let mut input = Zeroizing::new([0u8; $key_size * 3 / 2]);
+                source.fill(&mut input[..])?;
+                let (entropy, nonce) = input.split_at($key_size);
+                Self::instantiate(entropy, nonce, personalization)
+            }
+
+            /// Mix fresh entropy into the state and restart the reseed
+            /// counter.
+            ///
+            /// `entropy` is as [`Self::instantiate`] or
+            /// [`Self::instantiate_without_df`] required, whichever
+            /// this was made by; `additional` may be empty.
+            pub fn reseed(
+                &mut self,
+                entropy: &[u8],
+                additional: &[u8],
+            ) -> Result<(), DrbgError> {
+                self_test::check()?;
+                Self::check_entropy(entropy, self.df)?;
+                let seed = if self.df {
+                    Self::df(&[entropy, additional])?
+                } else {
+                    let mut seed = Self::padded(additional)?;
+                    xor_into(&mut seed[..], entropy);
+                    seed
+                };
+                self.update(&seed);
+                self.reseed_counter = 1;
+                Ok(())
+            }
+
+            /// Reseed with entropy drawn from `source`.
+            pub fn reseed_from(
+                &mut self,
+                source: &mut impl RandomSource,
+                additional: &[u8],
+            ) -> Result<(), DrbgError> {
+                let mut entropy = Zeroizing::new([0u8; $key_size + BLOCK_SIZE]);
+                let len = if self.df { $key_size } else { entropy.len() };
+                source.fill(&mut entropy[..len])?;
+                self.reseed(&entropy[..len], additional)
+            }
+
+            /// Fill `out` with the next bytes of output.
+            ///
+            /// `additional` is mixed into the state before and after,
+            /// and may be empty. Fails without touching the state if
+            /// the reseed interval has run out or `out` is longer than
+            /// [`MAX_REQUEST`].
+            pub fn generate(
+                &mut self,
+                out: &mut [u8],
+                additional: &[u8],
+            ) -> Result<(), DrbgError> {
+                self_test::check()?;
+                if out.len() > MAX_REQUEST {
+                    return Err(DrbgError::RequestTooLarge { got: out.len() });
+                }
+                if self.reseed_counter > self.reseed_interval {
+                    return Err(DrbgError::ReseedRequired);
+                }
+                let additional = if additional.is_empty() {
+                    Zeroizing::new([0u8; $key_size + BLOCK_SIZE])
+                } else {
+                    let additional = if self.df {
+                        Self::df(&[additional])?
+                    } else {
+                        Self::padded(additional)?
+                    };
+                    self.update(&additional);
+                    additional
+                };
+
+                // The output is the keystream from V + 1 on. A partial
+                // last block still uses up its counter value, so it is
+                // taken from a whole block of its own, and the update
+                // then carries on from the very next counter.
+                let mut stream =
+                    $ctr::new_constant_time(&self.key, &successor(&self.v));
+                out.fill(0);
+                let whole = out.len() / BLOCK_SIZE * BLOCK_SIZE;
+                let (blocks, tail) = out.split_at_mut(whole);
+                stream.apply_keystream(blocks);
+                if !tail.is_empty() {
+                    let mut block = Zeroizing::new([0u8; BLOCK_SIZE]);
+                    stream.apply_keystream(&mut block[..]);
+                    tail.copy_from_slice(&block[..tail.len()]);
+                }
+                self.update_from(stream, &additional);
+                self.reseed_counter += 1;
+                Ok(())
+            }
+
+            /// Reseed with `entropy` and `additional`, then generate
+            /// into `out`, so the output is unpredictable even to
+            /// someone who learned the state before this call.
+            pub fn generate_with_prediction_resistance(
+                &mut self,
+                out: &mut [u8],
+                entropy: &[u8],
+                additional: &[u8],
+            ) -> Result<(), DrbgError> {
+                if out.len() > MAX_REQUEST {
+                    return Err(DrbgError::RequestTooLarge { got: out.len() });
+                }
+                self.reseed(entropy, additional)?;
+                self.generate(out, &[])
+            }
+
+            /// As [`Self::generate_with_prediction_resistance`], drawing
+            /// the entropy from `source`.
+            pub fn generate_with_prediction_resistance_from(
+                &mut self,
+                out: &mut [u8],
+                source: &mut impl RandomSource,
+                additional: &[u8],
+            ) -> Result<(), DrbgError> {
+                if out.len() > MAX_REQUEST {
+                    return Err(DrbgError::RequestTooLarge { got: out.len() });
+                }
+                self.reseed_from(source, additional)?;
+                self.generate(out, &[])
+            }
+
+            /// Requests served since the last (re)seed, plus one, as
+            /// the standard counts them.
+            pub fn reseed_counter(&self) -> u64 {
+                self.reseed_counter
+            }
+
+            /// Allow `interval` requests between reseeds, clamped to
+            /// between 1 and [`MAX_RESEED_INTERVAL`].
+            pub fn set_reseed_interval(&mut self, interval: u64) {
+                self.reseed_interval = interval.clamp(1, MAX_RESEED_INTERVAL);
+            }
+
+            /// Whether this instance uses the derivation function.
+            pub fn uses_derivation_function(&self) -> bool {
+                self.df
+            }
+
+            /// A fresh state updated with `seed`.
+            fn seeded(seed: &[u8; $key_size + BLOCK_SIZE], df: bool) -> Self {
+                let mut this = Self {
+                    key: [0u8; $key_size],
+                    v: [0u8; BLOCK_SIZE],
+                    reseed_counter: 1,
+                    reseed_interval: MAX_RESEED_INTERVAL,
+                    df,
+                };
+                this.update(seed);
+                this
+            }
+
+            fn check_entropy(
+                entropy: &[u8],
+                df: bool,
+            ) -> Result<(), DrbgError> {
+                let fits = if df {
+                    entropy.len() >= $key_size
+                } else {
+                    entropy.len() == $key_size + BLOCK_SIZE
+                };
+                if fits {
+                    Ok(())
+                } else {
+                    Err(DrbgError::EntropyLength { got: entropy.len() })
+                }
+            }
+
+            /// `input` zero padded to a seed, as the configuration
+            /// without the derivation function takes it.
+            fn padded(
+                input: &[u8],
+            ) -> Result<Zeroizing<[u8; $key_size + BLOCK_SIZE]>, DrbgError>
+            {
+                let mut seed = Zeroizing::new([0u8; $key_size + BLOCK_SIZE]);
+                if input.len() > seed.len() {
+                    return Err(DrbgError::InputTooLong { got: input.len() });
+                }
+                seed[..input.len()].copy_from_slice(input);
+                Ok(seed)
+            }
+
+            /// CTR_DRBG_Update: replace the key and counter block with
+            /// the next seed's worth of keystream, XORed with `provided`.
+            fn update(&mut self, provided: &[u8; $key_size + BLOCK_SIZE]) {
+                let stream =
+                    $ctr::new_constant_time(&self.key, &successor(&self.v));
+                self.update_from(stream, provided);
+            }
+
+            /// CTR_DRBG_Update over a stream already positioned at the
+            /// counter the update starts from.
+            fn update_from(
+                &mut self,
+                mut stream: $ctr,
+                provided: &[u8; $key_size + BLOCK_SIZE],
+            ) {
+                let mut temp = Zeroizing::new(*provided);
+                stream.apply_keystream(&mut temp[..]);
+                self.key.copy_from_slice(&temp[..$key_size]);
+                self.v.copy_from_slice(&temp[$key_size..]);
+            }
+
+            /// Block_Cipher_df, condensing the concatenated `inputs`
+            /// into one seed.
+            fn df(
+                inputs: &[&[u8]],
+            ) -> Result<Zeroizing<[u8; $key_size + BLOCK_SIZE]>, DrbgError>
+            {
+                let len: usize = inputs.iter().map(|i| i.len()).sum();
+                let Ok(l) = u32::try_from(len) else {
+                    return Err(DrbgError::InputTooLong { got: len });
+                };
+
+                // IV || L || N || input || 0x80, zero padded to whole
+                // blocks. The IV block leads so each BCC pass need only
+                // rewrite its counter.
+                let mut s =
+                    Vec::with_capacity(BLOCK_SIZE + 8 + len + BLOCK_SIZE);
+                s.extend_from_slice(&[0u8; BLOCK_SIZE]);
+                s.extend_from_slice(&l.to_be_bytes());
+                s.extend_from_slice(&(Self::SEED_SIZE as u32).to_be_bytes());
+                for input in inputs {
+                    s.extend_from_slice(input);
+                }
+                s.push(0x80);
+                s.resize(s.len().next_multiple_of(BLOCK_SIZE), 0);
+
+                // BCC under the fixed key 00 01 02 .., once per block of
+                // key and counter block wanted.
+                let fixed: [u8; $key_size] = core::array::from_fn(|i| i as u8);
+                let cipher = $enc::new_constant_time(&fixed);
+                let mut temp = Zeroizing::new([0u8; $key_size + BLOCK_SIZE]);
+                let mut chain = Zeroizing::new([0u8; BLOCK_SIZE]);
+                for (i, chunk) in temp.chunks_mut(BLOCK_SIZE).enumerate() {
+                    s[..4].copy_from_slice(&(i as u32).to_be_bytes());
+                    chain.fill(0);
+                    for block in s.chunks_exact(BLOCK_SIZE) {
+                        xor_into(&mut chain[..], block);
+                        cipher.encrypt_block(&mut chain);
+                    }
+                    chunk.copy_from_slice(&chain[..chunk.len()]);
+                }
+                s.as_mut_slice().zeroize();
+
+                // Then chain the block cipher from X under the new key.
+                let key: &[u8; $key_size] =
+                    temp[..$key_size].try_into().expect("key sized");
+                let cipher = $enc::new_constant_time(key);
+                let mut x = Zeroizing::new([0u8; BLOCK_SIZE]);
+                x.copy_from_slice(&temp[$key_size..]);
+                let mut seed = Zeroizing::new([0u8; $key_size + BLOCK_SIZE]);
+                for chunk in seed.chunks_mut(BLOCK_SIZE) {
+                    cipher.encrypt_block(&mut x);
+                    chunk.copy_from_slice(&x[..chunk.len()]);
+                }
+                Ok(seed)
+            }
+        }
+
+        /// Requests are split at [`MAX_REQUEST`] bytes. A generator that
+        /// refuses, such as one due a reseed, fails with its
+        /// [`DrbgError`], having filled part of `buf` at most.
+        impl RandomSource for $name {
+            fn fill(&mut self, buf: &mut [u8]) -> Result<(), RandError> {
+                for chunk in buf.chunks_mut(MAX_REQUEST) {
+                    self.generate(chunk, &[])?;
+                }
+                Ok(())
+            }
+        }
+
+        impl Drop for $name {
+            fn drop(&mut self) {
+                self.key.zeroize();
+                self.v.zeroize();
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                // The state is the key; never format it.
+                f.write_str(concat!(stringify!($name), " { .. }"))
+            }
+        }
+    };
+}
+
+define_drbg!(
+    Aes128CtrDrbg, Aes128Ctr, Aes128Enc, 16,
+    "CTR_DRBG over AES-128, at a security strength of 128 bits."
+);
+define_drbg!(
+    Aes192CtrDrbg, Aes192Ctr, Aes192Enc, 24,
+    "CTR_DRBG over AES-192, at a security strength of 192 bits."
+);
+define_drbg!(
+    Aes256CtrDrbg, Aes256Ctr, Aes256Enc, 32,
+    "CTR_DRBG over AES-256, at a security strength of 256 bits."
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drbg() -> Aes128CtrDrbg {
+        Aes128CtrDrbg::instantiate(&[0x11; 16], &[0x22; 8], b"tests").unwrap()
+    }
+
+    #[test]
+    fn lengths_are_checked() {
+        assert_eq!(
+            Aes192CtrDrbg::instantiate(&[0; 23], &[0; 12], &[]).unwrap_err(),
+            DrbgError::EntropyLength { got: 23 }
+        );
+        assert_eq!(
+            Aes128CtrDrbg::instantiate_without_df(&[0; 31], &[]).unwrap_err(),
+            DrbgError::EntropyLength { got: 31 }
+        );
+        assert_eq!(
+            Aes128CtrDrbg::instantiate_without_df(&[0; 32], &[0; 33])
+                .unwrap_err(),
+            DrbgError::InputTooLong { got: 33 }
+        );
+
+        // With the derivation function, long inputs are fine.
+        let mut d = drbg();
+        d.reseed(&[0x33; 100], &[0x44; 100]).unwrap();
+        d.generate(&mut [0u8; 16], &[0x55; 1000]).unwrap();
+        assert_eq!(
+            d.generate(&mut vec![0u8; MAX_REQUEST + 1], &[]).unwrap_err(),
+            DrbgError::RequestTooLarge { got: MAX_REQUEST + 1 }
+        );
+        assert_eq!(
+            d.reseed(&[0x33; 15], &[]).unwrap_err(),
+            DrbgError::EntropyLength { got: 15 }
+        );
+    }
+
+    /// A shorter request is a prefix of a longer one from the same state.
+    #[test]
+    fn partial_blocks_are_prefixes() {
+        let mut whole = [0u8; 48];
+        drbg().generate(&mut whole, &[]).unwrap();
+        for len in 0..48 {
+            let mut part = vec![0u8; len];
+            drbg().generate(&mut part, &[]).unwrap();
+            assert_eq!(part, whole[..len], "length {len}");
+        }
+    }
+
+    #[test]
+    fn reseed_interval_is_enforced() {
+        let mut d = drbg();
+        d.set_reseed_interval(2);
+        let mut out = [0u8; 16];
+        d.generate(&mut out, &[]).unwrap();
+        d.generate(&mut out, &[]).unwrap();
+        assert_eq!(d.reseed_counter(), 3);
+        let before = out;
+        assert_eq!(
+            d.generate(&mut out, &[]).unwrap_err(),
+            DrbgError::ReseedRequired
+        );
+        assert_eq!(out, before, "a refused request must not write");
+
+        d.reseed(&[0x66; 16], &[]).unwrap();
+        assert_eq!(d.reseed_counter(), 1);
+        d.generate(&mut out, &[]).unwrap();
+        assert_ne!(out, before);
+    }
+
+    /// As a source, requests past the limit are split rather than
+    /// refused, and exhaustion surfaces as an error.
+    #[test]
+    fn is_a_random_source() {
+        let mut d = drbg();
+        let mut big = vec![0u8; MAX_REQUEST * 2 + 5];
+        d.fill(&mut big).unwrap();
+        assert_eq!(d.reseed_counter(), 4);
+        let mut direct = vec![0u8; MAX_REQUEST];
+        drbg().generate(&mut direct, &[]).unwrap();
+        assert_eq!(big[..MAX_REQUEST], direct[..]);
+
+        d.set_reseed_interval(3);
+        let e = d.fill(&mut [0u8; 4]).unwrap_err();
+        assert_eq!(e.raw_os_error(), 0);
+        assert_eq!(e.drbg_error(), Some(&DrbgError::ReseedRequired));
+        let cause = core::error::Error::source(&e).unwrap();
+        assert_eq!(cause.to_string(), "DRBG must be reseeded");
+
+        let key = crate::symmetric::aes::Aes256Enc::generate_from(&mut drbg());
+        assert!(key.is_ok());
+    }
+
+    #[test]
+    fn instantiates_and_reseeds_from_a_source() {
+        struct Counting(u8);
+        impl RandomSource for Counting {
+            fn fill(&mut self, buf: &mut [u8]) -> Result<(), RandError> {
+                for b in buf {
+                    *b = self.0;
+                    self.0 = self.0.wrapping_add(1);
+                }
+                Ok(())
+            }
+        }
+
+        let input: Vec<u8> = (0..48).collect();
+        let mut ours =
+            Aes256CtrDrbg::instantiate_from(&mut Counting(0), b"p").unwrap();
+        let mut theirs =
+            Aes256CtrDrbg::instantiate(&input[..32], &input[32..], b"p")
+                .unwrap();
+        ours.reseed_from(&mut Counting(48), b"a").unwrap();
+        let entropy: Vec<u8> = (48..80).collect();
+        theirs.reseed(&entropy, b"a").unwrap();
+
+        let (mut a, mut b) = ([0u8; 32], [0u8; 32]);
+        ours.generate(&mut a, &[]).unwrap();
+        theirs.generate(&mut b, &[]).unwrap();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn debug_does_not_leak_state() {
+        assert_eq!(format!("{:?}", drbg()), "Aes128CtrDrbg { .. }");
+    }
+}
@@ -0,0 +1,481 @@
+//! Memory for secrets: locked, guarded and kept out of core dumps.
+//!
+//! A key schedule lives wherever its owner puts it, which is usually the
+//! stack or the heap. Either can be written to swap, included in a core
+//! dump, or read past by an overrun in a neighbouring buffer. A
+//! [`SecretBox`] puts one value on pages of its own instead:
+//!
+//! - locked into RAM with `mlock`, so they are never swapped out;
+//! - marked `MADV_DONTDUMP`, so a core dump leaves them out;
+//! - between two inaccessible guard pages, so a linear overrun from either
+//!   side faults rather than reading or writing the secret;
+//! - wiped whole when the box is dropped, after the value's own `Drop`.
+//!
+//! The value sits at the end of its pages, so even an overrun from within
+//! it reaches the trailing guard page as soon as it leaves the value.
+//!
+//! ```
+//! use scytale::secret::SecretBox;
+//! use scytale::symmetric::aes::Aes128Enc;
+//!
+//! let cipher = SecretBox::new_with(|| Aes128Enc::new(&[0u8; 16]));
+//! let mut block = [0u8; 16];
+//! cipher.encrypt_block(&mut block);
+//! ```
+//!
+//! Locked memory is limited per process by `RLIMIT_MEMLOCK`, which is
+//! small by default. A box that cannot be locked is still guarded and
+//! still kept out of core dumps; [`SecretBox::is_locked`] says which
+//! happened. Every box costs at least three pages of address space and
+//! one of memory, so this is for keys, not bulk data.
+//!
+//! Two things are outside its reach. A value built on the stack and then
+//! moved in leaves its stack copy behind; [`SecretBox::new_with`] gives
+//! the compiler the chance to build it in place instead, which an
+//! optimised build takes for the cipher types here, but it cannot be made
+//! to. And heap allocations the value owns stay on the heap: the generic
+//! [`Ctr`](crate::symmetric::Ctr) stages keystream in buffers of its own,
+//! which it wipes on drop, while the AES counter types keep their whole
+//! state inline.
+//!
+//! Linux only.
+
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use core::ptr::NonNull;
+
+use zeroize::Zeroize;
+
+/// The kernel would not map pages for a secret.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocError {
+    /// The `errno` the failing call left.
+    errno: i32,
+}
+
+impl AllocError {
+    /// The last OS error, as the failing call left it.
+    fn last() -> Self {
+        let errno = std::io::Error::last_os_error().raw_os_error();
+        Self { errno: errno.unwrap_or(0) }
+    }
+
+    /// The `errno` value the kernel returned.
+    pub fn raw_os_error(&self) -> i32 {
+        self.errno
+    }
+}
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let e = std::io::Error::from_raw_os_error(self.errno);
+        write!(f, "cannot map memory for a secret: {e}")
+    }
+}
+
+impl core::error::Error for AllocError {}
+
+/// The system page size, which cannot change while the process runs.
+fn page_size() -> usize {
+    use std::sync::OnceLock;
+    static PAGE: OnceLock<usize> = OnceLock::new();
+    *PAGE.get_or_init(|| {
+        // SAFETY: sysconf has no preconditions.
+        unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+    })
+}
+
+/// A private mapping: data pages between two guard pages.
+///
+/// Owning the mapping separately from the value means a constructor that
+/// panics part way still unmaps what it mapped.
+struct Pages {
+    /// The start of the mapping, which is the leading guard page.
+    base: NonNull<u8>,
+    /// The whole mapping, guard pages included.
+    len: usize,
+    /// Whether `mlock` succeeded on the data pages.
+    locked: bool,
+}
+
+impl Pages {
+    /// Map enough data pages for `bytes`, guarded on both sides.
+    fn map(bytes: usize) -> Result<Self, AllocError> {
+        let page = page_size();
+        let data = bytes.div_ceil(page).max(1) * page;
+        let len = data + 2 * page;
+
+        // SAFETY: a fresh anonymous mapping, placed where the kernel
+        // chooses; nothing else refers to it.
+        let base = unsafe {
+            libc::mmap(
+                core::ptr::null_mut(),
+                len,
+                libc::PROT_NONE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if base == libc::MAP_FAILED {
+            return Err(AllocError::last());
+        }
+        // SAFETY: the mapping is three pages at the least.
+        let start = unsafe { base.cast::<u8>().add(page) }.cast();
+
+        // SAFETY: the range is the data pages, inside the mapping just
+        // made. Everything else stays PROT_NONE, which is the guard.
+        let opened = unsafe {
+            libc::mprotect(start, data, libc::PROT_READ | libc::PROT_WRITE)
+        };
+        if opened != 0 {
+            let e = AllocError::last();
+            // SAFETY: the mapping was made above and nothing refers to
+            // it; Pages, which would wipe it first, does not exist yet.
+            unsafe { libc::munmap(base, len) };
+            return Err(e);
+        }
+        let mut pages = Self {
+            base: NonNull::new(base.cast()).expect("mmap returned null"),
+            len,
+            locked: false,
+        };
+        // SAFETY: as above. Failing to exclude the pages from a dump is
+        // no reason to refuse the allocation: a kernel that does not
+        // know the advice has no such dump filter to apply it to.
+        unsafe { libc::madvise(start, data, libc::MADV_DONTDUMP) };
+        // SAFETY: as above. Failure here is the lock limit, which the
+        // caller finds out about through is_locked.
+        pages.locked = unsafe { libc::mlock(start, data) } == 0;
+        Ok(pages)
+    }
+
+    /// The first data page.
+    fn data(&self) -> *mut u8 {
+        // SAFETY: the mapping is at least three pages long.
+        unsafe { self.base.as_ptr().add(page_size()) }
+    }
+
+    /// The data pages' length in bytes.
+    fn data_len(&self) -> usize {
+        self.len - 2 * page_size()
+    }
+}
+
+impl Drop for Pages {
+    fn drop(&mut self) {
+        let start = self.data();
+        let len = self.data_len();
+        // SAFETY: the data pages are mapped read-write, page aligned and
+        // a whole number of words, and whatever lived there has been
+        // dropped already.
+        let words = unsafe {
+            core::slice::from_raw_parts_mut(start.cast::<u64>(), len / 8)
+        };
+        words.zeroize();
+        // SAFETY: the range is this mapping's, which nothing refers to
+        // any more. Unmapping releases the lock along with the pages.
+        unsafe { libc::munmap(self.base.as_ptr().cast(), self.len) };
+    }
+}
+
+/// One value on locked, guarded pages of its own.
+///
+/// Dereferences to the value, so a boxed cipher is used exactly as an
+/// unboxed one would be. `Debug` never shows the contents, and boxes of
+/// byte strings compare in constant time.
+pub struct SecretBox<T> {
+    /// The value, at the end of the data pages.
+    value: NonNull<T>,
+    pages: Pages,
+    _owns: PhantomData<T>,
+}
+
+// SAFETY: the box owns its value outright, as a Box would.
+unsafe impl<T: Send> Send for SecretBox<T> {}
+// SAFETY: shared access only ever hands out &T.
+unsafe impl<T: Sync> Sync for SecretBox<T> {}
+
+impl<T> SecretBox<T> {
+    /// Move `value` onto pages of its own.
+    ///
+    /// # Panics
+    ///
+    /// If the kernel will not map the pages. [`Self::try_new`] reports
+    /// that instead.
+    pub fn new(value: T) -> Self {
+        Self::new_with(|| value)
+    }
+
+    /// Move `value` onto pages of its own, or say why the kernel would
+    /// not map them.
+    pub fn try_new(value: T) -> Result<Self, AllocError> {
+        Self::try_new_with(|| value)
+    }
+
+    /// Build a value with `make` and put it on pages of its own.
+    ///
+    /// The pages are mapped first and the value written straight into
+    /// them, which lets the compiler construct it there rather than on
+    /// the stack and copy it in.
+    ///
+    /// # Panics
+    ///
+    /// If the kernel will not map the pages, or if `make` does.
+    pub fn new_with(make: impl FnOnce() -> T) -> Self {
+        match Self::try_new_with(make) {
+            Ok(this) => this,
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    /// As [`Self::new_with`], but reporting a mapping failure rather
+    /// than panicking.
+    ///
+    /// # Panics
+    ///
+    /// If `make` does, or if `T` needs a greater alignment than a page.
+    pub fn try_new_with(
+        make: impl FnOnce() -> T,
+    ) -> Result<Self, AllocError> {
+        let size = size_of::<T>();
+        let align = align_of::<T>();
+        assert!(align <= page_size(), "alignment beyond a page");
+        let pages = Pages::map(size)?;
+        // The last aligned position the value fits at, so that the byte
+        // after it is as close to the trailing guard page as it can be.
+        let offset = (pages.data_len() - size) & !(align - 1);
+        // SAFETY: offset + size is within the data pages, and the data
+        // pages start page aligned, so the result is aligned for T.
+        let value = unsafe { pages.data().add(offset).cast::<T>() };
+        // SAFETY: as above; the memory is writable and not yet in use.
+        unsafe { value.write(make()) };
+        Ok(Self {
+            value: NonNull::new(value).expect("mapping is not null"),
+            pages,
+            _owns: PhantomData,
+        })
+    }
+
+    /// Whether the pages are locked into RAM.
+    ///
+    /// False when `RLIMIT_MEMLOCK` would not stretch to them. They are
+    /// still guarded and still left out of core dumps, but the kernel may
+    /// write them to swap.
+    pub fn is_locked(&self) -> bool {
+        self.pages.locked
+    }
+}
+
+impl<T> Deref for SecretBox<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: the value was written at construction and lives until
+        // drop; the borrow of self keeps it there.
+        unsafe { self.value.as_ref() }
+    }
+}
+
+impl<T> DerefMut for SecretBox<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: as for deref, and the box is borrowed uniquely.
+        unsafe { self.value.as_mut() }
+    }
+}
+
+impl<T> Drop for SecretBox<T> {
+    fn drop(&mut self) {
+        // SAFETY: the value is live and is never touched again. The
+        // pages, wiped and unmapped when they drop next, outlive it.
+        unsafe { self.value.as_ptr().drop_in_place() };
+    }
+}
+
+impl<T> fmt::Debug for SecretBox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never format the contents.
+        f.write_str("SecretBox { .. }")
+    }
+}
+
+/// Byte strings compare in time that depends only on their lengths.
+///
+/// Every byte of both is read whatever the first difference is, so the
+/// comparison says nothing about where two secrets part ways. Lengths are
+/// treated as public.
+impl<T: AsRef<[u8]>> PartialEq for SecretBox<T> {
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b) = ((**self).as_ref(), (**other).as_ref());
+        if a.len() != b.len() {
+            return false;
+        }
+        let mut diff = 0u8;
+        for (x, y) in a.iter().zip(b) {
+            // Opaque to the optimiser, so it cannot stop at the first
+            // difference it sees.
+            diff |= core::hint::black_box(x ^ y);
+        }
+        diff == 0
+    }
+}
+
+impl<T: AsRef<[u8]>> Eq for SecretBox<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symmetric::Ctr;
+    use crate::symmetric::aes::{Aes128Ctr, Aes128Enc, Aes256};
+
+    use std::sync::Mutex;
+
+    /// Held by any test that changes or depends on the lock limit, which
+    /// is per process.
+    static LIMIT: Mutex<()> = Mutex::new(());
+
+    /// The permissions and `VmFlags` of the mapping holding `addr`, from
+    /// `/proc/self/smaps`.
+    fn mapping(addr: usize) -> (String, String) {
+        let smaps = std::fs::read_to_string("/proc/self/smaps").unwrap();
+        let mut current = None;
+        for line in smaps.lines() {
+            let mut words = line.split_whitespace();
+            let first = words.next().unwrap_or("");
+            if let Some((lo, hi)) = first.split_once('-')
+                && let (Ok(lo), Ok(hi)) = (
+                    usize::from_str_radix(lo, 16),
+                    usize::from_str_radix(hi, 16),
+                )
+            {
+                let perms = words.next().unwrap_or("").to_owned();
+                current = (lo..hi).contains(&addr).then_some(perms);
+            } else if let (Some(perms), Some(flags)) =
+                (&current, line.strip_prefix("VmFlags:"))
+            {
+                return (perms.clone(), flags.trim().to_owned());
+            }
+        }
+        panic!("no mapping holds {addr:#x}");
+    }
+
+    #[test]
+    fn pages_are_guarded_locked_and_left_out_of_dumps() {
+        let _limit = LIMIT.lock().unwrap();
+        let secret = SecretBox::new([0x5au8; 32]);
+        let page = page_size();
+        let value = secret.value.as_ptr() as usize;
+        let base = secret.pages.base.as_ptr() as usize;
+        let end = base + secret.pages.len;
+
+        let (perms, flags) = mapping(value);
+        assert!(perms.starts_with("rw"), "{perms}");
+        assert!(flags.split(' ').any(|f| f == "dd"), "{flags}");
+        if secret.is_locked() {
+            assert!(flags.split(' ').any(|f| f == "lo"), "{flags}");
+        }
+        assert!(mapping(base).0.starts_with("---"));
+        assert!(mapping(end - 1).0.starts_with("---"));
+
+        // The value ends where the trailing guard page begins.
+        assert_eq!(value + 32, end - page);
+    }
+
+    #[test]
+    fn holds_ciphers_and_stream_state() {
+        let key = [0x2bu8; 16];
+        let iv = [0xf0u8; 16];
+        let mut data = [0x11u8; 100];
+
+        let mut expected = data;
+        Aes128Ctr::new(&key, &iv).apply_keystream(&mut expected);
+
+        let mut boxed = SecretBox::new_with(|| Aes128Ctr::new(&key, &iv));
+        let mut ours = data;
+        boxed.apply_keystream(&mut ours);
+        assert_eq!(ours, expected);
+
+        let mut generic = SecretBox::new_with(|| {
+            Ctr::try_new(Aes128Enc::new(&key), &iv).unwrap()
+        });
+        generic.apply_keystream(&mut data);
+        assert_eq!(data, expected);
+
+        let both = SecretBox::new(Aes256::new(&[7u8; 32]));
+        let mut block = [3u8; 16];
+        both.encrypt_block(&mut block);
+        both.decrypt_block(&mut block);
+        assert_eq!(block, [3u8; 16]);
+    }
+
+    #[test]
+    fn debug_is_redacted() {
+        let secret = SecretBox::new([0xabu8; 16]);
+        let shown = format!("{secret:?}");
+        assert_eq!(shown, "SecretBox { .. }");
+        let cipher = SecretBox::new_with(|| Aes128Enc::new(&[0u8; 16]));
+        assert_eq!(format!("{cipher:?}"), "SecretBox { .. }");
+    }
+
+    #[test]
+    fn byte_strings_compare_by_content() {
+        let a = SecretBox::new(*b"correct horse battery staple");
+        let b = SecretBox::new(*b"correct horse battery staple");
+        let c = SecretBox::new(*b"correct horse battery stapld");
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_ne!(SecretBox::new(vec![1u8]), SecretBox::new(vec![1u8, 2]));
+    }
+
+    /// The value's own `Drop` runs, and then its pages are wiped.
+    #[test]
+    fn drop_runs_the_values_drop() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+        struct Counted;
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        drop(SecretBox::new(Counted));
+        assert_eq!(DROPS.load(Ordering::SeqCst), 1);
+    }
+
+    /// With the lock limit at zero, the box still works and is still
+    /// guarded; it only reports that it is not locked. A process that
+    /// may lock memory regardless of the limit, as root usually may,
+    /// locks anyway, and that is fine too.
+    #[test]
+    fn falls_back_when_the_lock_limit_is_reached() {
+        let _limit = LIMIT.lock().unwrap();
+        let mut saved = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+        // SAFETY: saved is a valid rlimit to fill in.
+        let got =
+            unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut saved) };
+        assert_eq!(got, 0);
+        let zero = libc::rlimit { rlim_cur: 0, rlim_max: saved.rlim_max };
+        // SAFETY: lowering a soft limit is always permitted.
+        assert_eq!(unsafe { libc::setrlimit(libc::RLIMIT_MEMLOCK, &zero) }, 0);
+
+        let secret = SecretBox::try_new([9u8; 64]);
+
+        // SAFETY: raising the soft limit back to where it was, which is
+        // within the hard limit it was read with.
+        assert_eq!(
+            unsafe { libc::setrlimit(libc::RLIMIT_MEMLOCK, &saved) },
+            0
+        );
+
+        let secret = secret.expect("the lock limit is no reason to fail");
+        assert_eq!(*secret, [9u8; 64]);
+        let (_, flags) = mapping(secret.value.as_ptr() as usize);
+        assert!(flags.split(' ').any(|f| f == "dd"), "{flags}");
+        assert_eq!(
+            secret.is_locked(),
+            flags.split(' ').any(|f| f == "lo"),
+            "{flags}"
+        );
+    }
+}
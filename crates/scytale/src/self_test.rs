@@ -0,0 +1,225 @@
+//! Power-on known-answer self tests, and the error state they guard.
+//!
+//! The first time any dispatching type is constructed, every AES
+//! implementation this CPU can run is checked against the SP 800-38A
+//! vectors: bulk and single block ECB in both directions, with the key
+//! expanded and with it inverted, and the counter kernels. That happens
+//! once per process, before the construction that triggered it goes on.
+//!
+//! A wrong answer puts the library in an error state, and there it stays
+//! until the process exits. Every later construction through the
+//! dispatching types, the counter modes, the [`Registry`] and the
+//! [`ctr_drbg`] generators fails: the `try_` forms with a
+//! [`SelfTestError`], and `new` with a panic. Values built before the
+//! failure are not recalled.
+//!
+//! [`self_test`] runs the tests again on demand, for a caller whose policy
+//! asks for periodic testing. A failure there enters the error state too.
+//!
+//! The types under [`aes::arch`] are not gated. They name one
+//! implementation exactly, and they are what the tests run on.
+//!
+//! [`Registry`]: crate::symmetric::registry::Registry
+//! [`ctr_drbg`]: crate::rand::ctr_drbg
+//! [`aes::arch`]: crate::symmetric::aes::arch
+
+pub(crate) mod vectors;
+
+use core::fmt;
+use std::sync::OnceLock;
+
+/// An implementation gave a wrong answer to a known-answer test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelfTestError {
+    algorithm: &'static str,
+    implementation: &'static str,
+    test: &'static str,
+}
+
+impl SelfTestError {
+    pub(crate) const fn new(
+        algorithm: &'static str,
+        implementation: &'static str,
+        test: &'static str,
+    ) -> Self {
+        Self { algorithm, implementation, test }
+    }
+
+    /// The algorithm that failed, such as `"AES-128"`.
+    pub fn algorithm(&self) -> &'static str {
+        self.algorithm
+    }
+
+    /// The implementation that failed, by its
+    /// [`Implementation::name`](crate::symmetric::aes::Implementation::name).
+    pub fn implementation(&self) -> &'static str {
+        self.implementation
+    }
+
+    /// The test that failed, such as `"ECB encrypt"`.
+    pub fn test(&self) -> &'static str {
+        self.test
+    }
+}
+
+impl fmt::Display for SelfTestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} self test failed on {}",
+            self.algorithm, self.test, self.implementation
+        )
+    }
+}
+
+impl core::error::Error for SelfTestError {}
+
+/// The first failure this process saw, from whichever run found it.
+static FAILURE: OnceLock<SelfTestError> = OnceLock::new();
+
+/// Run every known-answer test now.
+///
+/// Returns the failure this run found, or failing that one an earlier
+/// run left behind: passing now does not leave the error state.
+pub fn self_test() -> Result<(), SelfTestError> {
+    record(&FAILURE, run())
+}
+
+/// Fail if the library is in the error state, running the power-on tests
+/// first if nothing has yet.
+///
+/// Every dispatching constructor calls this before it expands a key. After
+/// the first call it costs two atomic loads.
+pub(crate) fn check() -> Result<(), SelfTestError> {
+    static POWER_ON: OnceLock<()> = OnceLock::new();
+    gate(&POWER_ON, &FAILURE, run)
+}
+
+/// The tests themselves.
+fn run() -> Result<(), SelfTestError> {
+    crate::symmetric::aes::kat::run()
+}
+
+/// Latch a run's failure into `failure`, and report it or an earlier one.
+fn record(
+    failure: &OnceLock<SelfTestError>,
+    result: Result<(), SelfTestError>,
+) -> Result<(), SelfTestError> {
+    if let Err(e) = result {
+        // Only the first failure is kept; a later one changes nothing.
+        let _ = failure.set(e);
+        return Err(e);
+    }
+    match failure.get() {
+        Some(e) => Err(*e),
+        None => Ok(()),
+    }
+}
+
+/// Run `tests` the first time through `power_on`, then report the state.
+///
+/// Callers on other threads wait for the first run to finish, so nothing
+/// is constructed on an implementation still under test.
+fn gate(
+    power_on: &OnceLock<()>,
+    failure: &OnceLock<SelfTestError>,
+    tests: impl FnOnce() -> Result<(), SelfTestError>,
+) -> Result<(), SelfTestError> {
+    power_on.get_or_init(|| {
+        let _ = record(failure, tests());
+    });
+    match failure.get() {
+        Some(e) => Err(*e),
+        None => Ok(()),
+    }
+}
+
+/// Decode a hex literal of exactly `N` bytes, at compile time.
+pub(crate) const fn unhex<const N: usize>(s: &str) -> [u8; N] {
+    const fn nibble(c: u8) -> u8 {
+        match c {
+            b'0'..=b'9' => c - b'0',
+            b'a'..=b'f' => c - b'a' + 10,
+            _ => panic!("test vector is not lowercase hex"),
+        }
+    }
+    let s = s.as_bytes();
+    assert!(s.len() == 2 * N, "test vector has the wrong length");
+    let mut out = [0u8; N];
+    let mut i = 0;
+    while i < N {
+        out[i] = nibble(s[2 * i]) << 4 | nibble(s[2 * i + 1]);
+        i += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BROKEN: SelfTestError = SelfTestError::new("AES-128", "x", "y");
+    const WORSE: SelfTestError = SelfTestError::new("AES-256", "x", "z");
+
+    /// This process's own library passes, however often it is asked.
+    #[test]
+    fn the_library_passes() {
+        assert_eq!(check(), Ok(()));
+        assert_eq!(self_test(), Ok(()));
+        assert_eq!(check(), Ok(()));
+    }
+
+    #[test]
+    fn power_on_tests_run_once() {
+        let power_on = OnceLock::new();
+        let failure = OnceLock::new();
+        let mut runs = 0;
+        for _ in 0..3 {
+            let result = gate(&power_on, &failure, || {
+                runs += 1;
+                Ok(())
+            });
+            assert_eq!(result, Ok(()));
+        }
+        assert_eq!(runs, 1);
+    }
+
+    #[test]
+    fn a_power_on_failure_is_permanent() {
+        let power_on = OnceLock::new();
+        let failure = OnceLock::new();
+        assert_eq!(gate(&power_on, &failure, || Err(BROKEN)), Err(BROKEN));
+        assert_eq!(gate(&power_on, &failure, || Ok(())), Err(BROKEN));
+        // Passing on demand does not clear it either.
+        assert_eq!(record(&failure, Ok(())), Err(BROKEN));
+    }
+
+    /// A later run reports what it found, and enters the error state if it
+    /// is the first to fail, but the first failure is the one kept.
+    #[test]
+    fn on_demand_failures_enter_the_error_state() {
+        let power_on = OnceLock::new();
+        let failure = OnceLock::new();
+        assert_eq!(gate(&power_on, &failure, || Ok(())), Ok(()));
+        assert_eq!(record(&failure, Err(BROKEN)), Err(BROKEN));
+        assert_eq!(gate(&power_on, &failure, || Ok(())), Err(BROKEN));
+        assert_eq!(record(&failure, Err(WORSE)), Err(WORSE));
+        assert_eq!(record(&failure, Ok(())), Err(BROKEN));
+    }
+
+    #[test]
+    fn errors_name_what_failed() {
+        let e = SelfTestError::new("AES-192", "aes-ni", "CTR");
+        assert_eq!(e.to_string(), "AES-192 CTR self test failed on aes-ni");
+        assert_eq!(
+            (e.algorithm(), e.implementation(), e.test()),
+            ("AES-192", "aes-ni", "CTR")
+        );
+    }
+
+    #[test]
+    fn hex_decodes_at_compile_time() {
+        const KEY: [u8; 4] = unhex("00a9ff10");
+        assert_eq!(KEY, [0x00, 0xa9, 0xff, 0x10]);
+    }
+}
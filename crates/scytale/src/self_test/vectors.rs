@@ -0,0 +1,61 @@
+//! The known answers the self tests check, from NIST SP 800-38A.
+//!
+//! Appendix F runs one four block message through each mode under one key
+//! per size. The library decodes these at compile time; the known-answer
+//! tests under `tests/` include this file by path, so both check exactly
+//! the same values.
+
+/// F.1.1, F.2.1 and F.5.1: the AES-128 key.
+pub const KEY_128: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+
+/// F.1.3, F.2.3 and F.5.3: the AES-192 key.
+pub const KEY_192: &str = "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b";
+
+/// F.1.5, F.2.5 and F.5.5: the AES-256 key.
+pub const KEY_256: &str = "603deb1015ca71be2b73aef0857d7781\
+                           1f352c073b6108d72d9810a30914dff4";
+
+/// The four block message every example encrypts.
+pub const PLAINTEXT: &str = "6bc1bee22e409f96e93d7e117393172a\
+                             ae2d8a571e03ac9c9eb76fac45af8e51\
+                             30c81c46a35ce411e5fbc1191a0a52ef\
+                             f69f2445df4f9b17ad2b417be66c3710";
+
+/// F.1.1 and F.1.2: ECB-AES128.
+pub const ECB_128: &str = "3ad77bb40d7a3660a89ecaf32466ef97\
+                           f5d3d58503b9699de785895a96fdbaaf\
+                           43b1cd7f598ece23881b00e3ed030688\
+                           7b0c785e27e8ad3f8223207104725dd4";
+
+/// F.1.3 and F.1.4: ECB-AES192.
+pub const ECB_192: &str = "bd334f1d6e45f25ff712a214571fa5cc\
+                           974104846d0ad3ad7734ecb3ecee4eef\
+                           ef7afd2270e2e60adce0ba2face6444e\
+                           9a4b41ba738d6c72fb16691603c18e0e";
+
+/// F.1.5 and F.1.6: ECB-AES256.
+pub const ECB_256: &str = "f3eed1bdb5d2a03c064b5a7e3db181f8\
+                           591ccb10d410ed26dc5ba74a31362870\
+                           b6ed21b99ca6f4f9f153e7b1beafed1d\
+                           23304b7a39f9f3ff067d8d8f9e24ecc7";
+
+/// The initial counter block of every CTR example.
+pub const CTR_IV: &str = "f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff";
+
+/// F.5.1 and F.5.2: CTR-AES128.
+pub const CTR_128: &str = "874d6191b620e3261bef6864990db6ce\
+                           9806f66b7970fdff8617187bb9fffdff\
+                           5ae4df3edbd5d35e5b4f09020db03eab\
+                           1e031dda2fbe03d1792170a0f3009cee";
+
+/// F.5.3 and F.5.4: CTR-AES192.
+pub const CTR_192: &str = "1abc932417521ca24f2b0459fe7e6e0b\
+                           090339ec0aa6faefd5ccc2c6f4ce8e94\
+                           1e36b26bd1ebc670d1bd1d665620abf7\
+                           4f78a7f6d29809585a97daec58c6b050";
+
+/// F.5.5 and F.5.6: CTR-AES256.
+pub const CTR_256: &str = "601ec313775789a5b7a7f504bbf3d228\
+                           f443e3ca4d62b59aca84e990cacaf5c5\
+                           2b0930daa23de94ce87017ba2d84988d\
+                           dfc9c58db67aada613c2dd08457941a6";
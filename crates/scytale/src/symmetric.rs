@@ -3,8 +3,14 @@
 pub mod aes;
 pub mod block_cipher;
 pub mod ctr;
+pub mod ctr_io;
+pub mod dyn_cipher;
+pub mod registry;
 
 pub use block_cipher::{
-    BlockDecrypt, BlockEncrypt, InvalidKeyLength, KeyInit,
+    BlockDecrypt, BlockEncrypt, InvalidKeyLength, KeyInit, KeyInitError,
 };
 pub use ctr::{Ctr, CtrInitError, InvalidIvLength};
+pub use ctr_io::{CtrReader, CtrWriter, IntoInnerError, Keystream};
+pub use dyn_cipher::{DynBlockCipher, DynStreamCipher};
+pub use registry::{Registry, RegistryError};
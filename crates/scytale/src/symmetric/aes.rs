@@ -6,17 +6,67 @@
 //!
 //! Each key size has three types. The `Enc` and `Dec` types hold a single
 //! key schedule; the bare name holds both and costs about twice as much to
-//! construct. Prefer `Aes128Enc` when you never decrypt.
+//! construct. Prefer `Aes128Enc` when you never decrypt. Where the key
+//! length is only known at run time, [`AesEnc`], [`AesDec`], [`Aes`] and
+//! [`AesCtr`] take any of the three through [`KeyInit`].
+//!
+//! Where the CPU has no AES instructions, an x86_64 CPU with SSSE3 still
+//! runs a constant-time cipher on its byte shuffle. Failing that, `new`
+//! falls back to the portable T-table cipher, whose cache footprint
+//! depends on the key. Callers who would rather fail than leak use
+//! `try_new_constant_time`, which reports the missing CPU features
+//! instead, and callers who would rather run slowly use
+//! `new_constant_time`, which takes the bitsliced cipher there, constant
+//! time everywhere at about three quarters of the speed. Building with
+//! the `constant-time` feature makes that swap for `new` too.
+//!
+//! A build whose target already guarantees the instructions, such as one
+//! with `-C target-feature=+aes,+ssse3` or a `-C target-cpu` that has
+//! them, has no choice to make. The tiers it rules out are compiled out of
+//! dispatch, the probe is replaced by a constant, and each type is its one
+//! remaining implementation with nothing to branch on. A tier so ruled out
+//! is left uninhabited, with a constructor that never returns, which is
+//! why the dispatching code here and in [`ctr`] allows `unreachable_code`.
+//!
+//! # Round keys
+//!
+//! With the `hazmat` feature, every `Enc` and `Dec` type, here and under
+//! [`arch`], has `round_keys` and `from_round_keys`. They use one layout
+//! whatever an implementation keeps internally: the words `w[0]` to
+//! `w[4 * Nr + 3]` of the FIPS-197 key expansion, each as its four bytes
+//! in order. Round key `i` is bytes `16 * i` to `16 * i + 15`, XORed onto
+//! the state byte for byte, and the schedule starts with the key itself.
+//! That is 176, 208 or 240 bytes.
+//!
+//! `Dec` types use the same forward schedule, not the equivalent inverse
+//! cipher's: they export the one they were inverted from and invert the
+//! one they are given, so an `Enc` and a `Dec` keyed alike export the same
+//! bytes. Exported copies come wrapped in [`zeroize::Zeroizing`], and wipe
+//! themselves in turn.
+//!
+//! Nothing checks that a schedule handed to `from_round_keys` came from a
+//! key expansion. Any bytes define some cipher, and a schedule is exactly
+//! as secret as the key it came from.
 
 pub mod arch;
 pub mod ctr;
+mod implementation;
+pub(crate) mod kat;
+#[cfg(test)]
+mod testing;
 
-pub use ctr::{Aes128Ctr, Aes192Ctr, Aes256Ctr};
+pub use ctr::{Aes128Ctr, Aes192Ctr, Aes256Ctr, AesCtr};
+pub use implementation::{Decision, Implementation, Report, Verdict};
 
 use arch::portable::ttable;
 
+use zeroize::Zeroizing;
+
+use crate::rand::{RandError, RandomSource};
+use crate::cpu::{Feature, UnsupportedCpu};
+use crate::self_test;
 use crate::symmetric::block_cipher::{
-    BlockDecrypt, BlockEncrypt, InvalidKeyLength, KeyInit,
+    BlockDecrypt, BlockEncrypt, InvalidKeyLength, KeyInit, KeyInitError,
 };
 
 /// The AES block size in bytes. Identical for all three key sizes.
@@ -24,70 +74,447 @@ pub const BLOCK_SIZE: usize = ttable::BLOCK_SIZE;
 
 /// The accelerated implementations for this target, if it has any.
 ///
-/// Targets with no acceleration name the portable types here and report no
-/// support, so the dispatch below needs no target specific spelling: the
-/// accelerated arm is simply never taken.
+/// Tiers a target has nothing in, or that its build rules out, are named
+/// by [`refused`] types here, so the dispatch below needs no target
+/// specific spelling: those arms are simply never taken.
 #[cfg(target_arch = "x86_64")]
 mod accel {
-    pub use super::arch::x86_64::{aesni, vaes};
+    use super::Implementation;
+    use crate::cpu::Feature;
+
+    pub use super::arch::x86_64::vaes512;
+
+    #[cfg(not(all(
+        target_feature = "aes",
+        target_feature = "avx2",
+        target_feature = "vaes",
+        target_feature = "avx512f",
+        target_feature = "avx512vl"
+    )))]
+    pub use super::arch::x86_64::vaes;
+
+    /// The target guarantees the 512 bit tier, so this one is never
+    /// chosen.
+    #[cfg(all(
+        target_feature = "aes",
+        target_feature = "avx2",
+        target_feature = "vaes",
+        target_feature = "avx512f",
+        target_feature = "avx512vl"
+    ))]
+    pub(super) use super::refused as vaes;
+
+    #[cfg(not(all(
+        target_feature = "aes",
+        target_feature = "avx2",
+        target_feature = "vaes"
+    )))]
+    pub use super::arch::x86_64::aesni;
+
+    /// The target guarantees the vector tier, so this one is never chosen.
+    #[cfg(all(
+        target_feature = "aes",
+        target_feature = "avx2",
+        target_feature = "vaes"
+    ))]
+    pub(super) use super::refused as aesni;
+
+    #[cfg(not(all(target_feature = "aes", target_feature = "ssse3")))]
+    pub use super::arch::x86_64::vpaes;
+
+    /// The target guarantees an accelerated tier, so this one is never
+    /// chosen.
+    #[cfg(all(target_feature = "aes", target_feature = "ssse3"))]
+    pub(super) use super::refused as vpaes;
+
+    pub const WIDE: Implementation = Implementation::Vaes512;
+    pub const VECTOR: Implementation = Implementation::Vaes;
+    pub const ACCELERATED: Implementation = Implementation::AesNi;
+    pub const SIMD: Implementation = Implementation::Vpaes;
+
+    /// What the 512 bit tier needs: AVX-512 on top of the 256 bit one.
+    const WIDE_REQUIRED: &[Feature] = &[
+        Feature::Aes,
+        Feature::Avx2,
+        Feature::Vaes,
+        Feature::Avx512f,
+        Feature::Avx512vl,
+    ];
+
+    /// The tiers dispatch tries, widest first, and what each needs.
+    pub const TIERS: &[(Implementation, &[Feature])] = &[
+        (WIDE, WIDE_REQUIRED),
+        (VECTOR, &[Feature::Aes, Feature::Avx2, Feature::Vaes]),
+        (ACCELERATED, &[Feature::Aes]),
+        (SIMD, &[Feature::Ssse3]),
+    ];
+
+    /// The same for the counter kernels. AES-NI's byte swap with
+    /// `pshufb`, so they need SSSE3 too.
+    pub const CTR_TIERS: &[(Implementation, &[Feature])] = &[
+        (WIDE, WIDE_REQUIRED),
+        (VECTOR, &[Feature::Aes, Feature::Avx2, Feature::Vaes]),
+        (ACCELERATED, &[Feature::Aes, Feature::Ssse3]),
+        (SIMD, &[Feature::Ssse3]),
+    ];
+}
+
+#[cfg(all(target_arch = "x86", target_feature = "sse2"))]
+mod accel {
+    use super::Implementation;
+    use crate::cpu::Feature;
+
+    pub use super::arch::x86::aesni;
+
+    /// The stand-ins below have no values, so these are never reported.
+    pub const WIDE: Implementation = Implementation::TTable;
+    pub const VECTOR: Implementation = Implementation::TTable;
+    pub const ACCELERATED: Implementation = Implementation::AesNi;
+    pub const SIMD: Implementation = Implementation::TTable;
+
+    /// The tiers dispatch tries, widest first, and what each needs.
+    pub const TIERS: &[(Implementation, &[Feature])] =
+        &[(ACCELERATED, &[Feature::Aes])];
+
+    /// The same for the counter kernels, which byte swap with `pshufb`,
+    /// so they need SSSE3 too.
+    pub const CTR_TIERS: &[(Implementation, &[Feature])] =
+        &[(ACCELERATED, &[Feature::Aes, Feature::Ssse3])];
+
+    /// Stand-ins for the tiers this target has nothing in. They report
+    /// no support, so those arms are never taken.
+    pub(super) use super::refused as vaes;
+    pub(super) use super::refused as vaes512;
+    pub(super) use super::refused as vpaes;
 }
 
 #[cfg(target_arch = "aarch64")]
 mod accel {
+    use super::Implementation;
+    use crate::cpu::Feature;
+
     pub use super::arch::aarch64::armv8 as aesni;
 
-    /// A stand-in for the tier this target has nothing in. It reports no
-    /// support, so that arm is never taken.
-    pub mod vaes {
-        pub use super::super::arch::portable::ttable::{
-            Aes128Dec, Aes128Enc, Aes192Dec, Aes192Enc, Aes256Dec,
-            Aes256Enc,
-        };
+    /// The stand-ins below have no values, so these are never reported.
+    pub const WIDE: Implementation = Implementation::TTable;
+    pub const VECTOR: Implementation = Implementation::TTable;
+    pub const ACCELERATED: Implementation = Implementation::Armv8;
+    pub const SIMD: Implementation = Implementation::TTable;
 
-        pub fn supported() -> bool {
-            false
-        }
-    }
+    /// The tiers dispatch tries, widest first, and what each needs.
+    pub const TIERS: &[(Implementation, &[Feature])] =
+        &[(ACCELERATED, &[Feature::Aes])];
+
+    /// The same for the counter kernels.
+    pub const CTR_TIERS: &[(Implementation, &[Feature])] = TIERS;
+
+    /// Stand-ins for the tiers this target has nothing in. They report
+    /// no support, so those arms are never taken.
+    pub(super) use super::refused as vaes;
+    pub(super) use super::refused as vaes512;
+    pub(super) use super::refused as vpaes;
 }
 
-#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+#[cfg(not(any(
+    target_arch = "x86_64",
+    all(target_arch = "x86", target_feature = "sse2"),
+    target_arch = "aarch64"
+)))]
 mod accel {
+    use super::Implementation;
+    use crate::cpu::Feature;
+
+    /// Every tier is a stand-in with no values, so none is reported.
+    pub const WIDE: Implementation = Implementation::TTable;
+    pub const VECTOR: Implementation = Implementation::TTable;
+    pub const ACCELERATED: Implementation = Implementation::TTable;
+    pub const SIMD: Implementation = Implementation::TTable;
+
+    /// Dispatch tries nothing before the portable cipher.
+    pub const TIERS: &[(Implementation, &[Feature])] = &[];
+
+    /// Nor for the counter kernels.
+    pub const CTR_TIERS: &[(Implementation, &[Feature])] = &[];
+
     /// Stand-ins so the dispatch below needs no target specific spelling.
-    /// Both report no support, so neither arm is ever taken.
-    pub mod vaes {
-        pub use super::super::arch::portable::ttable::{
-            Aes128Dec, Aes128Enc, Aes192Dec, Aes192Enc, Aes256Dec,
-            Aes256Enc,
+    /// All report no support, so none of their arms is ever taken.
+    pub(super) use super::refused as aesni;
+    pub(super) use super::refused as vaes;
+    pub(super) use super::refused as vaes512;
+    pub(super) use super::refused as vpaes;
+}
+
+/// The implementations dispatch falls back to when the CPU has nothing
+/// better.
+///
+/// That is the T-table cipher, or with the `constant-time` feature the
+/// bitsliced one. Where the build target guarantees a tier above it, as
+/// any x86_64 target with SSSE3 does, the fallback is never needed, the
+/// [`refused`] types stand in, and no portable code is linked into
+/// dispatch.
+mod fallback {
+    use super::Implementation;
+
+    pub use chosen::*;
+
+    #[cfg(not(any(
+        feature = "constant-time",
+        all(target_arch = "x86_64", target_feature = "ssse3"),
+        all(
+            target_arch = "x86",
+            target_feature = "sse2",
+            target_feature = "aes",
+            target_feature = "ssse3"
+        ),
+        all(target_arch = "aarch64", target_feature = "aes"),
+    )))]
+    mod chosen {
+        pub use crate::symmetric::aes::arch::portable::ttable::{
+            Aes128Dec, Aes128Enc, Aes192Dec, Aes192Enc, Aes256Dec, Aes256Enc,
         };
 
-        pub fn supported() -> bool {
-            false
-        }
+        /// What `new_constant_time` uses instead.
+        pub use super::super::arch::portable::bitsliced as constant_time;
+    }
 
-        /// The counter kernels are no more available than the rest.
-        pub use self::supported as ctr_supported;
+    #[cfg(all(
+        feature = "constant-time",
+        not(any(
+            all(target_arch = "x86_64", target_feature = "ssse3"),
+            all(
+                target_arch = "x86",
+                target_feature = "sse2",
+                target_feature = "aes",
+                target_feature = "ssse3"
+            ),
+            all(target_arch = "aarch64", target_feature = "aes"),
+        ))
+    ))]
+    mod chosen {
+        pub use crate::symmetric::aes::arch::portable::bitsliced::{
+            Aes128Dec, Aes128Enc, Aes192Dec, Aes192Enc, Aes256Dec, Aes256Enc,
+        };
+
+        /// Nothing: the fallback is already constant time.
+        pub(in super::super) use super::super::refused as constant_time;
+    }
+
+    #[cfg(any(
+        all(target_arch = "x86_64", target_feature = "ssse3"),
+        all(
+            target_arch = "x86",
+            target_feature = "sse2",
+            target_feature = "aes",
+            target_feature = "ssse3"
+        ),
+        all(target_arch = "aarch64", target_feature = "aes"),
+    ))]
+    mod chosen {
+        pub use crate::symmetric::aes::refused::{
+            Aes128Dec, Aes128Enc, Aes192Dec, Aes192Enc, Aes256Dec, Aes256Enc,
+        };
+
+        /// Nothing: no fallback is ever needed.
+        pub(in super::super) use super::super::refused as constant_time;
+    }
+
+    /// Which implementation the fallback is, or would be if it were
+    /// needed.
+    #[cfg(not(feature = "constant-time"))]
+    pub const IMPLEMENTATION: Implementation = Implementation::TTable;
+    #[cfg(feature = "constant-time")]
+    pub const IMPLEMENTATION: Implementation = Implementation::Bitsliced;
+}
+
+/// Stand-ins for a tier that can never be chosen.
+///
+/// These have no values, so every arm that names them is unreachable and
+/// compiles away, and a `Backend` whose other tiers are all stand-ins is
+/// laid out as the one real implementation, with no discriminant to test.
+mod refused {
+    // The arms naming these are unreachable, so the compiler counts none
+    // of their methods as used; they exist only to type check. Which of
+    // them are named at all depends on the target.
+    #![allow(dead_code)]
+
+    #[cfg(feature = "hazmat")]
+    use zeroize::Zeroizing;
+
+    use super::BLOCK_SIZE;
+
+    /// There is nothing here to support.
+    pub fn supported() -> bool {
+        false
+    }
+
+    /// Nor any counter kernels.
+    pub fn ctr_supported() -> bool {
+        false
+    }
+
+    macro_rules! refused {
+        ($($name:ident),+) => {$(
+            pub enum $name {}
+
+            impl $name {
+                pub const PARALLEL_BLOCKS: usize = 1;
+
+                /// Only reachable through a `supported` that said yes,
+                /// and a stand-in's never does.
+                pub fn new<const N: usize>(_: &[u8; N]) -> Self {
+                    unreachable!("a refused tier was chosen")
+                }
+
+                pub fn encrypt(&self, _: &mut [u8]) -> usize {
+                    match *self {}
+                }
+
+                pub fn decrypt(&self, _: &mut [u8]) -> usize {
+                    match *self {}
+                }
+
+                pub fn encrypt_block(&self, _: &mut [u8; BLOCK_SIZE]) {
+                    match *self {}
+                }
+
+                pub fn decrypt_block(&self, _: &mut [u8; BLOCK_SIZE]) {
+                    match *self {}
+                }
+
+                pub fn ctr(
+                    &self,
+                    _: &mut [u8; BLOCK_SIZE],
+                    _: &mut [u8],
+                ) -> usize {
+                    match *self {}
+                }
+
+                #[cfg(feature = "hazmat")]
+                pub fn from_round_keys<const N: usize>(_: &[u8; N]) -> Self {
+                    unreachable!("a refused tier was chosen")
+                }
+
+                #[cfg(feature = "hazmat")]
+                pub fn round_keys<const N: usize>(
+                    &self,
+                ) -> Zeroizing<[u8; N]> {
+                    match *self {}
+                }
+            }
+        )+};
     }
 
-    pub use vaes as aesni;
+    refused!(
+        Aes128Enc, Aes128Dec, Aes192Enc, Aes192Dec, Aes256Enc, Aes256Dec
+    );
+
+    macro_rules! inverted {
+        ($($enc:ident => $dec:ident),+) => {$(
+            impl From<&$enc> for $dec {
+                fn from(enc: &$enc) -> Self {
+                    match *enc {}
+                }
+            }
+        )+};
+    }
+
+    inverted!(
+        Aes128Enc => Aes128Dec, Aes192Enc => Aes192Dec, Aes256Enc => Aes256Dec
+    );
+}
+
+/// Explain the block ciphers' dispatch on this machine.
+///
+/// Lists every implementation the `Enc`, `Dec` and combined types would
+/// try, in order, and what became of each: the one chosen, those it made
+/// unnecessary, and for those the CPU could not run, the features it
+/// lacked. [`ctr::dispatch_report`] does the same for counter mode.
+pub fn dispatch_report() -> Report {
+    Report::walk(accel::TIERS)
 }
 
 /// Which implementation a dispatching type chose.
 ///
 /// The choice is made once, when the key is expanded, and then it is a
 /// property of the value. Nothing re-examines the CPU per call.
-enum Backend<V, A, P> {
+enum Backend<W, V, A, S, P, C> {
+    Wide(W),
     Vector(V),
     Accelerated(A),
+    Simd(S),
     Portable(P),
+    /// The bitsliced cipher, where `new_constant_time` cannot use the
+    /// portable one because it is the T-table cipher.
+    ConstantTime(C),
+}
+
+/// The most blocks any of `widths` keeps in flight.
+const fn widest(widths: &[usize]) -> usize {
+    let mut most = 0;
+    let mut i = 0;
+    while i < widths.len() {
+        if widths[i] > most {
+            most = widths[i];
+        }
+        i += 1;
+    }
+    most
+}
+
+/// Why a caller was refused a constant-time implementation: it lacks what
+/// the narrowest of `tiers` needs.
+fn refusal(tiers: &[(Implementation, &[Feature])]) -> KeyInitError {
+    let needed = tiers.last().map_or(&[][..], |&(_, needs)| needs);
+    UnsupportedCpu::lacking(needed).into()
+}
+
+/// `generate` and `generate_from`, for a type whose `new` takes a key of
+/// `$key_size` bytes.
+macro_rules! generate {
+    ($key_size:expr) => {
+        /// Expand a fresh key from the operating system's generator.
+        ///
+        /// The key exists nowhere but in the schedule, so this suits
+        /// data that never leaves the process, or a key exported
+        /// later through `hazmat`.
+        ///
+        /// # Panics
+        ///
+        /// If the operating system cannot supply random bytes, or as
+        /// [`Self::new`] does.
+        #[cfg(target_os = "linux")]
+        pub fn generate() -> Self {
+            match Self::generate_from(&mut crate::rand::OsRandom) {
+                Ok(this) => this,
+                Err(e) => panic!("{e}"),
+            }
+        }
+
+        /// Expand a fresh key drawn from `source`.
+        ///
+        /// # Panics
+        ///
+        /// As [`Self::new`] does.
+        pub fn generate_from(
+            source: &mut impl RandomSource,
+        ) -> Result<Self, RandError> {
+            let mut key = Zeroizing::new([0u8; $key_size]);
+            source.fill(&mut key[..])?;
+            Ok(Self::new(&key))
+        }
+    };
 }
 
 macro_rules! define_dispatch {
     (
-        $name:ident, $vector:ty, $accel:ty, $portable:ty, $key_size:expr,
-        $op:ident, $op_block:ident, $tr:ident, $doc:expr
+        $name:ident, $wide:ty, $vector:ty, $accel:ty, $simd:ty,
+        $portable:ty, $ct:ty, $key_size:expr, $bytes:expr, $op:ident,
+        $op_block:ident, $tr:ident, $doc:expr
     ) => {
         #[doc = $doc]
-        pub struct $name(Backend<$vector, $accel, $portable>);
+        pub struct $name(
+            Backend<$wide, $vector, $accel, $simd, $portable, $ct>,
+        );
 
         impl $name {
             /// The key length in bytes.
@@ -98,81 +525,222 @@ macro_rules! define_dispatch {
             ///
             /// Use [`Self::parallel_blocks`] for the number the selected
             /// one actually uses; this is the figure to size a buffer by.
-            pub const PARALLEL_BLOCKS: usize =
-                <$vector>::PARALLEL_BLOCKS;
+            pub const PARALLEL_BLOCKS: usize = widest(&[
+                <$wide>::PARALLEL_BLOCKS,
+                <$vector>::PARALLEL_BLOCKS,
+                <$accel>::PARALLEL_BLOCKS,
+                <$simd>::PARALLEL_BLOCKS,
+                <$portable>::PARALLEL_BLOCKS,
+                <$ct>::PARALLEL_BLOCKS,
+            ]);
 
             /// Expand `key`, choosing an implementation for this CPU.
             ///
             /// Widest first: the vector kernels do the most work per
-            /// instruction, and fall back to the single block accelerated
-            /// ones for anything they cannot fill.
+            /// instruction, the 512 bit ones most of all, and each falls
+            /// back to the next narrower for anything it cannot fill.
+            /// Without AES instructions, a CPU with SSSE3 runs the vector
+            /// permute cipher. Where there is nothing else, the portable cipher
+            /// is used: the T-table cipher, or with the `constant-time`
+            /// feature the bitsliced one.
+            ///
+            /// # Panics
+            ///
+            /// If the library failed its [self tests](crate::self_test).
+            /// [`Self::try_new_constant_time`] reports that instead.
             pub fn new(key: &[u8; $key_size]) -> Self {
-                Self(if accel::vaes::supported() {
+                if let Err(e) = self_test::check() {
+                    panic!("{e}");
+                }
+                Self::accelerated(key).unwrap_or_else(|| {
+                    Self(Backend::Portable(<$portable>::new(key)))
+                })
+            }
+
+            /// Expand `key` on an implementation whose timing does not
+            /// depend on it, or say which CPU features that would need.
+            ///
+            /// Chooses exactly as [`Self::new`] does, except that it
+            /// never falls back to the T-table cipher. Fails with
+            /// [`KeyInitError::Cpu`] for want of anything else, which
+            /// with the `constant-time` feature never happens, and with
+            /// [`KeyInitError::SelfTest`] if the library failed its self
+            /// tests.
+            pub fn try_new_constant_time(
+                key: &[u8; $key_size],
+            ) -> Result<Self, KeyInitError> {
+                self_test::check()?;
+                if let Some(this) = Self::accelerated(key) {
+                    Ok(this)
+                } else if fallback::IMPLEMENTATION.is_constant_time() {
+                    Ok(Self(Backend::Portable(<$portable>::new(key))))
+                } else {
+                    Err(refusal(accel::TIERS))
+                }
+            }
+
+            /// Expand `key` on an implementation whose timing does not
+            /// depend on it, whatever the CPU.
+            ///
+            /// Where [`Self::try_new_constant_time`] would fail for want
+            /// of CPU features, this uses the bitsliced cipher instead,
+            /// at about three quarters of the T-table cipher's speed.
+            ///
+            /// # Panics
+            ///
+            /// If the library failed its [self tests](crate::self_test).
+            pub fn new_constant_time(key: &[u8; $key_size]) -> Self {
+                match Self::try_new_constant_time(key) {
+                    Ok(this) => this,
+                    Err(KeyInitError::SelfTest(e)) => panic!("{e}"),
+                    Err(_) => Self(Backend::ConstantTime(<$ct>::new(key))),
+                }
+            }
+
+            /// The widest tier above the portable ciphers that this CPU
+            /// runs, if there is one.
+            #[allow(unreachable_code)]
+            fn accelerated(key: &[u8; $key_size]) -> Option<Self> {
+                Some(Self(if accel::vaes512::supported() {
+                    Backend::Wide(<$wide>::new(key))
+                } else if accel::vaes::supported() {
                     Backend::Vector(<$vector>::new(key))
                 } else if accel::aesni::supported() {
                     Backend::Accelerated(<$accel>::new(key))
+                } else if accel::vpaes::supported() {
+                    Backend::Simd(<$simd>::new(key))
                 } else {
-                    Backend::Portable(<$portable>::new(key))
-                })
+                    return None;
+                }))
             }
 
+            generate!($key_size);
+
             #[doc = concat!(
                 stringify!($op),
                 " whole blocks in place, returning bytes consumed."
             )]
             pub fn $op(&self, data: &mut [u8]) -> usize {
                 match &self.0 {
+                    Backend::Wide(w) => w.$op(data),
                     Backend::Vector(v) => v.$op(data),
                     Backend::Accelerated(a) => a.$op(data),
+                    Backend::Simd(s) => s.$op(data),
                     Backend::Portable(p) => p.$op(data),
+                    Backend::ConstantTime(c) => c.$op(data),
                 }
             }
 
             #[doc = concat!(stringify!($op), " exactly one block in place.")]
             pub fn $op_block(&self, block: &mut [u8; BLOCK_SIZE]) {
                 match &self.0 {
+                    Backend::Wide(w) => w.$op_block(block),
                     Backend::Vector(v) => v.$op_block(block),
                     Backend::Accelerated(a) => a.$op_block(block),
+                    Backend::Simd(s) => s.$op_block(block),
                     Backend::Portable(p) => p.$op_block(block),
+                    Backend::ConstantTime(c) => c.$op_block(block),
                 }
             }
 
             /// How many blocks the chosen implementation keeps in flight.
             pub fn parallel_blocks(&self) -> usize {
                 match &self.0 {
+                    Backend::Wide(_) => <$wide>::PARALLEL_BLOCKS,
                     Backend::Vector(_) => <$vector>::PARALLEL_BLOCKS,
                     Backend::Accelerated(_) => <$accel>::PARALLEL_BLOCKS,
+                    Backend::Simd(_) => <$simd>::PARALLEL_BLOCKS,
                     Backend::Portable(_) => <$portable>::PARALLEL_BLOCKS,
+                    Backend::ConstantTime(_) => <$ct>::PARALLEL_BLOCKS,
                 }
             }
 
             /// Whether this value is using an accelerated implementation.
             pub fn is_accelerated(&self) -> bool {
-                !matches!(self.0, Backend::Portable(_))
+                self.backend().is_accelerated()
             }
 
-            /// The name of the implementation this value chose.
+            /// The tier of the implementation this value chose.
+            ///
+            /// [`Self::backend`] names the implementation itself.
             pub fn implementation(&self) -> &'static str {
+                self.backend().tier()
+            }
+
+            /// The implementation this value chose.
+            pub fn backend(&self) -> Implementation {
+                match &self.0 {
+                    Backend::Wide(_) => accel::WIDE,
+                    Backend::Vector(_) => accel::VECTOR,
+                    Backend::Accelerated(_) => accel::ACCELERATED,
+                    Backend::Simd(_) => accel::SIMD,
+                    Backend::Portable(_) => fallback::IMPLEMENTATION,
+                    Backend::ConstantTime(_) => Implementation::Bitsliced,
+                }
+            }
+        }
+
+        #[cfg(feature = "hazmat")]
+        impl $name {
+            /// Copy out the round keys, in the [canonical layout].
+            ///
+            /// [canonical layout]: self#round-keys
+            pub fn round_keys(&self) -> Zeroizing<[u8; $bytes]> {
                 match &self.0 {
-                    Backend::Vector(_) => "vector",
-                    Backend::Accelerated(_) => "accelerated",
-                    Backend::Portable(_) => "portable",
+                    Backend::Wide(w) => w.round_keys(),
+                    Backend::Vector(v) => v.round_keys(),
+                    Backend::Accelerated(a) => a.round_keys(),
+                    Backend::Simd(s) => s.round_keys(),
+                    Backend::Portable(p) => p.round_keys(),
+                    Backend::ConstantTime(c) => c.round_keys(),
+                }
+            }
+
+            /// Take round keys in the canonical layout, choosing an
+            /// implementation exactly as [`Self::new`] does.
+            ///
+            /// Nothing checks that they came from a key expansion.
+            ///
+            /// # Panics
+            ///
+            /// As [`Self::new`].
+            #[allow(unreachable_code)]
+            pub fn from_round_keys(round_keys: &[u8; $bytes]) -> Self {
+                let rk = round_keys;
+                if let Err(e) = self_test::check() {
+                    panic!("{e}");
+                }
+                if accel::vaes512::supported() {
+                    Self(Backend::Wide(<$wide>::from_round_keys(rk)))
+                } else if accel::vaes::supported() {
+                    Self(Backend::Vector(<$vector>::from_round_keys(rk)))
+                } else if accel::aesni::supported() {
+                    Self(Backend::Accelerated(<$accel>::from_round_keys(rk)))
+                } else if accel::vpaes::supported() {
+                    Self(Backend::Simd(<$simd>::from_round_keys(rk)))
+                } else {
+                    Self(Backend::Portable(<$portable>::from_round_keys(rk)))
                 }
             }
         }
 
         impl KeyInit for $name {
-            fn try_new(key: &[u8]) -> Result<Self, InvalidKeyLength> {
+            type Error = KeyInitError;
+
+            // Checked first, so that a failed self test is an error
+            // here rather than a panic in `new`.
+            fn try_new(key: &[u8]) -> Result<Self, KeyInitError> {
                 let key: &[u8; $key_size] = key
                     .try_into()
                     .map_err(|_| InvalidKeyLength { got: key.len() })?;
+                self_test::check()?;
                 Ok(Self::new(key))
             }
         }
 
         impl $tr for $name {
             const BLOCK_SIZE: usize = BLOCK_SIZE;
-            const PARALLEL_BLOCKS: usize = <$vector>::PARALLEL_BLOCKS;
+            const PARALLEL_BLOCKS: usize = $name::PARALLEL_BLOCKS;
 
             fn $op(&self, data: &mut [u8]) -> usize {
                 $name::$op(self, data)
@@ -192,43 +760,80 @@ macro_rules! define_dispatch {
 }
 
 define_dispatch!(
-    Aes128Enc, accel::vaes::Aes128Enc, accel::aesni::Aes128Enc,
-    ttable::Aes128Enc, 16, encrypt, encrypt_block, BlockEncrypt,
+    Aes128Enc, accel::vaes512::Aes128Enc, accel::vaes::Aes128Enc,
+    accel::aesni::Aes128Enc, accel::vpaes::Aes128Enc, fallback::Aes128Enc,
+    fallback::constant_time::Aes128Enc,
+    16, 176, encrypt, encrypt_block, BlockEncrypt,
     "AES-128 encryption only."
 );
 define_dispatch!(
-    Aes128Dec, accel::vaes::Aes128Dec, accel::aesni::Aes128Dec,
-    ttable::Aes128Dec, 16, decrypt, decrypt_block, BlockDecrypt,
+    Aes128Dec, accel::vaes512::Aes128Dec, accel::vaes::Aes128Dec,
+    accel::aesni::Aes128Dec, accel::vpaes::Aes128Dec, fallback::Aes128Dec,
+    fallback::constant_time::Aes128Dec,
+    16, 176, decrypt, decrypt_block, BlockDecrypt,
     "AES-128 decryption only."
 );
 define_dispatch!(
-    Aes192Enc, accel::vaes::Aes192Enc, accel::aesni::Aes192Enc,
-    ttable::Aes192Enc, 24, encrypt, encrypt_block, BlockEncrypt,
+    Aes192Enc, accel::vaes512::Aes192Enc, accel::vaes::Aes192Enc,
+    accel::aesni::Aes192Enc, accel::vpaes::Aes192Enc, fallback::Aes192Enc,
+    fallback::constant_time::Aes192Enc,
+    24, 208, encrypt, encrypt_block, BlockEncrypt,
     "AES-192 encryption only."
 );
 define_dispatch!(
-    Aes192Dec, accel::vaes::Aes192Dec, accel::aesni::Aes192Dec,
-    ttable::Aes192Dec, 24, decrypt, decrypt_block, BlockDecrypt,
+    Aes192Dec, accel::vaes512::Aes192Dec, accel::vaes::Aes192Dec,
+    accel::aesni::Aes192Dec, accel::vpaes::Aes192Dec, fallback::Aes192Dec,
+    fallback::constant_time::Aes192Dec,
+    24, 208, decrypt, decrypt_block, BlockDecrypt,
     "AES-192 decryption only."
 );
 define_dispatch!(
-    Aes256Enc, accel::vaes::Aes256Enc, accel::aesni::Aes256Enc,
-    ttable::Aes256Enc, 32, encrypt, encrypt_block, BlockEncrypt,
+    Aes256Enc, accel::vaes512::Aes256Enc, accel::vaes::Aes256Enc,
+    accel::aesni::Aes256Enc, accel::vpaes::Aes256Enc, fallback::Aes256Enc,
+    fallback::constant_time::Aes256Enc,
+    32, 240, encrypt, encrypt_block, BlockEncrypt,
     "AES-256 encryption only."
 );
 define_dispatch!(
-    Aes256Dec, accel::vaes::Aes256Dec, accel::aesni::Aes256Dec,
-    ttable::Aes256Dec, 32, decrypt, decrypt_block, BlockDecrypt,
+    Aes256Dec, accel::vaes512::Aes256Dec, accel::vaes::Aes256Dec,
+    accel::aesni::Aes256Dec, accel::vpaes::Aes256Dec, fallback::Aes256Dec,
+    fallback::constant_time::Aes256Dec,
+    32, 240, decrypt, decrypt_block, BlockDecrypt,
     "AES-256 decryption only."
 );
 
+macro_rules! define_invert {
+    ($enc:ident, $dec:ident) => {
+        /// Invert an existing encryption schedule, on the implementation
+        /// it already chose, so the key itself need not be kept around in
+        /// case decryption is wanted later.
+        impl From<&$enc> for $dec {
+            #[allow(unreachable_code)]
+            fn from(enc: &$enc) -> Self {
+                Self(match &enc.0 {
+                    Backend::Wide(w) => Backend::Wide(w.into()),
+                    Backend::Vector(v) => Backend::Vector(v.into()),
+                    Backend::Accelerated(a) => Backend::Accelerated(a.into()),
+                    Backend::Simd(s) => Backend::Simd(s.into()),
+                    Backend::Portable(p) => Backend::Portable(p.into()),
+                    Backend::ConstantTime(c) => Backend::ConstantTime(c.into()),
+                })
+            }
+        }
+    };
+}
+
+define_invert!(Aes128Enc, Aes128Dec);
+define_invert!(Aes192Enc, Aes192Dec);
+define_invert!(Aes256Enc, Aes256Dec);
+
 macro_rules! define_both {
     ($name:ident, $enc:ident, $dec:ident, $key_size:expr, $doc:expr) => {
         #[doc = $doc]
         ///
-        /// Builds both key schedules, so it costs about twice what a
-        /// one-direction type does to construct. Use it when you genuinely
-        /// need both.
+        /// Holds both key schedules, the decryption one inverted from the
+        /// other, so it costs more than a one-direction type does to
+        /// construct. Use it when you genuinely need both.
         pub struct $name {
             enc: $enc,
             dec: $dec,
@@ -241,10 +846,39 @@ macro_rules! define_both {
             pub const BLOCK_SIZE: usize = BLOCK_SIZE;
 
             /// Expand `key` into both schedules.
+            ///
+            /// # Panics
+            ///
+            /// As the one-direction types' `new` does.
             pub fn new(key: &[u8; $key_size]) -> Self {
-                Self { enc: $enc::new(key), dec: $dec::new(key) }
+                let enc = $enc::new(key);
+                Self { dec: $dec::from(&enc), enc }
             }
 
+            /// Expand `key` into both schedules on an implementation
+            /// whose timing does not depend on it, or say why not, as
+            /// the one-direction types do.
+            pub fn try_new_constant_time(
+                key: &[u8; $key_size],
+            ) -> Result<Self, KeyInitError> {
+                let enc = $enc::try_new_constant_time(key)?;
+                Ok(Self { dec: $dec::from(&enc), enc })
+            }
+
+            /// Expand `key` into both schedules on an implementation
+            /// whose timing does not depend on it, whatever the CPU, as
+            /// the one-direction types do.
+            ///
+            /// # Panics
+            ///
+            /// As the one-direction types' `new_constant_time` does.
+            pub fn new_constant_time(key: &[u8; $key_size]) -> Self {
+                let enc = $enc::new_constant_time(key);
+                Self { dec: $dec::from(&enc), enc }
+            }
+
+            generate!($key_size);
+
             /// Encrypt whole blocks in place, returning bytes consumed.
             pub fn encrypt(&self, data: &mut [u8]) -> usize {
                 self.enc.encrypt(data)
@@ -279,13 +913,23 @@ macro_rules! define_both {
             pub fn is_accelerated(&self) -> bool {
                 self.enc.is_accelerated()
             }
+
+            /// The implementation this value chose, for both halves.
+            pub fn backend(&self) -> Implementation {
+                self.enc.backend()
+            }
         }
 
         impl KeyInit for $name {
-            fn try_new(key: &[u8]) -> Result<Self, InvalidKeyLength> {
+            type Error = KeyInitError;
+
+            // Checked first, so that a failed self test is an error
+            // here rather than a panic in `new`.
+            fn try_new(key: &[u8]) -> Result<Self, KeyInitError> {
                 let key: &[u8; $key_size] = key
                     .try_into()
                     .map_err(|_| InvalidKeyLength { got: key.len() })?;
+                self_test::check()?;
                 Ok(Self::new(key))
             }
         }
@@ -323,6 +967,244 @@ define_both!(Aes128, Aes128Enc, Aes128Dec, 16, "AES-128, both directions.");
 define_both!(Aes192, Aes192Enc, Aes192Dec, 24, "AES-192, both directions.");
 define_both!(Aes256, Aes256Enc, Aes256Dec, 32, "AES-256, both directions.");
 
+/// Which key size a key-size-agnostic type was given.
+///
+/// [`Backend`] one level up: the length of the key chooses once, and
+/// every call then dispatches on the key size and the backend together.
+enum KeySize<S, M, L> {
+    Aes128(S),
+    Aes192(M),
+    Aes256(L),
+}
+
+/// Run `$body` on whichever key size `$value` holds, bound to `$c`.
+macro_rules! by_key_size {
+    ($value:expr, $c:ident => $body:expr) => {
+        match $value {
+            KeySize::Aes128($c) => $body,
+            KeySize::Aes192($c) => $body,
+            KeySize::Aes256($c) => $body,
+        }
+    };
+}
+use by_key_size;
+
+macro_rules! define_any {
+    (
+        $name:ident, $a128:ident, $a192:ident, $a256:ident,
+        $op:ident, $op_block:ident, $tr:ident, $doc:expr
+    ) => {
+        #[doc = $doc]
+        ///
+        /// Keyed through [`KeyInit`] with any AES key length, for callers
+        /// who only learn the length at run time. The length chooses the
+        /// key size and the CPU the implementation, each exactly once.
+        pub struct $name(KeySize<$a128, $a192, $a256>);
+
+        impl $name {
+            /// The block length in bytes.
+            pub const BLOCK_SIZE: usize = BLOCK_SIZE;
+            /// The most blocks any implementation here keeps in flight.
+            pub const PARALLEL_BLOCKS: usize = widest(&[
+                $a128::PARALLEL_BLOCKS,
+                $a192::PARALLEL_BLOCKS,
+                $a256::PARALLEL_BLOCKS,
+            ]);
+
+            /// The key length in bytes: 16, 24 or 32.
+            pub fn key_size(&self) -> usize {
+                match &self.0 {
+                    KeySize::Aes128(_) => $a128::KEY_SIZE,
+                    KeySize::Aes192(_) => $a192::KEY_SIZE,
+                    KeySize::Aes256(_) => $a256::KEY_SIZE,
+                }
+            }
+
+            #[doc = concat!(
+                stringify!($op),
+                " whole blocks in place, returning bytes consumed."
+            )]
+            pub fn $op(&self, data: &mut [u8]) -> usize {
+                by_key_size!(&self.0, c => c.$op(data))
+            }
+
+            #[doc = concat!(stringify!($op), " exactly one block in place.")]
+            pub fn $op_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+                by_key_size!(&self.0, c => c.$op_block(block))
+            }
+
+            /// How many blocks the chosen implementation keeps in flight.
+            pub fn parallel_blocks(&self) -> usize {
+                by_key_size!(&self.0, c => c.parallel_blocks())
+            }
+
+            /// Whether this value is using an accelerated implementation.
+            pub fn is_accelerated(&self) -> bool {
+                by_key_size!(&self.0, c => c.is_accelerated())
+            }
+
+            /// The tier of the implementation this value chose.
+            pub fn implementation(&self) -> &'static str {
+                by_key_size!(&self.0, c => c.implementation())
+            }
+
+            /// The implementation this value chose.
+            pub fn backend(&self) -> Implementation {
+                by_key_size!(&self.0, c => c.backend())
+            }
+        }
+
+        impl KeyInit for $name {
+            type Error = KeyInitError;
+
+            fn try_new(key: &[u8]) -> Result<Self, KeyInitError> {
+                Ok(Self(match key.len() {
+                    16 => KeySize::Aes128(<$a128 as KeyInit>::try_new(key)?),
+                    24 => KeySize::Aes192(<$a192 as KeyInit>::try_new(key)?),
+                    32 => KeySize::Aes256(<$a256 as KeyInit>::try_new(key)?),
+                    got => return Err(InvalidKeyLength { got }.into()),
+                }))
+            }
+        }
+
+        impl $tr for $name {
+            const BLOCK_SIZE: usize = BLOCK_SIZE;
+            const PARALLEL_BLOCKS: usize = $name::PARALLEL_BLOCKS;
+
+            fn $op(&self, data: &mut [u8]) -> usize {
+                $name::$op(self, data)
+            }
+        }
+
+        impl core::fmt::Debug for $name {
+            fn fmt(
+                &self,
+                f: &mut core::fmt::Formatter<'_>,
+            ) -> core::fmt::Result {
+                // The key size is no secret; the round keys are.
+                f.debug_struct(stringify!($name))
+                    .field("key_size", &self.key_size())
+                    .finish_non_exhaustive()
+            }
+        }
+    };
+}
+
+define_any!(
+    AesEnc, Aes128Enc, Aes192Enc, Aes256Enc, encrypt, encrypt_block,
+    BlockEncrypt, "AES encryption only, at whichever key size it is given."
+);
+define_any!(
+    AesDec, Aes128Dec, Aes192Dec, Aes256Dec, decrypt, decrypt_block,
+    BlockDecrypt, "AES decryption only, at whichever key size it is given."
+);
+
+/// Invert an existing encryption schedule, at its key size and on the
+/// implementation it already chose.
+impl From<&AesEnc> for AesDec {
+    fn from(enc: &AesEnc) -> Self {
+        Self(match &enc.0 {
+            KeySize::Aes128(c) => KeySize::Aes128(c.into()),
+            KeySize::Aes192(c) => KeySize::Aes192(c.into()),
+            KeySize::Aes256(c) => KeySize::Aes256(c.into()),
+        })
+    }
+}
+
+/// AES, both directions, at whichever key size it is given.
+///
+/// Holds both key schedules, like the sized combined types. Keyed
+/// through [`KeyInit`].
+pub struct Aes {
+    enc: AesEnc,
+    dec: AesDec,
+}
+
+impl Aes {
+    /// The block length in bytes.
+    pub const BLOCK_SIZE: usize = BLOCK_SIZE;
+
+    /// The key length in bytes: 16, 24 or 32.
+    pub fn key_size(&self) -> usize {
+        self.enc.key_size()
+    }
+
+    /// Encrypt whole blocks in place, returning bytes consumed.
+    pub fn encrypt(&self, data: &mut [u8]) -> usize {
+        self.enc.encrypt(data)
+    }
+
+    /// Decrypt whole blocks in place, returning bytes consumed.
+    pub fn decrypt(&self, data: &mut [u8]) -> usize {
+        self.dec.decrypt(data)
+    }
+
+    /// Encrypt exactly one block in place.
+    pub fn encrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+        self.enc.encrypt_block(block);
+    }
+
+    /// Decrypt exactly one block in place.
+    pub fn decrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+        self.dec.decrypt_block(block);
+    }
+
+    /// Borrow just the encryption half.
+    pub fn encryptor(&self) -> &AesEnc {
+        &self.enc
+    }
+
+    /// Borrow just the decryption half.
+    pub fn decryptor(&self) -> &AesDec {
+        &self.dec
+    }
+
+    /// Whether this value is using an accelerated implementation.
+    pub fn is_accelerated(&self) -> bool {
+        self.enc.is_accelerated()
+    }
+
+    /// The implementation this value chose, for both halves.
+    pub fn backend(&self) -> Implementation {
+        self.enc.backend()
+    }
+}
+
+impl KeyInit for Aes {
+    type Error = KeyInitError;
+
+    fn try_new(key: &[u8]) -> Result<Self, KeyInitError> {
+        let enc = AesEnc::try_new(key)?;
+        Ok(Self { dec: AesDec::from(&enc), enc })
+    }
+}
+
+impl BlockEncrypt for Aes {
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+    const PARALLEL_BLOCKS: usize = AesEnc::PARALLEL_BLOCKS;
+
+    fn encrypt(&self, data: &mut [u8]) -> usize {
+        self.enc.encrypt(data)
+    }
+}
+
+impl BlockDecrypt for Aes {
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+    const PARALLEL_BLOCKS: usize = AesDec::PARALLEL_BLOCKS;
+
+    fn decrypt(&self, data: &mut [u8]) -> usize {
+        self.dec.decrypt(data)
+    }
+}
+
+impl core::fmt::Debug for Aes {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Aes")
+            .field("key_size", &self.key_size())
+            .finish_non_exhaustive()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -363,21 +1245,150 @@ mod tests {
         assert_eq!(combined, plaintext);
     }
 
+    /// An inverted schedule stays on the implementation the encryption
+    /// one chose and decrypts exactly as one expanded from the key does.
+    #[test]
+    fn inverting_matches_expanding() {
+        let key: Vec<u8> = (0..32u8).map(|i| i.wrapping_mul(0x9d)).collect();
+        let ciphertext: Vec<u8> = (0..16u8 * 9).collect();
+
+        let enc = Aes256Enc::new(key[..].try_into().unwrap());
+        let inverted = Aes256Dec::from(&enc);
+        assert_eq!(inverted.backend(), enc.backend());
+        let mut ours = ciphertext.clone();
+        inverted.decrypt(&mut ours);
+        let mut theirs = ciphertext.clone();
+        Aes256Dec::new(key[..].try_into().unwrap()).decrypt(&mut theirs);
+        assert_eq!(ours, theirs);
+
+        for len in [16, 24, 32] {
+            let enc = AesEnc::try_new(&key[..len]).unwrap();
+            let inverted = AesDec::from(&enc);
+            assert_eq!(inverted.key_size(), len);
+            let mut ours = ciphertext.clone();
+            inverted.decrypt(&mut ours);
+            let mut theirs = ciphertext.clone();
+            AesDec::try_new(&key[..len]).unwrap().decrypt(&mut theirs);
+            assert_eq!(ours, theirs, "{len} byte key");
+        }
+    }
+
+    /// Whichever implementation dispatch chose, it exports the portable
+    /// one's bytes and loads them back into a working cipher.
+    #[cfg(feature = "hazmat")]
+    #[test]
+    fn round_keys_are_the_same_on_every_implementation() {
+        let key = [0x2bu8; 24];
+        let rk = ttable::Aes192Enc::new(&key).round_keys();
+        assert_eq!(*Aes192Enc::new(&key).round_keys(), *rk);
+        assert_eq!(*Aes192Dec::new(&key).round_keys(), *rk);
+
+        let plaintext: Vec<u8> = (0..16u8 * 9).collect();
+        let mut loaded = plaintext.clone();
+        let enc = Aes192Enc::from_round_keys(&rk);
+        assert_eq!(enc.backend(), Aes192Enc::new(&key).backend());
+        enc.encrypt(&mut loaded);
+        let mut expanded = plaintext.clone();
+        Aes192Enc::new(&key).encrypt(&mut expanded);
+        assert_eq!(loaded, expanded);
+        Aes192Dec::from_round_keys(&rk).decrypt(&mut loaded);
+        assert_eq!(loaded, plaintext);
+    }
+
+    /// The length picks the key size, and each key size then behaves
+    /// exactly as its sized type does.
+    #[test]
+    fn any_key_size_matches_the_sized_types() {
+        let key: Vec<u8> = (0..32u8).map(|i| i.wrapping_mul(0x9d)).collect();
+        let plaintext: Vec<u8> = (0..16u8 * 7).collect();
+        for len in [16, 24, 32] {
+            let key = &key[..len];
+            let mut any = plaintext.clone();
+            let enc = AesEnc::try_new(key).unwrap();
+            assert_eq!(enc.key_size(), len);
+            enc.encrypt(&mut any);
+
+            let mut sized = plaintext.clone();
+            match len {
+                16 => Aes128Enc::try_new(key).unwrap().encrypt(&mut sized),
+                24 => Aes192Enc::try_new(key).unwrap().encrypt(&mut sized),
+                _ => Aes256Enc::try_new(key).unwrap().encrypt(&mut sized),
+            };
+            assert_eq!(any, sized, "{len} byte key");
+
+            AesDec::try_new(key).unwrap().decrypt(&mut any);
+            assert_eq!(any, plaintext);
+
+            let both = Aes::try_new(key).unwrap();
+            assert_eq!(both.key_size(), len);
+            both.encrypt(&mut any);
+            assert_eq!(any, sized);
+            both.decrypt(&mut any);
+            assert_eq!(any, plaintext);
+        }
+    }
+
+    #[test]
+    fn any_key_size_refuses_other_lengths() {
+        for len in [0, 15, 17, 20, 31, 33, 64] {
+            let key = vec![0u8; len];
+            assert_eq!(
+                AesEnc::try_new(&key).unwrap_err(),
+                KeyInitError::Key(InvalidKeyLength { got: len })
+            );
+            assert!(AesDec::try_new(&key).is_err());
+            assert!(Aes::try_new(&key).is_err());
+        }
+    }
+
+    /// The generic modes take the agnostic types as they take any other
+    /// block cipher, which is the point of implementing the traits.
+    #[test]
+    fn any_key_size_drives_the_generic_mode() {
+        use crate::symmetric::Ctr;
+        let iv = [0x24u8; 16];
+        let mut generic = [0x5au8; 45];
+        Ctr::try_new(AesEnc::try_new(&[7u8; 24]).unwrap(), &iv)
+            .unwrap()
+            .apply_keystream(&mut generic);
+        let mut fused = [0x5au8; 45];
+        Aes192Ctr::new(&[7u8; 24], &iv).apply_keystream(&mut fused);
+        assert_eq!(generic, fused);
+    }
+
+    #[test]
+    fn any_key_size_debug_shows_only_the_size() {
+        let aes = Aes::try_new(&[0x11u8; 32]).unwrap();
+        assert_eq!(format!("{aes:?}"), "Aes { key_size: 32, .. }");
+        let enc = AesEnc::try_new(&[0x11u8; 16]).unwrap();
+        assert_eq!(format!("{enc:?}"), "AesEnc { key_size: 16, .. }");
+    }
+
     /// What this target's accelerated tier is, if it has one.
     fn accelerated_here() -> bool {
         accel::aesni::supported()
     }
 
+    /// What this target's 512 bit tier is, if it has one.
+    fn wide_here() -> bool {
+        accel::vaes512::supported()
+    }
+
     /// What this target's vector tier is, if it has one.
     fn vector_here() -> bool {
         accel::vaes::supported()
     }
 
+    /// What this target's SIMD tier is, if it has one.
+    fn simd_here() -> bool {
+        accel::vpaes::supported()
+    }
+
     /// A machine with the instructions must actually be using them. Without
     /// this a silent fall back would look exactly like success.
     #[test]
     fn uses_acceleration_when_the_cpu_has_it() {
-        let expected = accelerated_here() || vector_here();
+        let expected = accelerated_here() || vector_here() || wide_here();
         assert_eq!(Aes128Enc::new(&[0u8; 16]).is_accelerated(), expected);
         assert_eq!(Aes192Enc::new(&[0u8; 24]).is_accelerated(), expected);
         assert_eq!(Aes256Dec::new(&[0u8; 32]).is_accelerated(), expected);
@@ -388,10 +1399,14 @@ mod tests {
     /// dispatch order is wrong and nobody would notice.
     #[test]
     fn picks_the_widest_implementation_available() {
-        let expected = if vector_here() {
+        let expected = if wide_here() {
+            "wide"
+        } else if vector_here() {
             "vector"
         } else if accelerated_here() {
             "accelerated"
+        } else if simd_here() {
+            "simd"
         } else {
             "portable"
         };
@@ -399,14 +1414,196 @@ mod tests {
         assert_eq!(Aes256Dec::new(&[0u8; 32]).implementation(), expected);
     }
 
+    /// Constant-time construction succeeds exactly where `new` chooses a
+    /// constant-time tier, and otherwise names what the CPU lacks.
+    #[test]
+    fn constant_time_construction_refuses_only_the_t_tables() {
+        let key = [0x2bu8; 16];
+        let chosen = Aes128Enc::new(&key).backend();
+        match Aes128Enc::try_new_constant_time(&key) {
+            Ok(aes) => assert_eq!(aes.backend(), chosen),
+            Err(e) => {
+                assert!(!chosen.is_constant_time());
+                let KeyInitError::Cpu(e) = e else {
+                    panic!("refused for a reason other than the CPU: {e}")
+                };
+                let missing: Vec<_> = e.missing().collect();
+                let needed = accel::TIERS.last().map_or(&[][..], |t| t.1);
+                assert_eq!(
+                    missing, needed,
+                    "everything the narrowest tier needs is missing"
+                );
+            }
+        }
+        assert_eq!(
+            Aes256::try_new_constant_time(&[0u8; 32]).is_ok(),
+            chosen.is_constant_time()
+        );
+    }
+
+    /// `new_constant_time` takes whatever `try_new_constant_time` would,
+    /// and the bitsliced cipher where that would fail.
+    #[test]
+    fn constant_time_construction_replaces_only_the_t_tables() {
+        let key = [0x2bu8; 16];
+        let aes = Aes128Enc::new_constant_time(&key);
+        let chosen = Aes128Enc::new(&key).backend();
+        if chosen.is_constant_time() {
+            assert_eq!(aes.backend(), chosen);
+        } else {
+            assert_eq!(aes.backend(), Implementation::Bitsliced);
+        }
+
+        let mut ours = [0x5au8; 16 * 9];
+        let mut theirs = ours;
+        aes.encrypt(&mut ours);
+        Aes128Enc::new(&key).encrypt(&mut theirs);
+        assert_eq!(ours, theirs);
+
+        let both = Aes256::new_constant_time(&[0u8; 32]);
+        let mut block = [0u8; 16];
+        both.encrypt(&mut block);
+        both.decrypt(&mut block);
+        assert_eq!(block, [0u8; 16]);
+    }
+
+    /// The report is worked out separately from dispatch, so it has to
+    /// be checked against what dispatch actually does.
+    #[test]
+    fn report_names_what_dispatch_chose() {
+        let chosen = dispatch_report().chosen();
+        assert_eq!(chosen, Some(Aes128Enc::new(&[0u8; 16]).backend()));
+        assert_eq!(chosen, Some(Aes256::new(&[0u8; 32]).backend()));
+        assert_eq!(
+            dispatch_report().decisions().last().map(|d| d.implementation()),
+            Some(fallback::IMPLEMENTATION)
+        );
+    }
+
+    #[test]
+    fn backend_agrees_with_the_tier() {
+        let aes = Aes192Dec::new(&[0u8; 24]);
+        assert_eq!(aes.backend().tier(), aes.implementation());
+        assert_eq!(aes.backend().is_accelerated(), aes.is_accelerated());
+        assert!(!aes.is_accelerated() || aes.backend().is_constant_time());
+    }
+
+    /// Where the build target guarantees the widest tier, the value is
+    /// that implementation alone: no discriminant, so nothing to branch on.
+    #[test]
+    fn a_guaranteed_tier_leaves_nothing_to_dispatch() {
+        use core::mem::size_of;
+        if cfg!(all(
+            target_arch = "x86_64",
+            target_feature = "aes",
+            target_feature = "avx2",
+            target_feature = "vaes",
+            target_feature = "avx512f",
+            target_feature = "avx512vl"
+        )) {
+            assert_eq!(
+                size_of::<Aes128Enc>(),
+                size_of::<accel::vaes512::Aes128Enc>()
+            );
+        }
+        if cfg!(all(target_arch = "aarch64", target_feature = "aes")) {
+            assert_eq!(
+                size_of::<Aes256Dec>(),
+                size_of::<accel::aesni::Aes256Dec>()
+            );
+        }
+        if cfg!(all(
+            target_arch = "x86",
+            target_feature = "sse2",
+            target_feature = "aes",
+            target_feature = "ssse3"
+        )) {
+            assert_eq!(
+                size_of::<Aes128Enc>(),
+                size_of::<accel::aesni::Aes128Enc>()
+            );
+        }
+        if cfg!(not(any(
+            target_arch = "x86_64",
+            all(target_arch = "x86", target_feature = "sse2"),
+            target_arch = "aarch64"
+        ))) {
+            // A T-table fallback keeps the bitsliced cipher beside it for
+            // `new_constant_time`, and so a discriminant.
+            let expected = if fallback::IMPLEMENTATION.is_constant_time() {
+                size_of::<fallback::Aes192Enc>()
+            } else {
+                size_of::<
+                    Result<
+                        fallback::Aes192Enc,
+                        fallback::constant_time::Aes192Enc,
+                    >,
+                >()
+            };
+            assert_eq!(size_of::<Aes192Enc>(), expected);
+        }
+    }
+
     #[test]
     fn parallel_blocks_reports_the_chosen_implementation() {
         let aes = Aes128Enc::new(&[0u8; 16]);
         let expected = match aes.implementation() {
+            "wide" => accel::vaes512::Aes128Enc::PARALLEL_BLOCKS,
             "vector" => accel::vaes::Aes128Enc::PARALLEL_BLOCKS,
             "accelerated" => accel::aesni::Aes128Enc::PARALLEL_BLOCKS,
-            _ => ttable::Aes128Enc::PARALLEL_BLOCKS,
+            "simd" => accel::vpaes::Aes128Enc::PARALLEL_BLOCKS,
+            _ => fallback::Aes128Enc::PARALLEL_BLOCKS,
         };
         assert_eq!(aes.parallel_blocks(), expected);
     }
+
+    /// Hands out 0, 1, 2, ... so a generated key is predictable.
+    struct Counting(u8);
+
+    impl RandomSource for Counting {
+        fn fill(&mut self, buf: &mut [u8]) -> Result<(), RandError> {
+            for b in buf {
+                *b = self.0;
+                self.0 = self.0.wrapping_add(1);
+            }
+            Ok(())
+        }
+    }
+
+    struct Refusing;
+
+    impl RandomSource for Refusing {
+        fn fill(&mut self, _: &mut [u8]) -> Result<(), RandError> {
+            Err(RandError::from_raw_os_error(38))
+        }
+    }
+
+    /// A generated key is exactly the bytes the source gave, and a
+    /// source's failure is the caller's to handle.
+    #[test]
+    fn generated_keys_come_from_the_source() {
+        let key: [u8; 32] = core::array::from_fn(|i| i as u8);
+        let mut block = [0x5au8; BLOCK_SIZE];
+        let mut expected = block;
+        Aes256Enc::new(&key).encrypt_block(&mut expected);
+
+        let enc = Aes256Enc::generate_from(&mut Counting(0)).unwrap();
+        enc.encrypt_block(&mut block);
+        assert_eq!(block, expected);
+        let both = Aes256::generate_from(&mut Counting(0)).unwrap();
+        both.decrypt_block(&mut block);
+        assert_eq!(block, [0x5au8; BLOCK_SIZE]);
+
+        let e = Aes128Dec::generate_from(&mut Refusing).unwrap_err();
+        assert_eq!(e.raw_os_error(), 38);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn generated_keys_differ() {
+        let (mut a, mut b) = ([0u8; BLOCK_SIZE], [0u8; BLOCK_SIZE]);
+        Aes128Enc::generate().encrypt_block(&mut a);
+        Aes128Enc::generate().encrypt_block(&mut b);
+        assert_ne!(a, b);
+    }
 }
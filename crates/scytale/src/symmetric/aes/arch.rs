@@ -7,7 +7,20 @@
 #[cfg(target_arch = "aarch64")]
 pub mod aarch64;
 
+#[cfg(all(target_arch = "x86", target_feature = "sse2"))]
+pub mod x86;
+
 #[cfg(target_arch = "x86_64")]
 pub mod x86_64;
 
 pub mod portable;
+
+#[cfg(all(
+    feature = "scrub",
+    any(
+        target_arch = "x86_64",
+        all(target_arch = "x86", target_feature = "sse2"),
+        target_arch = "aarch64"
+    )
+))]
+mod scrub;
@@ -29,9 +29,12 @@ use core::arch::aarch64::*;
 use core::arch::asm;
 
 use zeroize::Zeroize;
+#[cfg(feature = "hazmat")]
+use zeroize::Zeroizing;
 
+use crate::cpu::{Feature, UnsupportedCpu};
 use crate::symmetric::block_cipher::{
-    BlockDecrypt, BlockEncrypt, InvalidKeyLength, KeyInit,
+    BlockDecrypt, BlockEncrypt, InvalidKeyLength, KeyInit, KeyInitError,
 };
 
 /// The AES block size in bytes.
@@ -49,13 +52,22 @@ const WIDTH: usize = 8;
 /// Whether this CPU has the AES instructions.
 ///
 /// The answer cannot change while the process runs, so it is worked out
-/// once and remembered.
+/// once and remembered. A build for a target that guarantees the
+/// instructions does not ask at all.
 pub fn supported() -> bool {
+    if cfg!(target_feature = "aes") {
+        return true;
+    }
     use std::sync::OnceLock;
     static SUPPORTED: OnceLock<bool> = OnceLock::new();
     *SUPPORTED.get_or_init(|| std::arch::is_aarch64_feature_detected!("aes"))
 }
 
+/// What [`supported`] checks for, naming whichever the CPU lacks.
+fn unsupported() -> UnsupportedCpu {
+    UnsupportedCpu::lacking(&[Feature::Aes])
+}
+
 /// A fully unrolled 8 block kernel.
 ///
 /// The round keys are loaded once, into registers of their own, and
@@ -559,6 +571,7 @@ macro_rules! driver {
                     $w1(rk, data.add(i * BLOCK_SIZE));
                 }
             }
+            scrub_registers();
         }
     };
 }
@@ -907,6 +920,7 @@ macro_rules! ctr_driver {
                     store_counter(c, counter);
                 }
             }
+            scrub_registers();
         }
     };
 }
@@ -1024,6 +1038,103 @@ unsafe fn invert_schedule(ek: &[u8], dk: &mut [u8], rounds: usize) {
     }
 }
 
+/// Undo [`invert_schedule`], recovering the encryption schedule.
+///
+/// MixColumns has order four, so InvMixColumns three times over is
+/// MixColumns, and the one instruction serves both ways.
+///
+/// # Safety
+///
+/// As for [`invert_schedule`].
+#[cfg(feature = "hazmat")]
+#[target_feature(enable = "aes")]
+unsafe fn restore_schedule(dk: &[u8], ek: &mut [u8], rounds: usize) {
+    // SAFETY: the caller guarantees the instructions and that both
+    // schedules hold rounds + 1 round keys, which is what is indexed.
+    unsafe {
+        let at = |s: &[u8], i: usize| vld1q_u8(s[i * 16..].as_ptr());
+
+        vst1q_u8(ek.as_mut_ptr(), at(dk, rounds));
+        for i in 1..rounds {
+            let key = at(dk, rounds - i);
+            let key = vaesimcq_u8(vaesimcq_u8(vaesimcq_u8(key)));
+            vst1q_u8(ek[i * 16..].as_mut_ptr(), key);
+        }
+        vst1q_u8(ek[rounds * 16..].as_mut_ptr(), at(dk, 0));
+    }
+}
+
+/// Clear every vector register, when the `scrub` feature asks for it.
+///
+/// The kernels declare their registers clobbered, so the compiler keeps
+/// nothing there, but the last blocks and round keys they held are still
+/// in place on return until something else happens to overwrite them, and
+/// the key schedule's intrinsics leave key words wherever the compiler put
+/// them. All thirty-two are cleared rather than tracking which were used.
+#[inline(always)]
+fn scrub_registers() {
+    #[cfg(feature = "scrub")]
+    // SAFETY: movi is baseline on this target, and every register written
+    // is declared.
+    unsafe {
+        asm!(
+            "movi v0.16b, #0",
+            "movi v1.16b, #0",
+            "movi v2.16b, #0",
+            "movi v3.16b, #0",
+            "movi v4.16b, #0",
+            "movi v5.16b, #0",
+            "movi v6.16b, #0",
+            "movi v7.16b, #0",
+            "movi v8.16b, #0",
+            "movi v9.16b, #0",
+            "movi v10.16b, #0",
+            "movi v11.16b, #0",
+            "movi v12.16b, #0",
+            "movi v13.16b, #0",
+            "movi v14.16b, #0",
+            "movi v15.16b, #0",
+            "movi v16.16b, #0",
+            "movi v17.16b, #0",
+            "movi v18.16b, #0",
+            "movi v19.16b, #0",
+            "movi v20.16b, #0",
+            "movi v21.16b, #0",
+            "movi v22.16b, #0",
+            "movi v23.16b, #0",
+            "movi v24.16b, #0",
+            "movi v25.16b, #0",
+            "movi v26.16b, #0",
+            "movi v27.16b, #0",
+            "movi v28.16b, #0",
+            "movi v29.16b, #0",
+            "movi v30.16b, #0",
+            "movi v31.16b, #0",
+            out("v0") _, out("v1") _, out("v2") _,
+            out("v3") _, out("v4") _, out("v5") _,
+            out("v6") _, out("v7") _, out("v8") _,
+            out("v9") _, out("v10") _, out("v11") _,
+            out("v12") _, out("v13") _, out("v14") _,
+            out("v15") _, out("v16") _, out("v17") _,
+            out("v18") _, out("v19") _, out("v20") _,
+            out("v21") _, out("v22") _, out("v23") _,
+            out("v24") _, out("v25") _, out("v26") _,
+            out("v27") _, out("v28") _, out("v29") _,
+            out("v30") _, out("v31") _,
+            options(nomem, nostack, preserves_flags),
+        );
+    }
+}
+
+/// After a key schedule routine returns, clear its registers and the
+/// stack it ran on, when the `scrub` feature asks for it.
+#[inline(always)]
+fn scrub_schedule() {
+    scrub_registers();
+    #[cfg(feature = "scrub")]
+    crate::symmetric::aes::arch::scrub::stack();
+}
+
 macro_rules! define_aes {
     (
         $enc:ident, $dec:ident, $key_size:expr, $bytes:expr, $rounds:expr,
@@ -1058,14 +1169,29 @@ macro_rules! define_aes {
             ///
             /// If the CPU has no AES instructions. Naming this type asserts
             /// that it does; the parent module's type of the same name
-            /// checks and falls back instead.
+            /// checks and falls back instead, and [`Self::try_new`]
+            /// reports it.
             pub fn new(key: &[u8; $key_size]) -> Self {
-                assert!(supported(), "AES instructions are not available");
+                match Self::try_new(key) {
+                    Ok(this) => this,
+                    Err(e) => panic!("{e}"),
+                }
+            }
+
+            /// Expand `key` into an encryption schedule, or say which CPU
+            /// features that would need.
+            pub fn try_new(
+                key: &[u8; $key_size],
+            ) -> Result<Self, UnsupportedCpu> {
+                if !supported() {
+                    return Err(unsupported());
+                }
                 let mut this = Self { rk: [0u8; $bytes] };
                 // SAFETY: support was just checked; rk is $bytes long,
                 // which is rounds + 1 round keys.
                 unsafe { expand(key, &mut this.rk, $nk, $rounds) };
-                this
+                scrub_schedule();
+                Ok(this)
             }
 
             /// Encrypt whole blocks in place, returning bytes consumed.
@@ -1130,18 +1256,22 @@ macro_rules! define_aes {
             ///
             /// # Panics
             ///
-            /// If the CPU has no AES instructions.
+            /// If the CPU has no AES instructions. [`Self::try_new`]
+            /// reports that instead.
             pub fn new(key: &[u8; $key_size]) -> Self {
-                assert!(supported(), "AES instructions are not available");
-                let mut ek = Self { rk: [0u8; $bytes] };
-                let mut this = Self { rk: [0u8; $bytes] };
-                // SAFETY: support was just checked; both schedules are
-                // $bytes long, which is rounds + 1 round keys.
-                unsafe {
-                    expand(key, &mut ek.rk, $nk, $rounds);
-                    invert_schedule(&ek.rk, &mut this.rk, $rounds);
+                match Self::try_new(key) {
+                    Ok(this) => this,
+                    Err(e) => panic!("{e}"),
                 }
-                this
+            }
+
+            /// Expand `key` into a decryption schedule, or say which CPU
+            /// features that would need.
+            pub fn try_new(
+                key: &[u8; $key_size],
+            ) -> Result<Self, UnsupportedCpu> {
+                // The encryption schedule is wiped when it drops here.
+                Ok(Self::from(&$enc::try_new(key)?))
             }
 
             /// Decrypt whole blocks in place, returning bytes consumed.
@@ -1197,21 +1327,114 @@ macro_rules! define_aes {
             }
         }
 
+        /// Invert an existing encryption schedule, so the key itself
+        /// need not be kept around in case decryption is wanted later.
+        impl From<&$enc> for $dec {
+            fn from(enc: &$enc) -> Self {
+                let mut this = Self { rk: [0u8; $bytes] };
+                // SAFETY: the encryption schedule exists, so the
+                // instructions do; both schedules are $bytes long, which
+                // is rounds + 1 round keys.
+                unsafe { invert_schedule(&enc.rk, &mut this.rk, $rounds) };
+                scrub_schedule();
+                this
+            }
+        }
+
+        #[cfg(feature = "hazmat")]
+        impl $enc {
+            /// Copy out the round keys, in the [canonical layout].
+            ///
+            /// [canonical layout]: crate::symmetric::aes#round-keys
+            pub fn round_keys(&self) -> Zeroizing<[u8; $bytes]> {
+                // The instructions take round keys in the state's byte
+                // order, which is the canonical one.
+                Zeroizing::new(self.rk)
+            }
+
+            /// Take round keys in the canonical layout as they are.
+            ///
+            /// Nothing checks that they came from a key expansion.
+            ///
+            /// # Panics
+            ///
+            /// As [`Self::new`]; [`Self::try_from_round_keys`] reports it
+            /// instead.
+            pub fn from_round_keys(round_keys: &[u8; $bytes]) -> Self {
+                match Self::try_from_round_keys(round_keys) {
+                    Ok(this) => this,
+                    Err(e) => panic!("{e}"),
+                }
+            }
+
+            /// Take round keys in the canonical layout as they are, or
+            /// say which CPU features that would need.
+            pub fn try_from_round_keys(
+                round_keys: &[u8; $bytes],
+            ) -> Result<Self, UnsupportedCpu> {
+                if !supported() {
+                    return Err(unsupported());
+                }
+                Ok(Self { rk: *round_keys })
+            }
+        }
+
+        #[cfg(feature = "hazmat")]
+        impl $dec {
+            /// Copy out the encryption round keys this schedule was
+            /// inverted from, in the canonical layout.
+            pub fn round_keys(&self) -> Zeroizing<[u8; $bytes]> {
+                let mut enc = $enc { rk: [0u8; $bytes] };
+                // SAFETY: this schedule exists, so the instructions do;
+                // both are $bytes long, which is rounds + 1 round keys.
+                unsafe { restore_schedule(&self.rk, &mut enc.rk, $rounds) };
+                scrub_schedule();
+                enc.round_keys()
+            }
+
+            /// Invert encryption round keys in the canonical layout.
+            ///
+            /// Nothing checks that they came from a key expansion.
+            ///
+            /// # Panics
+            ///
+            /// As [`Self::new`]; [`Self::try_from_round_keys`] reports it
+            /// instead.
+            pub fn from_round_keys(round_keys: &[u8; $bytes]) -> Self {
+                match Self::try_from_round_keys(round_keys) {
+                    Ok(this) => this,
+                    Err(e) => panic!("{e}"),
+                }
+            }
+
+            /// Invert encryption round keys in the canonical layout, or
+            /// say which CPU features that would need.
+            pub fn try_from_round_keys(
+                round_keys: &[u8; $bytes],
+            ) -> Result<Self, UnsupportedCpu> {
+                Ok(Self::from(&$enc::try_from_round_keys(round_keys)?))
+            }
+        }
+
         impl KeyInit for $enc {
-            fn try_new(key: &[u8]) -> Result<Self, InvalidKeyLength> {
+            type Error = KeyInitError;
+
+            fn try_new(key: &[u8]) -> Result<Self, KeyInitError> {
                 let key: &[u8; $key_size] = key
                     .try_into()
                     .map_err(|_| InvalidKeyLength { got: key.len() })?;
-                Ok(Self::new(key))
+                Ok(Self::try_new(key)?)
             }
         }
 
         impl KeyInit for $dec {
-            fn try_new(key: &[u8]) -> Result<Self, InvalidKeyLength> {
+            type Error = KeyInitError;
+
+            fn try_new(key: &[u8]) -> Result<Self, KeyInitError> {
                 let key: &[u8; $key_size] = key
                     .try_into()
                     .map_err(|_| InvalidKeyLength { got: key.len() })?;
-                Ok(Self::new(key))
+                Ok(Self::try_new(key)?)
             }
         }
 
@@ -1251,21 +1474,26 @@ define_aes!(
 mod tests {
     use super::*;
     use crate::symmetric::aes::arch::portable::ttable;
+    use crate::symmetric::aes::testing::{assert_inverting_matches_expanding, Rng};
 
-    /// xorshift64*, so a divergence is reproducible from the seed.
-    struct Rng(u64);
-
-    impl Rng {
-        fn fill(&mut self, buf: &mut [u8]) {
-            for chunk in buf.chunks_mut(8) {
-                let mut x = self.0;
-                x ^= x >> 12;
-                x ^= x << 25;
-                x ^= x >> 27;
-                self.0 = x;
-                let b = x.wrapping_mul(0x2545_f491_4f6c_dd1d).to_le_bytes();
-                chunk.copy_from_slice(&b[..chunk.len()]);
-            }
+    /// Construction on the wrong CPU is an error naming what is missing,
+    /// not a panic, through both the inherent and the trait constructor.
+    #[test]
+    fn try_new_refuses_rather_than_panics() {
+        match Aes128Enc::try_new(&[0u8; 16]) {
+            Ok(_) => assert!(supported()),
+            Err(e) => assert!(!supported() && e.missing().count() > 0),
+        }
+        assert_eq!(Aes256Dec::try_new(&[0u8; 32]).is_ok(), supported());
+
+        // The length is checked first, so it is reported on any CPU.
+        assert!(matches!(
+            <Aes192Enc as KeyInit>::try_new(&[0u8; 16]),
+            Err(KeyInitError::Key(InvalidKeyLength { got: 16 }))
+        ));
+        match <Aes192Dec as KeyInit>::try_new(&[0u8; 24]) {
+            Ok(_) => assert!(supported()),
+            Err(e) => assert!(matches!(e, KeyInitError::Cpu(_))),
         }
     }
 
@@ -1514,6 +1742,45 @@ mod tests {
         assert_eq!(bulk, one);
     }
 
+    /// Inverting an encryption schedule must give exactly the schedule
+    /// `Dec::new` expands, at every key size.
+    #[test]
+    fn inverting_matches_expanding() {
+        if !supported() {
+            return;
+        }
+        assert_inverting_matches_expanding!();
+    }
+
+    /// The canonical layout is the portable one's, in both directions,
+    /// and loading a schedule gives back what expansion would build.
+    #[cfg(feature = "hazmat")]
+    #[test]
+    fn round_keys_match_the_portable_implementation() {
+        if !supported() {
+            return;
+        }
+        let mut rng = Rng(0x7f6e_5d4c_3b2a_1908);
+        macro_rules! check {
+            ($enc:ident, $dec:ident, $pe:path, $len:expr) => {
+                for _ in 0..16 {
+                    let mut key = [0u8; $len];
+                    rng.fill(&mut key);
+                    let rk = <$pe>::new(&key).round_keys();
+                    assert_eq!(*$enc::new(&key).round_keys(), *rk);
+                    assert_eq!(*$dec::new(&key).round_keys(), *rk);
+                    let enc = $enc::from_round_keys(&rk);
+                    assert_eq!(enc.rk, $enc::new(&key).rk);
+                    let dec = $dec::from_round_keys(&rk);
+                    assert_eq!(dec.rk, $dec::new(&key).rk);
+                }
+            };
+        }
+        check!(Aes128Enc, Aes128Dec, ttable::Aes128Enc, 16);
+        check!(Aes192Enc, Aes192Dec, ttable::Aes192Enc, 24);
+        check!(Aes256Enc, Aes256Dec, ttable::Aes256Enc, 32);
+    }
+
     #[test]
     fn schedules_are_wiped_on_drop() {
         if !supported() {
@@ -1,3 +1,6 @@
 //! AES implementations that run anywhere, with no target features.
 
+pub mod bitsliced;
+pub mod compact;
+pub mod masked;
 pub mod ttable;
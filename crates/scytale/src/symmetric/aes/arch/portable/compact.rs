@@ -0,0 +1,807 @@
+//! AES-128, AES-192 and AES-256 in as little memory as possible.
+//!
+//! This is the implementation for small microcontrollers, where the T-table
+//! cipher's eight kilobytes of tables and the bitsliced one's twenty of
+//! code do not fit. Its only table is the 256 byte S-box, and decryption
+//! shares it: the inverse S-box is the forward one between two copies of
+//! the inverse affine map. All three key sizes run through the same
+//! functions, so there is one copy of the code rather than three. Built
+//! for the Cortex-M0, AES-128 keying, encryption, decryption and counter
+//! mode together come to about two and a half kilobytes of flash.
+//!
+//! No key schedule is stored. An `Enc` type holds the key itself and a
+//! `Dec` type the last words of its expansion, and each block expands the
+//! round keys as it goes, forwards for encryption and backwards for
+//! decryption, in one window the size of the key. A value is the size of
+//! its key, and a call's stack holds little more than that window and the
+//! block.
+//!
+//! # Cost
+//!
+//! Every block pays for its own key expansion on top of byte-at-a-time
+//! rounds. On a desktop core that makes encryption about four times
+//! slower than the T-table cipher, and decryption, which also runs the
+//! expansion backwards and the inverse S-box through two affine maps,
+//! about eight. It exists to be small; where there is room for either
+//! other portable cipher, use that.
+//!
+//! # Side channels
+//!
+//! By default the S-box is a table indexed by secret bytes. On a core
+//! with no data cache, such as the Cortex-M0 and M0+, every entry costs
+//! the same to read, but that is a property of the memory system rather
+//! than of this code. With the `constant-time` feature the S-box is
+//! computed instead, as inversion in GF(2^8) by a fixed chain of
+//! multiplications and then the affine map, with no table and no branch
+//! or index on secret data. That is some forty times slower again.
+
+use zeroize::Zeroize;
+
+use crate::symmetric::block_cipher::{
+    BlockDecrypt, BlockEncrypt, InvalidKeyLength, KeyInit,
+};
+
+/// The AES block size in bytes. Identical for all three key sizes.
+pub const BLOCK_SIZE: usize = 16;
+
+/// Multiply by x in GF(2^8) modulo the AES polynomial x^8+x^4+x^3+x+1.
+const fn xtime(a: u8) -> u8 {
+    (a << 1) ^ (0x1b & 0u8.wrapping_sub(a >> 7))
+}
+
+/// Divide by x in GF(2^8), undoing [`xtime`].
+const fn inv_xtime(a: u8) -> u8 {
+    (a >> 1) ^ (0x8d & 0u8.wrapping_sub(a & 1))
+}
+
+/// Multiply two field elements, with no branch on either.
+const fn mul(a: u8, b: u8) -> u8 {
+    let mut acc = 0u8;
+    let mut a = a;
+    let mut i = 0;
+    while i < 8 {
+        acc ^= a & 0u8.wrapping_sub((b >> i) & 1);
+        a = xtime(a);
+        i += 1;
+    }
+    acc
+}
+
+/// Multiplicative inverse in GF(2^8), with 0 mapping to 0 as AES defines.
+///
+/// The inverse is `a^254`, and 254 is every power of two from 2 to 128
+/// summed, so it takes seven squarings and seven multiplications whatever
+/// `a` is.
+const fn invert(a: u8) -> u8 {
+    let mut square = a;
+    let mut acc = 1u8;
+    let mut i = 1;
+    while i < 8 {
+        square = mul(square, square);
+        acc = mul(acc, square);
+        i += 1;
+    }
+    acc
+}
+
+/// The S-box's affine map over GF(2).
+const fn affine(x: u8) -> u8 {
+    x ^ x.rotate_left(1) ^ x.rotate_left(2) ^ x.rotate_left(3)
+        ^ x.rotate_left(4) ^ 0x63
+}
+
+/// The inverse of [`affine`].
+const fn inv_affine(x: u8) -> u8 {
+    x.rotate_left(1) ^ x.rotate_left(3) ^ x.rotate_left(6) ^ 0x05
+}
+
+#[cfg(not(feature = "constant-time"))]
+const fn build_sbox() -> [u8; 256] {
+    let mut s = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        s[i] = affine(invert(i as u8));
+        i += 1;
+    }
+    s
+}
+
+/// The S-box, derived at compile time by the same arithmetic the
+/// constant-time build runs.
+#[cfg(not(feature = "constant-time"))]
+static SBOX: [u8; 256] = build_sbox();
+
+/// SubBytes on one byte.
+#[cfg(not(feature = "constant-time"))]
+fn sub(x: u8) -> u8 {
+    SBOX[x as usize]
+}
+
+/// SubBytes on one byte.
+#[cfg(feature = "constant-time")]
+fn sub(x: u8) -> u8 {
+    affine(invert(x))
+}
+
+/// InvSubBytes on one byte.
+///
+/// The S-box is inversion then [`affine`], so undoing the map on both
+/// sides of it leaves inversion after the inverse map.
+fn inv_sub(x: u8) -> u8 {
+    inv_affine(sub(inv_affine(x)))
+}
+
+/// The state, in the block's own byte order: byte `r + 4 * c` is row `r`
+/// of column `c`.
+type State = [u8; BLOCK_SIZE];
+
+fn sub_bytes(s: &mut State) {
+    for b in s.iter_mut() {
+        *b = sub(*b);
+    }
+}
+
+fn inv_sub_bytes(s: &mut State) {
+    for b in s.iter_mut() {
+        *b = inv_sub(*b);
+    }
+}
+
+/// Row `r` moves `r` columns left, or with `inverse` right.
+fn shift_rows(s: &mut State, inverse: bool) {
+    for r in 1..4 {
+        let row = [s[r], s[r + 4], s[r + 8], s[r + 12]];
+        let k = if inverse { 4 - r } else { r };
+        for c in 0..4 {
+            s[r + 4 * c] = row[(c + k) % 4];
+        }
+    }
+}
+
+/// Each output byte is `2a ^ 3b ^ c ^ d` for its column's bytes in order
+/// from its own, which is `a ^ 2(a ^ b) ^ (a ^ b ^ c ^ d)`.
+fn mix_columns(s: &mut State) {
+    for col in s.chunks_exact_mut(4) {
+        let a = [col[0], col[1], col[2], col[3]];
+        let t = a[0] ^ a[1] ^ a[2] ^ a[3];
+        for r in 0..4 {
+            col[r] ^= t ^ xtime(a[r] ^ a[(r + 1) % 4]);
+        }
+    }
+}
+
+/// InvMixColumns is MixColumns after taking each byte `a` to
+/// `a ^ 4(a ^ c)`, where `c` is two rows on.
+fn inv_mix_columns(s: &mut State) {
+    for col in s.chunks_exact_mut(4) {
+        let u = xtime(xtime(col[0] ^ col[2]));
+        let v = xtime(xtime(col[1] ^ col[3]));
+        col[0] ^= u;
+        col[1] ^= v;
+        col[2] ^= u;
+        col[3] ^= v;
+    }
+    mix_columns(s);
+}
+
+/// The round count for a key of `len` bytes.
+const fn rounds(len: usize) -> usize {
+    len / 4 + 6
+}
+
+/// How many key-sized windows the expansion runs to, counting the key.
+///
+/// The schedule is `4 * (rounds + 1)` words and each window `len / 4`, so
+/// for AES-192 and AES-256 the last window is only partly used.
+const fn windows(len: usize) -> usize {
+    (4 * (rounds(len) + 1)).div_ceil(len / 4)
+}
+
+/// One window of the key expansion, walked a word at a time.
+///
+/// The window holds `Nk` consecutive words of the FIPS-197 expansion, in
+/// bytes, and `at` is the next word to hand out. Stepping the window on
+/// computes the next `Nk` words in place, and stepping it back recovers
+/// the previous ones, which is what lets decryption start from the end.
+struct Schedule {
+    w: [u8; 32],
+    len: usize,
+    at: usize,
+    rcon: u8,
+}
+
+impl Schedule {
+    /// The window at the start of the expansion: the key itself.
+    fn first(key: &[u8]) -> Self {
+        let mut this = Self { w: [0; 32], len: key.len(), at: 0, rcon: 1 };
+        this.w[..key.len()].copy_from_slice(key);
+        this
+    }
+
+    /// The window at the end of the expansion, from its bytes.
+    fn last(w: &[u8]) -> Self {
+        let len = w.len();
+        let steps = windows(len) - 1;
+        let mut rcon = 1;
+        for _ in 0..steps {
+            rcon = xtime(rcon);
+        }
+        let mut this = Self {
+            w: [0; 32],
+            len,
+            at: 4 * (rounds(len) + 1) - steps * (len / 4),
+            rcon,
+        };
+        this.w[..len].copy_from_slice(w);
+        this
+    }
+
+    /// Step on to the next `Nk` words.
+    fn advance(&mut self) {
+        let len = self.len;
+        let w = &mut self.w[..len];
+        // The first word takes SubWord(RotWord) of the last.
+        let last = [w[len - 4], w[len - 3], w[len - 2], w[len - 1]];
+        w[0] ^= sub(last[1]) ^ self.rcon;
+        w[1] ^= sub(last[2]);
+        w[2] ^= sub(last[3]);
+        w[3] ^= sub(last[0]);
+        self.rcon = xtime(self.rcon);
+        for i in 4..len {
+            let prev = w[i - 4];
+            // AES-256 substitutes again at the midpoint of each window.
+            w[i] ^= if len == 32 && i / 4 == 4 { sub(prev) } else { prev };
+        }
+    }
+
+    /// Step back to the previous `Nk` words, undoing [`Self::advance`].
+    fn retreat(&mut self) {
+        let len = self.len;
+        let w = &mut self.w[..len];
+        // Each word was its predecessor's new value XORed in, so going
+        // downwards still finds that value in place.
+        for i in (4..len).rev() {
+            let prev = w[i - 4];
+            w[i] ^= if len == 32 && i / 4 == 4 { sub(prev) } else { prev };
+        }
+        self.rcon = inv_xtime(self.rcon);
+        let last = [w[len - 4], w[len - 3], w[len - 2], w[len - 1]];
+        w[0] ^= sub(last[1]) ^ self.rcon;
+        w[1] ^= sub(last[2]);
+        w[2] ^= sub(last[3]);
+        w[3] ^= sub(last[0]);
+    }
+
+    /// AddRoundKey with the next round key, stepping on as needed.
+    fn add_next(&mut self, s: &mut State) {
+        for col in s.chunks_exact_mut(4) {
+            if self.at == self.len / 4 {
+                self.advance();
+                self.at = 0;
+            }
+            for (b, k) in col.iter_mut().zip(&self.w[4 * self.at..]) {
+                *b ^= k;
+            }
+            self.at += 1;
+        }
+    }
+
+    /// AddRoundKey with the previous round key, stepping back as needed.
+    ///
+    /// Decryption takes the round keys in reverse, so within each one the
+    /// words come last first too.
+    fn add_prev(&mut self, s: &mut State) {
+        for col in s.rchunks_exact_mut(4) {
+            if self.at == 0 {
+                self.retreat();
+                self.at = self.len / 4;
+            }
+            self.at -= 1;
+            for (b, k) in col.iter_mut().zip(&self.w[4 * self.at..]) {
+                *b ^= k;
+            }
+        }
+    }
+}
+
+impl Drop for Schedule {
+    fn drop(&mut self) {
+        self.w.zeroize();
+    }
+}
+
+/// The window a decryption starts from: the expansion of `key` run to
+/// its end.
+fn last_window(key: &[u8], out: &mut [u8]) {
+    let mut schedule = Schedule::first(key);
+    for _ in 1..windows(key.len()) {
+        schedule.advance();
+    }
+    out.copy_from_slice(&schedule.w[..key.len()]);
+}
+
+/// One block through the encryption rounds, expanding `key` on the way.
+fn encrypt_block_with(key: &[u8], block: &mut [u8; BLOCK_SIZE]) {
+    let mut schedule = Schedule::first(key);
+    schedule.add_next(block);
+    let rounds = rounds(key.len());
+    for round in 1..=rounds {
+        sub_bytes(block);
+        shift_rows(block, false);
+        if round != rounds {
+            mix_columns(block);
+        }
+        schedule.add_next(block);
+    }
+}
+
+/// One block through the decryption rounds, from the window at the end
+/// of the expansion back to the key.
+fn decrypt_block_with(last: &[u8], block: &mut [u8; BLOCK_SIZE]) {
+    let mut schedule = Schedule::last(last);
+    schedule.add_prev(block);
+    let rounds = rounds(last.len());
+    for round in 1..=rounds {
+        if round != 1 {
+            inv_mix_columns(block);
+        }
+        shift_rows(block, true);
+        inv_sub_bytes(block);
+        schedule.add_prev(block);
+    }
+}
+
+/// Counter mode over [`encrypt_block_with`], one counter value at a time.
+fn ctr_with(
+    key: &[u8],
+    counter: &mut [u8; BLOCK_SIZE],
+    data: &mut [u8],
+) -> usize {
+    let (blocks, _tail) = data.as_chunks_mut::<BLOCK_SIZE>();
+    let mut c = u128::from_be_bytes(*counter);
+    let mut keystream = [0u8; BLOCK_SIZE];
+    for block in blocks.iter_mut() {
+        keystream = c.to_be_bytes();
+        encrypt_block_with(key, &mut keystream);
+        for (d, k) in block.iter_mut().zip(&keystream) {
+            *d ^= *k;
+        }
+        c = c.wrapping_add(1);
+    }
+    keystream.zeroize();
+    *counter = c.to_be_bytes();
+    blocks.len() * BLOCK_SIZE
+}
+
+macro_rules! define_aes {
+    (
+        $enc:ident, $dec:ident, $both:ident,
+        $key_size:expr, $bits:expr
+    ) => {
+        #[doc = concat!("AES-", $bits, " encryption only.")]
+        ///
+        /// Holds just the key, and expands it afresh for every block.
+        pub struct $enc {
+            key: [u8; $key_size],
+        }
+
+        #[doc = concat!("AES-", $bits, " decryption only.")]
+        ///
+        /// Holds the last key-sized window of the expansion, and expands
+        /// backwards from it for every block.
+        pub struct $dec {
+            last: [u8; $key_size],
+        }
+
+        #[doc = concat!("AES-", $bits, ", both directions.")]
+        ///
+        /// Twice the size of a one-direction type, which is still only
+        /// twice the key.
+        pub struct $both {
+            enc: $enc,
+            dec: $dec,
+        }
+
+        impl $enc {
+            /// The key length in bytes.
+            pub const KEY_SIZE: usize = $key_size;
+            /// The block length in bytes.
+            pub const BLOCK_SIZE: usize = BLOCK_SIZE;
+            /// Blocks kept in flight. This implementation is scalar.
+            pub const PARALLEL_BLOCKS: usize = 1;
+
+            /// Keep `key` for encryption. Nothing is expanded until a
+            /// block needs it.
+            ///
+            /// Taking a fixed-size array means a wrong key length is a
+            /// compile error, so there is no failure case to report.
+            pub fn new(key: &[u8; $key_size]) -> Self {
+                Self { key: *key }
+            }
+
+            /// Encrypt whole blocks in place, returning bytes consumed.
+            pub fn encrypt(&self, data: &mut [u8]) -> usize {
+                let (blocks, _tail) = data.as_chunks_mut::<BLOCK_SIZE>();
+                for block in blocks.iter_mut() {
+                    encrypt_block_with(&self.key, block);
+                }
+                blocks.len() * BLOCK_SIZE
+            }
+
+            /// Encrypt exactly one block in place.
+            pub fn encrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+                encrypt_block_with(&self.key, block);
+            }
+
+            /// Encrypt successive counter values and XOR them into
+            /// `data` in place, advancing `counter`.
+            ///
+            /// The counter is one block, big endian, wrapping at the
+            /// full block width, as SP 800-38A specifies. Whole blocks
+            /// only, like [`Self::encrypt`]; returns bytes consumed.
+            pub fn ctr(
+                &self,
+                counter: &mut [u8; BLOCK_SIZE],
+                data: &mut [u8],
+            ) -> usize {
+                ctr_with(&self.key, counter, data)
+            }
+        }
+
+        impl $dec {
+            /// The key length in bytes.
+            pub const KEY_SIZE: usize = $key_size;
+            /// The block length in bytes.
+            pub const BLOCK_SIZE: usize = BLOCK_SIZE;
+            /// Blocks kept in flight. This implementation is scalar.
+            pub const PARALLEL_BLOCKS: usize = 1;
+
+            /// Run the expansion of `key` to its end, and keep only that.
+            pub fn new(key: &[u8; $key_size]) -> Self {
+                let mut this = Self { last: [0u8; $key_size] };
+                last_window(key, &mut this.last);
+                this
+            }
+
+            /// Decrypt whole blocks in place, returning bytes consumed.
+            pub fn decrypt(&self, data: &mut [u8]) -> usize {
+                let (blocks, _tail) = data.as_chunks_mut::<BLOCK_SIZE>();
+                for block in blocks.iter_mut() {
+                    decrypt_block_with(&self.last, block);
+                }
+                blocks.len() * BLOCK_SIZE
+            }
+
+            /// Decrypt exactly one block in place.
+            pub fn decrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+                decrypt_block_with(&self.last, block);
+            }
+        }
+
+        impl $both {
+            /// The key length in bytes.
+            pub const KEY_SIZE: usize = $key_size;
+            /// The block length in bytes.
+            pub const BLOCK_SIZE: usize = BLOCK_SIZE;
+            /// Blocks kept in flight. This implementation is scalar.
+            pub const PARALLEL_BLOCKS: usize = 1;
+
+            /// Keep `key` for both directions.
+            pub fn new(key: &[u8; $key_size]) -> Self {
+                Self { enc: $enc::new(key), dec: $dec::new(key) }
+            }
+
+            /// Encrypt whole blocks in place, returning bytes consumed.
+            pub fn encrypt(&self, data: &mut [u8]) -> usize {
+                self.enc.encrypt(data)
+            }
+
+            /// Decrypt whole blocks in place, returning bytes consumed.
+            pub fn decrypt(&self, data: &mut [u8]) -> usize {
+                self.dec.decrypt(data)
+            }
+
+            /// Encrypt exactly one block in place.
+            pub fn encrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+                self.enc.encrypt_block(block);
+            }
+
+            /// Decrypt exactly one block in place.
+            pub fn decrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+                self.dec.decrypt_block(block);
+            }
+
+            /// Borrow just the encryption half.
+            pub fn encryptor(&self) -> &$enc {
+                &self.enc
+            }
+
+            /// Borrow just the decryption half.
+            pub fn decryptor(&self) -> &$dec {
+                &self.dec
+            }
+        }
+
+        /// Run an encryption key's expansion to its end, so the key
+        /// itself need not be kept around in case decryption is wanted
+        /// later.
+        impl From<&$enc> for $dec {
+            fn from(enc: &$enc) -> Self {
+                Self::new(&enc.key)
+            }
+        }
+
+        impl KeyInit for $enc {
+            type Error = InvalidKeyLength;
+
+            fn try_new(key: &[u8]) -> Result<Self, InvalidKeyLength> {
+                let key: &[u8; $key_size] = key
+                    .try_into()
+                    .map_err(|_| InvalidKeyLength { got: key.len() })?;
+                Ok(Self::new(key))
+            }
+        }
+
+        impl KeyInit for $dec {
+            type Error = InvalidKeyLength;
+
+            fn try_new(key: &[u8]) -> Result<Self, InvalidKeyLength> {
+                let key: &[u8; $key_size] = key
+                    .try_into()
+                    .map_err(|_| InvalidKeyLength { got: key.len() })?;
+                Ok(Self::new(key))
+            }
+        }
+
+        impl KeyInit for $both {
+            type Error = InvalidKeyLength;
+
+            fn try_new(key: &[u8]) -> Result<Self, InvalidKeyLength> {
+                let key: &[u8; $key_size] = key
+                    .try_into()
+                    .map_err(|_| InvalidKeyLength { got: key.len() })?;
+                Ok(Self::new(key))
+            }
+        }
+
+        impl BlockEncrypt for $enc {
+            const BLOCK_SIZE: usize = BLOCK_SIZE;
+            const PARALLEL_BLOCKS: usize = 1;
+
+            fn encrypt(&self, data: &mut [u8]) -> usize {
+                $enc::encrypt(self, data)
+            }
+        }
+
+        impl BlockDecrypt for $dec {
+            const BLOCK_SIZE: usize = BLOCK_SIZE;
+            const PARALLEL_BLOCKS: usize = 1;
+
+            fn decrypt(&self, data: &mut [u8]) -> usize {
+                $dec::decrypt(self, data)
+            }
+        }
+
+        impl BlockEncrypt for $both {
+            const BLOCK_SIZE: usize = BLOCK_SIZE;
+            const PARALLEL_BLOCKS: usize = 1;
+
+            fn encrypt(&self, data: &mut [u8]) -> usize {
+                self.enc.encrypt(data)
+            }
+        }
+
+        impl BlockDecrypt for $both {
+            const BLOCK_SIZE: usize = BLOCK_SIZE;
+            const PARALLEL_BLOCKS: usize = 1;
+
+            fn decrypt(&self, data: &mut [u8]) -> usize {
+                self.dec.decrypt(data)
+            }
+        }
+
+        impl Drop for $enc {
+            fn drop(&mut self) {
+                self.key.zeroize();
+            }
+        }
+
+        impl Drop for $dec {
+            fn drop(&mut self) {
+                self.last.zeroize();
+            }
+        }
+
+        impl core::fmt::Debug for $enc {
+            fn fmt(
+                &self,
+                f: &mut core::fmt::Formatter<'_>,
+            ) -> core::fmt::Result {
+                // Never format key material.
+                f.write_str(concat!(stringify!($enc), " { .. }"))
+            }
+        }
+
+        impl core::fmt::Debug for $dec {
+            fn fmt(
+                &self,
+                f: &mut core::fmt::Formatter<'_>,
+            ) -> core::fmt::Result {
+                f.write_str(concat!(stringify!($dec), " { .. }"))
+            }
+        }
+
+        impl core::fmt::Debug for $both {
+            fn fmt(
+                &self,
+                f: &mut core::fmt::Formatter<'_>,
+            ) -> core::fmt::Result {
+                f.write_str(concat!(stringify!($both), " { .. }"))
+            }
+        }
+    };
+}
+
+define_aes!(Aes128Enc, Aes128Dec, Aes128, 16, "128");
+define_aes!(Aes192Enc, Aes192Dec, Aes192, 24, "192");
+define_aes!(Aes256Enc, Aes256Dec, Aes256, 32, "256");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symmetric::aes::arch::portable::ttable;
+
+    const FIPS_PLAINTEXT: [u8; 16] = [
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa,
+        0xbb, 0xcc, 0xdd, 0xee, 0xff,
+    ];
+
+    /// FIPS-197 Appendix C, all three key sizes.
+    #[test]
+    fn fips_197_all_key_sizes() {
+        macro_rules! check {
+            ($enc:ident, $dec:ident, $len:expr, $expected:expr) => {
+                let mut key = [0u8; $len];
+                for (i, b) in key.iter_mut().enumerate() {
+                    *b = i as u8;
+                }
+                let mut block = FIPS_PLAINTEXT;
+                $enc::new(&key).encrypt_block(&mut block);
+                assert_eq!(block, $expected);
+                $dec::new(&key).decrypt_block(&mut block);
+                assert_eq!(block, FIPS_PLAINTEXT);
+            };
+        }
+        check!(Aes128Enc, Aes128Dec, 16, [
+            0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30, 0xd8, 0xcd,
+            0xb7, 0x80, 0x70, 0xb4, 0xc5, 0x5a,
+        ]);
+        check!(Aes192Enc, Aes192Dec, 24, [
+            0xdd, 0xa9, 0x7c, 0xa4, 0x86, 0x4c, 0xdf, 0xe0, 0x6e, 0xaf,
+            0x70, 0xa0, 0xec, 0x0d, 0x71, 0x91,
+        ]);
+        check!(Aes256Enc, Aes256Dec, 32, [
+            0x8e, 0xa2, 0xb7, 0xca, 0x51, 0x67, 0x45, 0xbf, 0xea, 0xfc,
+            0x49, 0x90, 0x4b, 0x49, 0x60, 0x89,
+        ]);
+    }
+
+    /// The computed S-box is the one the T-table cipher derives its own
+    /// tables from, and the inverse undoes it, whichever build this is.
+    #[test]
+    fn sbox_matches_the_fips_197_table() {
+        assert_eq!(sub(0x00), 0x63);
+        assert_eq!(sub(0x01), 0x7c);
+        assert_eq!(sub(0x53), 0xed);
+        assert_eq!(sub(0xff), 0x16);
+        for x in 0..=255u8 {
+            assert_eq!(inv_sub(sub(x)), x);
+        }
+    }
+
+    /// Stepping a window back undoes stepping it on, all the way from the
+    /// end of the expansion to the key, at every key size.
+    #[test]
+    fn retreating_undoes_advancing() {
+        for len in [16, 24, 32] {
+            let key: Vec<u8> =
+                (0..len as u8).map(|i| i.wrapping_mul(0x9d)).collect();
+            let mut last = vec![0u8; len];
+            last_window(&key, &mut last);
+            let mut schedule = Schedule::last(&last);
+            for _ in 1..windows(len) {
+                schedule.retreat();
+            }
+            assert_eq!(schedule.w[..len], key[..], "AES-{}", 8 * len);
+            assert_eq!(schedule.rcon, 1);
+        }
+    }
+
+    /// Whole buffers, and every key size, against the T-table cipher.
+    #[test]
+    fn agrees_with_the_t_table_cipher() {
+        macro_rules! check {
+            ($enc:ident, $dec:ident, $len:expr) => {
+                let key = [0x5au8; $len];
+                let mut data = [0u8; 16 * 5];
+                for (i, b) in data.iter_mut().enumerate() {
+                    *b = (i as u8).wrapping_mul(0x3b);
+                }
+                let mut reference = data;
+                ttable::$enc::new(&key).encrypt(&mut reference);
+                let mut ours = data;
+                assert_eq!($enc::new(&key).encrypt(&mut ours), data.len());
+                assert_eq!(ours, reference);
+                assert_eq!($dec::new(&key).decrypt(&mut ours), data.len());
+                assert_eq!(ours, data);
+
+                let mut counter = [0xff; 16];
+                let mut expected = [0xffu8; 16];
+                let mut reference = data;
+                ttable::$enc::new(&key).ctr(&mut expected, &mut reference);
+                $enc::new(&key).ctr(&mut counter, &mut ours);
+                assert_eq!(counter, expected);
+                assert_eq!(ours, reference);
+            };
+        }
+        check!(Aes128Enc, Aes128Dec, 16);
+        check!(Aes192Enc, Aes192Dec, 24);
+        check!(Aes256Enc, Aes256Dec, 32);
+    }
+
+    #[test]
+    fn inverting_matches_expanding() {
+        let key = [0x2bu8; 24];
+        let inverted = Aes192Dec::from(&Aes192Enc::new(&key));
+        assert_eq!(inverted.last, Aes192Dec::new(&key).last);
+    }
+
+    #[test]
+    fn partial_trailing_block_is_left_alone() {
+        let aes = Aes256Enc::new(&[0u8; 32]);
+        let mut data = [0xccu8; 16 + 5];
+
+        assert_eq!(aes.encrypt(&mut data), 16, "only whole blocks consumed");
+        assert_eq!(&data[16..], &[0xcc; 5], "tail must be untouched");
+    }
+
+    #[test]
+    fn try_new_rejects_wrong_key_lengths() {
+        assert!(Aes128Enc::try_new(&[0u8; 16]).is_ok());
+        assert_eq!(
+            Aes192Dec::try_new(&[0u8; 16]).unwrap_err(),
+            InvalidKeyLength { got: 16 }
+        );
+        assert!(Aes256::try_new(&[0u8; 32]).is_ok());
+    }
+
+    /// A value is its key and nothing more.
+    #[test]
+    fn values_are_the_size_of_their_keys() {
+        assert_eq!(core::mem::size_of::<Aes128Enc>(), 16);
+        assert_eq!(core::mem::size_of::<Aes192Dec>(), 24);
+        assert_eq!(core::mem::size_of::<Aes256>(), 64);
+    }
+
+    #[test]
+    fn keys_are_wiped_on_drop() {
+        let mut slot = core::mem::MaybeUninit::new(Aes256::new(&[0xab; 32]));
+        let ptr = slot.as_mut_ptr();
+        let bytes = ptr.cast::<u8>();
+        let len = core::mem::size_of::<Aes256>();
+        // SAFETY: slot holds an initialized value of exactly this size.
+        let live = unsafe { core::slice::from_raw_parts(bytes, len) };
+        assert!(live.iter().any(|&b| b != 0));
+        // SAFETY: the value is never read as a value again.
+        unsafe { core::ptr::drop_in_place(ptr) };
+        // SAFETY: the storage is ours and still allocated.
+        let dead = unsafe { core::slice::from_raw_parts(bytes, len) };
+        assert!(dead.iter().all(|&b| b == 0), "key material survived");
+    }
+
+    #[test]
+    fn debug_does_not_leak_keys() {
+        let aes = Aes128::new(&[0xab; 16]);
+        assert_eq!(format!("{aes:?}"), "Aes128 { .. }");
+    }
+}
@@ -0,0 +1,1057 @@
+//! AES-128, AES-192 and AES-256 with first-order Boolean masking, for
+//! devices that have to resist power and electromagnetic analysis.
+//!
+//! Differential power analysis works by correlating what a device draws
+//! with some intermediate value of the cipher, an S-box output say, over
+//! many inputs. Here every value that depends on the key is only ever
+//! held XORed with a random mask, and the masks are drawn afresh for
+//! every block from a [`RandomSource`] the caller supplies, so each value
+//! taken on its own is uniformly distributed whatever the key and data
+//! are. An attack has to combine two values from the same trace, which
+//! takes far more traces and a far better model of the device.
+//!
+//! # How
+//!
+//! Each block draws six mask bytes: one for the S-box input and one for
+//! its output, and one per row for the input of MixColumns. The S-box is
+//! rebuilt as a 256 byte table taking `x ^ m` to `S(x) ^ m'`, and for
+//! decryption a second one that undoes it. ShiftRows moves bytes within
+//! their rows, and MixColumns is linear, so the masks go through both
+//! unchanged or as MixColumns takes them. Between the steps the state is
+//! remasked with values worked out from the masks alone.
+//!
+//! No key schedule is stored. The key is held as two shares that XOR to
+//! it, and each block expands the round keys from them as it goes, like
+//! the [`compact`](super::compact) cipher, working on both shares at
+//! once and running SubWord through the same masked table. The shares
+//! are rerandomised with more fresh bytes before each block uses them.
+//!
+//! Masks are per block, not per call: with one mask over many blocks,
+//! the blocks of one call would leak against each other.
+//!
+//! # Cost
+//!
+//! Every block draws `6 + key size` random bytes, rebuilds one or two
+//! tables and expands the key, and every intermediate goes through an
+//! optimisation barrier, so it is slower than the compact cipher, and far
+//! slower than the T-table one, before counting the random source. A call
+//! needs some 600 bytes of stack, most of it the two tables.
+//!
+//! # Limits
+//!
+//! This is first-order masking. It stops attacks on one value at a time
+//! under a Hamming weight model, which the tests check on simulated
+//! traces. It does not stop second-order attacks that combine two
+//! values, nor, on every device, leakage from the transition between a
+//! register's old and new values, which can combine two shares that
+//! happen to follow one another through it. Masking is also only as good
+//! as the randomness behind it: a weak or stuck [`RandomSource`] leaves
+//! the cipher unmasked.
+//!
+//! Compilers know nothing of masks, and XOR is associative, so an
+//! optimiser would be within its rights to combine two shares into the
+//! value they hide. Every intermediate here passes through
+//! [`core::hint::black_box`], which LLVM treats as opaque, but Rust does
+//! not promise that. Check the code generated for the target.
+//!
+//! Taking a random source on every call, these types cannot implement
+//! [`KeyInit`](crate::symmetric::block_cipher::KeyInit) or the block
+//! cipher traits, and there is no combined both-directions type.
+
+use core::hint::black_box;
+
+use zeroize::Zeroize;
+
+use crate::rand::{RandError, RandomSource};
+
+/// The AES block size in bytes. Identical for all three key sizes.
+pub const BLOCK_SIZE: usize = 16;
+
+/// Mask bytes drawn per block before the key shares' fresh bytes.
+const MASKS: usize = 6;
+
+/// Multiply by x in GF(2^8) modulo the AES polynomial x^8+x^4+x^3+x+1.
+const fn xtime(a: u8) -> u8 {
+    (a << 1) ^ (0x1b & 0u8.wrapping_sub(a >> 7))
+}
+
+/// Divide by x in GF(2^8), undoing [`xtime`].
+const fn inv_xtime(a: u8) -> u8 {
+    (a >> 1) ^ (0x8d & 0u8.wrapping_sub(a & 1))
+}
+
+const fn mul(a: u8, b: u8) -> u8 {
+    let mut acc = 0u8;
+    let mut a = a;
+    let mut i = 0;
+    while i < 8 {
+        acc ^= a & 0u8.wrapping_sub((b >> i) & 1);
+        a = xtime(a);
+        i += 1;
+    }
+    acc
+}
+
+const fn build_sbox() -> [u8; 256] {
+    let mut s = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        // Inversion is x^254: seven squarings, multiplied together.
+        let mut square = i as u8;
+        let mut inv = 1u8;
+        let mut j = 1;
+        while j < 8 {
+            square = mul(square, square);
+            inv = mul(inv, square);
+            j += 1;
+        }
+        s[i] = inv ^ inv.rotate_left(1) ^ inv.rotate_left(2)
+            ^ inv.rotate_left(3) ^ inv.rotate_left(4) ^ 0x63;
+        i += 1;
+    }
+    s
+}
+
+/// The unmasked S-box. It is only ever indexed by public loop counters,
+/// to build the masked tables.
+static SBOX: [u8; 256] = build_sbox();
+
+/// Every intermediate value passes through here on its way to the next
+/// step.
+///
+/// The barrier keeps the optimiser from merging two steps, and with them
+/// perhaps two shares. Under test it is also where the leakage test
+/// watches the computation.
+#[inline(always)]
+fn seen(x: u8) -> u8 {
+    #[cfg(test)]
+    tests::probe(x);
+    black_box(x)
+}
+
+/// The state, in the block's own byte order: byte `r + 4 * c` is row `r`
+/// of column `c`.
+type State = [u8; BLOCK_SIZE];
+
+/// One block's masks, and the S-box tables with them built in.
+struct Masks {
+    /// Every S-box input is masked with this.
+    input: u8,
+    /// And every S-box output with this.
+    output: u8,
+    /// The masks MixColumns, or InvMixColumns, takes its input under, one
+    /// per row.
+    rows: [u8; 4],
+    /// What that step makes of the row masks.
+    mixed: [u8; 4],
+    /// `sbox[x ^ input] == S(x) ^ output`.
+    sbox: [u8; 256],
+    /// `inv_sbox[S(x) ^ input] == x ^ output`. Only built for decryption.
+    inv_sbox: [u8; 256],
+}
+
+impl Masks {
+    fn new(fresh: &[u8], inverse: bool) -> Self {
+        let mut this = Self {
+            input: fresh[0],
+            output: fresh[1],
+            rows: [fresh[2], fresh[3], fresh[4], fresh[5]],
+            mixed: [fresh[2], fresh[3], fresh[4], fresh[5]],
+            sbox: [0; 256],
+            inv_sbox: [0; 256],
+        };
+        // Every index and every unmasked entry is public; only the masks
+        // are secret.
+        for (x, s) in SBOX.iter().enumerate() {
+            let i = (x as u8 ^ this.input) as usize;
+            this.sbox[i] = seen(s ^ this.output);
+        }
+        if inverse {
+            inv_mix_column(&mut this.mixed);
+            for (x, s) in SBOX.iter().enumerate() {
+                let i = (s ^ this.input) as usize;
+                this.inv_sbox[i] = seen(x as u8 ^ this.output);
+            }
+        } else {
+            mix_column(&mut this.mixed);
+        }
+        this
+    }
+
+    /// `S(a ^ b) ^ output`, never forming `a ^ b`.
+    ///
+    /// The share `b` is traded for the input mask first, which takes only
+    /// mask values, so the table sees the value under its own mask.
+    fn sub_shared(&self, a: u8, b: u8) -> u8 {
+        let swap = seen(b ^ self.input);
+        seen(self.sbox[seen(a ^ swap) as usize])
+    }
+}
+
+impl Drop for Masks {
+    fn drop(&mut self) {
+        self.input.zeroize();
+        self.output.zeroize();
+        self.rows.zeroize();
+        self.mixed.zeroize();
+        self.sbox.zeroize();
+        self.inv_sbox.zeroize();
+    }
+}
+
+/// SubBytes, or InvSubBytes, through a masked table.
+fn sub_bytes(s: &mut State, table: &[u8; 256]) {
+    for b in s.iter_mut() {
+        *b = seen(table[*b as usize]);
+    }
+}
+
+/// Row `r` moves `r` columns left, or with `inverse` right.
+fn shift_rows(s: &mut State, inverse: bool) {
+    for r in 1..4 {
+        let row = [s[r], s[r + 4], s[r + 8], s[r + 12]];
+        let k = if inverse { 4 - r } else { r };
+        for c in 0..4 {
+            s[r + 4 * c] = row[(c + k) % 4];
+        }
+    }
+}
+
+/// Take each row from under its mask in `from` to under its mask in `to`.
+fn remask(s: &mut State, from: [u8; 4], to: [u8; 4]) {
+    for r in 0..4 {
+        let fix = seen(from[r] ^ to[r]);
+        for c in 0..4 {
+            s[r + 4 * c] = seen(s[r + 4 * c] ^ fix);
+        }
+    }
+}
+
+/// Each output byte is `a ^ 2(a ^ b) ^ (a ^ b ^ c ^ d)` for its column's
+/// bytes in order from its own, one XOR at a time.
+///
+/// The rows are under independent masks, so every partial sum is still
+/// masked by the sum of theirs.
+fn mix_column(col: &mut [u8]) {
+    let a = [col[0], col[1], col[2], col[3]];
+    let t = seen(seen(seen(a[0] ^ a[1]) ^ a[2]) ^ a[3]);
+    for r in 0..4 {
+        let d = seen(xtime(seen(a[r] ^ a[(r + 1) % 4])));
+        col[r] = seen(seen(col[r] ^ t) ^ d);
+    }
+}
+
+/// InvMixColumns is MixColumns after taking each byte `a` to
+/// `a ^ 4(a ^ c)`, where `c` is two rows on.
+fn inv_mix_column(col: &mut [u8]) {
+    let u = seen(xtime(seen(xtime(seen(col[0] ^ col[2])))));
+    let v = seen(xtime(seen(xtime(seen(col[1] ^ col[3])))));
+    col[0] = seen(col[0] ^ u);
+    col[1] = seen(col[1] ^ v);
+    col[2] = seen(col[2] ^ u);
+    col[3] = seen(col[3] ^ v);
+    mix_column(col);
+}
+
+fn mix_columns(s: &mut State) {
+    for col in s.chunks_exact_mut(4) {
+        mix_column(col);
+    }
+}
+
+fn inv_mix_columns(s: &mut State) {
+    for col in s.chunks_exact_mut(4) {
+        inv_mix_column(col);
+    }
+}
+
+/// The round count for a key of `len` bytes.
+const fn rounds(len: usize) -> usize {
+    len / 4 + 6
+}
+
+/// How many key-sized windows the expansion runs to, counting the key.
+const fn windows(len: usize) -> usize {
+    (4 * (rounds(len) + 1)).div_ceil(len / 4)
+}
+
+/// One window of the key expansion, as two shares, walked a word at a
+/// time.
+///
+/// Byte `i` of the window is `a[i] ^ b[i]`. Otherwise this is the compact
+/// cipher's window: `at` is the next word to hand out, and the window
+/// steps on or back a key's worth of words at a time.
+struct Schedule {
+    a: [u8; 32],
+    b: [u8; 32],
+    len: usize,
+    at: usize,
+    rcon: u8,
+}
+
+impl Schedule {
+    /// The window at the start of the expansion: the key itself.
+    fn first(a: &[u8], b: &[u8]) -> Self {
+        let len = a.len();
+        let mut this =
+            Self { a: [0; 32], b: [0; 32], len, at: 0, rcon: 1 };
+        this.a[..len].copy_from_slice(a);
+        this.b[..len].copy_from_slice(b);
+        this
+    }
+
+    /// The window at the end of the expansion, from its shares.
+    fn last(a: &[u8], b: &[u8]) -> Self {
+        let len = a.len();
+        let steps = windows(len) - 1;
+        let mut this = Self::first(a, b);
+        for _ in 0..steps {
+            this.rcon = xtime(this.rcon);
+        }
+        this.at = 4 * (rounds(len) + 1) - steps * (len / 4);
+        this
+    }
+
+    /// XOR the same fresh bytes into both shares, which leaves what they
+    /// hide alone and gives each a new mask.
+    fn refresh(&mut self, fresh: &[u8]) {
+        for (i, r) in fresh.iter().enumerate() {
+            self.a[i] = seen(self.a[i] ^ r);
+            self.b[i] = seen(self.b[i] ^ r);
+        }
+    }
+
+    /// Byte `i` takes `S` of byte `j` XORed in.
+    fn add_sub(&mut self, i: usize, j: usize, k: &Masks) {
+        let s = k.sub_shared(self.a[j], self.b[j]);
+        self.a[i] = seen(self.a[i] ^ s);
+        self.b[i] = seen(self.b[i] ^ k.output);
+    }
+
+    /// Byte `i` takes byte `j` XORed in.
+    fn add(&mut self, i: usize, j: usize) {
+        self.a[i] = seen(self.a[i] ^ self.a[j]);
+        self.b[i] = seen(self.b[i] ^ self.b[j]);
+    }
+
+    /// Step on to the next `Nk` words.
+    fn advance(&mut self, k: &Masks) {
+        let len = self.len;
+        // The first word takes SubWord(RotWord) of the last.
+        for j in 0..4 {
+            self.add_sub(j, len - 4 + (j + 1) % 4, k);
+        }
+        self.a[0] = seen(self.a[0] ^ self.rcon);
+        self.rcon = xtime(self.rcon);
+        for i in 4..len {
+            // AES-256 substitutes again at the midpoint of each window.
+            if len == 32 && i / 4 == 4 {
+                self.add_sub(i, i - 4, k);
+            } else {
+                self.add(i, i - 4);
+            }
+        }
+    }
+
+    /// Step back to the previous `Nk` words, undoing [`Self::advance`].
+    fn retreat(&mut self, k: &Masks) {
+        let len = self.len;
+        for i in (4..len).rev() {
+            if len == 32 && i / 4 == 4 {
+                self.add_sub(i, i - 4, k);
+            } else {
+                self.add(i, i - 4);
+            }
+        }
+        self.rcon = inv_xtime(self.rcon);
+        self.a[0] = seen(self.a[0] ^ self.rcon);
+        for j in 0..4 {
+            self.add_sub(j, len - 4 + (j + 1) % 4, k);
+        }
+    }
+
+    /// AddRoundKey with one word, taking each row of `col` from under its
+    /// mask in `from` to under its mask in `to`.
+    ///
+    /// The share `a` goes in first and then `b` already combined with
+    /// the masks, so the state is never under `b` alone.
+    fn add_word(&self, col: &mut [u8], from: [u8; 4], to: [u8; 4]) {
+        let w = 4 * self.at;
+        for r in 0..4 {
+            let fix = seen(seen(self.b[w + r] ^ from[r]) ^ to[r]);
+            col[r] = seen(seen(col[r] ^ self.a[w + r]) ^ fix);
+        }
+    }
+
+    /// AddRoundKey with the next round key, stepping on as needed.
+    fn add_next(
+        &mut self,
+        s: &mut State,
+        k: &Masks,
+        from: [u8; 4],
+        to: [u8; 4],
+    ) {
+        for col in s.chunks_exact_mut(4) {
+            if self.at == self.len / 4 {
+                self.advance(k);
+                self.at = 0;
+            }
+            self.add_word(col, from, to);
+            self.at += 1;
+        }
+    }
+
+    /// AddRoundKey with the previous round key, stepping back as needed.
+    fn add_prev(
+        &mut self,
+        s: &mut State,
+        k: &Masks,
+        from: [u8; 4],
+        to: [u8; 4],
+    ) {
+        for col in s.rchunks_exact_mut(4) {
+            if self.at == 0 {
+                self.retreat(k);
+                self.at = self.len / 4;
+            }
+            self.at -= 1;
+            self.add_word(col, from, to);
+        }
+    }
+}
+
+impl Drop for Schedule {
+    fn drop(&mut self) {
+        self.a.zeroize();
+        self.b.zeroize();
+    }
+}
+
+/// Take the last mask off the finished block.
+///
+/// What comes out is the output block, which is public, so unlike every
+/// intermediate it is not [`seen`].
+fn unmask(s: &mut State, mask: u8) {
+    for x in s.iter_mut() {
+        *x = black_box(*x ^ mask);
+    }
+}
+
+/// Draw one block's masks, and a schedule from the shares `a` and `b`
+/// rerandomised for it.
+fn prepare(
+    a: &[u8],
+    b: &[u8],
+    inverse: bool,
+    last: bool,
+    source: &mut impl RandomSource,
+) -> Result<(Masks, Schedule), RandError> {
+    let len = a.len();
+    let mut fresh = [0u8; MASKS + 32];
+    let drawn = source.fill(&mut fresh[..MASKS + len]);
+    if let Err(e) = drawn {
+        fresh.zeroize();
+        return Err(e);
+    }
+    let masks = Masks::new(&fresh[..MASKS], inverse);
+    let mut schedule =
+        if last { Schedule::last(a, b) } else { Schedule::first(a, b) };
+    schedule.refresh(&fresh[MASKS..MASKS + len]);
+    fresh.zeroize();
+    Ok((masks, schedule))
+}
+
+/// Split `key` into the shares `a` and `b`, drawing `b` from `source`.
+fn share(
+    key: &[u8],
+    a: &mut [u8],
+    b: &mut [u8],
+    source: &mut impl RandomSource,
+) -> Result<(), RandError> {
+    source.fill(b)?;
+    for i in 0..key.len() {
+        a[i] = seen(key[i] ^ b[i]);
+    }
+    Ok(())
+}
+
+/// Shares of the window a decryption starts from: the expansion of the
+/// key shared as `a ^ b` run to its end.
+fn last_window(
+    a: &mut [u8],
+    b: &mut [u8],
+    source: &mut impl RandomSource,
+) -> Result<(), RandError> {
+    let (masks, mut schedule) = prepare(a, b, false, false, source)?;
+    for _ in 1..windows(a.len()) {
+        schedule.advance(&masks);
+    }
+    a.copy_from_slice(&schedule.a[..a.len()]);
+    b.copy_from_slice(&schedule.b[..b.len()]);
+    Ok(())
+}
+
+/// One block through the encryption rounds under fresh masks.
+fn encrypt_block_with(
+    a: &[u8],
+    b: &[u8],
+    block: &mut [u8; BLOCK_SIZE],
+    source: &mut impl RandomSource,
+) -> Result<(), RandError> {
+    let (k, mut schedule) = prepare(a, b, false, false, source)?;
+    let input = [k.input; 4];
+    for x in block.iter_mut() {
+        *x = seen(*x ^ k.input);
+    }
+    schedule.add_next(block, &k, input, input);
+    let rounds = rounds(a.len());
+    for round in 1..=rounds {
+        sub_bytes(block, &k.sbox);
+        shift_rows(block, false);
+        if round != rounds {
+            remask(block, [k.output; 4], k.rows);
+            mix_columns(block);
+            schedule.add_next(block, &k, k.mixed, input);
+        } else {
+            schedule.add_next(block, &k, [k.output; 4], input);
+        }
+    }
+    unmask(block, k.input);
+    Ok(())
+}
+
+/// One block through the decryption rounds under fresh masks, from the
+/// window at the end of the expansion back to the key.
+fn decrypt_block_with(
+    a: &[u8],
+    b: &[u8],
+    block: &mut [u8; BLOCK_SIZE],
+    source: &mut impl RandomSource,
+) -> Result<(), RandError> {
+    let (k, mut schedule) = prepare(a, b, true, true, source)?;
+    let input = [k.input; 4];
+    for x in block.iter_mut() {
+        *x = seen(*x ^ k.input);
+    }
+    schedule.add_prev(block, &k, input, input);
+    let rounds = rounds(a.len());
+    for round in 1..=rounds {
+        if round != 1 {
+            inv_mix_columns(block);
+            remask(block, k.mixed, input);
+        }
+        shift_rows(block, true);
+        sub_bytes(block, &k.inv_sbox);
+        let to = if round != rounds { k.rows } else { input };
+        schedule.add_prev(block, &k, [k.output; 4], to);
+    }
+    unmask(block, k.input);
+    Ok(())
+}
+
+macro_rules! define_aes {
+    ($enc:ident, $dec:ident, $key_size:expr, $bits:expr) => {
+        #[doc = concat!("AES-", $bits, " encryption only, masked.")]
+        ///
+        /// Holds the key as two shares, and expands it afresh under new
+        /// masks for every block.
+        pub struct $enc {
+            a: [u8; $key_size],
+            b: [u8; $key_size],
+        }
+
+        #[doc = concat!("AES-", $bits, " decryption only, masked.")]
+        ///
+        /// Holds the last key-sized window of the expansion as two
+        /// shares, and expands backwards from it under new masks for
+        /// every block.
+        pub struct $dec {
+            a: [u8; $key_size],
+            b: [u8; $key_size],
+        }
+
+        impl $enc {
+            /// The key length in bytes.
+            pub const KEY_SIZE: usize = $key_size;
+            /// The block length in bytes.
+            pub const BLOCK_SIZE: usize = BLOCK_SIZE;
+
+            /// Keep `key` split into two shares, one drawn from
+            /// `source`.
+            pub fn new(
+                key: &[u8; $key_size],
+                source: &mut impl RandomSource,
+            ) -> Result<Self, RandError> {
+                let mut this =
+                    Self { a: [0u8; $key_size], b: [0u8; $key_size] };
+                share(key, &mut this.a, &mut this.b, source)?;
+                Ok(this)
+            }
+
+            /// Encrypt whole blocks in place, each under masks drawn
+            /// from `source`, returning bytes consumed.
+            ///
+            /// If `source` fails, so does the call, with the blocks
+            /// before that one encrypted and the rest untouched.
+            pub fn encrypt(
+                &self,
+                data: &mut [u8],
+                source: &mut impl RandomSource,
+            ) -> Result<usize, RandError> {
+                let (blocks, _tail) = data.as_chunks_mut::<BLOCK_SIZE>();
+                for block in blocks.iter_mut() {
+                    encrypt_block_with(&self.a, &self.b, block, source)?;
+                }
+                Ok(blocks.len() * BLOCK_SIZE)
+            }
+
+            /// Encrypt exactly one block in place, under masks drawn
+            /// from `source`.
+            pub fn encrypt_block(
+                &self,
+                block: &mut [u8; BLOCK_SIZE],
+                source: &mut impl RandomSource,
+            ) -> Result<(), RandError> {
+                encrypt_block_with(&self.a, &self.b, block, source)
+            }
+        }
+
+        impl $dec {
+            /// The key length in bytes.
+            pub const KEY_SIZE: usize = $key_size;
+            /// The block length in bytes.
+            pub const BLOCK_SIZE: usize = BLOCK_SIZE;
+
+            /// Split `key` into two shares and run its expansion to the
+            /// end on them, masked, with randomness from `source`.
+            pub fn new(
+                key: &[u8; $key_size],
+                source: &mut impl RandomSource,
+            ) -> Result<Self, RandError> {
+                let mut this =
+                    Self { a: [0u8; $key_size], b: [0u8; $key_size] };
+                share(key, &mut this.a, &mut this.b, source)?;
+                last_window(&mut this.a, &mut this.b, source)?;
+                Ok(this)
+            }
+
+            /// Decrypt whole blocks in place, each under masks drawn
+            /// from `source`, returning bytes consumed.
+            ///
+            /// If `source` fails, so does the call, with the blocks
+            /// before that one decrypted and the rest untouched.
+            pub fn decrypt(
+                &self,
+                data: &mut [u8],
+                source: &mut impl RandomSource,
+            ) -> Result<usize, RandError> {
+                let (blocks, _tail) = data.as_chunks_mut::<BLOCK_SIZE>();
+                for block in blocks.iter_mut() {
+                    decrypt_block_with(&self.a, &self.b, block, source)?;
+                }
+                Ok(blocks.len() * BLOCK_SIZE)
+            }
+
+            /// Decrypt exactly one block in place, under masks drawn
+            /// from `source`.
+            pub fn decrypt_block(
+                &self,
+                block: &mut [u8; BLOCK_SIZE],
+                source: &mut impl RandomSource,
+            ) -> Result<(), RandError> {
+                decrypt_block_with(&self.a, &self.b, block, source)
+            }
+        }
+
+        impl Drop for $enc {
+            fn drop(&mut self) {
+                self.a.zeroize();
+                self.b.zeroize();
+            }
+        }
+
+        impl Drop for $dec {
+            fn drop(&mut self) {
+                self.a.zeroize();
+                self.b.zeroize();
+            }
+        }
+
+        impl core::fmt::Debug for $enc {
+            fn fmt(
+                &self,
+                f: &mut core::fmt::Formatter<'_>,
+            ) -> core::fmt::Result {
+                // Never format key material, even shared.
+                f.write_str(concat!(stringify!($enc), " { .. }"))
+            }
+        }
+
+        impl core::fmt::Debug for $dec {
+            fn fmt(
+                &self,
+                f: &mut core::fmt::Formatter<'_>,
+            ) -> core::fmt::Result {
+                f.write_str(concat!(stringify!($dec), " { .. }"))
+            }
+        }
+    };
+}
+
+define_aes!(Aes128Enc, Aes128Dec, 16, "128");
+define_aes!(Aes192Enc, Aes192Dec, 24, "192");
+define_aes!(Aes256Enc, Aes256Dec, 32, "256");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symmetric::aes::arch::portable::ttable;
+    use std::cell::RefCell;
+    use std::vec::Vec;
+
+    std::thread_local! {
+        /// The simulated trace being recorded, if one is.
+        static TRACE: RefCell<Option<Vec<u8>>> = const { RefCell::new(None) };
+    }
+
+    /// The leakage model: a device leaks the Hamming weight of every
+    /// value it computes.
+    pub(super) fn probe(x: u8) {
+        TRACE.with(|t| {
+            if let Some(trace) = t.borrow_mut().as_mut() {
+                trace.push(x.count_ones() as u8);
+            }
+        });
+    }
+
+    /// The simulated trace of `f`.
+    fn trace(f: impl FnOnce()) -> Vec<u8> {
+        TRACE.with(|t| *t.borrow_mut() = Some(Vec::new()));
+        f();
+        TRACE.with(|t| t.borrow_mut().take().unwrap())
+    }
+
+    /// A deterministic source, so the tests are repeatable.
+    struct Rng(u64);
+
+    impl RandomSource for Rng {
+        fn fill(&mut self, buf: &mut [u8]) -> Result<(), RandError> {
+            for b in buf {
+                // SplitMix64.
+                self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+                let mut z = self.0;
+                z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+                *b = (z ^ (z >> 31)) as u8;
+            }
+            Ok(())
+        }
+    }
+
+    /// A stuck source: every mask zero, which is AES unmasked.
+    struct Zeros;
+
+    impl RandomSource for Zeros {
+        fn fill(&mut self, buf: &mut [u8]) -> Result<(), RandError> {
+            buf.fill(0);
+            Ok(())
+        }
+    }
+
+    /// Fails after handing out `0` bytes.
+    struct Runs(usize);
+
+    impl RandomSource for Runs {
+        fn fill(&mut self, buf: &mut [u8]) -> Result<(), RandError> {
+            if buf.len() > self.0 {
+                return Err(RandError::from_raw_os_error(5));
+            }
+            self.0 -= buf.len();
+            Rng(self.0 as u64).fill(buf)
+        }
+    }
+
+    const FIPS_PLAINTEXT: [u8; 16] = [
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa,
+        0xbb, 0xcc, 0xdd, 0xee, 0xff,
+    ];
+
+    /// FIPS-197 Appendix C, all three key sizes.
+    #[test]
+    fn fips_197_all_key_sizes() {
+        macro_rules! check {
+            ($enc:ident, $dec:ident, $len:expr, $expected:expr) => {
+                let mut rng = Rng(1);
+                let mut key = [0u8; $len];
+                for (i, b) in key.iter_mut().enumerate() {
+                    *b = i as u8;
+                }
+                let mut block = FIPS_PLAINTEXT;
+                let enc = $enc::new(&key, &mut rng).unwrap();
+                enc.encrypt_block(&mut block, &mut rng).unwrap();
+                assert_eq!(block, $expected);
+                let dec = $dec::new(&key, &mut rng).unwrap();
+                dec.decrypt_block(&mut block, &mut rng).unwrap();
+                assert_eq!(block, FIPS_PLAINTEXT);
+            };
+        }
+        check!(Aes128Enc, Aes128Dec, 16, [
+            0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30, 0xd8, 0xcd,
+            0xb7, 0x80, 0x70, 0xb4, 0xc5, 0x5a,
+        ]);
+        check!(Aes192Enc, Aes192Dec, 24, [
+            0xdd, 0xa9, 0x7c, 0xa4, 0x86, 0x4c, 0xdf, 0xe0, 0x6e, 0xaf,
+            0x70, 0xa0, 0xec, 0x0d, 0x71, 0x91,
+        ]);
+        check!(Aes256Enc, Aes256Dec, 32, [
+            0x8e, 0xa2, 0xb7, 0xca, 0x51, 0x67, 0x45, 0xbf, 0xea, 0xfc,
+            0x49, 0x90, 0x4b, 0x49, 0x60, 0x89,
+        ]);
+    }
+
+    /// Whole buffers, every key size, and masks that differ from block
+    /// to block, against the T-table cipher.
+    #[test]
+    fn agrees_with_the_t_table_cipher() {
+        macro_rules! check {
+            ($enc:ident, $dec:ident, $len:expr) => {
+                let mut rng = Rng(2);
+                let key = [0x5au8; $len];
+                let mut data = [0u8; 16 * 5];
+                for (i, b) in data.iter_mut().enumerate() {
+                    *b = (i as u8).wrapping_mul(0x3b);
+                }
+                let mut reference = data;
+                ttable::$enc::new(&key).encrypt(&mut reference);
+                let mut ours = data;
+                let enc = $enc::new(&key, &mut rng).unwrap();
+                assert_eq!(enc.encrypt(&mut ours, &mut rng), Ok(data.len()));
+                assert_eq!(ours, reference);
+                let dec = $dec::new(&key, &mut rng).unwrap();
+                assert_eq!(dec.decrypt(&mut ours, &mut rng), Ok(data.len()));
+                assert_eq!(ours, data);
+            };
+        }
+        check!(Aes128Enc, Aes128Dec, 16);
+        check!(Aes192Enc, Aes192Dec, 24);
+        check!(Aes256Enc, Aes256Dec, 32);
+    }
+
+    /// The shares hide the key, and are not the same from one value to
+    /// the next.
+    #[test]
+    fn keys_are_held_as_fresh_shares() {
+        let key = [0x2bu8; 24];
+        let mut rng = Rng(3);
+        let one = Aes192Enc::new(&key, &mut rng).unwrap();
+        let two = Aes192Enc::new(&key, &mut rng).unwrap();
+        assert_ne!(one.a, key);
+        assert_ne!(one.a, two.a);
+        for (i, k) in key.iter().enumerate() {
+            assert_eq!(one.a[i] ^ one.b[i], *k);
+            assert_eq!(two.a[i] ^ two.b[i], *k);
+        }
+    }
+
+    /// Stepping a masked window back undoes stepping it on, all the way
+    /// from the end of the expansion to the key, at every key size.
+    #[test]
+    fn retreating_undoes_advancing() {
+        let mut rng = Rng(4);
+        for len in [16, 24, 32] {
+            let key: Vec<u8> =
+                (0..len as u8).map(|i| i.wrapping_mul(0x9d)).collect();
+            let mut a = vec![0u8; len];
+            let mut b = vec![0u8; len];
+            share(&key, &mut a, &mut b, &mut rng).unwrap();
+            last_window(&mut a, &mut b, &mut rng).unwrap();
+            let (masks, mut schedule) =
+                prepare(&a, &b, true, true, &mut rng).unwrap();
+            for _ in 1..windows(len) {
+                schedule.retreat(&masks);
+            }
+            for (i, k) in key.iter().enumerate() {
+                let byte = schedule.a[i] ^ schedule.b[i];
+                assert_eq!(byte, *k, "AES-{}", 8 * len);
+            }
+            assert_eq!(schedule.rcon, 1);
+        }
+    }
+
+    #[test]
+    fn masked_tables_invert_each_other() {
+        let masks = Masks::new(&[0x3c, 0xa5, 1, 2, 3, 4], true);
+        for x in 0..=255u8 {
+            let y = masks.sbox[(x ^ 0x3c) as usize];
+            assert_eq!(y ^ 0xa5, SBOX[x as usize]);
+            assert_eq!(masks.inv_sbox[(y ^ 0xa5 ^ 0x3c) as usize], x ^ 0xa5);
+        }
+    }
+
+    #[test]
+    fn a_failing_source_fails_the_call() {
+        let mut rng = Rng(5);
+        let key = [7u8; 16];
+        let enc = Aes128Enc::new(&key, &mut rng).unwrap();
+        let mut data = [0u8; 48];
+        let mut reference = data;
+        ttable::Aes128Enc::new(&key).encrypt(&mut reference[..16]);
+
+        // Enough for one block's masks and key shares, not two.
+        let err = enc.encrypt(&mut data, &mut Runs(MASKS + 16 + 3));
+        assert_eq!(err, Err(RandError::from_raw_os_error(5)));
+        assert_eq!(data[..16], reference[..16]);
+        assert_eq!(data[16..], [0u8; 32], "later blocks must be untouched");
+
+        assert!(Aes128Dec::new(&key, &mut Runs(16)).is_err());
+    }
+
+    #[test]
+    fn partial_trailing_block_is_left_alone() {
+        let mut rng = Rng(6);
+        let aes = Aes256Enc::new(&[0u8; 32], &mut rng).unwrap();
+        let mut data = [0xccu8; 16 + 5];
+
+        assert_eq!(aes.encrypt(&mut data, &mut rng), Ok(16));
+        assert_eq!(&data[16..], &[0xcc; 5], "tail must be untouched");
+    }
+
+    /// The largest Welch's t statistic, over every sample, between two
+    /// sets of traces.
+    fn max_t(fixed: &[Vec<u8>], random: &[Vec<u8>]) -> f64 {
+        let samples = fixed[0].len();
+        // Weights are small, so exact integer sums keep this quick even
+        // unoptimised.
+        let moments = |traces: &[Vec<u8>]| {
+            let mut sx = vec![0u64; samples];
+            let mut sxx = vec![0u64; samples];
+            for trace in traces {
+                assert_eq!(trace.len(), samples, "traces must line up");
+                for ((sx, sxx), &x) in sx.iter_mut().zip(&mut sxx).zip(trace)
+                {
+                    *sx += x as u64;
+                    *sxx += (x as u64) * (x as u64);
+                }
+            }
+            let n = traces.len() as f64;
+            let mean: Vec<f64> = sx.iter().map(|&s| s as f64 / n).collect();
+            let var: Vec<f64> = (0..samples)
+                .map(|t| (sxx[t] as f64 / n - mean[t] * mean[t]) / n)
+                .collect();
+            (mean, var)
+        };
+        let (m0, v0) = moments(fixed);
+        let (m1, v1) = moments(random);
+        let mut max = 0f64;
+        for t in 0..samples {
+            let v = v0[t] + v1[t];
+            if v > 0.0 {
+                max = max.max(((m0[t] - m1[t]) / v.sqrt()).abs());
+            } else if m0[t] != m1[t] {
+                // Neither set varies, and they differ.
+                return f64::INFINITY;
+            }
+        }
+        max
+    }
+
+    const TRACES: usize = 1000;
+
+    /// The usual pass mark for the test, a little under five deviations.
+    const THRESHOLD: f64 = 4.5;
+
+    /// A fixed-versus-random leakage test on simulated traces, under the
+    /// Hamming weight model: `run` traces one fixed input and then a
+    /// random one, over and over, and the two sets are compared at every
+    /// point of the computation. Any value whose distribution depends on
+    /// the input, alone or with others in the same byte, shows up as a
+    /// difference in means.
+    fn leakage(count: usize, mut run: impl FnMut(&[u8; 16]) -> Vec<u8>) -> f64 {
+        let mut inputs = Rng(16);
+        let mut fixed = Vec::with_capacity(count);
+        let mut random = Vec::with_capacity(count);
+        for _ in 0..count {
+            fixed.push(run(&[0x42; 16]));
+            let mut input = [0u8; 16];
+            inputs.fill(&mut input).unwrap();
+            random.push(run(&input));
+        }
+        max_t(&fixed, &random)
+    }
+
+    /// Varying the data. The block going in and coming out is public, so
+    /// only what happens in between is traced.
+    ///
+    /// Unmasked, with a stuck source, the data is all over the trace and
+    /// a hundred traces show it. Masked, nothing does.
+    #[test]
+    fn encryption_has_no_first_order_leakage() {
+        let key: [u8; 16] = core::array::from_fn(|i| (i as u8) ^ 0xa7);
+        let enc = Aes128Enc::new(&key, &mut Rng(17)).unwrap();
+        let check = |mut source: &mut dyn RandomSource, count| {
+            leakage(count, |input| {
+                let mut block = *input;
+                trace(|| enc.encrypt_block(&mut block, &mut source).unwrap())
+            })
+        };
+        assert!(check(&mut Zeros, 100) > THRESHOLD, "must see leakage");
+        let t = check(&mut Rng(18), TRACES);
+        assert!(t < THRESHOLD, "leaks with t = {t}");
+    }
+
+    #[test]
+    fn decryption_has_no_first_order_leakage() {
+        let key: [u8; 32] = core::array::from_fn(|i| (i as u8) ^ 0x5c);
+        let dec = Aes256Dec::new(&key, &mut Rng(19)).unwrap();
+        let check = |mut source: &mut dyn RandomSource, count| {
+            leakage(count, |input| {
+                let mut block = *input;
+                trace(|| dec.decrypt_block(&mut block, &mut source).unwrap())
+            })
+        };
+        assert!(check(&mut Zeros, 100) > THRESHOLD, "must see leakage");
+        let t = check(&mut Rng(20), TRACES);
+        assert!(t < THRESHOLD, "leaks with t = {t}");
+    }
+
+    /// Varying the key, from splitting it into shares through running the
+    /// expansion to its end and back again over one block.
+    #[test]
+    fn key_schedule_has_no_first_order_leakage() {
+        let check = |mut source: &mut dyn RandomSource, count| {
+            leakage(count, |key| {
+                let mut block = [0u8; 16];
+                trace(|| {
+                    let dec = Aes128Dec::new(key, &mut source).unwrap();
+                    dec.decrypt_block(&mut block, &mut source).unwrap();
+                })
+            })
+        };
+        assert!(check(&mut Zeros, 100) > THRESHOLD, "must see leakage");
+        let t = check(&mut Rng(21), TRACES);
+        assert!(t < THRESHOLD, "leaks with t = {t}");
+    }
+
+    #[test]
+    fn keys_are_wiped_on_drop() {
+        let aes = Aes256Dec::new(&[0xab; 32], &mut Rng(14)).unwrap();
+        let mut slot = core::mem::MaybeUninit::new(aes);
+        let ptr = slot.as_mut_ptr();
+        let bytes = ptr.cast::<u8>();
+        let len = core::mem::size_of::<Aes256Dec>();
+        // SAFETY: slot holds an initialized value of exactly this size.
+        let live = unsafe { core::slice::from_raw_parts(bytes, len) };
+        assert!(live.iter().any(|&b| b != 0));
+        // SAFETY: the value is never read as a value again.
+        unsafe { core::ptr::drop_in_place(ptr) };
+        // SAFETY: the storage is ours and still allocated.
+        let dead = unsafe { core::slice::from_raw_parts(bytes, len) };
+        assert!(dead.iter().all(|&b| b == 0), "key material survived");
+    }
+
+    #[test]
+    fn debug_does_not_leak_keys() {
+        let aes = Aes128Enc::new(&[0xab; 16], &mut Rng(15)).unwrap();
+        assert_eq!(format!("{aes:?}"), "Aes128Enc { .. }");
+    }
+}
@@ -24,6 +24,8 @@ mod tables;
 use tables::{INV_SBOX, RCON, SBOX, TD, TE};
 
 use zeroize::Zeroize;
+#[cfg(feature = "hazmat")]
+use zeroize::Zeroizing;
 
 use crate::symmetric::block_cipher::{
     BlockDecrypt, BlockEncrypt, InvalidKeyLength, KeyInit,
@@ -84,24 +86,44 @@ fn expand_encrypt_key<const W: usize>(
 /// InvMixColumns, which lets decryption use the same table-driven round
 /// structure as encryption instead of a separate slower one.
 fn derive_decrypt_key<const W: usize>(rk: &mut [u32; W]) {
-    let rounds = W / 4 - 1;
+    reverse_rounds(rk);
+
+    // The first and last round keys are used before and after the table
+    // rounds, so they stay untransformed.
+    for word in rk[4..W - 4].iter_mut() {
+        *word = inv_mix_column(*word);
+    }
+}
 
+/// Undo [`derive_decrypt_key`], recovering the encryption round keys.
+#[cfg(feature = "hazmat")]
+fn recover_encrypt_key<const W: usize>(rk: &mut [u32; W]) {
+    // MixColumns has order four, so InvMixColumns three times over is
+    // MixColumns, and no forward table is needed.
+    for word in rk[4..W - 4].iter_mut() {
+        *word = inv_mix_column(inv_mix_column(inv_mix_column(*word)));
+    }
+    reverse_rounds(rk);
+}
+
+/// Reverse the order of the round keys, four words at a time.
+fn reverse_rounds<const W: usize>(rk: &mut [u32; W]) {
+    let rounds = W / 4 - 1;
     for i in 0..rounds.div_ceil(2) {
         let (lo, hi) = (4 * i, 4 * (rounds - i));
         for j in 0..4 {
             rk.swap(lo + j, hi + j);
         }
     }
+}
 
-    // The first and last round keys are used before and after the table
-    // rounds, so they stay untransformed.
-    for word in rk[4..W - 4].iter_mut() {
-        let w = *word;
-        *word = TD[0][SBOX[byte(w, 0)] as usize]
-            ^ TD[1][SBOX[byte(w, 1)] as usize]
-            ^ TD[2][SBOX[byte(w, 2)] as usize]
-            ^ TD[3][SBOX[byte(w, 3)] as usize];
-    }
+/// InvMixColumns on one column. The decryption tables fold in InvSubBytes,
+/// so each byte goes through the S-box first to cancel it.
+fn inv_mix_column(w: u32) -> u32 {
+    TD[0][SBOX[byte(w, 0)] as usize]
+        ^ TD[1][SBOX[byte(w, 1)] as usize]
+        ^ TD[2][SBOX[byte(w, 2)] as usize]
+        ^ TD[3][SBOX[byte(w, 3)] as usize]
 }
 
 /// The state as four columns.
@@ -436,7 +458,64 @@ macro_rules! define_aes {
             }
         }
 
+        /// Invert an existing encryption schedule, so the key itself
+        /// need not be kept around in case decryption is wanted later.
+        impl From<&$enc> for $dec {
+            fn from(enc: &$enc) -> Self {
+                let mut this = Self { rk: enc.rk };
+                derive_decrypt_key(&mut this.rk);
+                this
+            }
+        }
+
+        #[cfg(feature = "hazmat")]
+        impl $enc {
+            /// Copy out the round keys, in the [canonical layout].
+            ///
+            /// [canonical layout]: crate::symmetric::aes#round-keys
+            pub fn round_keys(&self) -> Zeroizing<[u8; 4 * $words]> {
+                let mut out = Zeroizing::new([0u8; 4 * $words]);
+                let (chunks, _) = out.as_chunks_mut::<4>();
+                for (chunk, word) in chunks.iter_mut().zip(&self.rk) {
+                    *chunk = word.to_le_bytes();
+                }
+                out
+            }
+
+            /// Take round keys in the canonical layout as they are.
+            ///
+            /// Nothing checks that they came from a key expansion.
+            pub fn from_round_keys(round_keys: &[u8; 4 * $words]) -> Self {
+                let mut this = Self { rk: [0u32; $words] };
+                let (chunks, _) = round_keys.as_chunks::<4>();
+                for (word, chunk) in this.rk.iter_mut().zip(chunks) {
+                    *word = u32::from_le_bytes(*chunk);
+                }
+                this
+            }
+        }
+
+        #[cfg(feature = "hazmat")]
+        impl $dec {
+            /// Copy out the encryption round keys this schedule was
+            /// inverted from, in the canonical layout.
+            pub fn round_keys(&self) -> Zeroizing<[u8; 4 * $words]> {
+                let mut enc = $enc { rk: self.rk };
+                recover_encrypt_key(&mut enc.rk);
+                enc.round_keys()
+            }
+
+            /// Invert encryption round keys in the canonical layout.
+            ///
+            /// Nothing checks that they came from a key expansion.
+            pub fn from_round_keys(round_keys: &[u8; 4 * $words]) -> Self {
+                Self::from(&$enc::from_round_keys(round_keys))
+            }
+        }
+
         impl KeyInit for $enc {
+            type Error = InvalidKeyLength;
+
             fn try_new(key: &[u8]) -> Result<Self, InvalidKeyLength> {
                 let key: &[u8; $key_size] = key
                     .try_into()
@@ -446,6 +525,8 @@ macro_rules! define_aes {
         }
 
         impl KeyInit for $dec {
+            type Error = InvalidKeyLength;
+
             fn try_new(key: &[u8]) -> Result<Self, InvalidKeyLength> {
                 let key: &[u8; $key_size] = key
                     .try_into()
@@ -455,6 +536,8 @@ macro_rules! define_aes {
         }
 
         impl KeyInit for $both {
+            type Error = InvalidKeyLength;
+
             fn try_new(key: &[u8]) -> Result<Self, InvalidKeyLength> {
                 let key: &[u8; $key_size] = key
                     .try_into()
@@ -632,6 +715,65 @@ mod tests {
         assert_eq!(split, plaintext);
     }
 
+    /// Inverting an encryption schedule must give exactly the schedule
+    /// `Dec::new` expands, at every key size.
+    #[test]
+    fn inverting_matches_expanding() {
+        macro_rules! check {
+            ($enc:ident, $dec:ident, $len:expr) => {
+                let mut key = [0u8; $len];
+                for (i, b) in key.iter_mut().enumerate() {
+                    *b = (i as u8).wrapping_mul(0x9d);
+                }
+                let inverted = $dec::from(&$enc::new(&key));
+                assert_eq!(inverted.rk, $dec::new(&key).rk);
+            };
+        }
+        check!(Aes128Enc, Aes128Dec, 16);
+        check!(Aes192Enc, Aes192Dec, 24);
+        check!(Aes256Enc, Aes256Dec, 32);
+    }
+
+    /// FIPS-197 Appendix A.1: the schedule starts with the key and ends
+    /// with w[40..44], and the decryption type exports the same bytes.
+    #[cfg(feature = "hazmat")]
+    #[test]
+    fn round_keys_are_the_fips_197_expansion() {
+        let key = [
+            0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15,
+            0x88, 0x09, 0xcf, 0x4f, 0x3c,
+        ];
+        let last = [
+            0xd0, 0x14, 0xf9, 0xa8, 0xc9, 0xee, 0x25, 0x89, 0xe1, 0x3f, 0x0c,
+            0xc8, 0xb6, 0x63, 0x0c, 0xa6,
+        ];
+        let rk = Aes128Enc::new(&key).round_keys();
+        assert_eq!(rk[..16], key);
+        assert_eq!(rk[160..], last);
+        assert_eq!(*Aes128Dec::new(&key).round_keys(), *rk);
+    }
+
+    /// Whatever goes in comes back out, and schedules loaded that way
+    /// are the ones expansion would have built.
+    #[cfg(feature = "hazmat")]
+    #[test]
+    fn from_round_keys_round_trips() {
+        macro_rules! check {
+            ($enc:ident, $dec:ident, $len:expr) => {
+                let key = [0x5au8; $len];
+                let rk = $enc::new(&key).round_keys();
+                let enc = $enc::from_round_keys(&rk);
+                assert_eq!(enc.rk, $enc::new(&key).rk);
+                let dec = $dec::from_round_keys(&rk);
+                assert_eq!(dec.rk, $dec::new(&key).rk);
+                assert_eq!(*dec.round_keys(), *rk);
+            };
+        }
+        check!(Aes128Enc, Aes128Dec, 16);
+        check!(Aes192Enc, Aes192Dec, 24);
+        check!(Aes256Enc, Aes256Dec, 32);
+    }
+
     #[test]
     fn bulk_matches_block_at_a_time() {
         let aes = Aes128Enc::new(&[0x2bu8; 16]);
@@ -0,0 +1,36 @@
+//! Clearing the stack the hardware key schedules ran on.
+//!
+//! Round keys are wiped when the value holding them drops, but expanding
+//! them passes key material through the expansion routine's own frame: a
+//! spilled register, a word array, an intrinsic's temporary. Returning
+//! releases that frame without clearing it, and the next deep call, or a
+//! core dump, finds it there.
+//!
+//! Each backend clears its own vector registers, since only it knows which
+//! ones its kernels write. The stack is the same everywhere, so the one
+//! routine lives here.
+
+use zeroize::Zeroize;
+
+/// Bytes of stack cleared beneath the caller.
+///
+/// Optimised, the expansion routines keep everything in registers and
+/// have no frame at all, so a kilobyte is room for a future spill or two.
+/// Unoptimised, every intrinsic is a call and every value has a stack
+/// slot, and the deepest routine takes a little over six kilobytes.
+/// Whether debug assertions are on is the nearest thing to the
+/// optimisation level a build can see; clearing eight kilobytes costs
+/// several times what expanding the key does, so it is not done where it
+/// is not needed.
+const DEPTH: usize = if cfg!(debug_assertions) { 8192 } else { 1024 };
+
+/// Overwrite the stack beneath the caller with zeros.
+///
+/// Called straight after an expansion routine returns, from the function
+/// that called it, this frame lands where that routine's did.
+#[inline(never)]
+pub(crate) fn stack() {
+    let mut frame = [0u128; DEPTH / 16];
+    // Volatile, so the stores survive although nothing reads them back.
+    frame.zeroize();
+}
@@ -0,0 +1,7 @@
+//! AES implementations using 32-bit x86 instructions.
+//!
+//! Only targets with SSE2 build these: the key schedule and the helpers
+//! around the kernels are SSE2 intrinsics, which every CPU with the AES
+//! instructions has anyway.
+
+pub mod aesni;
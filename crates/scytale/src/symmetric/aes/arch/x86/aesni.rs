@@ -0,0 +1,1986 @@
+//! AES on the AES instructions, for 32-bit x86.
+//!
+//! These types can only be constructed on a CPU that has the instructions,
+//! which [`supported`] reports. Naming them directly is for callers who have
+//! already established that; everything else should use the parent module's
+//! names, which check once and choose.
+//!
+//! This is the x86_64 backend's cipher and key schedule, with kernels cut
+//! down to the eight vector registers and handful of general purpose ones
+//! a 32-bit build can name. Like it, it is constant time with respect to
+//! the key: the round transformation is a single instruction with no data
+//! dependent memory access.
+//!
+//! The kernels are hand written assembly, fully unrolled, with every round
+//! key named by a fixed offset. The register file holds no more than one
+//! round key beside the blocks, so every kernel streams the schedule from
+//! memory one key per round.
+
+use core::arch::asm;
+use core::arch::x86::*;
+use core::sync::atomic::{Ordering, compiler_fence};
+
+#[cfg(feature = "hazmat")]
+use zeroize::Zeroizing;
+
+use crate::cpu::{Feature, UnsupportedCpu};
+use crate::symmetric::block_cipher::{
+    BlockDecrypt, BlockEncrypt, InvalidKeyLength, KeyInit, KeyInitError,
+};
+
+/// The AES block size in bytes.
+pub const BLOCK_SIZE: usize = 16;
+
+/// Blocks processed per iteration of the bulk loop.
+///
+/// Six independent blocks cover `aesenc`'s latency on every core that has
+/// it, and six and a round key are what the eight vector registers hold
+/// with one left over for the counter kernel's base counter. The x86_64
+/// backend's twelve would need twice the registers.
+const WIDTH: usize = 6;
+
+/// Whether this CPU has the AES instructions.
+///
+/// The answer cannot change while the process runs, so it is worked out
+/// once and remembered. A build for a target that guarantees the
+/// instructions does not ask at all.
+pub fn supported() -> bool {
+    if cfg!(target_feature = "aes") {
+        return true;
+    }
+    use std::sync::OnceLock;
+    static SUPPORTED: OnceLock<bool> = OnceLock::new();
+    *SUPPORTED.get_or_init(|| is_x86_feature_detected!("aes"))
+}
+
+/// What [`supported`] checks for, naming whichever the CPU lacks.
+fn unsupported() -> UnsupportedCpu {
+    UnsupportedCpu::lacking(&[Feature::Aes])
+}
+
+/// Whether this CPU can run the counter kernels.
+///
+/// They need SSSE3's `pshufb` alongside the AES instructions to byte
+/// reverse counters in registers. Every CPU with AES also has SSSE3 in
+/// practice, but the feature bits are formally independent, so it is
+/// checked rather than assumed.
+pub fn ctr_supported() -> bool {
+    if cfg!(all(target_feature = "aes", target_feature = "ssse3")) {
+        return true;
+    }
+    use std::sync::OnceLock;
+    static SUPPORTED: OnceLock<bool> = OnceLock::new();
+    *SUPPORTED
+        .get_or_init(|| supported() && is_x86_feature_detected!("ssse3"))
+}
+
+/// The counter kernel's constants, in one table so that they cost it one
+/// general purpose register rather than three.
+///
+/// The `pshufb` selector that reverses all sixteen bytes, turning a
+/// big-endian counter block into a little-endian integer and back, is at
+/// offset zero. Then come quadword pairs {low, high} adding 1 through 6
+/// to a little-endian counter's low quadword, one row each; the last is
+/// also the kernel's per-iteration advance. The high quadword is
+/// untouched: the driver only enters the kernel when the low one cannot
+/// carry.
+#[repr(C, align(16))]
+struct Counting {
+    bswap: [u8; 16],
+    offsets: [u64; 12],
+}
+
+static COUNTING: Counting = Counting {
+    bswap: [15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0],
+    offsets: [1, 0, 2, 0, 3, 0, 4, 0, 5, 0, 6, 0],
+};
+
+/// The wide counter kernel's base counter, as a little-endian integer.
+///
+/// There is no register to spare for it, so it stays in memory, aligned
+/// so that the kernel can use it as an operand.
+#[repr(C, align(16))]
+struct Base([u8; 16]);
+
+/// A fully unrolled 6 block kernel.
+///
+/// Six blocks and a round key leave one of the eight registers free, which
+/// the counter kernel, with the same shape, loads its input through. Each
+/// round key is loaded once and used for all six.
+macro_rules! kernel6 {
+    (
+        $name:ident, $round:literal, $last:literal,
+        [$($key:literal),+], $final:literal
+    ) => {
+        /// # Safety
+        ///
+        /// The CPU must have the AES instructions. `rk` must
+        /// hold the whole schedule and `data` 6 blocks.
+        #[inline(always)]
+        unsafe fn $name(rk: *const u8, data: *mut u8) {
+            // SAFETY: the caller guarantees the instructions
+            // and both ranges.
+            unsafe {
+                asm!(
+                    "movups xmm0, [{d} + 0x00]",
+                    "movups xmm1, [{d} + 0x10]",
+                    "movups xmm2, [{d} + 0x20]",
+                    "movups xmm3, [{d} + 0x30]",
+                    "movups xmm4, [{d} + 0x40]",
+                    "movups xmm5, [{d} + 0x50]",
+                    "movups xmm6, [{rk}]",
+                    "xorps xmm0, xmm6",
+                    "xorps xmm1, xmm6",
+                    "xorps xmm2, xmm6",
+                    "xorps xmm3, xmm6",
+                    "xorps xmm4, xmm6",
+                    "xorps xmm5, xmm6",
+                    $(
+                        concat!("movups xmm6, [{rk} + ", $key, "]"),
+                        concat!($round, " xmm0, xmm6"),
+                        concat!($round, " xmm1, xmm6"),
+                        concat!($round, " xmm2, xmm6"),
+                        concat!($round, " xmm3, xmm6"),
+                        concat!($round, " xmm4, xmm6"),
+                        concat!($round, " xmm5, xmm6"),
+                    )+
+                    concat!("movups xmm6, [{rk} + ", $final, "]"),
+                    concat!($last, " xmm0, xmm6"),
+                    concat!($last, " xmm1, xmm6"),
+                    concat!($last, " xmm2, xmm6"),
+                    concat!($last, " xmm3, xmm6"),
+                    concat!($last, " xmm4, xmm6"),
+                    concat!($last, " xmm5, xmm6"),
+                    "movups [{d} + 0x00], xmm0",
+                    "movups [{d} + 0x10], xmm1",
+                    "movups [{d} + 0x20], xmm2",
+                    "movups [{d} + 0x30], xmm3",
+                    "movups [{d} + 0x40], xmm4",
+                    "movups [{d} + 0x50], xmm5",
+                    rk = in(reg) rk,
+                    d = in(reg) data,
+                    out("xmm0") _, out("xmm1") _, out("xmm2") _,
+                    out("xmm3") _, out("xmm4") _, out("xmm5") _,
+                    out("xmm6") _,
+                    options(nostack),
+                );
+            }
+        }
+    };
+}
+
+/// A fully unrolled 4 block kernel.
+///
+/// The same construction as the six block kernel, for what is left.
+macro_rules! kernel4 {
+    (
+        $name:ident, $round:literal, $last:literal,
+        [$($key:literal),+], $final:literal
+    ) => {
+        /// # Safety
+        ///
+        /// The CPU must have the AES instructions. `rk` must
+        /// hold the whole schedule and `data` 4 blocks.
+        #[inline(always)]
+        unsafe fn $name(rk: *const u8, data: *mut u8) {
+            // SAFETY: the caller guarantees the instructions
+            // and both ranges.
+            unsafe {
+                asm!(
+                    "movups xmm0, [{d} + 0x00]",
+                    "movups xmm1, [{d} + 0x10]",
+                    "movups xmm2, [{d} + 0x20]",
+                    "movups xmm3, [{d} + 0x30]",
+                    "movups xmm4, [{rk}]",
+                    "xorps xmm0, xmm4",
+                    "xorps xmm1, xmm4",
+                    "xorps xmm2, xmm4",
+                    "xorps xmm3, xmm4",
+                    $(
+                        concat!("movups xmm4, [{rk} + ", $key, "]"),
+                        concat!($round, " xmm0, xmm4"),
+                        concat!($round, " xmm1, xmm4"),
+                        concat!($round, " xmm2, xmm4"),
+                        concat!($round, " xmm3, xmm4"),
+                    )+
+                    concat!("movups xmm4, [{rk} + ", $final, "]"),
+                    concat!($last, " xmm0, xmm4"),
+                    concat!($last, " xmm1, xmm4"),
+                    concat!($last, " xmm2, xmm4"),
+                    concat!($last, " xmm3, xmm4"),
+                    "movups [{d} + 0x00], xmm0",
+                    "movups [{d} + 0x10], xmm1",
+                    "movups [{d} + 0x20], xmm2",
+                    "movups [{d} + 0x30], xmm3",
+                    rk = in(reg) rk,
+                    d = in(reg) data,
+                    out("xmm0") _, out("xmm1") _, out("xmm2") _,
+                    out("xmm3") _, out("xmm4") _,
+                    options(nostack),
+                );
+            }
+        }
+    };
+}
+
+/// A fully unrolled 2 block kernel.
+///
+/// The same construction as the six block kernel, for what is left.
+macro_rules! kernel2 {
+    (
+        $name:ident, $round:literal, $last:literal,
+        [$($key:literal),+], $final:literal
+    ) => {
+        /// # Safety
+        ///
+        /// The CPU must have the AES instructions. `rk` must
+        /// hold the whole schedule and `data` 2 blocks.
+        #[inline(always)]
+        unsafe fn $name(rk: *const u8, data: *mut u8) {
+            // SAFETY: the caller guarantees the instructions
+            // and both ranges.
+            unsafe {
+                asm!(
+                    "movups xmm0, [{d} + 0x00]",
+                    "movups xmm1, [{d} + 0x10]",
+                    "movups xmm2, [{rk}]",
+                    "xorps xmm0, xmm2",
+                    "xorps xmm1, xmm2",
+                    $(
+                        concat!("movups xmm2, [{rk} + ", $key, "]"),
+                        concat!($round, " xmm0, xmm2"),
+                        concat!($round, " xmm1, xmm2"),
+                    )+
+                    concat!("movups xmm2, [{rk} + ", $final, "]"),
+                    concat!($last, " xmm0, xmm2"),
+                    concat!($last, " xmm1, xmm2"),
+                    "movups [{d} + 0x00], xmm0",
+                    "movups [{d} + 0x10], xmm1",
+                    rk = in(reg) rk,
+                    d = in(reg) data,
+                    out("xmm0") _, out("xmm1") _, out("xmm2") _,
+                    options(nostack),
+                );
+            }
+        }
+    };
+}
+
+/// A fully unrolled 1 block kernel.
+///
+/// The same construction as the six block kernel, for what is left.
+macro_rules! kernel1 {
+    (
+        $name:ident, $round:literal, $last:literal,
+        [$($key:literal),+], $final:literal
+    ) => {
+        /// # Safety
+        ///
+        /// The CPU must have the AES instructions. `rk` must
+        /// hold the whole schedule and `data` 1 block.
+        #[inline(always)]
+        unsafe fn $name(rk: *const u8, data: *mut u8) {
+            // SAFETY: the caller guarantees the instructions
+            // and both ranges.
+            unsafe {
+                asm!(
+                    "movups xmm0, [{d} + 0x00]",
+                    "movups xmm1, [{rk}]",
+                    "xorps xmm0, xmm1",
+                    $(
+                        concat!("movups xmm1, [{rk} + ", $key, "]"),
+                        concat!($round, " xmm0, xmm1"),
+                    )+
+                    concat!("movups xmm1, [{rk} + ", $final, "]"),
+                    concat!($last, " xmm0, xmm1"),
+                    "movups [{d} + 0x00], xmm0",
+                    rk = in(reg) rk,
+                    d = in(reg) data,
+                    out("xmm0") _, out("xmm1") _,
+                    options(nostack),
+                );
+            }
+        }
+    };
+}
+
+kernel6!(
+    e128_6, "aesenc", "aesenclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90"
+    ],
+    "0xa0"
+);
+kernel4!(
+    e128_4, "aesenc", "aesenclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90"
+    ],
+    "0xa0"
+);
+kernel2!(
+    e128_2, "aesenc", "aesenclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90"
+    ],
+    "0xa0"
+);
+kernel1!(
+    e128_1, "aesenc", "aesenclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90"
+    ],
+    "0xa0"
+);
+kernel6!(
+    d128_6, "aesdec", "aesdeclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90"
+    ],
+    "0xa0"
+);
+kernel4!(
+    d128_4, "aesdec", "aesdeclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90"
+    ],
+    "0xa0"
+);
+kernel2!(
+    d128_2, "aesdec", "aesdeclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90"
+    ],
+    "0xa0"
+);
+kernel1!(
+    d128_1, "aesdec", "aesdeclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90"
+    ],
+    "0xa0"
+);
+kernel6!(
+    e192_6, "aesenc", "aesenclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90",
+        "0xa0", "0xb0"
+    ],
+    "0xc0"
+);
+kernel4!(
+    e192_4, "aesenc", "aesenclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90",
+        "0xa0", "0xb0"
+    ],
+    "0xc0"
+);
+kernel2!(
+    e192_2, "aesenc", "aesenclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90",
+        "0xa0", "0xb0"
+    ],
+    "0xc0"
+);
+kernel1!(
+    e192_1, "aesenc", "aesenclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90",
+        "0xa0", "0xb0"
+    ],
+    "0xc0"
+);
+kernel6!(
+    d192_6, "aesdec", "aesdeclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90",
+        "0xa0", "0xb0"
+    ],
+    "0xc0"
+);
+kernel4!(
+    d192_4, "aesdec", "aesdeclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90",
+        "0xa0", "0xb0"
+    ],
+    "0xc0"
+);
+kernel2!(
+    d192_2, "aesdec", "aesdeclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90",
+        "0xa0", "0xb0"
+    ],
+    "0xc0"
+);
+kernel1!(
+    d192_1, "aesdec", "aesdeclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90",
+        "0xa0", "0xb0"
+    ],
+    "0xc0"
+);
+kernel6!(
+    e256_6, "aesenc", "aesenclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90",
+        "0xa0", "0xb0", "0xc0", "0xd0"
+    ],
+    "0xe0"
+);
+kernel4!(
+    e256_4, "aesenc", "aesenclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90",
+        "0xa0", "0xb0", "0xc0", "0xd0"
+    ],
+    "0xe0"
+);
+kernel2!(
+    e256_2, "aesenc", "aesenclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90",
+        "0xa0", "0xb0", "0xc0", "0xd0"
+    ],
+    "0xe0"
+);
+kernel1!(
+    e256_1, "aesenc", "aesenclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90",
+        "0xa0", "0xb0", "0xc0", "0xd0"
+    ],
+    "0xe0"
+);
+kernel6!(
+    d256_6, "aesdec", "aesdeclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90",
+        "0xa0", "0xb0", "0xc0", "0xd0"
+    ],
+    "0xe0"
+);
+kernel4!(
+    d256_4, "aesdec", "aesdeclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90",
+        "0xa0", "0xb0", "0xc0", "0xd0"
+    ],
+    "0xe0"
+);
+kernel2!(
+    d256_2, "aesdec", "aesdeclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90",
+        "0xa0", "0xb0", "0xc0", "0xd0"
+    ],
+    "0xe0"
+);
+kernel1!(
+    d256_1, "aesdec", "aesdeclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90",
+        "0xa0", "0xb0", "0xc0", "0xd0"
+    ],
+    "0xe0"
+);
+
+/// Walk a buffer through the widest kernel that fits, repeatedly.
+///
+/// Each key size and direction gets its own driver so the calls are direct
+/// and inline.
+macro_rules! driver {
+    ($name:ident, $w6:ident, $w4:ident, $w2:ident, $w1:ident) => {
+        /// # Safety
+        ///
+        /// The CPU must have the AES instructions, `rk` must be the
+        /// schedule these kernels were built for, and `data` must hold
+        /// `blocks` whole blocks.
+        #[inline(always)]
+        unsafe fn $name(rk: *const u8, data: *mut u8, blocks: usize) {
+            // SAFETY: each call gets a pointer to at least as many whole
+            // blocks as its kernel touches, and blocks are contiguous.
+            unsafe {
+                let mut i = 0;
+                while i + 6 <= blocks {
+                    $w6(rk, data.add(i * BLOCK_SIZE));
+                    i += 6;
+                }
+                if i + 4 <= blocks {
+                    $w4(rk, data.add(i * BLOCK_SIZE));
+                    i += 4;
+                }
+                if i + 2 <= blocks {
+                    $w2(rk, data.add(i * BLOCK_SIZE));
+                    i += 2;
+                }
+                if i < blocks {
+                    $w1(rk, data.add(i * BLOCK_SIZE));
+                }
+            }
+            scrub_registers();
+        }
+    };
+}
+
+driver!(encrypt_128, e128_6, e128_4, e128_2, e128_1);
+driver!(decrypt_128, d128_6, d128_4, d128_2, d128_1);
+driver!(encrypt_192, e192_6, e192_4, e192_2, e192_1);
+driver!(decrypt_192, d192_6, d192_4, d192_2, d192_1);
+driver!(encrypt_256, e256_6, e256_4, e256_2, e256_1);
+driver!(decrypt_256, d256_6, d256_4, d256_2, d256_1);
+
+/// A fully unrolled six block counter kernel, looping over the buffer.
+///
+/// The counter blocks are built in registers: each starts from the
+/// little-endian base counter, gets its block offset added to the low
+/// quadword, and is byte reversed to big endian. The caller has checked
+/// that the low quadword cannot carry anywhere in the span this call
+/// covers, so the high one never changes. Six blocks, one round key and
+/// one register to load input through fill all eight, so the base counter
+/// lives in memory and advances there between iterations, and the byte
+/// reversal selector and the offsets are memory operands, all from the one
+/// [`COUNTING`] table.
+macro_rules! ctr_kernel6 {
+    ($name:ident, [$($key:literal),+], $final:literal) => {
+        /// # Safety
+        ///
+        /// The CPU must have the AES and SSSE3 instructions. `rk` must
+        /// hold the whole schedule, `data` at least `groups * 6` whole
+        /// blocks, and `base` the counter as a little-endian integer whose
+        /// low 64 bits are at most `u64::MAX - (groups * 6 - 1)`. On
+        /// return `base` has advanced past the blocks used.
+        #[inline(always)]
+        unsafe fn $name(
+            rk: *const u8,
+            data: *mut u8,
+            groups: usize,
+            base: &mut Base,
+        ) {
+            // SAFETY: the caller guarantees the instructions and all
+            // ranges. COUNTING is an aligned static and Base is aligned,
+            // so both can be aligned memory operands.
+            unsafe {
+                asm!(
+                    "2:",
+                    "movdqa xmm0, [{c}]",
+                    "movdqa xmm1, xmm0",
+                    "movdqa xmm2, xmm0",
+                    "movdqa xmm3, xmm0",
+                    "movdqa xmm4, xmm0",
+                    "movdqa xmm5, xmm0",
+                    "movdqa xmm7, xmm0",
+                    "paddq xmm7, [{k} + 0x60]",
+                    "movdqa [{c}], xmm7",
+                    "paddq xmm1, [{k} + 0x10]",
+                    "paddq xmm2, [{k} + 0x20]",
+                    "paddq xmm3, [{k} + 0x30]",
+                    "paddq xmm4, [{k} + 0x40]",
+                    "paddq xmm5, [{k} + 0x50]",
+                    "pshufb xmm0, [{k}]",
+                    "pshufb xmm1, [{k}]",
+                    "pshufb xmm2, [{k}]",
+                    "pshufb xmm3, [{k}]",
+                    "pshufb xmm4, [{k}]",
+                    "pshufb xmm5, [{k}]",
+                    "movups xmm6, [{rk}]",
+                    "xorps xmm0, xmm6",
+                    "xorps xmm1, xmm6",
+                    "xorps xmm2, xmm6",
+                    "xorps xmm3, xmm6",
+                    "xorps xmm4, xmm6",
+                    "xorps xmm5, xmm6",
+                    $(
+                        concat!("movups xmm6, [{rk} + ", $key, "]"),
+                        concat!("aesenc xmm0, xmm6"),
+                        concat!("aesenc xmm1, xmm6"),
+                        concat!("aesenc xmm2, xmm6"),
+                        concat!("aesenc xmm3, xmm6"),
+                        concat!("aesenc xmm4, xmm6"),
+                        concat!("aesenc xmm5, xmm6"),
+                    )+
+                    concat!("movups xmm6, [{rk} + ", $final, "]"),
+                    concat!("aesenclast xmm0, xmm6"),
+                    concat!("aesenclast xmm1, xmm6"),
+                    concat!("aesenclast xmm2, xmm6"),
+                    concat!("aesenclast xmm3, xmm6"),
+                    concat!("aesenclast xmm4, xmm6"),
+                    concat!("aesenclast xmm5, xmm6"),
+                    "movups xmm7, [{d} + 0x00]",
+                    "xorps xmm0, xmm7",
+                    "movups [{d} + 0x00], xmm0",
+                    "movups xmm7, [{d} + 0x10]",
+                    "xorps xmm1, xmm7",
+                    "movups [{d} + 0x10], xmm1",
+                    "movups xmm7, [{d} + 0x20]",
+                    "xorps xmm2, xmm7",
+                    "movups [{d} + 0x20], xmm2",
+                    "movups xmm7, [{d} + 0x30]",
+                    "xorps xmm3, xmm7",
+                    "movups [{d} + 0x30], xmm3",
+                    "movups xmm7, [{d} + 0x40]",
+                    "xorps xmm4, xmm7",
+                    "movups [{d} + 0x40], xmm4",
+                    "movups xmm7, [{d} + 0x50]",
+                    "xorps xmm5, xmm7",
+                    "movups [{d} + 0x50], xmm5",
+                    "add {d}, 96",
+                    "dec {g}",
+                    "jnz 2b",
+                    rk = in(reg) rk,
+                    d = inout(reg) data => _,
+                    g = inout(reg) groups => _,
+                    c = in(reg) base,
+                    k = in(reg) &COUNTING,
+                    out("xmm0") _, out("xmm1") _, out("xmm2") _,
+                    out("xmm3") _, out("xmm4") _, out("xmm5") _,
+                    out("xmm6") _, out("xmm7") _,
+                    options(nostack),
+                );
+            }
+        }
+    };
+}
+
+/// A single block counter kernel.
+///
+/// One counter block needs no byte order tricks: the caller hands the
+/// exact big-endian counter bytes, so this also serves as the carry
+/// fallback when the wide kernel's no-carry precondition fails. The round
+/// keys go through the wide kernel's key register.
+macro_rules! ctr_kernel1 {
+    ($name:ident, [$($key:literal),+], $final:literal) => {
+        /// # Safety
+        ///
+        /// The CPU must have the AES instructions. `rk` must hold the
+        /// whole schedule, `data` 1 block, and `ctr` one counter block.
+        #[inline(always)]
+        unsafe fn $name(rk: *const u8, data: *mut u8, ctr: *const u8) {
+            // SAFETY: the caller guarantees the instructions and all
+            // three ranges.
+            unsafe {
+                asm!(
+                    "movups xmm6, [{rk}]",
+                    "movdqu xmm0, [{c}]",
+                    "pxor xmm0, xmm6",
+                    $(
+                        concat!("movups xmm6, [{rk} + ", $key, "]"),
+                        concat!("aesenc xmm0, xmm6"),
+                    )+
+                    concat!("movups xmm6, [{rk} + ", $final, "]"),
+                    concat!("aesenclast xmm0, xmm6"),
+                    "movdqu xmm1, [{d}]",
+                    "pxor xmm0, xmm1",
+                    "movdqu [{d}], xmm0",
+                    rk = in(reg) rk,
+                    d = in(reg) data,
+                    c = in(reg) ctr,
+                    out("xmm0") _, out("xmm1") _, out("xmm6") _,
+                    options(nostack),
+                );
+            }
+        }
+    };
+}
+
+ctr_kernel6!(
+    ctr_e128_6,
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90"
+    ],
+    "0xa0"
+);
+ctr_kernel1!(
+    ctr_e128_1,
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90"
+    ],
+    "0xa0"
+);
+ctr_kernel6!(
+    ctr_e192_6,
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90",
+        "0xa0", "0xb0"
+    ],
+    "0xc0"
+);
+ctr_kernel1!(
+    ctr_e192_1,
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90",
+        "0xa0", "0xb0"
+    ],
+    "0xc0"
+);
+ctr_kernel6!(
+    ctr_e256_6,
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90",
+        "0xa0", "0xb0", "0xc0", "0xd0"
+    ],
+    "0xe0"
+);
+ctr_kernel1!(
+    ctr_e256_1,
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90",
+        "0xa0", "0xb0", "0xc0", "0xd0"
+    ],
+    "0xe0"
+);
+
+/// Write a counter value as one big-endian block with a single
+/// sixteen byte store.
+///
+/// `u128::to_be_bytes` compiles to two eight byte stores, and the
+/// kernels read the block back immediately with a sixteen byte load,
+/// which cannot forward from a pair of narrower stores and stalls.
+/// One vector store forwards cleanly.
+#[inline(always)]
+fn store_counter(c: u128, out: &mut [u8; BLOCK_SIZE]) {
+    let hi = ((c >> 64) as u64).swap_bytes();
+    let lo = (c as u64).swap_bytes();
+    // SAFETY: both intrinsics are SSE2, which the module's gate
+    // requires, and out is sixteen writable bytes. The high half of the
+    // counter forms the first eight big-endian bytes, so it goes in the
+    // vector's low lane.
+    unsafe {
+        _mm_storeu_si128(
+            out.as_mut_ptr().cast(),
+            _mm_set_epi64x(lo as i64, hi as i64),
+        );
+    }
+}
+
+/// Walk a buffer through the widest counter kernel that fits.
+///
+/// The wide kernel adds block offsets to the counter's low 64 bits only,
+/// so each entry is gated on how many blocks fit before that quadword
+/// would carry. Within six blocks of the boundary, or for the last few
+/// blocks of a buffer, the single block kernel takes over with exact
+/// counters, and the loop then returns to the wide kernel on the far side
+/// of the wrap.
+macro_rules! ctr_driver {
+    ($name:ident, $w6:ident, $w1:ident) => {
+        /// # Safety
+        ///
+        /// The CPU must have the AES and SSSE3 instructions, `rk` must
+        /// be the schedule these kernels were built for, and `data`
+        /// must hold `blocks` whole blocks.
+        #[inline(always)]
+        unsafe fn $name(
+            rk: *const u8,
+            data: *mut u8,
+            blocks: usize,
+            counter: &mut [u8; BLOCK_SIZE],
+        ) {
+            // SAFETY: each call gets a pointer to at least as many
+            // whole blocks as its kernel touches, and a counter block
+            // that satisfies the kernel's no-carry precondition. The
+            // wide kernel starts from `c`, and the single block one
+            // reads the counter bytes from `counter` itself, which
+            // store_counter keeps in step with it.
+            unsafe {
+                let mut c = u128::from_be_bytes(*counter);
+                let mut i = 0;
+                while i < blocks {
+                    let left = blocks - i;
+                    // How many blocks can count up in the low quadword
+                    // before it carries.
+                    let fit = (u64::MAX - (c as u64)) as u128 + 1;
+                    if left >= 6 && fit >= 6 {
+                        let groups = (left / 6).min((fit / 6) as usize);
+                        let mut base = Base(c.to_le_bytes());
+                        $w6(rk, data.add(i * BLOCK_SIZE), groups, &mut base);
+                        i += groups * 6;
+                        c = c.wrapping_add((groups * 6) as u128);
+                    } else {
+                        $w1(
+                            rk,
+                            data.add(i * BLOCK_SIZE),
+                            counter.as_ptr(),
+                        );
+                        i += 1;
+                        c = c.wrapping_add(1);
+                    }
+                    store_counter(c, counter);
+                }
+            }
+            scrub_registers();
+        }
+    };
+}
+
+ctr_driver!(ctr_128, ctr_e128_6, ctr_e128_1);
+ctr_driver!(ctr_192, ctr_e192_6, ctr_e192_1);
+ctr_driver!(ctr_256, ctr_e256_6, ctr_e256_1);
+
+
+/// Splice the low halves of two round key registers together.
+///
+/// AES-192's six word key blocks do not line up with sixteen byte round
+/// keys, so half of its round keys are built from two registers.
+#[inline(always)]
+unsafe fn splice<const MASK: i32>(a: __m128i, b: __m128i) -> __m128i {
+    // SAFETY: shuffle_pd is SSE2, which the module's gate requires.
+    unsafe {
+        _mm_castpd_si128(_mm_shuffle_pd::<MASK>(
+            _mm_castsi128_pd(a),
+            _mm_castsi128_pd(b),
+        ))
+    }
+}
+
+/// SubWord(RotWord(w)) ^ rcon, for word `LANE` of `t`, in every lane.
+///
+/// This is what `aeskeygenassist` computes, but that instruction is
+/// microcoded on most cores and costs several times a round. With the
+/// word broadcast to every column ShiftRows moves nothing, so the last
+/// round instruction is SubBytes alone, and its round key adds the round
+/// constant. The rotation commutes with the substitution, so it is done
+/// first, with shifts that need nothing beyond SSE2.
+#[inline(always)]
+unsafe fn sub_rot_word<const LANE: i32>(t: __m128i, rcon: i32) -> __m128i {
+    // SAFETY: the caller is a function that requires the AES feature;
+    // everything else here is SSE2.
+    unsafe {
+        let w = _mm_shuffle_epi32::<LANE>(t);
+        let w = _mm_or_si128(_mm_srli_epi32(w, 8), _mm_slli_epi32(w, 24));
+        _mm_aesenclast_si128(w, _mm_set1_epi32(rcon))
+    }
+}
+
+/// SubWord(w) for word `LANE` of `t`, in every lane: the AES-256 step
+/// that substitutes without rotating.
+#[inline(always)]
+unsafe fn sub_word<const LANE: i32>(t: __m128i) -> __m128i {
+    // SAFETY: as for sub_rot_word.
+    unsafe {
+        let w = _mm_shuffle_epi32::<LANE>(t);
+        _mm_aesenclast_si128(w, _mm_setzero_si128())
+    }
+}
+
+/// One step of the AES-128 and AES-256 key schedules, given the
+/// substituted word in every lane of `b`.
+#[inline(always)]
+unsafe fn assist(a: __m128i, b: __m128i) -> __m128i {
+    // SAFETY: every intrinsic here is SSE2.
+    unsafe {
+        let c = _mm_slli_si128(a, 4);
+        let a = _mm_xor_si128(a, c);
+        let c = _mm_slli_si128(c, 4);
+        let a = _mm_xor_si128(a, c);
+        let c = _mm_slli_si128(c, 4);
+        let a = _mm_xor_si128(a, c);
+        _mm_xor_si128(a, b)
+    }
+}
+
+/// Expand an AES-128 key into its eleven round keys.
+///
+/// # Safety
+///
+/// The CPU must have the AES instructions, and `rk` must be a sixteen byte
+/// aligned pointer to eleven round keys.
+#[target_feature(enable = "aes")]
+unsafe fn expand_128(key: &[u8; 16], rk: *mut __m128i) {
+    // SAFETY: the caller guarantees the instructions and the destination;
+    // sixteen bytes of key are read, which is its size.
+    unsafe {
+        let mut t = _mm_loadu_si128(key.as_ptr().cast());
+        _mm_store_si128(rk, t);
+        t = assist(t, sub_rot_word::<0xff>(t, 0x01));
+        _mm_store_si128(rk.add(1), t);
+        t = assist(t, sub_rot_word::<0xff>(t, 0x02));
+        _mm_store_si128(rk.add(2), t);
+        t = assist(t, sub_rot_word::<0xff>(t, 0x04));
+        _mm_store_si128(rk.add(3), t);
+        t = assist(t, sub_rot_word::<0xff>(t, 0x08));
+        _mm_store_si128(rk.add(4), t);
+        t = assist(t, sub_rot_word::<0xff>(t, 0x10));
+        _mm_store_si128(rk.add(5), t);
+        t = assist(t, sub_rot_word::<0xff>(t, 0x20));
+        _mm_store_si128(rk.add(6), t);
+        t = assist(t, sub_rot_word::<0xff>(t, 0x40));
+        _mm_store_si128(rk.add(7), t);
+        t = assist(t, sub_rot_word::<0xff>(t, 0x80));
+        _mm_store_si128(rk.add(8), t);
+        t = assist(t, sub_rot_word::<0xff>(t, 0x1b));
+        _mm_store_si128(rk.add(9), t);
+        t = assist(t, sub_rot_word::<0xff>(t, 0x36));
+        _mm_store_si128(rk.add(10), t);
+    }
+}
+
+/// One step of the AES-192 key schedule, given the substituted word in
+/// every lane of `t2`.
+#[inline(always)]
+unsafe fn assist_192(t1: &mut __m128i, t2: __m128i, t3: &mut __m128i) {
+    // SAFETY: every intrinsic here is SSE2.
+    unsafe {
+        let mut c = _mm_slli_si128(*t1, 4);
+        *t1 = _mm_xor_si128(*t1, c);
+        c = _mm_slli_si128(c, 4);
+        *t1 = _mm_xor_si128(*t1, c);
+        c = _mm_slli_si128(c, 4);
+        *t1 = _mm_xor_si128(*t1, c);
+        *t1 = _mm_xor_si128(*t1, t2);
+
+        let spread = _mm_shuffle_epi32(*t1, 0xff);
+        let c = _mm_slli_si128(*t3, 4);
+        *t3 = _mm_xor_si128(*t3, c);
+        *t3 = _mm_xor_si128(*t3, spread);
+    }
+}
+
+/// Expand an AES-192 key into its thirteen round keys.
+///
+/// # Safety
+///
+/// The CPU must have the AES instructions, and `rk` must be a sixteen byte
+/// aligned pointer to thirteen round keys.
+#[target_feature(enable = "aes")]
+unsafe fn expand_192(key: &[u8; 24], rk: *mut __m128i) {
+    // SAFETY: the caller guarantees the instructions and the destination.
+    // The second load takes eight bytes, so only the 24 bytes of key are
+    // read.
+    unsafe {
+        let mut t1 = _mm_loadu_si128(key.as_ptr().cast());
+        let mut t3 = _mm_loadl_epi64(key.as_ptr().add(16).cast());
+        _mm_store_si128(rk, t1);
+        let prev = t3;
+        assist_192(
+            &mut t1,
+            sub_rot_word::<0x55>(t3, 0x01),
+            &mut t3,
+        );
+        _mm_store_si128(rk.add(1), splice::<0>(prev, t1));
+        _mm_store_si128(rk.add(2), splice::<1>(t1, t3));
+        assist_192(
+            &mut t1,
+            sub_rot_word::<0x55>(t3, 0x02),
+            &mut t3,
+        );
+        _mm_store_si128(rk.add(3), t1);
+        _mm_store_si128(rk.add(4), t3);
+        let prev = t3;
+        assist_192(
+            &mut t1,
+            sub_rot_word::<0x55>(t3, 0x04),
+            &mut t3,
+        );
+        _mm_store_si128(rk.add(4), splice::<0>(prev, t1));
+        _mm_store_si128(rk.add(5), splice::<1>(t1, t3));
+        assist_192(
+            &mut t1,
+            sub_rot_word::<0x55>(t3, 0x08),
+            &mut t3,
+        );
+        _mm_store_si128(rk.add(6), t1);
+        _mm_store_si128(rk.add(7), t3);
+        let prev = t3;
+        assist_192(
+            &mut t1,
+            sub_rot_word::<0x55>(t3, 0x10),
+            &mut t3,
+        );
+        _mm_store_si128(rk.add(7), splice::<0>(prev, t1));
+        _mm_store_si128(rk.add(8), splice::<1>(t1, t3));
+        assist_192(
+            &mut t1,
+            sub_rot_word::<0x55>(t3, 0x20),
+            &mut t3,
+        );
+        _mm_store_si128(rk.add(9), t1);
+        _mm_store_si128(rk.add(10), t3);
+        let prev = t3;
+        assist_192(
+            &mut t1,
+            sub_rot_word::<0x55>(t3, 0x40),
+            &mut t3,
+        );
+        _mm_store_si128(rk.add(10), splice::<0>(prev, t1));
+        _mm_store_si128(rk.add(11), splice::<1>(t1, t3));
+        assist_192(
+            &mut t1,
+            sub_rot_word::<0x55>(t3, 0x80),
+            &mut t3,
+        );
+        _mm_store_si128(rk.add(12), t1);
+    }
+}
+
+/// The second AES-256 step, which substitutes without rotating.
+#[inline(always)]
+unsafe fn assist_256(t1: __m128i, t3: __m128i) -> __m128i {
+    // SAFETY: the caller is a function that requires the AES feature.
+    unsafe {
+        let b = sub_word::<0xff>(t1);
+        let c = _mm_slli_si128(t3, 4);
+        let t3 = _mm_xor_si128(t3, c);
+        let c = _mm_slli_si128(c, 4);
+        let t3 = _mm_xor_si128(t3, c);
+        let c = _mm_slli_si128(c, 4);
+        let t3 = _mm_xor_si128(t3, c);
+        _mm_xor_si128(t3, b)
+    }
+}
+
+/// Expand an AES-256 key into its fifteen round keys.
+///
+/// # Safety
+///
+/// The CPU must have the AES instructions, and `rk` must be a sixteen byte
+/// aligned pointer to fifteen round keys.
+#[target_feature(enable = "aes")]
+unsafe fn expand_256(key: &[u8; 32], rk: *mut __m128i) {
+    // SAFETY: the caller guarantees the instructions and the destination;
+    // 32 bytes of key are read, which is its size.
+    unsafe {
+        let mut t1 = _mm_loadu_si128(key.as_ptr().cast());
+        let mut t3 = _mm_loadu_si128(key.as_ptr().add(16).cast());
+        _mm_store_si128(rk, t1);
+        _mm_store_si128(rk.add(1), t3);
+        t1 = assist(t1, sub_rot_word::<0xff>(t3, 0x01));
+        _mm_store_si128(rk.add(2), t1);
+        t3 = assist_256(t1, t3);
+        _mm_store_si128(rk.add(3), t3);
+        t1 = assist(t1, sub_rot_word::<0xff>(t3, 0x02));
+        _mm_store_si128(rk.add(4), t1);
+        t3 = assist_256(t1, t3);
+        _mm_store_si128(rk.add(5), t3);
+        t1 = assist(t1, sub_rot_word::<0xff>(t3, 0x04));
+        _mm_store_si128(rk.add(6), t1);
+        t3 = assist_256(t1, t3);
+        _mm_store_si128(rk.add(7), t3);
+        t1 = assist(t1, sub_rot_word::<0xff>(t3, 0x08));
+        _mm_store_si128(rk.add(8), t1);
+        t3 = assist_256(t1, t3);
+        _mm_store_si128(rk.add(9), t3);
+        t1 = assist(t1, sub_rot_word::<0xff>(t3, 0x10));
+        _mm_store_si128(rk.add(10), t1);
+        t3 = assist_256(t1, t3);
+        _mm_store_si128(rk.add(11), t3);
+        t1 = assist(t1, sub_rot_word::<0xff>(t3, 0x20));
+        _mm_store_si128(rk.add(12), t1);
+        t3 = assist_256(t1, t3);
+        _mm_store_si128(rk.add(13), t3);
+        t1 = assist(t1, sub_rot_word::<0xff>(t3, 0x40));
+        _mm_store_si128(rk.add(14), t1);
+    }
+}
+
+/// Turn an encryption schedule into a decryption one.
+///
+/// The order reverses, and every key but the first and last passes through
+/// InvMixColumns, which is what lets decryption use the same round
+/// structure as encryption.
+///
+/// # Safety
+///
+/// The CPU must have the AES instructions, and both pointers must be
+/// sixteen byte aligned and cover `rounds + 1` round keys.
+#[target_feature(enable = "aes")]
+unsafe fn invert_schedule(ek: *const __m128i, dk: *mut __m128i, rounds: usize) {
+    // SAFETY: the caller guarantees the instructions and both ranges.
+    unsafe {
+        _mm_store_si128(dk, _mm_load_si128(ek.add(rounds)));
+        for i in 1..rounds {
+            let k = _mm_load_si128(ek.add(rounds - i));
+            _mm_store_si128(dk.add(i), _mm_aesimc_si128(k));
+        }
+        _mm_store_si128(dk.add(rounds), _mm_load_si128(ek));
+    }
+}
+
+/// Undo [`invert_schedule`], recovering the encryption schedule.
+///
+/// MixColumns has order four, so InvMixColumns three times over is
+/// MixColumns, and the one instruction serves both ways.
+///
+/// # Safety
+///
+/// As for [`invert_schedule`].
+#[cfg(feature = "hazmat")]
+#[target_feature(enable = "aes")]
+unsafe fn restore_schedule(
+    dk: *const __m128i,
+    ek: *mut __m128i,
+    rounds: usize,
+) {
+    // SAFETY: the caller guarantees the instructions and both ranges.
+    unsafe {
+        _mm_store_si128(ek, _mm_load_si128(dk.add(rounds)));
+        for i in 1..rounds {
+            let k = _mm_load_si128(dk.add(rounds - i));
+            let k = _mm_aesimc_si128(_mm_aesimc_si128(_mm_aesimc_si128(k)));
+            _mm_store_si128(ek.add(i), k);
+        }
+        _mm_store_si128(ek.add(rounds), _mm_load_si128(dk));
+    }
+}
+
+/// Zero a schedule sixteen bytes at a time, in a way the compiler may not
+/// remove.
+///
+/// `Zeroize` on a byte array is a volatile store per byte, which costs
+/// more than expanding the schedule did, and a caller keying every
+/// connection afresh pays it on every drop.
+///
+/// # Safety
+///
+/// `rk` must be sixteen byte aligned and a whole number of round keys.
+pub(super) unsafe fn wipe(rk: &mut [u8]) {
+    let base = rk.as_mut_ptr().cast::<__m128i>();
+    for i in 0..rk.len() / 16 {
+        // SAFETY: the caller guarantees the alignment and the length;
+        // the store is SSE2, which the module's gate requires.
+        unsafe {
+            core::ptr::write_volatile(base.add(i), _mm_setzero_si128());
+        }
+    }
+    // As zeroize does, so nothing after the wipe is reordered before it.
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Clear every vector register the kernels and key schedules write, when
+/// the `scrub` feature asks for it.
+///
+/// The kernels declare their registers clobbered, so the compiler keeps
+/// nothing there, but the last blocks and round key they held are still
+/// in place on return until something else happens to overwrite them.
+#[inline(always)]
+pub(super) fn scrub_registers() {
+    #[cfg(feature = "scrub")]
+    // SAFETY: pxor is SSE2, which the module's gate requires, and every
+    // register written is declared.
+    unsafe {
+        asm!(
+            "pxor xmm0, xmm0",
+            "pxor xmm1, xmm1",
+            "pxor xmm2, xmm2",
+            "pxor xmm3, xmm3",
+            "pxor xmm4, xmm4",
+            "pxor xmm5, xmm5",
+            "pxor xmm6, xmm6",
+            "pxor xmm7, xmm7",
+            out("xmm0") _, out("xmm1") _, out("xmm2") _,
+            out("xmm3") _, out("xmm4") _, out("xmm5") _,
+            out("xmm6") _, out("xmm7") _,
+            options(nomem, nostack, preserves_flags),
+        );
+    }
+}
+
+/// After a key schedule routine returns, clear its registers and the
+/// stack it ran on, when the `scrub` feature asks for it.
+#[inline(always)]
+fn scrub_schedule() {
+    scrub_registers();
+    #[cfg(feature = "scrub")]
+    crate::symmetric::aes::arch::scrub::stack();
+}
+
+macro_rules! define_aes {
+    (
+        $enc:ident, $dec:ident, $key_size:expr, $bytes:expr, $rounds:expr,
+        $expand:ident, $enc_set:ident, $dec_set:ident, $ctr_set:ident,
+        $bits:expr
+    ) => {
+        // Aligned so the round keys can be read with aligned loads,
+        // which lets each one fold into the round instruction instead of
+        // costing a separate move.
+        #[doc = concat!("AES-", $bits, " encryption only.")]
+        #[repr(align(16))]
+        pub struct $enc {
+            rk: [u8; $bytes],
+        }
+
+        #[doc = concat!("AES-", $bits, " decryption only.")]
+        #[repr(align(16))]
+        pub struct $dec {
+            rk: [u8; $bytes],
+        }
+
+        impl $enc {
+            /// The key length in bytes.
+            pub const KEY_SIZE: usize = $key_size;
+            /// The block length in bytes.
+            pub const BLOCK_SIZE: usize = BLOCK_SIZE;
+            /// Blocks kept in flight.
+            pub const PARALLEL_BLOCKS: usize = WIDTH;
+
+            /// Expand `key` into an encryption schedule.
+            ///
+            /// # Panics
+            ///
+            /// If the CPU has no AES instructions. Naming this type asserts
+            /// that it does; the parent module's type of the same name
+            /// checks and falls back instead, and [`Self::try_new`]
+            /// reports it.
+            pub fn new(key: &[u8; $key_size]) -> Self {
+                match Self::try_new(key) {
+                    Ok(this) => this,
+                    Err(e) => panic!("{e}"),
+                }
+            }
+
+            /// Expand `key` into an encryption schedule, or say which CPU
+            /// features that would need.
+            pub fn try_new(
+                key: &[u8; $key_size],
+            ) -> Result<Self, UnsupportedCpu> {
+                if !supported() {
+                    return Err(unsupported());
+                }
+                let mut this = Self { rk: [0u8; $bytes] };
+                // SAFETY: support was just checked; rk is $bytes long and
+                // aligned by the type.
+                unsafe { $expand(key, this.rk.as_mut_ptr().cast()) };
+                scrub_schedule();
+                Ok(this)
+            }
+
+            /// Encrypt whole blocks in place, returning bytes consumed.
+            pub fn encrypt(&self, data: &mut [u8]) -> usize {
+                let blocks = data.len() / BLOCK_SIZE;
+                if blocks == 0 {
+                    return 0;
+                }
+                // SAFETY: the schedule exists, so the instructions do,
+                // and the buffer holds that many whole blocks.
+                unsafe {
+                    $enc_set(self.rk.as_ptr(), data.as_mut_ptr(), blocks)
+                };
+                blocks * BLOCK_SIZE
+            }
+
+            /// Encrypt exactly one block in place.
+            pub fn encrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+                // SAFETY: as for encrypt.
+                unsafe {
+                    $enc_set(self.rk.as_ptr(), block.as_mut_ptr(), 1)
+                };
+            }
+
+            /// Encrypt successive counter values and XOR them into
+            /// `data` in place, advancing `counter`.
+            ///
+            /// The counter is one block, big endian, wrapping at the
+            /// full block width, as SP 800-38A specifies. Whole blocks
+            /// only, like [`Self::encrypt`]; returns bytes consumed.
+            ///
+            /// # Panics
+            ///
+            /// If the CPU lacks SSSE3, which the counter kernels need
+            /// on top of AES. See [`ctr_supported`].
+            pub fn ctr(
+                &self,
+                counter: &mut [u8; BLOCK_SIZE],
+                data: &mut [u8],
+            ) -> usize {
+                assert!(
+                    ctr_supported(),
+                    "counter kernels are not available"
+                );
+                let blocks = data.len() / BLOCK_SIZE;
+                if blocks != 0 {
+                    // SAFETY: support was just checked, rk is the
+                    // schedule these kernels were built for, and data
+                    // holds that many whole blocks.
+                    unsafe {
+                        $ctr_set(
+                            self.rk.as_ptr(),
+                            data.as_mut_ptr(),
+                            blocks,
+                            counter,
+                        )
+                    };
+                }
+                blocks * BLOCK_SIZE
+            }
+        }
+
+        impl $dec {
+            /// The key length in bytes.
+            pub const KEY_SIZE: usize = $key_size;
+            /// The block length in bytes.
+            pub const BLOCK_SIZE: usize = BLOCK_SIZE;
+            /// Blocks kept in flight.
+            pub const PARALLEL_BLOCKS: usize = WIDTH;
+
+            /// Expand `key` into a decryption schedule.
+            ///
+            /// # Panics
+            ///
+            /// If the CPU has no AES instructions. [`Self::try_new`]
+            /// reports that instead.
+            pub fn new(key: &[u8; $key_size]) -> Self {
+                match Self::try_new(key) {
+                    Ok(this) => this,
+                    Err(e) => panic!("{e}"),
+                }
+            }
+
+            /// Expand `key` into a decryption schedule, or say which CPU
+            /// features that would need.
+            pub fn try_new(
+                key: &[u8; $key_size],
+            ) -> Result<Self, UnsupportedCpu> {
+                // The encryption schedule is wiped when it drops here.
+                Ok(Self::from(&$enc::try_new(key)?))
+            }
+
+            /// Decrypt whole blocks in place, returning bytes consumed.
+            pub fn decrypt(&self, data: &mut [u8]) -> usize {
+                let blocks = data.len() / BLOCK_SIZE;
+                if blocks == 0 {
+                    return 0;
+                }
+                // SAFETY: as for the encryption side.
+                unsafe {
+                    $dec_set(self.rk.as_ptr(), data.as_mut_ptr(), blocks)
+                };
+                blocks * BLOCK_SIZE
+            }
+
+            /// Decrypt exactly one block in place.
+            pub fn decrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+                // SAFETY: as for decrypt.
+                unsafe {
+                    $dec_set(self.rk.as_ptr(), block.as_mut_ptr(), 1)
+                };
+            }
+        }
+
+        impl Drop for $enc {
+            fn drop(&mut self) {
+                // SAFETY: rk is aligned by the type and whole round keys.
+                unsafe { wipe(&mut self.rk) };
+            }
+        }
+
+        impl Drop for $dec {
+            fn drop(&mut self) {
+                // SAFETY: as for the encryption schedule.
+                unsafe { wipe(&mut self.rk) };
+            }
+        }
+
+        impl core::fmt::Debug for $enc {
+            fn fmt(
+                &self,
+                f: &mut core::fmt::Formatter<'_>,
+            ) -> core::fmt::Result {
+                // Never format round keys.
+                f.write_str(concat!(stringify!($enc), " { .. }"))
+            }
+        }
+
+        impl core::fmt::Debug for $dec {
+            fn fmt(
+                &self,
+                f: &mut core::fmt::Formatter<'_>,
+            ) -> core::fmt::Result {
+                f.write_str(concat!(stringify!($dec), " { .. }"))
+            }
+        }
+
+        /// Invert an existing encryption schedule, so the key itself
+        /// need not be kept around in case decryption is wanted later.
+        impl From<&$enc> for $dec {
+            fn from(enc: &$enc) -> Self {
+                let mut this = Self { rk: [0u8; $bytes] };
+                // SAFETY: the encryption schedule exists, so the
+                // instructions do; both schedules are $bytes long and
+                // aligned by their types.
+                unsafe {
+                    invert_schedule(
+                        enc.rk.as_ptr().cast(),
+                        this.rk.as_mut_ptr().cast(),
+                        $rounds,
+                    );
+                }
+                scrub_schedule();
+                this
+            }
+        }
+
+        #[cfg(feature = "hazmat")]
+        impl $enc {
+            /// Copy out the round keys, in the [canonical layout].
+            ///
+            /// [canonical layout]: crate::symmetric::aes#round-keys
+            pub fn round_keys(&self) -> Zeroizing<[u8; $bytes]> {
+                // The instructions take round keys in the state's byte
+                // order, which is the canonical one.
+                Zeroizing::new(self.rk)
+            }
+
+            /// Take round keys in the canonical layout as they are.
+            ///
+            /// Nothing checks that they came from a key expansion.
+            ///
+            /// # Panics
+            ///
+            /// As [`Self::new`]; [`Self::try_from_round_keys`] reports it
+            /// instead.
+            pub fn from_round_keys(round_keys: &[u8; $bytes]) -> Self {
+                match Self::try_from_round_keys(round_keys) {
+                    Ok(this) => this,
+                    Err(e) => panic!("{e}"),
+                }
+            }
+
+            /// Take round keys in the canonical layout as they are, or
+            /// say which CPU features that would need.
+            pub fn try_from_round_keys(
+                round_keys: &[u8; $bytes],
+            ) -> Result<Self, UnsupportedCpu> {
+                if !supported() {
+                    return Err(unsupported());
+                }
+                Ok(Self { rk: *round_keys })
+            }
+        }
+
+        #[cfg(feature = "hazmat")]
+        impl $dec {
+            /// Copy out the encryption round keys this schedule was
+            /// inverted from, in the canonical layout.
+            pub fn round_keys(&self) -> Zeroizing<[u8; $bytes]> {
+                let mut enc = $enc { rk: [0u8; $bytes] };
+                // SAFETY: this schedule exists, so the instructions do;
+                // both are $bytes long and aligned by their types.
+                unsafe {
+                    restore_schedule(
+                        self.rk.as_ptr().cast(),
+                        enc.rk.as_mut_ptr().cast(),
+                        $rounds,
+                    );
+                }
+                scrub_schedule();
+                enc.round_keys()
+            }
+
+            /// Invert encryption round keys in the canonical layout.
+            ///
+            /// Nothing checks that they came from a key expansion.
+            ///
+            /// # Panics
+            ///
+            /// As [`Self::new`]; [`Self::try_from_round_keys`] reports it
+            /// instead.
+            pub fn from_round_keys(round_keys: &[u8; $bytes]) -> Self {
+                match Self::try_from_round_keys(round_keys) {
+                    Ok(this) => this,
+                    Err(e) => panic!("{e}"),
+                }
+            }
+
+            /// Invert encryption round keys in the canonical layout, or
+            /// say which CPU features that would need.
+            pub fn try_from_round_keys(
+                round_keys: &[u8; $bytes],
+            ) -> Result<Self, UnsupportedCpu> {
+                Ok(Self::from(&$enc::try_from_round_keys(round_keys)?))
+            }
+        }
+
+        impl KeyInit for $enc {
+            type Error = KeyInitError;
+
+            fn try_new(key: &[u8]) -> Result<Self, KeyInitError> {
+                let key: &[u8; $key_size] = key
+                    .try_into()
+                    .map_err(|_| InvalidKeyLength { got: key.len() })?;
+                Ok(Self::try_new(key)?)
+            }
+        }
+
+        impl KeyInit for $dec {
+            type Error = KeyInitError;
+
+            fn try_new(key: &[u8]) -> Result<Self, KeyInitError> {
+                let key: &[u8; $key_size] = key
+                    .try_into()
+                    .map_err(|_| InvalidKeyLength { got: key.len() })?;
+                Ok(Self::try_new(key)?)
+            }
+        }
+
+        impl BlockEncrypt for $enc {
+            const BLOCK_SIZE: usize = BLOCK_SIZE;
+            const PARALLEL_BLOCKS: usize = WIDTH;
+
+            fn encrypt(&self, data: &mut [u8]) -> usize {
+                $enc::encrypt(self, data)
+            }
+        }
+
+        impl BlockDecrypt for $dec {
+            const BLOCK_SIZE: usize = BLOCK_SIZE;
+            const PARALLEL_BLOCKS: usize = WIDTH;
+
+            fn decrypt(&self, data: &mut [u8]) -> usize {
+                $dec::decrypt(self, data)
+            }
+        }
+    };
+}
+
+define_aes!(
+    Aes128Enc, Aes128Dec, 16, 176, 10, expand_128,
+    encrypt_128, decrypt_128, ctr_128, "128"
+);
+define_aes!(
+    Aes192Enc, Aes192Dec, 24, 208, 12, expand_192,
+    encrypt_192, decrypt_192, ctr_192, "192"
+);
+define_aes!(
+    Aes256Enc, Aes256Dec, 32, 240, 14, expand_256,
+    encrypt_256, decrypt_256, ctr_256, "256"
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symmetric::aes::arch::portable::ttable;
+    use crate::symmetric::aes::testing::{assert_inverting_matches_expanding, Rng};
+
+    /// Construction on the wrong CPU is an error naming what is missing,
+    /// not a panic, through both the inherent and the trait constructor.
+    #[test]
+    fn try_new_refuses_rather_than_panics() {
+        match Aes128Enc::try_new(&[0u8; 16]) {
+            Ok(_) => assert!(supported()),
+            Err(e) => assert!(!supported() && e.missing().count() > 0),
+        }
+        assert_eq!(Aes256Dec::try_new(&[0u8; 32]).is_ok(), supported());
+
+        // The length is checked first, so it is reported on any CPU.
+        assert!(matches!(
+            <Aes192Enc as KeyInit>::try_new(&[0u8; 16]),
+            Err(KeyInitError::Key(InvalidKeyLength { got: 16 }))
+        ));
+        match <Aes192Dec as KeyInit>::try_new(&[0u8; 24]) {
+            Ok(_) => assert!(supported()),
+            Err(e) => assert!(matches!(e, KeyInitError::Cpu(_))),
+        }
+    }
+
+    /// FIPS-197 Appendix C.1, C.2 and C.3.
+    #[test]
+    fn fips_197_all_key_sizes() {
+        if !supported() {
+            return;
+        }
+        let key = [
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09,
+            0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+        ];
+        let plaintext = [
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99,
+            0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff,
+        ];
+        let expected = [
+            0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30, 0xd8, 0xcd,
+            0xb7, 0x80, 0x70, 0xb4, 0xc5, 0x5a,
+        ];
+
+        let mut block = plaintext;
+        Aes128Enc::new(&key).encrypt_block(&mut block);
+        assert_eq!(block, expected);
+        Aes128Dec::new(&key).decrypt_block(&mut block);
+        assert_eq!(block, plaintext);
+
+        let key192: [u8; 24] = core::array::from_fn(|i| i as u8);
+        let expected192 = [
+            0xdd, 0xa9, 0x7c, 0xa4, 0x86, 0x4c, 0xdf, 0xe0, 0x6e, 0xaf,
+            0x70, 0xa0, 0xec, 0x0d, 0x71, 0x91,
+        ];
+        let mut block = plaintext;
+        Aes192Enc::new(&key192).encrypt_block(&mut block);
+        assert_eq!(block, expected192, "AES-192");
+        Aes192Dec::new(&key192).decrypt_block(&mut block);
+        assert_eq!(block, plaintext);
+
+        let key256: [u8; 32] = core::array::from_fn(|i| i as u8);
+        let expected256 = [
+            0x8e, 0xa2, 0xb7, 0xca, 0x51, 0x67, 0x45, 0xbf, 0xea, 0xfc,
+            0x49, 0x90, 0x4b, 0x49, 0x60, 0x89,
+        ];
+        let mut block = plaintext;
+        Aes256Enc::new(&key256).encrypt_block(&mut block);
+        assert_eq!(block, expected256, "AES-256");
+        Aes256Dec::new(&key256).decrypt_block(&mut block);
+        assert_eq!(block, plaintext);
+    }
+
+    /// The two implementations must agree exactly, at every length that
+    /// exercises the wide kernel, its tail, and the boundary between them.
+    macro_rules! check_against_portable {
+        ($enc:ident, $dec:ident, $pe:path, $len:expr, $seed:expr) => {{
+            let mut rng = Rng($seed);
+            for blocks in [0usize, 1, 2, 7, 8, 9, 15, 16, 17, 64, 100] {
+                let mut key = [0u8; $len];
+                rng.fill(&mut key);
+                let mut plaintext = vec![0u8; blocks * BLOCK_SIZE];
+                rng.fill(&mut plaintext);
+
+                let mut ours = plaintext.clone();
+                let mut theirs = plaintext.clone();
+                assert_eq!(
+                    $enc::new(&key).encrypt(&mut ours),
+                    blocks * BLOCK_SIZE
+                );
+                <$pe>::new(&key).encrypt(&mut theirs);
+                assert_eq!(
+                    ours, theirs,
+                    "{} bit encrypt differs at {} blocks",
+                    $len * 8, blocks
+                );
+
+                $dec::new(&key).decrypt(&mut ours);
+                assert_eq!(
+                    ours, plaintext,
+                    "{} bit decrypt failed at {} blocks",
+                    $len * 8, blocks
+                );
+            }
+        }};
+    }
+
+    /// Every length here exercises the wide kernel, its tail, or the
+    /// boundary between them.
+    #[test]
+    fn agrees_with_the_portable_implementation() {
+        if !supported() {
+            return;
+        }
+        check_against_portable!(
+            Aes128Enc, Aes128Dec, ttable::Aes128Enc, 16,
+            0x0123_4567_89ab_cdef
+        );
+        check_against_portable!(
+            Aes192Enc, Aes192Dec, ttable::Aes192Enc, 24,
+            0xfedc_ba98_7654_3210
+        );
+        check_against_portable!(
+            Aes256Enc, Aes256Dec, ttable::Aes256Enc, 32,
+            0x2468_ace0_1357_9bdf
+        );
+    }
+
+    #[test]
+    fn partial_trailing_block_is_left_alone() {
+        if !supported() {
+            return;
+        }
+        let aes = Aes128Enc::new(&[0u8; 16]);
+        let mut data = [0xccu8; BLOCK_SIZE + 5];
+        assert_eq!(aes.encrypt(&mut data), BLOCK_SIZE);
+        assert_eq!(&data[BLOCK_SIZE..], &[0xcc; 5]);
+    }
+
+    #[test]
+    fn bulk_matches_block_at_a_time() {
+        if !supported() {
+            return;
+        }
+        let aes = Aes128Enc::new(&[0x2bu8; 16]);
+        let mut bulk = [0u8; BLOCK_SIZE * 20];
+        for (i, b) in bulk.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        let mut one = bulk;
+
+        aes.encrypt(&mut bulk);
+        for block in one.as_chunks_mut::<BLOCK_SIZE>().0 {
+            aes.encrypt_block(block);
+        }
+        assert_eq!(bulk, one);
+    }
+
+    /// The fused counter kernels against the portable scalar `ctr`, at
+    /// lengths exercising the wide kernel, the singles tail, and the
+    /// boundary between them, plus the counter write-back.
+    macro_rules! check_ctr_against_portable {
+        ($enc:ident, $pe:path, $len:expr, $seed:expr) => {{
+            let mut rng = Rng($seed);
+            for blocks in [0usize, 1, 2, 7, 8, 9, 15, 16, 17, 64, 100] {
+                let mut key = [0u8; $len];
+                rng.fill(&mut key);
+                let mut iv = [0u8; BLOCK_SIZE];
+                rng.fill(&mut iv);
+                let mut data = vec![0u8; blocks * BLOCK_SIZE];
+                rng.fill(&mut data);
+
+                let mut ours = data.clone();
+                let mut ours_ctr = iv;
+                assert_eq!(
+                    $enc::new(&key).ctr(&mut ours_ctr, &mut ours),
+                    blocks * BLOCK_SIZE
+                );
+
+                let mut theirs = data.clone();
+                let mut theirs_ctr = iv;
+                <$pe>::new(&key).ctr(&mut theirs_ctr, &mut theirs);
+
+                assert_eq!(
+                    ours, theirs,
+                    "{} bit ctr differs at {} blocks",
+                    $len * 8, blocks
+                );
+                assert_eq!(
+                    ours_ctr, theirs_ctr,
+                    "{} bit counter write-back differs at {} blocks",
+                    $len * 8, blocks
+                );
+            }
+        }};
+    }
+
+    #[test]
+    fn ctr_agrees_with_the_portable_implementation() {
+        if !ctr_supported() {
+            return;
+        }
+        check_ctr_against_portable!(
+            Aes128Enc, ttable::Aes128Enc, 16, 0x0123_4567_89ab_cdef
+        );
+        check_ctr_against_portable!(
+            Aes192Enc, ttable::Aes192Enc, 24, 0xfedc_ba98_7654_3210
+        );
+        check_ctr_against_portable!(
+            Aes256Enc, ttable::Aes256Enc, 32, 0x2468_ace0_1357_9bdf
+        );
+    }
+
+    /// IVs whose low 64 bits are about to overflow force the wide
+    /// kernel's no-carry precondition to fail mid-stream, taking the
+    /// single block fallback exactly where the carry crosses.
+    #[test]
+    fn ctr_carries_across_the_low_quadword() {
+        if !ctr_supported() {
+            return;
+        }
+        let key = [0x2bu8; 16];
+        let ours_aes = Aes128Enc::new(&key);
+        let theirs_aes = ttable::Aes128Enc::new(&key);
+
+        for k in [0u64, 1, 7, 8, 9] {
+            let start = ((0x0123_4567_89ab_cdefu128) << 64)
+                | (u64::MAX - k) as u128;
+            let iv = start.to_be_bytes();
+            let mut data = [0xa5u8; BLOCK_SIZE * 20];
+
+            let mut ours = data;
+            let mut ours_ctr = iv;
+            ours_aes.ctr(&mut ours_ctr, &mut ours);
+
+            let mut theirs_ctr = iv;
+            theirs_aes.ctr(&mut theirs_ctr, &mut data);
+
+            assert_eq!(ours, data, "carry case k = {k}");
+            assert_eq!(ours_ctr, theirs_ctr, "counter, k = {k}");
+        }
+
+        // The full wrap to zero as well.
+        let iv = [0xffu8; BLOCK_SIZE];
+        let mut data = [0x5au8; BLOCK_SIZE * 20];
+        let mut ours = data;
+        let mut ours_ctr = iv;
+        ours_aes.ctr(&mut ours_ctr, &mut ours);
+        let mut theirs_ctr = iv;
+        theirs_aes.ctr(&mut theirs_ctr, &mut data);
+        assert_eq!(ours, data, "wrap at 2^128");
+        assert_eq!(ours_ctr, theirs_ctr, "counter after the wrap");
+    }
+
+    /// The key schedule is built without `aeskeygenassist`, so every
+    /// round key has to be checked against an independent expansion, and
+    /// one block under each of many keys touches all of them.
+    #[test]
+    fn expansion_agrees_with_the_portable_implementation() {
+        if !supported() {
+            return;
+        }
+        let mut rng = Rng(0x5eed_0f6b_1c0d_e5a1);
+        macro_rules! check {
+            ($enc:ident, $pe:path, $len:expr) => {
+                for _ in 0..1000 {
+                    let mut key = [0u8; $len];
+                    let mut ours = [0u8; BLOCK_SIZE];
+                    rng.fill(&mut key);
+                    rng.fill(&mut ours);
+                    let mut theirs = ours;
+                    $enc::new(&key).encrypt_block(&mut ours);
+                    <$pe>::new(&key).encrypt_block(&mut theirs);
+                    assert_eq!(
+                        ours, theirs,
+                        "{} bit key {key:02x?}", $len * 8
+                    );
+                }
+            };
+        }
+        check!(Aes128Enc, ttable::Aes128Enc, 16);
+        check!(Aes192Enc, ttable::Aes192Enc, 24);
+        check!(Aes256Enc, ttable::Aes256Enc, 32);
+    }
+
+    /// Inverting an encryption schedule must give exactly the schedule
+    /// `Dec::new` expands, at every key size.
+    #[test]
+    fn inverting_matches_expanding() {
+        if !supported() {
+            return;
+        }
+        assert_inverting_matches_expanding!();
+    }
+
+    /// The canonical layout is the portable one's, in both directions,
+    /// and loading a schedule gives back what expansion would build.
+    #[cfg(feature = "hazmat")]
+    #[test]
+    fn round_keys_match_the_portable_implementation() {
+        if !supported() {
+            return;
+        }
+        let mut rng = Rng(0x7f6e_5d4c_3b2a_1908);
+        macro_rules! check {
+            ($enc:ident, $dec:ident, $pe:path, $len:expr) => {
+                for _ in 0..16 {
+                    let mut key = [0u8; $len];
+                    rng.fill(&mut key);
+                    let rk = <$pe>::new(&key).round_keys();
+                    assert_eq!(*$enc::new(&key).round_keys(), *rk);
+                    assert_eq!(*$dec::new(&key).round_keys(), *rk);
+                    let enc = $enc::from_round_keys(&rk);
+                    assert_eq!(enc.rk, $enc::new(&key).rk);
+                    let dec = $dec::from_round_keys(&rk);
+                    assert_eq!(dec.rk, $dec::new(&key).rk);
+                }
+            };
+        }
+        check!(Aes128Enc, Aes128Dec, ttable::Aes128Enc, 16);
+        check!(Aes192Enc, Aes192Dec, ttable::Aes192Enc, 24);
+        check!(Aes256Enc, Aes256Dec, ttable::Aes256Enc, 32);
+    }
+
+    #[test]
+    fn schedules_are_wiped_on_drop() {
+        if !supported() {
+            return;
+        }
+        let mut slot =
+            core::mem::MaybeUninit::new(Aes128Enc::new(&[0xab; 16]));
+        let ptr = slot.as_mut_ptr();
+        let bytes = ptr.cast::<u8>();
+        let len = core::mem::size_of::<Aes128Enc>();
+        // SAFETY: slot holds an initialized value of exactly this size.
+        let live = unsafe { core::slice::from_raw_parts(bytes, len) };
+        assert!(live.iter().any(|&b| b != 0));
+        // SAFETY: the value is never read as a value again.
+        unsafe { core::ptr::drop_in_place(ptr) };
+        // SAFETY: the storage is ours and still allocated.
+        let dead = unsafe { core::slice::from_raw_parts(bytes, len) };
+        assert!(dead.iter().all(|&b| b == 0), "key material survived");
+    }
+
+    /// Run `f`, then read all eight xmm registers.
+    ///
+    /// The call is made from the same asm block that reads them back, so
+    /// no code the compiler emits runs between `f` returning and the
+    /// reads. The trampoline takes its argument in `ecx`, everything the
+    /// calling convention lets a callee clobber is declared, and the
+    /// buffer pointer rides in `edi`, which it must preserve.
+    fn registers_after(mut f: impl FnMut()) -> [[u8; 16]; 8] {
+        extern "fastcall" fn trampoline(f: &mut &mut dyn FnMut()) {
+            f();
+        }
+        let mut f: &mut dyn FnMut() = &mut f;
+        let mut regs = [[0u8; 16]; 8];
+        // SAFETY: the stack is call-aligned on entry to an asm block
+        // without nostack, trampoline has the ABI that clobber_abi
+        // describes, and regs has room for every store.
+        unsafe {
+            asm!(
+                "call {t}",
+                "movdqu [edi + 0x0], xmm0",
+                "movdqu [edi + 0x10], xmm1",
+                "movdqu [edi + 0x20], xmm2",
+                "movdqu [edi + 0x30], xmm3",
+                "movdqu [edi + 0x40], xmm4",
+                "movdqu [edi + 0x50], xmm5",
+                "movdqu [edi + 0x60], xmm6",
+                "movdqu [edi + 0x70], xmm7",
+                t = sym trampoline,
+                in("ecx") &mut f,
+                in("edi") regs.as_mut_ptr(),
+                clobber_abi("fastcall"),
+            );
+        }
+        regs
+    }
+
+    /// With `scrub`, every kernel and key schedule leaves the vector
+    /// registers empty. Without it, the same inspection finds a round key
+    /// still sitting in one, which is what shows it can see them.
+    #[test]
+    fn registers_are_scrubbed_after_every_kernel() {
+        if !ctr_supported() {
+            return;
+        }
+        let mut rng = Rng(0x5c2b_0e11_7d4a_9f63);
+        let mut key = [0u8; 32];
+        rng.fill(&mut key);
+        let enc = Aes256Enc::new(&key);
+        let dec = Aes256Dec::new(&key);
+        let round_keys = enc.rk;
+        let mut data = [0u8; 40 * BLOCK_SIZE];
+        rng.fill(&mut data);
+
+        let check = |what: &str, regs: [[u8; 16]; 8]| {
+            if cfg!(feature = "scrub") {
+                assert!(
+                    regs.iter().all(|r| *r == [0; 16]),
+                    "{what} left {regs:02x?}"
+                );
+            } else {
+                assert!(
+                    regs.iter().any(|r| {
+                        round_keys.chunks(16).any(|k| k == r.as_slice())
+                    }),
+                    "{what} left no round key to find"
+                );
+            }
+        };
+        for blocks in [1, 2, 4, 6, 7, 40] {
+            let data = &mut data[..blocks * BLOCK_SIZE];
+            check("encrypt", registers_after(|| {
+                enc.encrypt(data);
+            }));
+            check("decrypt", registers_after(|| {
+                dec.decrypt(data);
+            }));
+            let mut counter = [0xff; BLOCK_SIZE];
+            check("ctr", registers_after(|| {
+                enc.ctr(&mut counter, data);
+            }));
+        }
+        check("expansion", registers_after(|| {
+            let mut rk = Aes256Enc { rk: [0u8; 240] };
+            // SAFETY: support was checked, and rk is fifteen round keys,
+            // aligned by the type.
+            unsafe { expand_256(&key, rk.rk.as_mut_ptr().cast()) };
+            scrub_schedule();
+        }));
+    }
+}
@@ -2,3 +2,5 @@
 
 pub mod aesni;
 pub mod vaes;
+pub mod vaes512;
+pub mod vpaes;
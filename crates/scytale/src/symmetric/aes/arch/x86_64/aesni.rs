@@ -16,11 +16,14 @@
 
 use core::arch::asm;
 use core::arch::x86_64::*;
+use core::sync::atomic::{Ordering, compiler_fence};
 
-use zeroize::Zeroize;
+#[cfg(feature = "hazmat")]
+use zeroize::Zeroizing;
 
+use crate::cpu::{Feature, UnsupportedCpu};
 use crate::symmetric::block_cipher::{
-    BlockDecrypt, BlockEncrypt, InvalidKeyLength, KeyInit,
+    BlockDecrypt, BlockEncrypt, InvalidKeyLength, KeyInit, KeyInitError,
 };
 
 /// The AES block size in bytes.
@@ -39,13 +42,22 @@ const WIDTH: usize = 12;
 /// Whether this CPU has the AES instructions.
 ///
 /// The answer cannot change while the process runs, so it is worked out
-/// once and remembered.
+/// once and remembered. A build for a target that guarantees the
+/// instructions does not ask at all.
 pub fn supported() -> bool {
+    if cfg!(target_feature = "aes") {
+        return true;
+    }
     use std::sync::OnceLock;
     static SUPPORTED: OnceLock<bool> = OnceLock::new();
     *SUPPORTED.get_or_init(|| is_x86_feature_detected!("aes"))
 }
 
+/// What [`supported`] checks for, naming whichever the CPU lacks.
+fn unsupported() -> UnsupportedCpu {
+    UnsupportedCpu::lacking(&[Feature::Aes])
+}
+
 /// Whether this CPU can run the counter kernels.
 ///
 /// They need SSSE3's `pshufb` alongside the AES instructions to byte
@@ -53,6 +65,9 @@ pub fn supported() -> bool {
 /// practice, but the feature bits are formally independent, so it is
 /// checked rather than assumed.
 pub fn ctr_supported() -> bool {
+    if cfg!(all(target_feature = "aes", target_feature = "ssse3")) {
+        return true;
+    }
     use std::sync::OnceLock;
     static SUPPORTED: OnceLock<bool> = OnceLock::new();
     *SUPPORTED
@@ -916,6 +931,7 @@ macro_rules! driver {
                     $w1(rk, data.add(i * BLOCK_SIZE));
                 }
             }
+            scrub_registers();
         }
     };
 }
@@ -1378,6 +1394,7 @@ macro_rules! ctr_driver {
                     store_counter(c, counter);
                 }
             }
+            scrub_registers();
         }
     };
 }
@@ -1401,12 +1418,42 @@ unsafe fn splice<const MASK: i32>(a: __m128i, b: __m128i) -> __m128i {
     }
 }
 
-/// One step of the AES-128 and AES-256 key schedules.
+/// SubWord(RotWord(w)) ^ rcon, for word `LANE` of `t`, in every lane.
+///
+/// This is what `aeskeygenassist` computes, but that instruction is
+/// microcoded on most cores and costs several times a round. With the
+/// word broadcast to every column ShiftRows moves nothing, so the last
+/// round instruction is SubBytes alone, and its round key adds the round
+/// constant. The rotation commutes with the substitution, so it is done
+/// first, with shifts that need nothing beyond SSE2.
+#[inline(always)]
+unsafe fn sub_rot_word<const LANE: i32>(t: __m128i, rcon: i32) -> __m128i {
+    // SAFETY: the caller is a function that requires the AES feature;
+    // everything else here is SSE2.
+    unsafe {
+        let w = _mm_shuffle_epi32::<LANE>(t);
+        let w = _mm_or_si128(_mm_srli_epi32(w, 8), _mm_slli_epi32(w, 24));
+        _mm_aesenclast_si128(w, _mm_set1_epi32(rcon))
+    }
+}
+
+/// SubWord(w) for word `LANE` of `t`, in every lane: the AES-256 step
+/// that substitutes without rotating.
+#[inline(always)]
+unsafe fn sub_word<const LANE: i32>(t: __m128i) -> __m128i {
+    // SAFETY: as for sub_rot_word.
+    unsafe {
+        let w = _mm_shuffle_epi32::<LANE>(t);
+        _mm_aesenclast_si128(w, _mm_setzero_si128())
+    }
+}
+
+/// One step of the AES-128 and AES-256 key schedules, given the
+/// substituted word in every lane of `b`.
 #[inline(always)]
 unsafe fn assist(a: __m128i, b: __m128i) -> __m128i {
     // SAFETY: every intrinsic here is SSE2.
     unsafe {
-        let b = _mm_shuffle_epi32(b, 0xff);
         let c = _mm_slli_si128(a, 4);
         let a = _mm_xor_si128(a, c);
         let c = _mm_slli_si128(c, 4);
@@ -1430,35 +1477,35 @@ unsafe fn expand_128(key: &[u8; 16], rk: *mut __m128i) {
     unsafe {
         let mut t = _mm_loadu_si128(key.as_ptr().cast());
         _mm_store_si128(rk, t);
-        t = assist(t, _mm_aeskeygenassist_si128(t, 0x01));
+        t = assist(t, sub_rot_word::<0xff>(t, 0x01));
         _mm_store_si128(rk.add(1), t);
-        t = assist(t, _mm_aeskeygenassist_si128(t, 0x02));
+        t = assist(t, sub_rot_word::<0xff>(t, 0x02));
         _mm_store_si128(rk.add(2), t);
-        t = assist(t, _mm_aeskeygenassist_si128(t, 0x04));
+        t = assist(t, sub_rot_word::<0xff>(t, 0x04));
         _mm_store_si128(rk.add(3), t);
-        t = assist(t, _mm_aeskeygenassist_si128(t, 0x08));
+        t = assist(t, sub_rot_word::<0xff>(t, 0x08));
         _mm_store_si128(rk.add(4), t);
-        t = assist(t, _mm_aeskeygenassist_si128(t, 0x10));
+        t = assist(t, sub_rot_word::<0xff>(t, 0x10));
         _mm_store_si128(rk.add(5), t);
-        t = assist(t, _mm_aeskeygenassist_si128(t, 0x20));
+        t = assist(t, sub_rot_word::<0xff>(t, 0x20));
         _mm_store_si128(rk.add(6), t);
-        t = assist(t, _mm_aeskeygenassist_si128(t, 0x40));
+        t = assist(t, sub_rot_word::<0xff>(t, 0x40));
         _mm_store_si128(rk.add(7), t);
-        t = assist(t, _mm_aeskeygenassist_si128(t, 0x80));
+        t = assist(t, sub_rot_word::<0xff>(t, 0x80));
         _mm_store_si128(rk.add(8), t);
-        t = assist(t, _mm_aeskeygenassist_si128(t, 0x1b));
+        t = assist(t, sub_rot_word::<0xff>(t, 0x1b));
         _mm_store_si128(rk.add(9), t);
-        t = assist(t, _mm_aeskeygenassist_si128(t, 0x36));
+        t = assist(t, sub_rot_word::<0xff>(t, 0x36));
         _mm_store_si128(rk.add(10), t);
     }
 }
 
-/// One step of the AES-192 key schedule.
+/// One step of the AES-192 key schedule, given the substituted word in
+/// every lane of `t2`.
 #[inline(always)]
 unsafe fn assist_192(t1: &mut __m128i, t2: __m128i, t3: &mut __m128i) {
     // SAFETY: every intrinsic here is SSE2.
     unsafe {
-        let t2 = _mm_shuffle_epi32(t2, 0x55);
         let mut c = _mm_slli_si128(*t1, 4);
         *t1 = _mm_xor_si128(*t1, c);
         c = _mm_slli_si128(c, 4);
@@ -1492,14 +1539,14 @@ unsafe fn expand_192(key: &[u8; 24], rk: *mut __m128i) {
         let prev = t3;
         assist_192(
             &mut t1,
-            _mm_aeskeygenassist_si128(t3, 0x01),
+            sub_rot_word::<0x55>(t3, 0x01),
             &mut t3,
         );
         _mm_store_si128(rk.add(1), splice::<0>(prev, t1));
         _mm_store_si128(rk.add(2), splice::<1>(t1, t3));
         assist_192(
             &mut t1,
-            _mm_aeskeygenassist_si128(t3, 0x02),
+            sub_rot_word::<0x55>(t3, 0x02),
             &mut t3,
         );
         _mm_store_si128(rk.add(3), t1);
@@ -1507,14 +1554,14 @@ unsafe fn expand_192(key: &[u8; 24], rk: *mut __m128i) {
         let prev = t3;
         assist_192(
             &mut t1,
-            _mm_aeskeygenassist_si128(t3, 0x04),
+            sub_rot_word::<0x55>(t3, 0x04),
             &mut t3,
         );
         _mm_store_si128(rk.add(4), splice::<0>(prev, t1));
         _mm_store_si128(rk.add(5), splice::<1>(t1, t3));
         assist_192(
             &mut t1,
-            _mm_aeskeygenassist_si128(t3, 0x08),
+            sub_rot_word::<0x55>(t3, 0x08),
             &mut t3,
         );
         _mm_store_si128(rk.add(6), t1);
@@ -1522,14 +1569,14 @@ unsafe fn expand_192(key: &[u8; 24], rk: *mut __m128i) {
         let prev = t3;
         assist_192(
             &mut t1,
-            _mm_aeskeygenassist_si128(t3, 0x10),
+            sub_rot_word::<0x55>(t3, 0x10),
             &mut t3,
         );
         _mm_store_si128(rk.add(7), splice::<0>(prev, t1));
         _mm_store_si128(rk.add(8), splice::<1>(t1, t3));
         assist_192(
             &mut t1,
-            _mm_aeskeygenassist_si128(t3, 0x20),
+            sub_rot_word::<0x55>(t3, 0x20),
             &mut t3,
         );
         _mm_store_si128(rk.add(9), t1);
@@ -1537,14 +1584,14 @@ unsafe fn expand_192(key: &[u8; 24], rk: *mut __m128i) {
         let prev = t3;
         assist_192(
             &mut t1,
-            _mm_aeskeygenassist_si128(t3, 0x40),
+            sub_rot_word::<0x55>(t3, 0x40),
             &mut t3,
         );
         _mm_store_si128(rk.add(10), splice::<0>(prev, t1));
         _mm_store_si128(rk.add(11), splice::<1>(t1, t3));
         assist_192(
             &mut t1,
-            _mm_aeskeygenassist_si128(t3, 0x80),
+            sub_rot_word::<0x55>(t3, 0x80),
             &mut t3,
         );
         _mm_store_si128(rk.add(12), t1);
@@ -1556,7 +1603,7 @@ unsafe fn expand_192(key: &[u8; 24], rk: *mut __m128i) {
 unsafe fn assist_256(t1: __m128i, t3: __m128i) -> __m128i {
     // SAFETY: the caller is a function that requires the AES feature.
     unsafe {
-        let b = _mm_shuffle_epi32(_mm_aeskeygenassist_si128(t1, 0x00), 0xaa);
+        let b = sub_word::<0xff>(t1);
         let c = _mm_slli_si128(t3, 4);
         let t3 = _mm_xor_si128(t3, c);
         let c = _mm_slli_si128(c, 4);
@@ -1582,31 +1629,31 @@ unsafe fn expand_256(key: &[u8; 32], rk: *mut __m128i) {
         let mut t3 = _mm_loadu_si128(key.as_ptr().add(16).cast());
         _mm_store_si128(rk, t1);
         _mm_store_si128(rk.add(1), t3);
-        t1 = assist(t1, _mm_aeskeygenassist_si128(t3, 0x01));
+        t1 = assist(t1, sub_rot_word::<0xff>(t3, 0x01));
         _mm_store_si128(rk.add(2), t1);
         t3 = assist_256(t1, t3);
         _mm_store_si128(rk.add(3), t3);
-        t1 = assist(t1, _mm_aeskeygenassist_si128(t3, 0x02));
+        t1 = assist(t1, sub_rot_word::<0xff>(t3, 0x02));
         _mm_store_si128(rk.add(4), t1);
         t3 = assist_256(t1, t3);
         _mm_store_si128(rk.add(5), t3);
-        t1 = assist(t1, _mm_aeskeygenassist_si128(t3, 0x04));
+        t1 = assist(t1, sub_rot_word::<0xff>(t3, 0x04));
         _mm_store_si128(rk.add(6), t1);
         t3 = assist_256(t1, t3);
         _mm_store_si128(rk.add(7), t3);
-        t1 = assist(t1, _mm_aeskeygenassist_si128(t3, 0x08));
+        t1 = assist(t1, sub_rot_word::<0xff>(t3, 0x08));
         _mm_store_si128(rk.add(8), t1);
         t3 = assist_256(t1, t3);
         _mm_store_si128(rk.add(9), t3);
-        t1 = assist(t1, _mm_aeskeygenassist_si128(t3, 0x10));
+        t1 = assist(t1, sub_rot_word::<0xff>(t3, 0x10));
         _mm_store_si128(rk.add(10), t1);
         t3 = assist_256(t1, t3);
         _mm_store_si128(rk.add(11), t3);
-        t1 = assist(t1, _mm_aeskeygenassist_si128(t3, 0x20));
+        t1 = assist(t1, sub_rot_word::<0xff>(t3, 0x20));
         _mm_store_si128(rk.add(12), t1);
         t3 = assist_256(t1, t3);
         _mm_store_si128(rk.add(13), t3);
-        t1 = assist(t1, _mm_aeskeygenassist_si128(t3, 0x40));
+        t1 = assist(t1, sub_rot_word::<0xff>(t3, 0x40));
         _mm_store_si128(rk.add(14), t1);
     }
 }
@@ -1634,6 +1681,107 @@ unsafe fn invert_schedule(ek: *const __m128i, dk: *mut __m128i, rounds: usize) {
     }
 }
 
+/// Undo [`invert_schedule`], recovering the encryption schedule.
+///
+/// MixColumns has order four, so InvMixColumns three times over is
+/// MixColumns, and the one instruction serves both ways.
+///
+/// # Safety
+///
+/// As for [`invert_schedule`].
+#[cfg(feature = "hazmat")]
+#[target_feature(enable = "aes")]
+unsafe fn restore_schedule(
+    dk: *const __m128i,
+    ek: *mut __m128i,
+    rounds: usize,
+) {
+    // SAFETY: the caller guarantees the instructions and both ranges.
+    unsafe {
+        _mm_store_si128(ek, _mm_load_si128(dk.add(rounds)));
+        for i in 1..rounds {
+            let k = _mm_load_si128(dk.add(rounds - i));
+            let k = _mm_aesimc_si128(_mm_aesimc_si128(_mm_aesimc_si128(k)));
+            _mm_store_si128(ek.add(i), k);
+        }
+        _mm_store_si128(ek.add(rounds), _mm_load_si128(dk));
+    }
+}
+
+/// Zero a schedule sixteen bytes at a time, in a way the compiler may not
+/// remove.
+///
+/// `Zeroize` on a byte array is a volatile store per byte, which costs
+/// more than expanding the schedule did, and a caller keying every
+/// connection afresh pays it on every drop.
+///
+/// # Safety
+///
+/// `rk` must be sixteen byte aligned and a whole number of round keys.
+pub(super) unsafe fn wipe(rk: &mut [u8]) {
+    let base = rk.as_mut_ptr().cast::<__m128i>();
+    for i in 0..rk.len() / 16 {
+        // SAFETY: the caller guarantees the alignment and the length;
+        // the store is SSE2, which is baseline on this target.
+        unsafe {
+            core::ptr::write_volatile(base.add(i), _mm_setzero_si128());
+        }
+    }
+    // As zeroize does, so nothing after the wipe is reordered before it.
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Clear every vector register the kernels and key schedules write, when
+/// the `scrub` feature asks for it.
+///
+/// The kernels declare their registers clobbered, so the compiler keeps
+/// nothing there, but the last blocks and round key they held are still
+/// in place on return until something else happens to overwrite them.
+/// Zeroing a register with itself is resolved at rename, so sixteen of
+/// them cost next to nothing beside a kernel.
+#[inline(always)]
+pub(super) fn scrub_registers() {
+    #[cfg(feature = "scrub")]
+    // SAFETY: pxor is SSE2, which is baseline on this target, and every
+    // register written is declared.
+    unsafe {
+        asm!(
+            "pxor xmm0, xmm0",
+            "pxor xmm1, xmm1",
+            "pxor xmm2, xmm2",
+            "pxor xmm3, xmm3",
+            "pxor xmm4, xmm4",
+            "pxor xmm5, xmm5",
+            "pxor xmm6, xmm6",
+            "pxor xmm7, xmm7",
+            "pxor xmm8, xmm8",
+            "pxor xmm9, xmm9",
+            "pxor xmm10, xmm10",
+            "pxor xmm11, xmm11",
+            "pxor xmm12, xmm12",
+            "pxor xmm13, xmm13",
+            "pxor xmm14, xmm14",
+            "pxor xmm15, xmm15",
+            out("xmm0") _, out("xmm1") _, out("xmm2") _,
+            out("xmm3") _, out("xmm4") _, out("xmm5") _,
+            out("xmm6") _, out("xmm7") _, out("xmm8") _,
+            out("xmm9") _, out("xmm10") _, out("xmm11") _,
+            out("xmm12") _, out("xmm13") _, out("xmm14") _,
+            out("xmm15") _,
+            options(nomem, nostack, preserves_flags),
+        );
+    }
+}
+
+/// After a key schedule routine returns, clear its registers and the
+/// stack it ran on, when the `scrub` feature asks for it.
+#[inline(always)]
+fn scrub_schedule() {
+    scrub_registers();
+    #[cfg(feature = "scrub")]
+    crate::symmetric::aes::arch::scrub::stack();
+}
+
 macro_rules! define_aes {
     (
         $enc:ident, $dec:ident, $key_size:expr, $bytes:expr, $rounds:expr,
@@ -1669,14 +1817,29 @@ macro_rules! define_aes {
             ///
             /// If the CPU has no AES instructions. Naming this type asserts
             /// that it does; the parent module's type of the same name
-            /// checks and falls back instead.
+            /// checks and falls back instead, and [`Self::try_new`]
+            /// reports it.
             pub fn new(key: &[u8; $key_size]) -> Self {
-                assert!(supported(), "AES instructions are not available");
+                match Self::try_new(key) {
+                    Ok(this) => this,
+                    Err(e) => panic!("{e}"),
+                }
+            }
+
+            /// Expand `key` into an encryption schedule, or say which CPU
+            /// features that would need.
+            pub fn try_new(
+                key: &[u8; $key_size],
+            ) -> Result<Self, UnsupportedCpu> {
+                if !supported() {
+                    return Err(unsupported());
+                }
                 let mut this = Self { rk: [0u8; $bytes] };
                 // SAFETY: support was just checked; rk is $bytes long and
                 // aligned by the type.
                 unsafe { $expand(key, this.rk.as_mut_ptr().cast()) };
-                this
+                scrub_schedule();
+                Ok(this)
             }
 
             /// The expanded schedule, for a sibling backend that shares
@@ -1757,22 +1920,22 @@ macro_rules! define_aes {
             ///
             /// # Panics
             ///
-            /// If the CPU has no AES instructions.
+            /// If the CPU has no AES instructions. [`Self::try_new`]
+            /// reports that instead.
             pub fn new(key: &[u8; $key_size]) -> Self {
-                assert!(supported(), "AES instructions are not available");
-                let mut ek = Self { rk: [0u8; $bytes] };
-                let mut this = Self { rk: [0u8; $bytes] };
-                // SAFETY: support was just checked; both schedules are
-                // $bytes long and aligned by the type.
-                unsafe {
-                    $expand(key, ek.rk.as_mut_ptr().cast());
-                    invert_schedule(
-                        ek.rk.as_ptr().cast(),
-                        this.rk.as_mut_ptr().cast(),
-                        $rounds,
-                    );
+                match Self::try_new(key) {
+                    Ok(this) => this,
+                    Err(e) => panic!("{e}"),
                 }
-                this
+            }
+
+            /// Expand `key` into a decryption schedule, or say which CPU
+            /// features that would need.
+            pub fn try_new(
+                key: &[u8; $key_size],
+            ) -> Result<Self, UnsupportedCpu> {
+                // The encryption schedule is wiped when it drops here.
+                Ok(Self::from(&$enc::try_new(key)?))
             }
 
             /// The expanded schedule, for a sibling backend that shares
@@ -1805,13 +1968,15 @@ macro_rules! define_aes {
 
         impl Drop for $enc {
             fn drop(&mut self) {
-                self.rk.zeroize();
+                // SAFETY: rk is aligned by the type and whole round keys.
+                unsafe { wipe(&mut self.rk) };
             }
         }
 
         impl Drop for $dec {
             fn drop(&mut self) {
-                self.rk.zeroize();
+                // SAFETY: as for the encryption schedule.
+                unsafe { wipe(&mut self.rk) };
             }
         }
 
@@ -1834,21 +1999,126 @@ macro_rules! define_aes {
             }
         }
 
+        /// Invert an existing encryption schedule, so the key itself
+        /// need not be kept around in case decryption is wanted later.
+        impl From<&$enc> for $dec {
+            fn from(enc: &$enc) -> Self {
+                let mut this = Self { rk: [0u8; $bytes] };
+                // SAFETY: the encryption schedule exists, so the
+                // instructions do; both schedules are $bytes long and
+                // aligned by their types.
+                unsafe {
+                    invert_schedule(
+                        enc.rk.as_ptr().cast(),
+                        this.rk.as_mut_ptr().cast(),
+                        $rounds,
+                    );
+                }
+                scrub_schedule();
+                this
+            }
+        }
+
+        #[cfg(feature = "hazmat")]
+        impl $enc {
+            /// Copy out the round keys, in the [canonical layout].
+            ///
+            /// [canonical layout]: crate::symmetric::aes#round-keys
+            pub fn round_keys(&self) -> Zeroizing<[u8; $bytes]> {
+                // The instructions take round keys in the state's byte
+                // order, which is the canonical one.
+                Zeroizing::new(self.rk)
+            }
+
+            /// Take round keys in the canonical layout as they are.
+            ///
+            /// Nothing checks that they came from a key expansion.
+            ///
+            /// # Panics
+            ///
+            /// As [`Self::new`]; [`Self::try_from_round_keys`] reports it
+            /// instead.
+            pub fn from_round_keys(round_keys: &[u8; $bytes]) -> Self {
+                match Self::try_from_round_keys(round_keys) {
+                    Ok(this) => this,
+                    Err(e) => panic!("{e}"),
+                }
+            }
+
+            /// Take round keys in the canonical layout as they are, or
+            /// say which CPU features that would need.
+            pub fn try_from_round_keys(
+                round_keys: &[u8; $bytes],
+            ) -> Result<Self, UnsupportedCpu> {
+                if !supported() {
+                    return Err(unsupported());
+                }
+                Ok(Self { rk: *round_keys })
+            }
+        }
+
+        #[cfg(feature = "hazmat")]
+        impl $dec {
+            /// Copy out the encryption round keys this schedule was
+            /// inverted from, in the canonical layout.
+            pub fn round_keys(&self) -> Zeroizing<[u8; $bytes]> {
+                let mut enc = $enc { rk: [0u8; $bytes] };
+                // SAFETY: this schedule exists, so the instructions do;
+                // both are $bytes long and aligned by their types.
+                unsafe {
+                    restore_schedule(
+                        self.rk.as_ptr().cast(),
+                        enc.rk.as_mut_ptr().cast(),
+                        $rounds,
+                    );
+                }
+                scrub_schedule();
+                enc.round_keys()
+            }
+
+            /// Invert encryption round keys in the canonical layout.
+            ///
+            /// Nothing checks that they came from a key expansion.
+            ///
+            /// # Panics
+            ///
+            /// As [`Self::new`]; [`Self::try_from_round_keys`] reports it
+            /// instead.
+            pub fn from_round_keys(round_keys: &[u8; $bytes]) -> Self {
+                match Self::try_from_round_keys(round_keys) {
+                    Ok(this) => this,
+                    Err(e) => panic!("{e}"),
+                }
+            }
+
+            /// Invert encryption round keys in the canonical layout, or
+            /// say which CPU features that would need.
+            pub fn try_from_round_keys(
+                round_keys: &[u8; $bytes],
+            ) -> Result<Self, UnsupportedCpu> {
+                Ok(Self::from(&$enc::try_from_round_keys(round_keys)?))
+            }
+        }
+
         impl KeyInit for $enc {
-            fn try_new(key: &[u8]) -> Result<Self, InvalidKeyLength> {
+            type Error = KeyInitError;
+
+            fn try_new(key: &[u8]) -> Result<Self, KeyInitError> {
                 let key: &[u8; $key_size] = key
                     .try_into()
                     .map_err(|_| InvalidKeyLength { got: key.len() })?;
-                Ok(Self::new(key))
+                Ok(Self::try_new(key)?)
             }
         }
 
         impl KeyInit for $dec {
-            fn try_new(key: &[u8]) -> Result<Self, InvalidKeyLength> {
+            type Error = KeyInitError;
+
+            fn try_new(key: &[u8]) -> Result<Self, KeyInitError> {
                 let key: &[u8; $key_size] = key
                     .try_into()
                     .map_err(|_| InvalidKeyLength { got: key.len() })?;
-                Ok(Self::new(key))
+                Ok(Self::try_new(key)?)
             }
         }
 
@@ -1889,21 +2159,26 @@ define_aes!(
 mod tests {
     use super::*;
     use crate::symmetric::aes::arch::portable::ttable;
+    use crate::symmetric::aes::testing::{assert_inverting_matches_expanding, Rng};
 
-    /// xorshift64*, so a divergence is reproducible from the seed.
-    struct Rng(u64);
-
-    impl Rng {
-        fn fill(&mut self, buf: &mut [u8]) {
-            for chunk in buf.chunks_mut(8) {
-                let mut x = self.0;
-                x ^= x >> 12;
-                x ^= x << 25;
-                x ^= x >> 27;
-                self.0 = x;
-                let b = x.wrapping_mul(0x2545_f491_4f6c_dd1d).to_le_bytes();
-                chunk.copy_from_slice(&b[..chunk.len()]);
-            }
+    /// Construction on the wrong CPU is an error naming what is missing,
+    /// not a panic, through both the inherent and the trait constructor.
+    #[test]
+    fn try_new_refuses_rather_than_panics() {
+        match Aes128Enc::try_new(&[0u8; 16]) {
+            Ok(_) => assert!(supported()),
+            Err(e) => assert!(!supported() && e.missing().count() > 0),
+        }
+        assert_eq!(Aes256Dec::try_new(&[0u8; 32]).is_ok(), supported());
+
+        // The length is checked first, so it is reported on any CPU.
+        assert!(matches!(
+            <Aes192Enc as KeyInit>::try_new(&[0u8; 16]),
+            Err(KeyInitError::Key(InvalidKeyLength { got: 16 }))
+        ));
+        match <Aes192Dec as KeyInit>::try_new(&[0u8; 24]) {
+            Ok(_) => assert!(supported()),
+            Err(e) => assert!(matches!(e, KeyInitError::Cpu(_))),
         }
     }
 
@@ -2136,6 +2411,76 @@ mod tests {
         assert_eq!(ours_ctr, theirs_ctr, "counter after the wrap");
     }
 
+    /// The key schedule is built without `aeskeygenassist`, so every
+    /// round key has to be checked against an independent expansion, and
+    /// one block under each of many keys touches all of them.
+    #[test]
+    fn expansion_agrees_with_the_portable_implementation() {
+        if !supported() {
+            return;
+        }
+        let mut rng = Rng(0x5eed_0f6b_1c0d_e5a1);
+        macro_rules! check {
+            ($enc:ident, $pe:path, $len:expr) => {
+                for _ in 0..1000 {
+                    let mut key = [0u8; $len];
+                    let mut ours = [0u8; BLOCK_SIZE];
+                    rng.fill(&mut key);
+                    rng.fill(&mut ours);
+                    let mut theirs = ours;
+                    $enc::new(&key).encrypt_block(&mut ours);
+                    <$pe>::new(&key).encrypt_block(&mut theirs);
+                    assert_eq!(
+                        ours, theirs,
+                        "{} bit key {key:02x?}", $len * 8
+                    );
+                }
+            };
+        }
+        check!(Aes128Enc, ttable::Aes128Enc, 16);
+        check!(Aes192Enc, ttable::Aes192Enc, 24);
+        check!(Aes256Enc, ttable::Aes256Enc, 32);
+    }
+
+    /// Inverting an encryption schedule must give exactly the schedule
+    /// `Dec::new` expands, at every key size.
+    #[test]
+    fn inverting_matches_expanding() {
+        if !supported() {
+            return;
+        }
+        assert_inverting_matches_expanding!();
+    }
+
+    /// The canonical layout is the portable one's, in both directions,
+    /// and loading a schedule gives back what expansion would build.
+    #[cfg(feature = "hazmat")]
+    #[test]
+    fn round_keys_match_the_portable_implementation() {
+        if !supported() {
+            return;
+        }
+        let mut rng = Rng(0x7f6e_5d4c_3b2a_1908);
+        macro_rules! check {
+            ($enc:ident, $dec:ident, $pe:path, $len:expr) => {
+                for _ in 0..16 {
+                    let mut key = [0u8; $len];
+                    rng.fill(&mut key);
+                    let rk = <$pe>::new(&key).round_keys();
+                    assert_eq!(*$enc::new(&key).round_keys(), *rk);
+                    assert_eq!(*$dec::new(&key).round_keys(), *rk);
+                    let enc = $enc::from_round_keys(&rk);
+                    assert_eq!(enc.rk, $enc::new(&key).rk);
+                    let dec = $dec::from_round_keys(&rk);
+                    assert_eq!(dec.rk, $dec::new(&key).rk);
+                }
+            };
+        }
+        check!(Aes128Enc, Aes128Dec, ttable::Aes128Enc, 16);
+        check!(Aes192Enc, Aes192Dec, ttable::Aes192Enc, 24);
+        check!(Aes256Enc, Aes256Dec, ttable::Aes256Enc, 32);
+    }
+
     #[test]
     fn schedules_are_wiped_on_drop() {
         if !supported() {
@@ -2155,4 +2500,101 @@ mod tests {
         let dead = unsafe { core::slice::from_raw_parts(bytes, len) };
         assert!(dead.iter().all(|&b| b == 0), "key material survived");
     }
+
+    /// Run `f`, then read all sixteen xmm registers.
+    ///
+    /// The call is made from the same asm block that reads them back, so
+    /// no code the compiler emits runs between `f` returning and the
+    /// reads. Everything the calling convention lets a callee clobber is
+    /// declared, and the buffer pointer rides in a callee-saved register.
+    fn registers_after(mut f: impl FnMut()) -> [[u8; 16]; 16] {
+        extern "C" fn trampoline(f: &mut &mut dyn FnMut()) {
+            f();
+        }
+        let mut f: &mut dyn FnMut() = &mut f;
+        let mut regs = [[0u8; 16]; 16];
+        // SAFETY: the stack is call-aligned on entry to an asm block
+        // without nostack, trampoline has the C ABI that clobber_abi
+        // describes, and regs has room for every store.
+        unsafe {
+            asm!(
+                "call {t}",
+                "movdqu [r12 + 0x0], xmm0",
+                "movdqu [r12 + 0x10], xmm1",
+                "movdqu [r12 + 0x20], xmm2",
+                "movdqu [r12 + 0x30], xmm3",
+                "movdqu [r12 + 0x40], xmm4",
+                "movdqu [r12 + 0x50], xmm5",
+                "movdqu [r12 + 0x60], xmm6",
+                "movdqu [r12 + 0x70], xmm7",
+                "movdqu [r12 + 0x80], xmm8",
+                "movdqu [r12 + 0x90], xmm9",
+                "movdqu [r12 + 0xa0], xmm10",
+                "movdqu [r12 + 0xb0], xmm11",
+                "movdqu [r12 + 0xc0], xmm12",
+                "movdqu [r12 + 0xd0], xmm13",
+                "movdqu [r12 + 0xe0], xmm14",
+                "movdqu [r12 + 0xf0], xmm15",
+                t = sym trampoline,
+                in("rdi") &mut f,
+                in("r12") regs.as_mut_ptr(),
+                clobber_abi("C"),
+            );
+        }
+        regs
+    }
+
+    /// With `scrub`, every kernel and key schedule leaves the vector
+    /// registers empty. Without it, the same inspection finds a round key
+    /// still sitting in one, which is what shows it can see them.
+    #[test]
+    fn registers_are_scrubbed_after_every_kernel() {
+        if !ctr_supported() {
+            return;
+        }
+        let mut rng = Rng(0x5c2b_0e11_7d4a_9f63);
+        let mut key = [0u8; 32];
+        rng.fill(&mut key);
+        let enc = Aes256Enc::new(&key);
+        let dec = Aes256Dec::new(&key);
+        let round_keys = enc.rk;
+        let mut data = [0u8; 40 * BLOCK_SIZE];
+        rng.fill(&mut data);
+
+        let check = |what: &str, regs: [[u8; 16]; 16]| {
+            if cfg!(feature = "scrub") {
+                assert!(
+                    regs.iter().all(|r| *r == [0; 16]),
+                    "{what} left {regs:02x?}"
+                );
+            } else {
+                assert!(
+                    regs.iter().any(|r| {
+                        round_keys.chunks(16).any(|k| k == r.as_slice())
+                    }),
+                    "{what} left no round key to find"
+                );
+            }
+        };
+        for blocks in [1, 2, 4, 8, 12, 40] {
+            let data = &mut data[..blocks * BLOCK_SIZE];
+            check("encrypt", registers_after(|| {
+                enc.encrypt(data);
+            }));
+            check("decrypt", registers_after(|| {
+                dec.decrypt(data);
+            }));
+            let mut counter = [0xff; BLOCK_SIZE];
+            check("ctr", registers_after(|| {
+                enc.ctr(&mut counter, data);
+            }));
+        }
+        check("expansion", registers_after(|| {
+            let mut rk = Aligned16([0u8; 240]);
+            // SAFETY: support was checked, and rk is fifteen aligned
+            // round keys.
+            unsafe { expand_256(&key, rk.0.as_mut_ptr().cast()) };
+            scrub_schedule();
+        }));
+    }
 }
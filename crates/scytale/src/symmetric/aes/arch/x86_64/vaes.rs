@@ -17,9 +17,13 @@
 
 use core::arch::asm;
 
+#[cfg(feature = "hazmat")]
+use zeroize::Zeroizing;
+
 use super::aesni;
+use crate::cpu::{Feature, UnsupportedCpu};
 use crate::symmetric::block_cipher::{
-    BlockDecrypt, BlockEncrypt, InvalidKeyLength, KeyInit,
+    BlockDecrypt, BlockEncrypt, InvalidKeyLength, KeyInit, KeyInitError,
 };
 
 /// The AES block size in bytes.
@@ -31,7 +35,15 @@ const WIDTH: usize = 16;
 /// Whether this CPU can run these kernels.
 ///
 /// AES-NI is required as well: it expands the key and takes the tails.
+/// A build for a target that guarantees all three does not ask.
 pub fn supported() -> bool {
+    if cfg!(all(
+        target_feature = "aes",
+        target_feature = "avx2",
+        target_feature = "vaes"
+    )) {
+        return true;
+    }
     use std::sync::OnceLock;
     static SUPPORTED: OnceLock<bool> = OnceLock::new();
     *SUPPORTED.get_or_init(|| {
@@ -41,6 +53,11 @@ pub fn supported() -> bool {
     })
 }
 
+/// What [`supported`] checks for, naming whichever the CPU lacks.
+fn unsupported() -> UnsupportedCpu {
+    UnsupportedCpu::lacking(&[Feature::Aes, Feature::Avx2, Feature::Vaes])
+}
+
 /// A fully unrolled sixteen block kernel, looping over the buffer.
 ///
 /// The round keys are the only repetition, so the round sequence is
@@ -343,10 +360,36 @@ ctr_kernel!(
     "0xe0"
 );
 
+/// Clear every vector register the kernels write, when the `scrub`
+/// feature asks for it.
+///
+/// The kernels already end with `vzeroupper`, which leaves the low halves
+/// in place, and the AES-NI backend only clears those after a tail it
+/// took itself, which not every call has. `vzeroall` clears all sixteen
+/// registers whole.
+#[inline(always)]
+fn scrub_registers() {
+    #[cfg(feature = "scrub")]
+    // SAFETY: vzeroall is AVX, which supported() requires before any of
+    // these types exist, and every register written is declared.
+    unsafe {
+        asm!(
+            "vzeroall",
+            out("ymm0") _, out("ymm1") _, out("ymm2") _,
+            out("ymm3") _, out("ymm4") _, out("ymm5") _,
+            out("ymm6") _, out("ymm7") _, out("ymm8") _,
+            out("ymm9") _, out("ymm10") _, out("ymm11") _,
+            out("ymm12") _, out("ymm13") _, out("ymm14") _,
+            out("ymm15") _,
+            options(nomem, nostack, preserves_flags),
+        );
+    }
+}
+
 macro_rules! define_aes {
     (
         $enc:ident, $dec:ident, $inner_enc:path, $inner_dec:path,
-        $key_size:expr, $enc_kernel:ident, $dec_kernel:ident,
+        $key_size:expr, $bytes:expr, $enc_kernel:ident, $dec_kernel:ident,
         $ctr_kernel:ident, $bits:expr
     ) => {
         #[doc = concat!("AES-", $bits, " encryption only, on VAES.")]
@@ -373,10 +416,30 @@ macro_rules! define_aes {
             ///
             /// If the CPU cannot run these kernels. Naming this type
             /// asserts that it can; the parent module's type of the same
-            /// name checks and falls back instead.
+            /// name checks and falls back instead, and [`Self::try_new`]
+            /// reports it.
             pub fn new(key: &[u8; $key_size]) -> Self {
-                assert!(supported(), "VAES is not available");
-                Self { inner: <$inner_enc>::new(key) }
+                match Self::try_new(key) {
+                    Ok(this) => this,
+                    Err(e) => panic!("{e}"),
+                }
+            }
+
+            /// Expand `key` into an encryption schedule, or say which CPU
+            /// features that would need.
+            pub fn try_new(
+                key: &[u8; $key_size],
+            ) -> Result<Self, UnsupportedCpu> {
+                if !supported() {
+                    return Err(unsupported());
+                }
+                Ok(Self { inner: <$inner_enc>::new(key) })
+            }
+
+            /// The expanded schedule, AES-NI's, for the 512 bit kernels
+            /// that share it.
+            pub(super) fn schedule(&self) -> &[u8] {
+                self.inner.schedule()
             }
 
             /// Encrypt whole blocks in place, returning bytes consumed.
@@ -393,6 +456,7 @@ macro_rules! define_aes {
                             groups,
                         );
                     }
+                    scrub_registers();
                 }
                 // Anything left is shorter than a group; AES-NI takes it.
                 self.inner.encrypt(&mut data[groups * WIDTH * BLOCK_SIZE..]);
@@ -436,6 +500,7 @@ macro_rules! define_aes {
                             counter.as_ptr(),
                         );
                     }
+                    scrub_registers();
                     *counter = c.wrapping_add(span as u128).to_be_bytes();
                     self.inner.ctr(
                         counter,
@@ -460,10 +525,29 @@ macro_rules! define_aes {
             ///
             /// # Panics
             ///
-            /// If the CPU cannot run these kernels.
+            /// If the CPU cannot run these kernels. [`Self::try_new`]
+            /// reports that instead.
             pub fn new(key: &[u8; $key_size]) -> Self {
-                assert!(supported(), "VAES is not available");
-                Self { inner: <$inner_dec>::new(key) }
+                match Self::try_new(key) {
+                    Ok(this) => this,
+                    Err(e) => panic!("{e}"),
+                }
+            }
+
+            /// Expand `key` into a decryption schedule, or say which CPU
+            /// features that would need.
+            pub fn try_new(
+                key: &[u8; $key_size],
+            ) -> Result<Self, UnsupportedCpu> {
+                if !supported() {
+                    return Err(unsupported());
+                }
+                Ok(Self { inner: <$inner_dec>::new(key) })
+            }
+
+            /// The expanded schedule, for the 512 bit kernels.
+            pub(super) fn schedule(&self) -> &[u8] {
+                self.inner.schedule()
             }
 
             /// Decrypt whole blocks in place, returning bytes consumed.
@@ -479,6 +563,7 @@ macro_rules! define_aes {
                             groups,
                         );
                     }
+                    scrub_registers();
                 }
                 self.inner.decrypt(&mut data[groups * WIDTH * BLOCK_SIZE..]);
                 blocks * BLOCK_SIZE
@@ -490,21 +575,104 @@ macro_rules! define_aes {
             }
         }
 
+        /// Invert an existing encryption schedule, so the key itself
+        /// need not be kept around in case decryption is wanted later.
+        impl From<&$enc> for $dec {
+            fn from(enc: &$enc) -> Self {
+                Self { inner: <$inner_dec>::from(&enc.inner) }
+            }
+        }
+
+        #[cfg(feature = "hazmat")]
+        impl $enc {
+            /// Copy out the round keys, in the [canonical layout].
+            ///
+            /// [canonical layout]: crate::symmetric::aes#round-keys
+            pub fn round_keys(&self) -> Zeroizing<[u8; $bytes]> {
+                self.inner.round_keys()
+            }
+
+            /// Take round keys in the canonical layout as they are.
+            ///
+            /// Nothing checks that they came from a key expansion.
+            ///
+            /// # Panics
+            ///
+            /// As [`Self::new`]; [`Self::try_from_round_keys`] reports it
+            /// instead.
+            pub fn from_round_keys(round_keys: &[u8; $bytes]) -> Self {
+                match Self::try_from_round_keys(round_keys) {
+                    Ok(this) => this,
+                    Err(e) => panic!("{e}"),
+                }
+            }
+
+            /// Take round keys in the canonical layout as they are, or
+            /// say which CPU features that would need.
+            pub fn try_from_round_keys(
+                round_keys: &[u8; $bytes],
+            ) -> Result<Self, UnsupportedCpu> {
+                if !supported() {
+                    return Err(unsupported());
+                }
+                Ok(Self { inner: <$inner_enc>::from_round_keys(round_keys) })
+            }
+        }
+
+        #[cfg(feature = "hazmat")]
+        impl $dec {
+            /// Copy out the encryption round keys this schedule was
+            /// inverted from, in the canonical layout.
+            pub fn round_keys(&self) -> Zeroizing<[u8; $bytes]> {
+                self.inner.round_keys()
+            }
+
+            /// Invert encryption round keys in the canonical layout.
+            ///
+            /// Nothing checks that they came from a key expansion.
+            ///
+            /// # Panics
+            ///
+            /// As [`Self::new`]; [`Self::try_from_round_keys`] reports it
+            /// instead.
+            pub fn from_round_keys(round_keys: &[u8; $bytes]) -> Self {
+                match Self::try_from_round_keys(round_keys) {
+                    Ok(this) => this,
+                    Err(e) => panic!("{e}"),
+                }
+            }
+
+            /// Invert encryption round keys in the canonical layout, or
+            /// say which CPU features that would need.
+            pub fn try_from_round_keys(
+                round_keys: &[u8; $bytes],
+            ) -> Result<Self, UnsupportedCpu> {
+                if !supported() {
+                    return Err(unsupported());
+                }
+                Ok(Self { inner: <$inner_dec>::from_round_keys(round_keys) })
+            }
+        }
+
         impl KeyInit for $enc {
-            fn try_new(key: &[u8]) -> Result<Self, InvalidKeyLength> {
+            type Error = KeyInitError;
+
+            fn try_new(key: &[u8]) -> Result<Self, KeyInitError> {
                 let key: &[u8; $key_size] = key
                     .try_into()
                     .map_err(|_| InvalidKeyLength { got: key.len() })?;
-                Ok(Self::new(key))
+                Ok(Self::try_new(key)?)
             }
         }
 
         impl KeyInit for $dec {
-            fn try_new(key: &[u8]) -> Result<Self, InvalidKeyLength> {
+            type Error = KeyInitError;
+
+            fn try_new(key: &[u8]) -> Result<Self, KeyInitError> {
                 let key: &[u8; $key_size] = key
                     .try_into()
                     .map_err(|_| InvalidKeyLength { got: key.len() })?;
-                Ok(Self::new(key))
+                Ok(Self::try_new(key)?)
             }
         }
 
@@ -548,15 +716,15 @@ macro_rules! define_aes {
 }
 
 define_aes!(
-    Aes128Enc, Aes128Dec, aesni::Aes128Enc, aesni::Aes128Dec, 16,
+    Aes128Enc, Aes128Dec, aesni::Aes128Enc, aesni::Aes128Dec, 16, 176,
     e128, d128, ctr_e128, "128"
 );
 define_aes!(
-    Aes192Enc, Aes192Dec, aesni::Aes192Enc, aesni::Aes192Dec, 24,
+    Aes192Enc, Aes192Dec, aesni::Aes192Enc, aesni::Aes192Dec, 24, 208,
     e192, d192, ctr_e192, "192"
 );
 define_aes!(
-    Aes256Enc, Aes256Dec, aesni::Aes256Enc, aesni::Aes256Dec, 32,
+    Aes256Enc, Aes256Dec, aesni::Aes256Enc, aesni::Aes256Dec, 32, 240,
     e256, d256, ctr_e256, "256"
 );
 
@@ -564,21 +732,26 @@ define_aes!(
 mod tests {
     use super::*;
     use crate::symmetric::aes::arch::portable::ttable;
+    use crate::symmetric::aes::testing::Rng;
 
-    /// xorshift64*, so a divergence is reproducible from the seed.
-    struct Rng(u64);
-
-    impl Rng {
-        fn fill(&mut self, buf: &mut [u8]) {
-            for chunk in buf.chunks_mut(8) {
-                let mut x = self.0;
-                x ^= x >> 12;
-                x ^= x << 25;
-                x ^= x >> 27;
-                self.0 = x;
-                let b = x.wrapping_mul(0x2545_f491_4f6c_dd1d).to_le_bytes();
-                chunk.copy_from_slice(&b[..chunk.len()]);
-            }
+    /// Construction on the wrong CPU is an error naming what is missing,
+    /// not a panic, through both the inherent and the trait constructor.
+    #[test]
+    fn try_new_refuses_rather_than_panics() {
+        match Aes128Enc::try_new(&[0u8; 16]) {
+            Ok(_) => assert!(supported()),
+            Err(e) => assert!(!supported() && e.missing().count() > 0),
+        }
+        assert_eq!(Aes256Dec::try_new(&[0u8; 32]).is_ok(), supported());
+
+        // The length is checked first, so it is reported on any CPU.
+        assert!(matches!(
+            <Aes192Enc as KeyInit>::try_new(&[0u8; 16]),
+            Err(KeyInitError::Key(InvalidKeyLength { got: 16 }))
+        ));
+        match <Aes192Dec as KeyInit>::try_new(&[0u8; 24]) {
+            Ok(_) => assert!(supported()),
+            Err(e) => assert!(matches!(e, KeyInitError::Cpu(_))),
         }
     }
 
@@ -729,6 +902,57 @@ mod tests {
         assert_eq!(block, plaintext);
     }
 
+    /// The inner schedule is inverted by AES-NI, so this only has to show
+    /// the wrapper hands over the right one, through the wide kernel too.
+    #[test]
+    fn inverting_matches_expanding() {
+        if !supported() {
+            return;
+        }
+        let mut rng = Rng(0x0f1e_2d3c_4b5a_6978);
+        macro_rules! check_inverted {
+            ($enc:ident, $dec:ident, $len:expr) => {
+                let mut key = [0u8; $len];
+                rng.fill(&mut key);
+                let mut ciphertext = [0u8; (WIDTH + 3) * BLOCK_SIZE];
+                rng.fill(&mut ciphertext);
+                let mut inverted = ciphertext;
+                $dec::from(&$enc::new(&key)).decrypt(&mut inverted);
+                let mut expanded = ciphertext;
+                $dec::new(&key).decrypt(&mut expanded);
+                assert_eq!(inverted, expanded, "{} bit", $len * 8);
+            };
+        }
+        check_inverted!(Aes128Enc, Aes128Dec, 16);
+        check_inverted!(Aes192Enc, Aes192Dec, 24);
+        check_inverted!(Aes256Enc, Aes256Dec, 32);
+    }
+
+    /// The schedule is AES-NI's, so this only has to show the wrapper
+    /// passes it through, and that a loaded one drives the wide kernel.
+    #[cfg(feature = "hazmat")]
+    #[test]
+    fn round_keys_pass_through() {
+        if !supported() {
+            return;
+        }
+        let mut rng = Rng(0x7f6e_5d4c_3b2a_1908);
+        let mut key = [0u8; 32];
+        rng.fill(&mut key);
+        let rk = aesni::Aes256Enc::new(&key).round_keys();
+        assert_eq!(*Aes256Enc::new(&key).round_keys(), *rk);
+        assert_eq!(*Aes256Dec::new(&key).round_keys(), *rk);
+
+        let plaintext = [0x3cu8; (WIDTH + 3) * BLOCK_SIZE];
+        let mut loaded = plaintext;
+        Aes256Enc::from_round_keys(&rk).encrypt(&mut loaded);
+        let mut expanded = plaintext;
+        Aes256Enc::new(&key).encrypt(&mut expanded);
+        assert_eq!(loaded, expanded);
+        Aes256Dec::from_round_keys(&rk).decrypt(&mut loaded);
+        assert_eq!(loaded, plaintext);
+    }
+
     #[test]
     fn partial_trailing_block_is_left_alone() {
         if !supported() {
@@ -742,4 +966,93 @@ mod tests {
         );
         assert_eq!(&data[(WIDTH + 1) * BLOCK_SIZE..], &[0xcc; 5]);
     }
+
+    /// Run `f`, then read all sixteen ymm registers whole, as the AES-NI
+    /// tests do the xmm ones.
+    fn registers_after(mut f: impl FnMut()) -> [[u8; 32]; 16] {
+        extern "C" fn trampoline(f: &mut &mut dyn FnMut()) {
+            f();
+        }
+        let mut f: &mut dyn FnMut() = &mut f;
+        let mut regs = [[0u8; 32]; 16];
+        // SAFETY: the stack is call-aligned on entry to an asm block
+        // without nostack, trampoline has the C ABI that clobber_abi
+        // describes, regs has room for every store, and the caller has
+        // checked for AVX.
+        unsafe {
+            asm!(
+                "call {t}",
+                "vmovdqu [r12 + 0x0], ymm0",
+                "vmovdqu [r12 + 0x20], ymm1",
+                "vmovdqu [r12 + 0x40], ymm2",
+                "vmovdqu [r12 + 0x60], ymm3",
+                "vmovdqu [r12 + 0x80], ymm4",
+                "vmovdqu [r12 + 0xa0], ymm5",
+                "vmovdqu [r12 + 0xc0], ymm6",
+                "vmovdqu [r12 + 0xe0], ymm7",
+                "vmovdqu [r12 + 0x100], ymm8",
+                "vmovdqu [r12 + 0x120], ymm9",
+                "vmovdqu [r12 + 0x140], ymm10",
+                "vmovdqu [r12 + 0x160], ymm11",
+                "vmovdqu [r12 + 0x180], ymm12",
+                "vmovdqu [r12 + 0x1a0], ymm13",
+                "vmovdqu [r12 + 0x1c0], ymm14",
+                "vmovdqu [r12 + 0x1e0], ymm15",
+                t = sym trampoline,
+                in("rdi") &mut f,
+                in("r12") regs.as_mut_ptr(),
+                clobber_abi("C"),
+            );
+        }
+        regs
+    }
+
+    /// With `scrub`, the wide kernels leave the ymm registers empty,
+    /// upper halves included. Without it, a round key is still found in
+    /// one.
+    #[test]
+    fn registers_are_scrubbed_after_every_kernel() {
+        if !supported() {
+            return;
+        }
+        let mut rng = Rng(0x0d1e_2f3a_4b5c_6d7e);
+        let mut key = [0u8; 32];
+        rng.fill(&mut key);
+        let enc = Aes256Enc::new(&key);
+        let dec = Aes256Dec::new(&key);
+        let round_keys = enc.inner.schedule().to_vec();
+        let mut data = [0u8; 2 * WIDTH * BLOCK_SIZE];
+        rng.fill(&mut data);
+
+        let check = |what: &str, regs: [[u8; 32]; 16]| {
+            if cfg!(feature = "scrub") {
+                assert!(
+                    regs.iter().all(|r| *r == [0; 32]),
+                    "{what} left {regs:02x?}"
+                );
+            } else {
+                assert!(
+                    regs.iter().flat_map(|r| r.chunks(16)).any(|r| {
+                        round_keys.chunks(16).any(|k| k == r)
+                    }),
+                    "{what} left no round key to find"
+                );
+            }
+        };
+        // Whole groups only, so the AES-NI tail never runs and its own
+        // scrub cannot stand in for this one.
+        for groups in [1, 2] {
+            let data = &mut data[..groups * WIDTH * BLOCK_SIZE];
+            check("encrypt", registers_after(|| {
+                enc.encrypt(data);
+            }));
+            check("decrypt", registers_after(|| {
+                dec.decrypt(data);
+            }));
+            let mut counter = [0u8; BLOCK_SIZE];
+            check("ctr", registers_after(|| {
+                enc.ctr(&mut counter, data);
+            }));
+        }
+    }
 }
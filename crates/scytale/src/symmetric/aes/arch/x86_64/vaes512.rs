@@ -0,0 +1,1178 @@
+//! AES on the 512 bit VAES vector instructions.
+//!
+//! Each zmm register carries four blocks and the kernels run eight
+//! registers wide, so thirty-two blocks are in flight per iteration and
+//! every round instruction does the work of four AES-NI ones. Round keys
+//! are broadcast to all four lanes with `vbroadcasti32x4`.
+//!
+//! This needs AVX-512 as well as VAES, which Ice Lake and later Intel
+//! cores and Zen 4 and later AMD ones have. Key expansion is AES-NI's and
+//! anything shorter than thirty-two blocks goes to the 256 bit [`vaes`]
+//! backend, which hands what it cannot fill on to AES-NI in turn; all
+//! three read exactly the same schedule, and [`supported`] requires all
+//! three.
+//!
+//! Only zmm0 to zmm15 are used, and the kernels end with `vzeroupper`, so
+//! the SSE code they return to pays no transition penalty.
+//!
+//! Where neither the CPU nor an emulator offers AVX-512, the tests here
+//! say so and pass. `cargo test-sde` runs the suite under Intel's
+//! Software Development Emulator, as an Ice Lake server, to cover them
+//! anyway.
+//!
+//! Like AES-NI, and unlike a T-table cipher, this is constant time with
+//! respect to the key.
+//!
+//! [`vaes`]: super::vaes
+
+use core::arch::asm;
+
+#[cfg(feature = "hazmat")]
+use zeroize::Zeroizing;
+
+use super::vaes;
+use crate::cpu::{Feature, UnsupportedCpu};
+use crate::symmetric::block_cipher::{
+    BlockDecrypt, BlockEncrypt, InvalidKeyLength, KeyInit, KeyInitError,
+};
+
+/// The AES block size in bytes.
+pub const BLOCK_SIZE: usize = 16;
+
+/// Blocks in flight: eight registers of four blocks each.
+const WIDTH: usize = 32;
+
+/// Whether this CPU can run these kernels.
+///
+/// The 256 bit VAES backend is required as well: it takes the tails, and
+/// AES-NI under it expands the key. A build for a target that guarantees
+/// all of them does not ask.
+pub fn supported() -> bool {
+    if cfg!(all(
+        target_feature = "aes",
+        target_feature = "avx2",
+        target_feature = "vaes",
+        target_feature = "avx512f",
+        target_feature = "avx512vl"
+    )) {
+        return true;
+    }
+    use std::sync::OnceLock;
+    static SUPPORTED: OnceLock<bool> = OnceLock::new();
+    *SUPPORTED.get_or_init(|| {
+        is_x86_feature_detected!("avx512f")
+            && is_x86_feature_detected!("avx512vl")
+            && vaes::supported()
+    })
+}
+
+/// What [`supported`] checks for, naming whichever the CPU lacks.
+fn unsupported() -> UnsupportedCpu {
+    UnsupportedCpu::lacking(&[
+        Feature::Aes,
+        Feature::Avx2,
+        Feature::Vaes,
+        Feature::Avx512f,
+        Feature::Avx512vl,
+    ])
+}
+
+/// A fully unrolled thirty-two block kernel, looping over the buffer.
+///
+/// As the 256 bit kernel, on registers twice as wide: the loop walks
+/// whole groups of thirty-two blocks and is amortised over 128 round
+/// instructions.
+macro_rules! kernel {
+    (
+        $name:ident, $round:literal, $last:literal,
+        [$($key:literal),+], $final:literal
+    ) => {
+        /// # Safety
+        ///
+        /// The CPU must have VAES and AVX-512F. `rk` must hold the
+        /// schedule, and `data` at least `groups * 32` whole blocks.
+        #[inline]
+        unsafe fn $name(rk: *const u8, data: *mut u8, groups: usize) {
+            // SAFETY: the caller guarantees the instructions and the
+            // range. EVEX encoded loads have no alignment requirement.
+            unsafe {
+                asm!(
+                    "2:",
+                    "vmovdqu64 zmm0, [{d} + 0]",
+                    "vmovdqu64 zmm1, [{d} + 64]",
+                    "vmovdqu64 zmm2, [{d} + 128]",
+                    "vmovdqu64 zmm3, [{d} + 192]",
+                    "vmovdqu64 zmm4, [{d} + 256]",
+                    "vmovdqu64 zmm5, [{d} + 320]",
+                    "vmovdqu64 zmm6, [{d} + 384]",
+                    "vmovdqu64 zmm7, [{d} + 448]",
+                    "vbroadcasti32x4 zmm8, [{rk}]",
+                    "vpxorq zmm0, zmm0, zmm8",
+                    "vpxorq zmm1, zmm1, zmm8",
+                    "vpxorq zmm2, zmm2, zmm8",
+                    "vpxorq zmm3, zmm3, zmm8",
+                    "vpxorq zmm4, zmm4, zmm8",
+                    "vpxorq zmm5, zmm5, zmm8",
+                    "vpxorq zmm6, zmm6, zmm8",
+                    "vpxorq zmm7, zmm7, zmm8",
+                    $(
+                        concat!("vbroadcasti32x4 zmm8, [{rk} + ", $key, "]"),
+                        concat!($round, " zmm0, zmm0, zmm8"),
+                        concat!($round, " zmm1, zmm1, zmm8"),
+                        concat!($round, " zmm2, zmm2, zmm8"),
+                        concat!($round, " zmm3, zmm3, zmm8"),
+                        concat!($round, " zmm4, zmm4, zmm8"),
+                        concat!($round, " zmm5, zmm5, zmm8"),
+                        concat!($round, " zmm6, zmm6, zmm8"),
+                        concat!($round, " zmm7, zmm7, zmm8"),
+                    )+
+                    concat!("vbroadcasti32x4 zmm8, [{rk} + ", $final, "]"),
+                    concat!($last, " zmm0, zmm0, zmm8"),
+                    concat!($last, " zmm1, zmm1, zmm8"),
+                    concat!($last, " zmm2, zmm2, zmm8"),
+                    concat!($last, " zmm3, zmm3, zmm8"),
+                    concat!($last, " zmm4, zmm4, zmm8"),
+                    concat!($last, " zmm5, zmm5, zmm8"),
+                    concat!($last, " zmm6, zmm6, zmm8"),
+                    concat!($last, " zmm7, zmm7, zmm8"),
+                    "vmovdqu64 [{d} + 0], zmm0",
+                    "vmovdqu64 [{d} + 64], zmm1",
+                    "vmovdqu64 [{d} + 128], zmm2",
+                    "vmovdqu64 [{d} + 192], zmm3",
+                    "vmovdqu64 [{d} + 256], zmm4",
+                    "vmovdqu64 [{d} + 320], zmm5",
+                    "vmovdqu64 [{d} + 384], zmm6",
+                    "vmovdqu64 [{d} + 448], zmm7",
+                    "add {d}, 512",
+                    "dec {g}",
+                    "jnz 2b",
+                    // Zero the upper halves, as the 256 bit kernels do, so
+                    // the SSE code this returns to pays no transition
+                    // penalty.
+                    "vzeroupper",
+                    rk = in(reg) rk,
+                    d = inout(reg) data => _,
+                    g = inout(reg) groups => _,
+                    out("zmm0") _, out("zmm1") _, out("zmm2") _,
+                    out("zmm3") _, out("zmm4") _, out("zmm5") _,
+                    out("zmm6") _, out("zmm7") _, out("zmm8") _,
+                    options(nostack),
+                );
+            }
+        }
+    };
+}
+
+kernel!(
+    e128, "vaesenc", "vaesenclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90"
+    ],
+    "0xa0"
+);
+kernel!(
+    d128, "vaesdec", "vaesdeclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90"
+    ],
+    "0xa0"
+);
+kernel!(
+    e192, "vaesenc", "vaesenclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90",
+        "0xa0", "0xb0"
+    ],
+    "0xc0"
+);
+kernel!(
+    d192, "vaesdec", "vaesdeclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90",
+        "0xa0", "0xb0"
+    ],
+    "0xc0"
+);
+kernel!(
+    e256, "vaesenc", "vaesenclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90",
+        "0xa0", "0xb0", "0xc0", "0xd0"
+    ],
+    "0xe0"
+);
+kernel!(
+    d256, "vaesdec", "vaesdeclast",
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80", "0x90",
+        "0xa0", "0xb0", "0xc0", "0xd0"
+    ],
+    "0xe0"
+);
+
+/// A table aligned to a cache line, so no load from it splits one.
+#[repr(align(64))]
+struct Aligned64<T>(T);
+
+/// The `vpshufb` selector reversing each sixteen byte lane, turning a
+/// big-endian counter block into a little-endian integer and back.
+static BSWAP: Aligned64<[u8; 32]> = Aligned64([
+    15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0, 15, 14, 13,
+    12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0,
+]);
+
+/// Quadword pairs adding block offsets {4i, .., 4i+3} to the four lanes
+/// of each counter register, two lanes at a time. Only the low quadword
+/// of each lane moves: the caller has checked that it cannot carry
+/// across the whole call.
+static OFFSETS: Aligned64<[u64; 64]> = Aligned64([
+    0, 0, 1, 0, 2, 0, 3, 0, 4, 0, 5, 0, 6, 0, 7, 0, 8, 0, 9, 0, 10, 0,
+    11, 0, 12, 0, 13, 0, 14, 0, 15, 0, 16, 0, 17, 0, 18, 0, 19, 0, 20, 0,
+    21, 0, 22, 0, 23, 0, 24, 0, 25, 0, 26, 0, 27, 0, 28, 0, 29, 0, 30, 0,
+    31, 0,
+]);
+
+/// The per-iteration advance: thirty-two blocks in both lanes.
+static STEP: Aligned64<[u64; 4]> = Aligned64([32, 0, 32, 0]);
+
+/// A fully unrolled thirty-two block counter kernel, looping over the
+/// buffer.
+///
+/// The counters are built as the 256 bit kernel builds them, a pair of
+/// lanes at a time on ymm, and each register's upper pair is inserted
+/// above its lower one. That keeps the byte swap to AVX2's `vpshufb`, so
+/// AVX512BW is not needed. The keystream is XORed with the data straight
+/// from memory, so each block is read once and written once.
+macro_rules! ctr_kernel {
+    ($name:ident, [$($key:literal),+], $final:literal) => {
+        /// # Safety
+        ///
+        /// The CPU must have VAES, AVX2 and AVX-512F. `rk` must hold
+        /// the schedule, `data` at least `groups * 32` whole blocks,
+        /// and `ctr` one big-endian counter block whose low 64 bits are
+        /// at most `u64::MAX - (groups * 32 - 1)`.
+        #[inline]
+        unsafe fn $name(
+            rk: *const u8,
+            data: *mut u8,
+            groups: usize,
+            ctr: *const u8,
+        ) {
+            // SAFETY: the caller guarantees the instructions and all
+            // ranges. EVEX and VEX encoded loads have no alignment
+            // requirement; the tables are aligned anyway so vmovdqa can
+            // take them.
+            unsafe {
+                asm!(
+                    "vbroadcasti128 ymm10, [{c}]",
+                    "vmovdqa ymm9, [{bsw}]",
+                    "vpshufb ymm10, ymm10, ymm9",
+                    "vmovdqa ymm11, [{step}]",
+                    "2:",
+                    // The low pair of each register's blocks, byte swapped
+                    // on ymm: vpshufb on zmm would need AVX512BW.
+                    "vpaddq ymm0, ymm10, [{off} + 0x00]",
+                    "vpaddq ymm1, ymm10, [{off} + 0x40]",
+                    "vpaddq ymm2, ymm10, [{off} + 0x80]",
+                    "vpaddq ymm3, ymm10, [{off} + 0xc0]",
+                    "vpaddq ymm4, ymm10, [{off} + 0x100]",
+                    "vpaddq ymm5, ymm10, [{off} + 0x140]",
+                    "vpaddq ymm6, ymm10, [{off} + 0x180]",
+                    "vpaddq ymm7, ymm10, [{off} + 0x1c0]",
+                    "vpshufb ymm0, ymm0, ymm9",
+                    "vpshufb ymm1, ymm1, ymm9",
+                    "vpshufb ymm2, ymm2, ymm9",
+                    "vpshufb ymm3, ymm3, ymm9",
+                    "vpshufb ymm4, ymm4, ymm9",
+                    "vpshufb ymm5, ymm5, ymm9",
+                    "vpshufb ymm6, ymm6, ymm9",
+                    "vpshufb ymm7, ymm7, ymm9",
+                    // The high pair, inserted above it.
+                    "vpaddq ymm12, ymm10, [{off} + 0x20]",
+                    "vpshufb ymm12, ymm12, ymm9",
+                    "vinserti64x4 zmm0, zmm0, ymm12, 1",
+                    "vpaddq ymm13, ymm10, [{off} + 0x60]",
+                    "vpshufb ymm13, ymm13, ymm9",
+                    "vinserti64x4 zmm1, zmm1, ymm13, 1",
+                    "vpaddq ymm12, ymm10, [{off} + 0xa0]",
+                    "vpshufb ymm12, ymm12, ymm9",
+                    "vinserti64x4 zmm2, zmm2, ymm12, 1",
+                    "vpaddq ymm13, ymm10, [{off} + 0xe0]",
+                    "vpshufb ymm13, ymm13, ymm9",
+                    "vinserti64x4 zmm3, zmm3, ymm13, 1",
+                    "vpaddq ymm12, ymm10, [{off} + 0x120]",
+                    "vpshufb ymm12, ymm12, ymm9",
+                    "vinserti64x4 zmm4, zmm4, ymm12, 1",
+                    "vpaddq ymm13, ymm10, [{off} + 0x160]",
+                    "vpshufb ymm13, ymm13, ymm9",
+                    "vinserti64x4 zmm5, zmm5, ymm13, 1",
+                    "vpaddq ymm12, ymm10, [{off} + 0x1a0]",
+                    "vpshufb ymm12, ymm12, ymm9",
+                    "vinserti64x4 zmm6, zmm6, ymm12, 1",
+                    "vpaddq ymm13, ymm10, [{off} + 0x1e0]",
+                    "vpshufb ymm13, ymm13, ymm9",
+                    "vinserti64x4 zmm7, zmm7, ymm13, 1",
+                    "vbroadcasti32x4 zmm8, [{rk}]",
+                    "vpxorq zmm0, zmm0, zmm8",
+                    "vpxorq zmm1, zmm1, zmm8",
+                    "vpxorq zmm2, zmm2, zmm8",
+                    "vpxorq zmm3, zmm3, zmm8",
+                    "vpxorq zmm4, zmm4, zmm8",
+                    "vpxorq zmm5, zmm5, zmm8",
+                    "vpxorq zmm6, zmm6, zmm8",
+                    "vpxorq zmm7, zmm7, zmm8",
+                    $(
+                        concat!(
+                            "vbroadcasti32x4 zmm8, [{rk} + ", $key, "]"
+                        ),
+                        "vaesenc zmm0, zmm0, zmm8",
+                        "vaesenc zmm1, zmm1, zmm8",
+                        "vaesenc zmm2, zmm2, zmm8",
+                        "vaesenc zmm3, zmm3, zmm8",
+                        "vaesenc zmm4, zmm4, zmm8",
+                        "vaesenc zmm5, zmm5, zmm8",
+                        "vaesenc zmm6, zmm6, zmm8",
+                        "vaesenc zmm7, zmm7, zmm8",
+                    )+
+                    concat!("vbroadcasti32x4 zmm8, [{rk} + ", $final, "]"),
+                    "vaesenclast zmm0, zmm0, zmm8",
+                    "vaesenclast zmm1, zmm1, zmm8",
+                    "vaesenclast zmm2, zmm2, zmm8",
+                    "vaesenclast zmm3, zmm3, zmm8",
+                    "vaesenclast zmm4, zmm4, zmm8",
+                    "vaesenclast zmm5, zmm5, zmm8",
+                    "vaesenclast zmm6, zmm6, zmm8",
+                    "vaesenclast zmm7, zmm7, zmm8",
+                    "vpxorq zmm0, zmm0, [{d} + 0]",
+                    "vpxorq zmm1, zmm1, [{d} + 64]",
+                    "vpxorq zmm2, zmm2, [{d} + 128]",
+                    "vpxorq zmm3, zmm3, [{d} + 192]",
+                    "vpxorq zmm4, zmm4, [{d} + 256]",
+                    "vpxorq zmm5, zmm5, [{d} + 320]",
+                    "vpxorq zmm6, zmm6, [{d} + 384]",
+                    "vpxorq zmm7, zmm7, [{d} + 448]",
+                    "vmovdqu64 [{d} + 0], zmm0",
+                    "vmovdqu64 [{d} + 64], zmm1",
+                    "vmovdqu64 [{d} + 128], zmm2",
+                    "vmovdqu64 [{d} + 192], zmm3",
+                    "vmovdqu64 [{d} + 256], zmm4",
+                    "vmovdqu64 [{d} + 320], zmm5",
+                    "vmovdqu64 [{d} + 384], zmm6",
+                    "vmovdqu64 [{d} + 448], zmm7",
+                    "vpaddq ymm10, ymm10, ymm11",
+                    "add {d}, 512",
+                    "dec {g}",
+                    "jnz 2b",
+                    // Zero the upper halves, as the 256 bit kernels do, so
+                    // the SSE code this returns to pays no transition
+                    // penalty.
+                    "vzeroupper",
+                    rk = in(reg) rk,
+                    d = inout(reg) data => _,
+                    g = inout(reg) groups => _,
+                    c = in(reg) ctr,
+                    bsw = in(reg) &BSWAP,
+                    off = in(reg) &OFFSETS,
+                    step = in(reg) &STEP,
+                    out("zmm0") _, out("zmm1") _, out("zmm2") _,
+                    out("zmm3") _, out("zmm4") _, out("zmm5") _,
+                    out("zmm6") _, out("zmm7") _, out("zmm8") _,
+                    out("ymm9") _, out("ymm10") _, out("ymm11") _,
+                    out("ymm12") _, out("ymm13") _,
+                    options(nostack),
+                );
+            }
+        }
+    };
+}
+
+ctr_kernel!(
+    ctr_e128,
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80",
+        "0x90"
+    ],
+    "0xa0"
+);
+ctr_kernel!(
+    ctr_e192,
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80",
+        "0x90", "0xa0", "0xb0"
+    ],
+    "0xc0"
+);
+ctr_kernel!(
+    ctr_e256,
+    [
+        "0x10", "0x20", "0x30", "0x40", "0x50", "0x60", "0x70", "0x80",
+        "0x90", "0xa0", "0xb0", "0xc0", "0xd0"
+    ],
+    "0xe0"
+);
+
+/// Clear every vector register the kernels write, when the `scrub`
+/// feature asks for it.
+///
+/// `vzeroupper` leaves the low halves in place. `vzeroall` clears zmm0
+/// to zmm15 whole, which are all the kernels touch, but nothing clears
+/// zmm16 to zmm31 short of AVX-512 itself, and a build that enables it
+/// lets the compiler spill key material there around the kernels. Those
+/// are zeroed one by one.
+#[inline(always)]
+fn scrub_registers() {
+    #[cfg(feature = "scrub")]
+    // SAFETY: vzeroall is AVX and vpxord AVX-512F, both of which
+    // supported() requires before any of these types exist, and every
+    // register written is declared.
+    unsafe {
+        asm!(
+            "vzeroall",
+            "vpxord zmm16, zmm16, zmm16",
+            "vpxord zmm17, zmm17, zmm17",
+            "vpxord zmm18, zmm18, zmm18",
+            "vpxord zmm19, zmm19, zmm19",
+            "vpxord zmm20, zmm20, zmm20",
+            "vpxord zmm21, zmm21, zmm21",
+            "vpxord zmm22, zmm22, zmm22",
+            "vpxord zmm23, zmm23, zmm23",
+            "vpxord zmm24, zmm24, zmm24",
+            "vpxord zmm25, zmm25, zmm25",
+            "vpxord zmm26, zmm26, zmm26",
+            "vpxord zmm27, zmm27, zmm27",
+            "vpxord zmm28, zmm28, zmm28",
+            "vpxord zmm29, zmm29, zmm29",
+            "vpxord zmm30, zmm30, zmm30",
+            "vpxord zmm31, zmm31, zmm31",
+            out("zmm0") _, out("zmm1") _, out("zmm2") _,
+            out("zmm3") _, out("zmm4") _, out("zmm5") _,
+            out("zmm6") _, out("zmm7") _, out("zmm8") _,
+            out("zmm9") _, out("zmm10") _, out("zmm11") _,
+            out("zmm12") _, out("zmm13") _, out("zmm14") _,
+            out("zmm15") _, out("zmm16") _, out("zmm17") _,
+            out("zmm18") _, out("zmm19") _, out("zmm20") _,
+            out("zmm21") _, out("zmm22") _, out("zmm23") _,
+            out("zmm24") _, out("zmm25") _, out("zmm26") _,
+            out("zmm27") _, out("zmm28") _, out("zmm29") _,
+            out("zmm30") _, out("zmm31") _,
+            options(nomem, nostack, preserves_flags),
+        );
+    }
+}
+
+macro_rules! define_aes {
+    (
+        $enc:ident, $dec:ident, $inner_enc:path, $inner_dec:path,
+        $key_size:expr, $bytes:expr, $enc_kernel:ident, $dec_kernel:ident,
+        $ctr_kernel:ident, $bits:expr
+    ) => {
+        #[doc = concat!("AES-", $bits, " encryption only, on 512 bit VAES.")]
+        pub struct $enc {
+            inner: $inner_enc,
+        }
+
+        #[doc = concat!("AES-", $bits, " decryption only, on 512 bit VAES.")]
+        pub struct $dec {
+            inner: $inner_dec,
+        }
+
+        impl $enc {
+            /// The key length in bytes.
+            pub const KEY_SIZE: usize = $key_size;
+            /// The block length in bytes.
+            pub const BLOCK_SIZE: usize = BLOCK_SIZE;
+            /// Blocks kept in flight.
+            pub const PARALLEL_BLOCKS: usize = WIDTH;
+
+            /// Expand `key` into an encryption schedule.
+            ///
+            /// # Panics
+            ///
+            /// If the CPU cannot run these kernels. Naming this type
+            /// asserts that it can; the parent module's type of the same
+            /// name checks and falls back instead, and [`Self::try_new`]
+            /// reports it.
+            pub fn new(key: &[u8; $key_size]) -> Self {
+                match Self::try_new(key) {
+                    Ok(this) => this,
+                    Err(e) => panic!("{e}"),
+                }
+            }
+
+            /// Expand `key` into an encryption schedule, or say which CPU
+            /// features that would need.
+            pub fn try_new(
+                key: &[u8; $key_size],
+            ) -> Result<Self, UnsupportedCpu> {
+                if !supported() {
+                    return Err(unsupported());
+                }
+                Ok(Self { inner: <$inner_enc>::new(key) })
+            }
+
+            /// Encrypt whole blocks in place, returning bytes consumed.
+            pub fn encrypt(&self, data: &mut [u8]) -> usize {
+                let blocks = data.len() / BLOCK_SIZE;
+                let groups = blocks / WIDTH;
+                if groups > 0 {
+                    // SAFETY: support was checked when the key was
+                    // expanded, and the buffer holds this many groups.
+                    unsafe {
+                        $enc_kernel(
+                            self.inner.schedule().as_ptr(),
+                            data.as_mut_ptr(),
+                            groups,
+                        );
+                    }
+                    scrub_registers();
+                }
+                // Anything left is shorter than a group; the 256 bit
+                // kernels take it.
+                self.inner.encrypt(&mut data[groups * WIDTH * BLOCK_SIZE..]);
+                blocks * BLOCK_SIZE
+            }
+
+            /// Encrypt exactly one block in place.
+            pub fn encrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+                self.inner.encrypt_block(block);
+            }
+
+            /// Encrypt successive counter values and XOR them into
+            /// `data` in place, advancing `counter`.
+            ///
+            /// The counter is one block, big endian, wrapping at the
+            /// full block width, as SP 800-38A specifies. Whole blocks
+            /// only, like [`Self::encrypt`]; returns bytes consumed.
+            pub fn ctr(
+                &self,
+                counter: &mut [u8; BLOCK_SIZE],
+                data: &mut [u8],
+            ) -> usize {
+                let blocks = data.len() / BLOCK_SIZE;
+                let groups = blocks / WIDTH;
+                let c = u128::from_be_bytes(*counter);
+                let span = (groups * WIDTH) as u64;
+                // As in the 256 bit driver: when the low quadword would
+                // carry somewhere in this span, the narrower backends
+                // take the whole call, and handle the carry themselves.
+                if groups > 0 && (c as u64) <= u64::MAX - (span - 1) {
+                    // SAFETY: support was checked when the key was
+                    // expanded, the buffer holds this many groups, and
+                    // the no-carry precondition was just checked.
+                    unsafe {
+                        $ctr_kernel(
+                            self.inner.schedule().as_ptr(),
+                            data.as_mut_ptr(),
+                            groups,
+                            counter.as_ptr(),
+                        );
+                    }
+                    *counter = c.wrapping_add(span as u128).to_be_bytes();
+                    self.inner.ctr(
+                        counter,
+                        &mut data[groups * WIDTH * BLOCK_SIZE..],
+                    );
+                    // Last, so the counter arithmetic above cannot leave
+                    // anything in a register after it.
+                    scrub_registers();
+                } else {
+                    self.inner.ctr(counter, data);
+                }
+                blocks * BLOCK_SIZE
+            }
+        }
+
+        impl $dec {
+            /// The key length in bytes.
+            pub const KEY_SIZE: usize = $key_size;
+            /// The block length in bytes.
+            pub const BLOCK_SIZE: usize = BLOCK_SIZE;
+            /// Blocks kept in flight.
+            pub const PARALLEL_BLOCKS: usize = WIDTH;
+
+            /// Expand `key` into a decryption schedule.
+            ///
+            /// # Panics
+            ///
+            /// If the CPU cannot run these kernels. [`Self::try_new`]
+            /// reports that instead.
+            pub fn new(key: &[u8; $key_size]) -> Self {
+                match Self::try_new(key) {
+                    Ok(this) => this,
+                    Err(e) => panic!("{e}"),
+                }
+            }
+
+            /// Expand `key` into a decryption schedule, or say which CPU
+            /// features that would need.
+            pub fn try_new(
+                key: &[u8; $key_size],
+            ) -> Result<Self, UnsupportedCpu> {
+                if !supported() {
+                    return Err(unsupported());
+                }
+                Ok(Self { inner: <$inner_dec>::new(key) })
+            }
+
+            /// Decrypt whole blocks in place, returning bytes consumed.
+            pub fn decrypt(&self, data: &mut [u8]) -> usize {
+                let blocks = data.len() / BLOCK_SIZE;
+                let groups = blocks / WIDTH;
+                if groups > 0 {
+                    // SAFETY: as for the encryption side.
+                    unsafe {
+                        $dec_kernel(
+                            self.inner.schedule().as_ptr(),
+                            data.as_mut_ptr(),
+                            groups,
+                        );
+                    }
+                    scrub_registers();
+                }
+                self.inner.decrypt(&mut data[groups * WIDTH * BLOCK_SIZE..]);
+                blocks * BLOCK_SIZE
+            }
+
+            /// Decrypt exactly one block in place.
+            pub fn decrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+                self.inner.decrypt_block(block);
+            }
+        }
+
+        /// Invert an existing encryption schedule, so the key itself
+        /// need not be kept around in case decryption is wanted later.
+        impl From<&$enc> for $dec {
+            fn from(enc: &$enc) -> Self {
+                Self { inner: <$inner_dec>::from(&enc.inner) }
+            }
+        }
+
+        #[cfg(feature = "hazmat")]
+        impl $enc {
+            /// Copy out the round keys, in the [canonical layout].
+            ///
+            /// [canonical layout]: crate::symmetric::aes#round-keys
+            pub fn round_keys(&self) -> Zeroizing<[u8; $bytes]> {
+                self.inner.round_keys()
+            }
+
+            /// Take round keys in the canonical layout as they are.
+            ///
+            /// Nothing checks that they came from a key expansion.
+            ///
+            /// # Panics
+            ///
+            /// As [`Self::new`]; [`Self::try_from_round_keys`] reports it
+            /// instead.
+            pub fn from_round_keys(round_keys: &[u8; $bytes]) -> Self {
+                match Self::try_from_round_keys(round_keys) {
+                    Ok(this) => this,
+                    Err(e) => panic!("{e}"),
+                }
+            }
+
+            /// Take round keys in the canonical layout as they are, or
+            /// say which CPU features that would need.
+            pub fn try_from_round_keys(
+                round_keys: &[u8; $bytes],
+            ) -> Result<Self, UnsupportedCpu> {
+                if !supported() {
+                    return Err(unsupported());
+                }
+                Ok(Self { inner: <$inner_enc>::from_round_keys(round_keys) })
+            }
+        }
+
+        #[cfg(feature = "hazmat")]
+        impl $dec {
+            /// Copy out the encryption round keys this schedule was
+            /// inverted from, in the canonical layout.
+            pub fn round_keys(&self) -> Zeroizing<[u8; $bytes]> {
+                self.inner.round_keys()
+            }
+
+            /// Invert encryption round keys in the canonical layout.
+            ///
+            /// Nothing checks that they came from a key expansion.
+            ///
+            /// # Panics
+            ///
+            /// As [`Self::new`]; [`Self::try_from_round_keys`] reports it
+            /// instead.
+            pub fn from_round_keys(round_keys: &[u8; $bytes]) -> Self {
+                match Self::try_from_round_keys(round_keys) {
+                    Ok(this) => this,
+                    Err(e) => panic!("{e}"),
+                }
+            }
+
+            /// Invert encryption round keys in the canonical layout, or
+            /// say which CPU features that would need.
+            pub fn try_from_round_keys(
+                round_keys: &[u8; $bytes],
+            ) -> Result<Self, UnsupportedCpu> {
+                if !supported() {
+                    return Err(unsupported());
+                }
+                Ok(Self { inner: <$inner_dec>::from_round_keys(round_keys) })
+            }
+        }
+
+        impl KeyInit for $enc {
+            type Error = KeyInitError;
+
+            fn try_new(key: &[u8]) -> Result<Self, KeyInitError> {
+                let key: &[u8; $key_size] = key
+                    .try_into()
+                    .map_err(|_| InvalidKeyLength { got: key.len() })?;
+                Ok(Self::try_new(key)?)
+            }
+        }
+
+        impl KeyInit for $dec {
+            type Error = KeyInitError;
+
+            fn try_new(key: &[u8]) -> Result<Self, KeyInitError> {
+                let key: &[u8; $key_size] = key
+                    .try_into()
+                    .map_err(|_| InvalidKeyLength { got: key.len() })?;
+                Ok(Self::try_new(key)?)
+            }
+        }
+
+        impl BlockEncrypt for $enc {
+            const BLOCK_SIZE: usize = BLOCK_SIZE;
+            const PARALLEL_BLOCKS: usize = WIDTH;
+
+            fn encrypt(&self, data: &mut [u8]) -> usize {
+                $enc::encrypt(self, data)
+            }
+        }
+
+        impl BlockDecrypt for $dec {
+            const BLOCK_SIZE: usize = BLOCK_SIZE;
+            const PARALLEL_BLOCKS: usize = WIDTH;
+
+            fn decrypt(&self, data: &mut [u8]) -> usize {
+                $dec::decrypt(self, data)
+            }
+        }
+
+        impl core::fmt::Debug for $enc {
+            fn fmt(
+                &self,
+                f: &mut core::fmt::Formatter<'_>,
+            ) -> core::fmt::Result {
+                // Never format round keys.
+                f.write_str(concat!(stringify!($enc), " { .. }"))
+            }
+        }
+
+        impl core::fmt::Debug for $dec {
+            fn fmt(
+                &self,
+                f: &mut core::fmt::Formatter<'_>,
+            ) -> core::fmt::Result {
+                f.write_str(concat!(stringify!($dec), " { .. }"))
+            }
+        }
+    };
+}
+
+define_aes!(
+    Aes128Enc, Aes128Dec, vaes::Aes128Enc, vaes::Aes128Dec, 16, 176,
+    e128, d128, ctr_e128, "128"
+);
+define_aes!(
+    Aes192Enc, Aes192Dec, vaes::Aes192Enc, vaes::Aes192Dec, 24, 208,
+    e192, d192, ctr_e192, "192"
+);
+define_aes!(
+    Aes256Enc, Aes256Dec, vaes::Aes256Enc, vaes::Aes256Dec, 32, 240,
+    e256, d256, ctr_e256, "256"
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symmetric::aes::arch::portable::ttable;
+    use crate::symmetric::aes::testing::Rng;
+
+    /// Whether these kernels cannot run here, announcing the skip so a
+    /// run that covered nothing says so.
+    fn unavailable() -> bool {
+        if supported() {
+            return false;
+        }
+        eprintln!(
+            "skipping: {}; `cargo test-sde` runs these under Intel SDE",
+            unsupported()
+        );
+        true
+    }
+
+    /// Construction on the wrong CPU is an error naming what is missing,
+    /// not a panic, through both the inherent and the trait constructor.
+    #[test]
+    fn try_new_refuses_rather_than_panics() {
+        match Aes128Enc::try_new(&[0u8; 16]) {
+            Ok(_) => assert!(supported()),
+            Err(e) => assert!(!supported() && e.missing().count() > 0),
+        }
+        assert_eq!(Aes256Dec::try_new(&[0u8; 32]).is_ok(), supported());
+
+        // The length is checked first, so it is reported on any CPU.
+        assert!(matches!(
+            <Aes192Enc as KeyInit>::try_new(&[0u8; 16]),
+            Err(KeyInitError::Key(InvalidKeyLength { got: 16 }))
+        ));
+        match <Aes192Dec as KeyInit>::try_new(&[0u8; 24]) {
+            Ok(_) => assert!(supported()),
+            Err(e) => assert!(matches!(e, KeyInitError::Cpu(_))),
+        }
+    }
+
+    macro_rules! check {
+        ($enc:ident, $dec:ident, $pe:path, $len:expr, $seed:expr) => {{
+            let mut rng = Rng($seed);
+            // Lengths either side of a thirty-two block group and of the
+            // sixteen block one under it, so every kernel, the AES-NI
+            // tail and the boundaries between them are all hit.
+            for blocks in
+                [0usize, 1, 15, 16, 17, 31, 32, 33, 47, 48, 49, 64, 100, 129]
+            {
+                let mut key = [0u8; $len];
+                rng.fill(&mut key);
+                let mut plaintext = vec![0u8; blocks * BLOCK_SIZE];
+                rng.fill(&mut plaintext);
+
+                let mut ours = plaintext.clone();
+                let mut theirs = plaintext.clone();
+                assert_eq!(
+                    $enc::new(&key).encrypt(&mut ours),
+                    blocks * BLOCK_SIZE
+                );
+                <$pe>::new(&key).encrypt(&mut theirs);
+                assert_eq!(
+                    ours, theirs,
+                    "{} bit encrypt differs at {} blocks",
+                    $len * 8, blocks
+                );
+
+                $dec::new(&key).decrypt(&mut ours);
+                assert_eq!(
+                    ours, plaintext,
+                    "{} bit decrypt failed at {} blocks",
+                    $len * 8, blocks
+                );
+            }
+        }};
+    }
+
+    #[test]
+    fn agrees_with_the_portable_implementation() {
+        if unavailable() {
+            return;
+        }
+        check!(Aes128Enc, Aes128Dec, ttable::Aes128Enc, 16, 0x1212_2323);
+        check!(Aes192Enc, Aes192Dec, ttable::Aes192Enc, 24, 0x3434_4545);
+        check!(Aes256Enc, Aes256Dec, ttable::Aes256Enc, 32, 0x5656_6767);
+    }
+
+    /// The fused counter kernel against the portable scalar `ctr`, at
+    /// the same lengths as the block kernels.
+    macro_rules! check_ctr {
+        ($enc:ident, $pe:path, $len:expr, $seed:expr) => {{
+            let mut rng = Rng($seed);
+            for blocks in
+                [0usize, 1, 15, 16, 17, 31, 32, 33, 47, 48, 49, 64, 100, 129]
+            {
+                let mut key = [0u8; $len];
+                rng.fill(&mut key);
+                let mut iv = [0u8; BLOCK_SIZE];
+                rng.fill(&mut iv);
+                let mut data = vec![0u8; blocks * BLOCK_SIZE];
+                rng.fill(&mut data);
+
+                let mut ours = data.clone();
+                let mut ours_ctr = iv;
+                assert_eq!(
+                    $enc::new(&key).ctr(&mut ours_ctr, &mut ours),
+                    blocks * BLOCK_SIZE
+                );
+
+                let mut theirs_ctr = iv;
+                <$pe>::new(&key).ctr(&mut theirs_ctr, &mut data);
+
+                assert_eq!(
+                    ours, data,
+                    "{} bit ctr differs at {} blocks",
+                    $len * 8, blocks
+                );
+                assert_eq!(
+                    ours_ctr, theirs_ctr,
+                    "{} bit counter write-back differs at {} blocks",
+                    $len * 8, blocks
+                );
+            }
+        }};
+    }
+
+    #[test]
+    fn ctr_agrees_with_the_portable_implementation() {
+        if unavailable() {
+            return;
+        }
+        check_ctr!(Aes128Enc, ttable::Aes128Enc, 16, 0x7878_8989);
+        check_ctr!(Aes192Enc, ttable::Aes192Enc, 24, 0x9a9a_abab);
+        check_ctr!(Aes256Enc, ttable::Aes256Enc, 32, 0xbcbc_cdcd);
+    }
+
+    /// IVs about to carry out of the low quadword must push the whole
+    /// call onto the narrower backends and still agree, and one just
+    /// clear of it must not.
+    #[test]
+    fn ctr_carries_across_the_low_quadword() {
+        if unavailable() {
+            return;
+        }
+        let key = [0x2bu8; 16];
+        let ours_aes = Aes128Enc::new(&key);
+        let theirs_aes = ttable::Aes128Enc::new(&key);
+
+        for k in [0u64, 1, 15, 16, 31, 32, 33, 63, 64, 65] {
+            let start = ((0xfedc_ba98_7654_3210u128) << 64)
+                | (u64::MAX - k) as u128;
+            let iv = start.to_be_bytes();
+            let mut data = [0xa5u8; BLOCK_SIZE * 72];
+
+            let mut ours = data;
+            let mut ours_ctr = iv;
+            ours_aes.ctr(&mut ours_ctr, &mut ours);
+
+            let mut theirs_ctr = iv;
+            theirs_aes.ctr(&mut theirs_ctr, &mut data);
+
+            assert_eq!(ours, data, "carry case k = {k}");
+            assert_eq!(ours_ctr, theirs_ctr, "counter, k = {k}");
+        }
+    }
+
+    /// FIPS-197 Appendix C.3, in every lane of both kernels.
+    #[test]
+    fn fips_197_aes256_in_every_lane() {
+        if unavailable() {
+            return;
+        }
+        let key: [u8; 32] = core::array::from_fn(|i| i as u8);
+        let plaintext = [
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99,
+            0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff,
+        ];
+        let expected = [
+            0x8e, 0xa2, 0xb7, 0xca, 0x51, 0x67, 0x45, 0xbf, 0xea, 0xfc,
+            0x49, 0x90, 0x4b, 0x49, 0x60, 0x89,
+        ];
+        let mut data = plaintext.repeat(WIDTH);
+        Aes256Enc::new(&key).encrypt(&mut data);
+        for (i, block) in data.chunks(BLOCK_SIZE).enumerate() {
+            assert_eq!(block, expected, "lane {i}");
+        }
+        Aes256Dec::new(&key).decrypt(&mut data);
+        assert_eq!(data, plaintext.repeat(WIDTH));
+    }
+
+    /// The inner schedule is inverted by AES-NI, so this only has to show
+    /// the wrapper hands over the right one, through the wide kernel too.
+    #[test]
+    fn inverting_matches_expanding() {
+        if unavailable() {
+            return;
+        }
+        let mut rng = Rng(0x1f2e_3d4c_5b6a_7988);
+        macro_rules! check_inverted {
+            ($enc:ident, $dec:ident, $len:expr) => {
+                let mut key = [0u8; $len];
+                rng.fill(&mut key);
+                let mut ciphertext = [0u8; (WIDTH + 3) * BLOCK_SIZE];
+                rng.fill(&mut ciphertext);
+                let mut inverted = ciphertext;
+                $dec::from(&$enc::new(&key)).decrypt(&mut inverted);
+                let mut expanded = ciphertext;
+                $dec::new(&key).decrypt(&mut expanded);
+                assert_eq!(inverted, expanded, "{} bit", $len * 8);
+            };
+        }
+        check_inverted!(Aes128Enc, Aes128Dec, 16);
+        check_inverted!(Aes192Enc, Aes192Dec, 24);
+        check_inverted!(Aes256Enc, Aes256Dec, 32);
+    }
+
+    /// The schedule is AES-NI's, so this only has to show the wrapper
+    /// passes it through, and that a loaded one drives the wide kernel.
+    #[cfg(feature = "hazmat")]
+    #[test]
+    fn round_keys_pass_through() {
+        if unavailable() {
+            return;
+        }
+        let mut rng = Rng(0x8f7e_6d5c_4b3a_2918);
+        let mut key = [0u8; 24];
+        rng.fill(&mut key);
+        let rk = vaes::Aes192Enc::new(&key).round_keys();
+        assert_eq!(*Aes192Enc::new(&key).round_keys(), *rk);
+        assert_eq!(*Aes192Dec::new(&key).round_keys(), *rk);
+
+        let plaintext = [0x3cu8; (WIDTH + 3) * BLOCK_SIZE];
+        let mut loaded = plaintext;
+        Aes192Enc::from_round_keys(&rk).encrypt(&mut loaded);
+        let mut expanded = plaintext;
+        Aes192Enc::new(&key).encrypt(&mut expanded);
+        assert_eq!(loaded, expanded);
+        Aes192Dec::from_round_keys(&rk).decrypt(&mut loaded);
+        assert_eq!(loaded, plaintext);
+    }
+
+    #[test]
+    fn partial_trailing_block_is_left_alone() {
+        if unavailable() {
+            return;
+        }
+        let aes = Aes128Enc::new(&[0u8; 16]);
+        let mut data = [0xccu8; WIDTH * BLOCK_SIZE + BLOCK_SIZE + 5];
+        assert_eq!(
+            aes.encrypt(&mut data),
+            (WIDTH + 1) * BLOCK_SIZE
+        );
+        assert_eq!(&data[(WIDTH + 1) * BLOCK_SIZE..], &[0xcc; 5]);
+    }
+
+    /// Run `f`, then read all 32 zmm registers whole, as the 256 bit tests
+    /// do the ymm registers.
+    ///
+    /// zmm16 to zmm31 are set to all ones first. Code built without
+    /// AVX-512 never touches them, so only a scrub clears them.
+    fn registers_after(mut f: impl FnMut()) -> [[u8; 64]; 32] {
+        extern "C" fn trampoline(f: &mut &mut dyn FnMut()) {
+            f();
+        }
+        let mut f: &mut dyn FnMut() = &mut f;
+        let mut regs = [[0u8; 64]; 32];
+        // SAFETY: the stack is call-aligned on entry to an asm block
+        // without nostack, trampoline has the C ABI that clobber_abi
+        // describes, the upper sixteen registers are declared besides,
+        // regs has room for every store, and the caller has checked for
+        // AVX-512F.
+        unsafe {
+            asm!(
+                "vpternlogd zmm16, zmm16, zmm16, 0xff",
+                "vpternlogd zmm17, zmm17, zmm17, 0xff",
+                "vpternlogd zmm18, zmm18, zmm18, 0xff",
+                "vpternlogd zmm19, zmm19, zmm19, 0xff",
+                "vpternlogd zmm20, zmm20, zmm20, 0xff",
+                "vpternlogd zmm21, zmm21, zmm21, 0xff",
+                "vpternlogd zmm22, zmm22, zmm22, 0xff",
+                "vpternlogd zmm23, zmm23, zmm23, 0xff",
+                "vpternlogd zmm24, zmm24, zmm24, 0xff",
+                "vpternlogd zmm25, zmm25, zmm25, 0xff",
+                "vpternlogd zmm26, zmm26, zmm26, 0xff",
+                "vpternlogd zmm27, zmm27, zmm27, 0xff",
+                "vpternlogd zmm28, zmm28, zmm28, 0xff",
+                "vpternlogd zmm29, zmm29, zmm29, 0xff",
+                "vpternlogd zmm30, zmm30, zmm30, 0xff",
+                "vpternlogd zmm31, zmm31, zmm31, 0xff",
+                "call {t}",
+                "vmovdqu64 [r12 + 0x0], zmm0",
+                "vmovdqu64 [r12 + 0x40], zmm1",
+                "vmovdqu64 [r12 + 0x80], zmm2",
+                "vmovdqu64 [r12 + 0xc0], zmm3",
+                "vmovdqu64 [r12 + 0x100], zmm4",
+                "vmovdqu64 [r12 + 0x140], zmm5",
+                "vmovdqu64 [r12 + 0x180], zmm6",
+                "vmovdqu64 [r12 + 0x1c0], zmm7",
+                "vmovdqu64 [r12 + 0x200], zmm8",
+                "vmovdqu64 [r12 + 0x240], zmm9",
+                "vmovdqu64 [r12 + 0x280], zmm10",
+                "vmovdqu64 [r12 + 0x2c0], zmm11",
+                "vmovdqu64 [r12 + 0x300], zmm12",
+                "vmovdqu64 [r12 + 0x340], zmm13",
+                "vmovdqu64 [r12 + 0x380], zmm14",
+                "vmovdqu64 [r12 + 0x3c0], zmm15",
+                "vmovdqu64 [r12 + 0x400], zmm16",
+                "vmovdqu64 [r12 + 0x440], zmm17",
+                "vmovdqu64 [r12 + 0x480], zmm18",
+                "vmovdqu64 [r12 + 0x4c0], zmm19",
+                "vmovdqu64 [r12 + 0x500], zmm20",
+                "vmovdqu64 [r12 + 0x540], zmm21",
+                "vmovdqu64 [r12 + 0x580], zmm22",
+                "vmovdqu64 [r12 + 0x5c0], zmm23",
+                "vmovdqu64 [r12 + 0x600], zmm24",
+                "vmovdqu64 [r12 + 0x640], zmm25",
+                "vmovdqu64 [r12 + 0x680], zmm26",
+                "vmovdqu64 [r12 + 0x6c0], zmm27",
+                "vmovdqu64 [r12 + 0x700], zmm28",
+                "vmovdqu64 [r12 + 0x740], zmm29",
+                "vmovdqu64 [r12 + 0x780], zmm30",
+                "vmovdqu64 [r12 + 0x7c0], zmm31",
+                t = sym trampoline,
+                in("rdi") &mut f,
+                in("r12") regs.as_mut_ptr(),
+                out("zmm16") _, out("zmm17") _, out("zmm18") _, out("zmm19") _,
+                out("zmm20") _, out("zmm21") _, out("zmm22") _, out("zmm23") _,
+                out("zmm24") _, out("zmm25") _, out("zmm26") _, out("zmm27") _,
+                out("zmm28") _, out("zmm29") _, out("zmm30") _, out("zmm31") _,
+                clobber_abi("C"),
+            );
+        }
+        regs
+    }
+
+    /// With `scrub`, the wide kernels leave the registers empty, upper
+    /// lanes and upper sixteen included. Without it, a round key is still
+    /// found in one.
+    #[test]
+    fn registers_are_scrubbed_after_every_kernel() {
+        if unavailable() {
+            return;
+        }
+        let mut rng = Rng(0x1d2e_3f4a_5b6c_7d8e);
+        let mut key = [0u8; 32];
+        rng.fill(&mut key);
+        let enc = Aes256Enc::new(&key);
+        let dec = Aes256Dec::new(&key);
+        let round_keys = enc.inner.schedule().to_vec();
+        let mut data = [0u8; 2 * WIDTH * BLOCK_SIZE];
+        rng.fill(&mut data);
+
+        let check = |what: &str, regs: [[u8; 64]; 32]| {
+            if cfg!(feature = "scrub") {
+                assert!(
+                    regs.iter().all(|r| *r == [0; 64]),
+                    "{what} left {regs:02x?}"
+                );
+            } else {
+                assert!(
+                    regs.iter().flat_map(|r| r.chunks(16)).any(|r| {
+                        round_keys.chunks(16).any(|k| k == r)
+                    }),
+                    "{what} left no round key to find"
+                );
+            }
+        };
+        // Whole groups only, so the narrower tails never run and their
+        // own scrub cannot stand in for this one.
+        for groups in [1, 2] {
+            let data = &mut data[..groups * WIDTH * BLOCK_SIZE];
+            check("encrypt", registers_after(|| {
+                enc.encrypt(data);
+            }));
+            check("decrypt", registers_after(|| {
+                dec.decrypt(data);
+            }));
+            let mut counter = [0u8; BLOCK_SIZE];
+            check("ctr", registers_after(|| {
+                enc.ctr(&mut counter, data);
+            }));
+        }
+    }
+}
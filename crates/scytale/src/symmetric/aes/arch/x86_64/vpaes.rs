@@ -0,0 +1,1526 @@
+//! AES on SSSE3's byte shuffle, for x86_64 CPUs without AES-NI.
+//!
+//! Some Atoms, and virtual machines that hide the AES instructions from
+//! their guests, still have SSSE3. There the T-table cipher is both slower
+//! and leaky; this is neither. It follows Hamburg's vector permutation
+//! AES: `pshufb` looks up sixteen entry tables, held in registers, by the
+//! nibbles of all sixteen state bytes at once. The index is data, but the
+//! table is a register, so no address depends on the key or the data and
+//! neither does the timing.
+//!
+//! The S-box is an inversion in GF(2^8), taken as a tower of two GF(2^4)
+//! extensions in which every inverse a nibble at a time is one lookup.
+//! The state stays in the tower's basis from the first round key to the
+//! last, and the S-box's linear map, MixColumns' doubling and the change
+//! back of basis are all folded into the tables that leave the inversion.
+//! The S-box constant is folded into the round keys.
+//!
+//! ShiftRows is never computed. As in the fixsliced portable cipher, each
+//! round leaves the state one row rotation further from the true layout;
+//! MixColumns' rotations are conjugated to match, the round keys are
+//! stored rotated, and one shuffle before the last round puts it right.
+//! Decryption runs the equivalent inverse cipher over its own schedule,
+//! with InvMixColumns evaluated Horner fashion, one multiple at a time.
+//!
+//! The key schedule is the FIPS-197 expansion, with SubWord run through
+//! the same vector S-box so that it is constant time too, and the round
+//! keys are then moved into the state's basis and layout.
+//!
+//! The kernels are intrinsics rather than assembly. With no round
+//! instruction to schedule around, the work is a dozen shuffles a round,
+//! and they read better as expressions.
+
+use core::arch::x86_64::*;
+
+use zeroize::Zeroize;
+#[cfg(feature = "hazmat")]
+use zeroize::Zeroizing;
+
+use super::aesni;
+use crate::cpu::{Feature, UnsupportedCpu};
+use crate::symmetric::block_cipher::{
+    BlockDecrypt, BlockEncrypt, InvalidKeyLength, KeyInit, KeyInitError,
+};
+
+/// The AES block size in bytes.
+pub const BLOCK_SIZE: usize = 16;
+
+/// Blocks carried through the rounds together.
+///
+/// A round is a chain of a dozen dependent shuffles, so a single block
+/// leaves the shuffle unit waiting on each one in turn. Four independent
+/// blocks keep it busy; more spill the state, since the tables already
+/// want most of the sixteen registers.
+const WIDTH: usize = 4;
+
+/// Whether this CPU has SSSE3.
+///
+/// The answer cannot change while the process runs, so it is worked out
+/// once and remembered. A build for a target that guarantees SSSE3 does
+/// not ask at all.
+pub fn supported() -> bool {
+    if cfg!(target_feature = "ssse3") {
+        return true;
+    }
+    use std::sync::OnceLock;
+    static SUPPORTED: OnceLock<bool> = OnceLock::new();
+    *SUPPORTED.get_or_init(|| is_x86_feature_detected!("ssse3"))
+}
+
+/// What [`supported`] checks for, naming whichever the CPU lacks.
+fn unsupported() -> UnsupportedCpu {
+    UnsupportedCpu::lacking(&[Feature::Ssse3])
+}
+
+/// A sixteen byte `pshufb` table, aligned so that it can be loaded with
+/// `movdqa` or taken as the shuffle's memory operand.
+///
+/// The tables below that work on field elements use GF(2^4) reduced by
+/// x^4 + x + 1, extended by t^2 + t + 8 to GF(2^8). A byte of state in
+/// the tower basis holds `i` in its high nibble and `i + j` in its low
+/// one, for the element `i t + j (t + 1)`; the pairs of tables take the
+/// two indices [`invert`] returns.
+#[repr(align(16))]
+struct Table([u8; 16]);
+
+/// Into the tower basis, from the AES one: the images of the low and the
+/// high nibble, XORed together by [`change`].
+static BASIS: [Table; 2] = [
+    Table([
+        0x00, 0x10, 0x22, 0x32, 0x24, 0x34, 0x06, 0x16,
+        0x84, 0x94, 0xa6, 0xb6, 0xa0, 0xb0, 0x82, 0x92,
+    ]),
+    Table([
+        0x00, 0xf3, 0x8d, 0x7e, 0x73, 0x80, 0xfe, 0x0d,
+        0xbe, 0x4d, 0x33, 0xc0, 0xcd, 0x3e, 0x40, 0xb3,
+    ]),
+];
+
+/// Back out of the tower basis, for the round keys' canonical form.
+static UNBASIS: [Table; 2] = [
+    Table([
+        0x00, 0xa3, 0x5e, 0xfd, 0x58, 0xfb, 0x06, 0xa5,
+        0x8b, 0x28, 0xd5, 0x76, 0xd3, 0x70, 0x8d, 0x2e,
+    ]),
+    Table([
+        0x00, 0x01, 0x5c, 0x5d, 0xe0, 0xe1, 0xbc, 0xbd,
+        0x50, 0x51, 0x0c, 0x0d, 0xb0, 0xb1, 0xec, 0xed,
+    ]),
+];
+
+/// Into the tower basis for decryption, from the AES one, with the inverse
+/// of the S-box's linear map applied first, so that inverting there gives
+/// the inverse S-box.
+static INV_BASIS: [Table; 2] = [
+    Table([
+        0x00, 0xd5, 0x69, 0xbc, 0x19, 0xcc, 0x70, 0xa5,
+        0xa2, 0x77, 0xcb, 0x1e, 0xbb, 0x6e, 0xd2, 0x07,
+    ]),
+    Table([
+        0x00, 0x17, 0xe7, 0xf0, 0x6f, 0x78, 0x88, 0x9f,
+        0xb9, 0xae, 0x5e, 0x49, 0xd6, 0xc1, 0x31, 0x26,
+    ]),
+];
+
+/// Back out of [`INV_BASIS`], for the round keys' canonical form.
+#[cfg(feature = "hazmat")]
+static INV_UNBASIS: [Table; 2] = [
+    Table([
+        0x00, 0x4d, 0x8c, 0xc1, 0xce, 0x83, 0x42, 0x0f,
+        0x56, 0x1b, 0xda, 0x97, 0x98, 0xd5, 0x14, 0x59,
+    ]),
+    Table([
+        0x00, 0x1f, 0xb2, 0xad, 0xab, 0xb4, 0x19, 0x06,
+        0x36, 0x29, 0x84, 0x9b, 0x9d, 0x82, 0x2f, 0x30,
+    ]),
+];
+
+/// Inverses in GF(2^4), reduced by x^4 + x + 1. The inverse of zero is
+/// written as 0x80, which a later lookup turns back into zero.
+static INVERSE: Table = Table([
+    0x80, 0x01, 0x09, 0x0e, 0x0d, 0x0b, 0x07, 0x06,
+    0x0f, 0x02, 0x0c, 0x05, 0x0a, 0x04, 0x03, 0x08,
+]);
+
+/// `1 / (8 k)` in GF(2^4), the inversion's first step, zero as in
+/// [`INVERSE`].
+static SCALED_INVERSE: Table = Table([
+    0x80, 0x0f, 0x0e, 0x05, 0x07, 0x03, 0x0b, 0x04,
+    0x0a, 0x0d, 0x08, 0x06, 0x0c, 0x09, 0x02, 0x01,
+]);
+
+/// From the two halves of an inverse in the tower basis to the S-box's
+/// linear map of it, still in the tower basis: the S-box, less its
+/// constant.
+static SUB: [Table; 2] = [
+    Table([
+        0x00, 0xbb, 0xc0, 0xce, 0xe8, 0x5d, 0x0e, 0xb5,
+        0x75, 0x9d, 0x53, 0x93, 0xe6, 0x28, 0x26, 0x7b,
+    ]),
+    Table([
+        0x00, 0xda, 0xd9, 0xd1, 0x74, 0xa6, 0x08, 0xd2,
+        0x0b, 0x7f, 0xae, 0x77, 0x7c, 0xad, 0xa5, 0x03,
+    ]),
+];
+
+/// As [`SUB`], doubled in GF(2^8), for MixColumns.
+static SUB2: [Table; 2] = [
+    Table([
+        0x00, 0xb5, 0xbb, 0xab, 0x4f, 0xea, 0x10, 0xa5,
+        0x1e, 0x51, 0xfa, 0x41, 0x5f, 0xf4, 0xe4, 0x0e,
+    ]),
+    Table([
+        0x00, 0x49, 0x19, 0xa9, 0x2e, 0xd7, 0xb0, 0xf9,
+        0xe0, 0xce, 0x67, 0x7e, 0x9e, 0x37, 0x87, 0x50,
+    ]),
+];
+
+/// As [`SUB`], but landing in the AES basis, for the last round and the key
+/// schedule.
+static SUB_LAST: [Table; 2] = [
+    Table([
+        0x00, 0x7b, 0xb0, 0x3d, 0x67, 0x91, 0x8d, 0xf6,
+        0x46, 0x21, 0x1c, 0xac, 0xea, 0xd7, 0x5a, 0xcb,
+    ]),
+    Table([
+        0x00, 0x64, 0x99, 0x12, 0xe5, 0x0a, 0x8b, 0xef,
+        0x76, 0x93, 0x81, 0x18, 0x6e, 0x7c, 0xf7, 0xfd,
+    ]),
+];
+
+/// From the two halves of an inverse to nine times it, in the basis
+/// [`INV_BASIS`] makes, for InvMixColumns.
+static INV_SUB9: [Table; 2] = [
+    Table([
+        0x00, 0x2c, 0xa8, 0xf8, 0xdd, 0xa1, 0x50, 0x7c,
+        0xd4, 0x09, 0xf1, 0x59, 0x8d, 0x75, 0x25, 0x84,
+    ]),
+    Table([
+        0x00, 0x5b, 0x9e, 0x40, 0x60, 0xe5, 0xde, 0x85,
+        0x1b, 0x7b, 0x3b, 0xa5, 0xbe, 0xfe, 0x20, 0xc5,
+    ]),
+];
+
+/// As [`INV_SUB9`], thirteen times.
+static INV_SUB13: [Table; 2] = [
+    Table([
+        0x00, 0xc8, 0xc6, 0xee, 0x94, 0x74, 0x28, 0xe0,
+        0x26, 0xb2, 0x5c, 0x9a, 0xbc, 0x52, 0x7a, 0x0e,
+    ]),
+    Table([
+        0x00, 0xa6, 0x8f, 0x96, 0x66, 0xd9, 0x19, 0xbf,
+        0x30, 0x56, 0xc0, 0x4f, 0x7f, 0xe9, 0xf0, 0x29,
+    ]),
+];
+
+/// As [`INV_SUB9`], eleven times.
+static INV_SUB11: [Table; 2] = [
+    Table([
+        0x00, 0x64, 0x0f, 0x1a, 0x07, 0x76, 0x15, 0x71,
+        0x7e, 0x79, 0x63, 0x6c, 0x12, 0x08, 0x1d, 0x6b,
+    ]),
+    Table([
+        0x00, 0x7a, 0x0e, 0xc8, 0x52, 0xee, 0xc6, 0xbc,
+        0xb2, 0xe0, 0x28, 0x26, 0x94, 0x5c, 0x9a, 0x74,
+    ]),
+];
+
+/// As [`INV_SUB9`], fourteen times.
+static INV_SUB14: [Table; 2] = [
+    Table([
+        0x00, 0x1a, 0x15, 0x76, 0x12, 0x6b, 0x63, 0x79,
+        0x6c, 0x7e, 0x08, 0x1d, 0x71, 0x07, 0x64, 0x0f,
+    ]),
+    Table([
+        0x00, 0xc8, 0xc6, 0xee, 0x94, 0x74, 0x28, 0xe0,
+        0x26, 0xb2, 0x5c, 0x9a, 0xbc, 0x52, 0x7a, 0x0e,
+    ]),
+];
+
+/// From the two halves of an inverse to the inverse itself, in the AES
+/// basis, for decryption's last round.
+static INV_SUB_LAST: [Table; 2] = [
+    Table([
+        0x00, 0xf3, 0xc8, 0xdc, 0x2c, 0xcb, 0x14, 0xe7,
+        0x2f, 0x03, 0xdf, 0x17, 0x38, 0xe4, 0xf0, 0x3b,
+    ]),
+    Table([
+        0x00, 0xf2, 0x99, 0x30, 0x9d, 0xc6, 0xa9, 0x5b,
+        0xc2, 0x5f, 0x6f, 0xf6, 0x34, 0x04, 0xad, 0x6b,
+    ]),
+];
+
+/// ShiftRows applied `k` times, as `pshufb` selectors: byte `4 c + r`
+/// comes from column `c + k r`.
+static SHIFT_ROWS: [Table; 4] = [
+    Table([
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07,
+        0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+    ]),
+    Table([
+        0x00, 0x05, 0x0a, 0x0f, 0x04, 0x09, 0x0e, 0x03,
+        0x08, 0x0d, 0x02, 0x07, 0x0c, 0x01, 0x06, 0x0b,
+    ]),
+    Table([
+        0x00, 0x09, 0x02, 0x0b, 0x04, 0x0d, 0x06, 0x0f,
+        0x08, 0x01, 0x0a, 0x03, 0x0c, 0x05, 0x0e, 0x07,
+    ]),
+    Table([
+        0x00, 0x0d, 0x0a, 0x07, 0x04, 0x01, 0x0e, 0x0b,
+        0x08, 0x05, 0x02, 0x0f, 0x0c, 0x09, 0x06, 0x03,
+    ]),
+];
+
+/// MixColumns' rotation of each column by one row, for a state left `k`
+/// ShiftRows behind its true layout.
+static ROTATE: [Table; 4] = [
+    Table([
+        0x01, 0x02, 0x03, 0x00, 0x05, 0x06, 0x07, 0x04,
+        0x09, 0x0a, 0x0b, 0x08, 0x0d, 0x0e, 0x0f, 0x0c,
+    ]),
+    Table([
+        0x05, 0x06, 0x07, 0x04, 0x09, 0x0a, 0x0b, 0x08,
+        0x0d, 0x0e, 0x0f, 0x0c, 0x01, 0x02, 0x03, 0x00,
+    ]),
+    Table([
+        0x09, 0x0a, 0x0b, 0x08, 0x0d, 0x0e, 0x0f, 0x0c,
+        0x01, 0x02, 0x03, 0x00, 0x05, 0x06, 0x07, 0x04,
+    ]),
+    Table([
+        0x0d, 0x0e, 0x0f, 0x0c, 0x01, 0x02, 0x03, 0x00,
+        0x05, 0x06, 0x07, 0x04, 0x09, 0x0a, 0x0b, 0x08,
+    ]),
+];
+
+/// The inverse of each [`ROTATE`].
+static ROTATE_BACK: [Table; 4] = [
+    Table([
+        0x03, 0x00, 0x01, 0x02, 0x07, 0x04, 0x05, 0x06,
+        0x0b, 0x08, 0x09, 0x0a, 0x0f, 0x0c, 0x0d, 0x0e,
+    ]),
+    Table([
+        0x0f, 0x0c, 0x0d, 0x0e, 0x03, 0x00, 0x01, 0x02,
+        0x07, 0x04, 0x05, 0x06, 0x0b, 0x08, 0x09, 0x0a,
+    ]),
+    Table([
+        0x0b, 0x08, 0x09, 0x0a, 0x0f, 0x0c, 0x0d, 0x0e,
+        0x03, 0x00, 0x01, 0x02, 0x07, 0x04, 0x05, 0x06,
+    ]),
+    Table([
+        0x07, 0x04, 0x05, 0x06, 0x0b, 0x08, 0x09, 0x0a,
+        0x0f, 0x0c, 0x0d, 0x0e, 0x03, 0x00, 0x01, 0x02,
+    ]),
+];
+
+/// Look every byte of `x` up in `t`. An index with its top bit set gives
+/// zero, which is how [`INVERSE`] spells the inverse of zero.
+#[inline(always)]
+unsafe fn lookup(t: &Table, x: __m128i) -> __m128i {
+    // SAFETY: the table is aligned by its type, and pshufb is SSSE3,
+    // which the caller has.
+    unsafe { _mm_shuffle_epi8(_mm_load_si128(t.0.as_ptr().cast()), x) }
+}
+
+/// The low and high nibble of every byte of `x`.
+#[inline(always)]
+unsafe fn nibbles(x: __m128i) -> (__m128i, __m128i) {
+    // SAFETY: every intrinsic here is SSE2.
+    unsafe {
+        let mask = _mm_set1_epi8(0x0f);
+        let lo = _mm_and_si128(x, mask);
+        let hi = _mm_and_si128(_mm_srli_epi16(x, 4), mask);
+        (lo, hi)
+    }
+}
+
+/// Apply the linear map `t` to every byte of `x`, a nibble at a time.
+#[inline(always)]
+unsafe fn change(t: &[Table; 2], x: __m128i) -> __m128i {
+    // SAFETY: as for lookup.
+    unsafe {
+        let (lo, hi) = nibbles(x);
+        _mm_xor_si128(lookup(&t[0], lo), lookup(&t[1], hi))
+    }
+}
+
+/// Invert every byte of `x`, which is in the tower basis, as far as the
+/// two indices an output table pair takes.
+///
+/// Writing `x = i t + j (t + 1)`, its inverse is `a t + b (t + 1)` with
+/// `1 / a = i + 1 / (1 / i + 1 / (8 (i + j)))`, and `b` likewise with `i`
+/// and `j` exchanged. The output tables take `1 / a` and `1 / b`, which
+/// saves the last two inversions. Zero comes out as zero: every infinity
+/// along the way is absorbed by a lookup, which is why [`INVERSE`] has
+/// the top bit for it.
+#[inline(always)]
+unsafe fn invert(x: __m128i) -> (__m128i, __m128i) {
+    // SAFETY: as for lookup.
+    unsafe {
+        let (k, i) = nibbles(x);
+        let j = _mm_xor_si128(i, k);
+        let ak = lookup(&SCALED_INVERSE, k);
+        let iak = _mm_xor_si128(lookup(&INVERSE, i), ak);
+        let jak = _mm_xor_si128(lookup(&INVERSE, j), ak);
+        let io = _mm_xor_si128(lookup(&INVERSE, iak), j);
+        let jo = _mm_xor_si128(lookup(&INVERSE, jak), i);
+        (io, jo)
+    }
+}
+
+/// Finish an inversion through the output table pair `t`.
+#[inline(always)]
+unsafe fn output(t: &[Table; 2], (io, jo): (__m128i, __m128i)) -> __m128i {
+    // SAFETY: as for lookup.
+    unsafe { _mm_xor_si128(lookup(&t[0], io), lookup(&t[1], jo)) }
+}
+
+/// Permute the bytes of `x` by the selector `t`.
+#[inline(always)]
+unsafe fn permute(x: __m128i, t: &Table) -> __m128i {
+    // SAFETY: as for lookup.
+    unsafe { _mm_shuffle_epi8(x, _mm_load_si128(t.0.as_ptr().cast())) }
+}
+
+/// Encrypt `N` blocks held in registers.
+///
+/// # Safety
+///
+/// The CPU must have SSSE3, and `rk` must point to `R + 1` round keys in
+/// this module's encryption layout.
+#[inline(always)]
+unsafe fn encrypt_x<const R: usize, const N: usize>(
+    rk: *const __m128i,
+    s: &mut [__m128i; N],
+) {
+    // SAFETY: the caller guarantees the instructions and the schedule.
+    unsafe {
+        let k = _mm_load_si128(rk);
+        for s in s.iter_mut() {
+            *s = _mm_xor_si128(change(&BASIS, *s), k);
+        }
+        for r in 1..R {
+            let k = _mm_load_si128(rk.add(r));
+            for s in s.iter_mut() {
+                let inverse = invert(*s);
+                let a = output(&SUB, inverse);
+                let a2 = output(&SUB2, inverse);
+                // With the columns rotated by one row, MixColumns is
+                // 2a + 3 rot(a) + rot^2(a) + rot^3(a), and that is
+                // x + rot(x) + rot^3(a) for x = 2a + rot(a).
+                let x = _mm_xor_si128(a2, permute(a, &ROTATE[r % 4]));
+                let y = _mm_xor_si128(x, permute(x, &ROTATE[r % 4]));
+                let y = _mm_xor_si128(y, permute(a, &ROTATE_BACK[r % 4]));
+                *s = _mm_xor_si128(y, k);
+            }
+        }
+        let k = _mm_load_si128(rk.add(R));
+        for s in s.iter_mut() {
+            let x = permute(*s, &SHIFT_ROWS[R % 4]);
+            *s = _mm_xor_si128(output(&SUB_LAST, invert(x)), k);
+        }
+    }
+}
+
+/// Decrypt `N` blocks held in registers.
+///
+/// # Safety
+///
+/// The CPU must have SSSE3, and `rk` must point to `R + 1` round keys in
+/// this module's decryption layout.
+#[inline(always)]
+unsafe fn decrypt_x<const R: usize, const N: usize>(
+    rk: *const __m128i,
+    s: &mut [__m128i; N],
+) {
+    // SAFETY: the caller guarantees the instructions and the schedule.
+    unsafe {
+        let k = _mm_load_si128(rk);
+        for s in s.iter_mut() {
+            *s = _mm_xor_si128(change(&INV_BASIS, *s), k);
+        }
+        for r in 1..R {
+            let k = _mm_load_si128(rk.add(r));
+            let rotate = &ROTATE[(4 - r % 4) % 4];
+            for s in s.iter_mut() {
+                let inverse = invert(*s);
+                // 14a + 11 rot(a) + 13 rot^2(a) + 9 rot^3(a), by Horner.
+                let x = output(&INV_SUB9, inverse);
+                let x = permute(x, rotate);
+                let x = _mm_xor_si128(x, output(&INV_SUB13, inverse));
+                let x = permute(x, rotate);
+                let x = _mm_xor_si128(x, output(&INV_SUB11, inverse));
+                let x = permute(x, rotate);
+                let x = _mm_xor_si128(x, output(&INV_SUB14, inverse));
+                *s = _mm_xor_si128(x, k);
+            }
+        }
+        let k = _mm_load_si128(rk.add(R));
+        for s in s.iter_mut() {
+            let x = permute(*s, &SHIFT_ROWS[(4 - R % 4) % 4]);
+            *s = _mm_xor_si128(output(&INV_SUB_LAST, invert(x)), k);
+        }
+    }
+}
+
+/// Encrypt or decrypt `blocks` whole blocks at `data` in place, `WIDTH`
+/// at a time and then one at a time.
+macro_rules! kernel {
+    ($name:ident, $x:ident) => {
+        /// # Safety
+        ///
+        /// The CPU must have SSSE3. `rk` must point to `R + 1` round
+        /// keys in the layout this direction expects, and `data` to
+        /// `blocks` whole blocks.
+        #[target_feature(enable = "ssse3")]
+        unsafe fn $name<const R: usize>(
+            rk: *const __m128i,
+            data: *mut u8,
+            blocks: usize,
+        ) {
+            let data = data.cast::<__m128i>();
+            let mut done = 0;
+            // SAFETY: the caller guarantees the instructions, the
+            // schedule and the range; the loads and stores are unaligned.
+            unsafe {
+                while blocks - done >= WIDTH {
+                    let p = data.add(done);
+                    let mut s: [__m128i; WIDTH] =
+                        core::array::from_fn(|b| _mm_loadu_si128(p.add(b)));
+                    $x::<R, WIDTH>(rk, &mut s);
+                    for (b, s) in s.iter().enumerate() {
+                        _mm_storeu_si128(p.add(b), *s);
+                    }
+                    done += WIDTH;
+                }
+                for b in done..blocks {
+                    let mut s = [_mm_loadu_si128(data.add(b))];
+                    $x::<R, 1>(rk, &mut s);
+                    _mm_storeu_si128(data.add(b), s[0]);
+                }
+            }
+        }
+    };
+}
+
+kernel!(encrypt_blocks, encrypt_x);
+kernel!(decrypt_blocks, decrypt_x);
+
+/// The counter block `c`, big endian, in a register.
+#[inline(always)]
+unsafe fn counter_block(c: u128) -> __m128i {
+    // SAFETY: SSE2 only.
+    unsafe {
+        _mm_set_epi64x(
+            (c as u64).swap_bytes() as i64,
+            ((c >> 64) as u64).swap_bytes() as i64,
+        )
+    }
+}
+
+/// Encrypt successive counter values from `counter`, XOR them into
+/// `blocks` whole blocks at `data`, and advance `counter` past them.
+///
+/// The counters are made in registers and the keystream never leaves
+/// them, so each block of data is read once and written once.
+///
+/// # Safety
+///
+/// The CPU must have SSSE3. `rk` must point to `R + 1` round keys in the
+/// encryption layout, and `data` to `blocks` whole blocks.
+#[target_feature(enable = "ssse3")]
+unsafe fn ctr_blocks<const R: usize>(
+    rk: *const __m128i,
+    data: *mut u8,
+    blocks: usize,
+    counter: &mut [u8; BLOCK_SIZE],
+) {
+    let data = data.cast::<__m128i>();
+    let mut c = u128::from_be_bytes(*counter);
+    let mut done = 0;
+    // SAFETY: the caller guarantees the instructions, the schedule and
+    // the range; the loads and stores are unaligned.
+    unsafe {
+        while blocks - done >= WIDTH {
+            let p = data.add(done);
+            let mut s: [__m128i; WIDTH] = core::array::from_fn(|b| {
+                counter_block(c.wrapping_add(b as u128))
+            });
+            encrypt_x::<R, WIDTH>(rk, &mut s);
+            for (b, s) in s.iter().enumerate() {
+                let d = _mm_loadu_si128(p.add(b));
+                _mm_storeu_si128(p.add(b), _mm_xor_si128(d, *s));
+            }
+            c = c.wrapping_add(WIDTH as u128);
+            done += WIDTH;
+        }
+        for b in done..blocks {
+            let mut s = [counter_block(c)];
+            encrypt_x::<R, 1>(rk, &mut s);
+            let d = _mm_loadu_si128(data.add(b));
+            _mm_storeu_si128(data.add(b), _mm_xor_si128(d, s[0]));
+            c = c.wrapping_add(1);
+        }
+    }
+    *counter = c.to_be_bytes();
+}
+
+/// SubBytes on every byte of `x`, in the AES basis, in constant time.
+#[inline(always)]
+unsafe fn sub_bytes(x: __m128i) -> __m128i {
+    // SAFETY: as for lookup.
+    unsafe {
+        let y = output(&SUB_LAST, invert(change(&BASIS, x)));
+        _mm_xor_si128(y, _mm_set1_epi8(0x63))
+    }
+}
+
+/// Multiply every byte of `x` by two in GF(2^8), without branching.
+#[inline(always)]
+unsafe fn double(x: __m128i) -> __m128i {
+    // SAFETY: SSE2 only.
+    unsafe {
+        let carry = _mm_cmplt_epi8(x, _mm_setzero_si128());
+        let reduce = _mm_and_si128(carry, _mm_set1_epi8(0x1b));
+        _mm_xor_si128(_mm_add_epi8(x, x), reduce)
+    }
+}
+
+/// MixColumns on a round key in the AES basis and layout.
+#[inline(always)]
+unsafe fn mix_columns(x: __m128i) -> __m128i {
+    // SAFETY: as for lookup.
+    unsafe {
+        let r1 = permute(x, &ROTATE[0]);
+        let r2 = permute(r1, &ROTATE[0]);
+        let r3 = permute(r2, &ROTATE[0]);
+        let y = _mm_xor_si128(double(_mm_xor_si128(x, r1)), r1);
+        _mm_xor_si128(y, _mm_xor_si128(r2, r3))
+    }
+}
+
+/// InvMixColumns on a round key in the AES basis and layout.
+///
+/// The inverse polynomial is the forward one times `4 x^2 + 5`, so this
+/// is that product followed by [`mix_columns`].
+#[inline(always)]
+unsafe fn inv_mix_columns(x: __m128i) -> __m128i {
+    // SAFETY: as for lookup.
+    unsafe {
+        let r2 = permute(permute(x, &ROTATE[0]), &ROTATE[0]);
+        let u = double(double(_mm_xor_si128(x, r2)));
+        mix_columns(_mm_xor_si128(x, u))
+    }
+}
+
+/// Round constants, one per application of SubWord after a rotation.
+const RCON: [u32; 10] = [
+    0x0100_0000, 0x0200_0000, 0x0400_0000, 0x0800_0000, 0x1000_0000,
+    0x2000_0000, 0x4000_0000, 0x8000_0000, 0x1b00_0000, 0x3600_0000,
+];
+
+/// Substitute all four bytes of a word through the vector S-box.
+#[inline(always)]
+unsafe fn sub_word(w: u32) -> u32 {
+    // SAFETY: as for lookup. The S-box works byte by byte, so the lane
+    // order the word lands in does not matter.
+    unsafe { _mm_cvtsi128_si32(sub_bytes(_mm_cvtsi32_si128(w as i32))) as u32 }
+}
+
+/// Expand `key` into the canonical round keys, as FIPS-197 does.
+///
+/// # Safety
+///
+/// The CPU must have SSSE3. `out` must be `4 * (Nk + 7)` words of round
+/// keys for the `4 * Nk` byte key.
+#[target_feature(enable = "ssse3")]
+unsafe fn expand(key: &[u8], out: &mut [u8]) {
+    let nk = key.len() / 4;
+    let mut w = [0u32; 60];
+    let words = out.len() / 4;
+    let (chunks, _) = key.as_chunks::<4>();
+    for (word, chunk) in w.iter_mut().zip(chunks) {
+        *word = u32::from_be_bytes(*chunk);
+    }
+    for i in nk..words {
+        let mut temp = w[i - 1];
+        // SAFETY: the caller guarantees the instructions.
+        if i % nk == 0 {
+            temp = unsafe { sub_word(temp.rotate_left(8)) } ^ RCON[i / nk - 1];
+        } else if nk > 6 && i % nk == 4 {
+            // AES-256 substitutes again at the midpoint of each key block.
+            temp = unsafe { sub_word(temp) };
+        }
+        w[i] = w[i - nk] ^ temp;
+    }
+    let (out, _) = out.as_chunks_mut::<4>();
+    for (chunk, word) in out.iter_mut().zip(&w) {
+        *chunk = word.to_be_bytes();
+    }
+    w.zeroize();
+}
+
+/// Move canonical round keys into the encryption layout.
+///
+/// The first key is only changed into the tower basis. The middle ones
+/// also carry the S-box constant the tables leave out, and are rotated
+/// back by as many rows as the state is behind when it meets them. The
+/// last meets the state in the AES basis with its rows put right, so it
+/// carries only the constant.
+///
+/// # Safety
+///
+/// The CPU must have SSSE3, and `rk` must point to room for as many
+/// aligned round keys as `canonical` holds.
+#[target_feature(enable = "ssse3")]
+unsafe fn encryption_schedule(canonical: &[u8], rk: *mut __m128i) {
+    let rounds = canonical.len() / BLOCK_SIZE - 1;
+    let k = canonical.as_ptr().cast::<__m128i>();
+    // SAFETY: the caller guarantees the instructions and the destination,
+    // and the source is whole round keys.
+    unsafe {
+        let constant = _mm_set1_epi8(0x63);
+        _mm_store_si128(rk, change(&BASIS, _mm_loadu_si128(k)));
+        for r in 1..rounds {
+            let x = _mm_xor_si128(_mm_loadu_si128(k.add(r)), constant);
+            let x = permute(change(&BASIS, x), &SHIFT_ROWS[(4 - r % 4) % 4]);
+            _mm_store_si128(rk.add(r), x);
+        }
+        let x = _mm_loadu_si128(k.add(rounds));
+        _mm_store_si128(rk.add(rounds), _mm_xor_si128(x, constant));
+    }
+}
+
+/// Undo [`encryption_schedule`].
+///
+/// # Safety
+///
+/// The CPU must have SSSE3, and `rk` must point to as many aligned round
+/// keys as `canonical` has room for.
+#[target_feature(enable = "ssse3")]
+unsafe fn restore_encryption(rk: *const __m128i, canonical: &mut [u8]) {
+    let rounds = canonical.len() / BLOCK_SIZE - 1;
+    let k = canonical.as_mut_ptr().cast::<__m128i>();
+    // SAFETY: as for encryption_schedule.
+    unsafe {
+        let constant = _mm_set1_epi8(0x63);
+        _mm_storeu_si128(k, change(&UNBASIS, _mm_load_si128(rk)));
+        for r in 1..rounds {
+            let x = permute(_mm_load_si128(rk.add(r)), &SHIFT_ROWS[r % 4]);
+            let x = _mm_xor_si128(change(&UNBASIS, x), constant);
+            _mm_storeu_si128(k.add(r), x);
+        }
+        let x = _mm_load_si128(rk.add(rounds));
+        _mm_storeu_si128(k.add(rounds), _mm_xor_si128(x, constant));
+    }
+}
+
+/// Move canonical round keys into the decryption layout: the equivalent
+/// inverse cipher's, last key first and the middle ones through
+/// InvMixColumns, then as [`encryption_schedule`] does with the roles of
+/// the bases reversed and the rows rotated the other way.
+///
+/// The S-box constant goes in as 0x63 before the change of basis, which
+/// is the inverse affine map's constant after it.
+///
+/// # Safety
+///
+/// As for [`encryption_schedule`].
+#[target_feature(enable = "ssse3")]
+unsafe fn decryption_schedule(canonical: &[u8], rk: *mut __m128i) {
+    let rounds = canonical.len() / BLOCK_SIZE - 1;
+    let k = canonical.as_ptr().cast::<__m128i>();
+    // SAFETY: as for encryption_schedule.
+    unsafe {
+        let constant = _mm_set1_epi8(0x63);
+        let x = _mm_xor_si128(_mm_loadu_si128(k.add(rounds)), constant);
+        _mm_store_si128(rk, change(&INV_BASIS, x));
+        for r in 1..rounds {
+            let x = inv_mix_columns(_mm_loadu_si128(k.add(rounds - r)));
+            let x = change(&INV_BASIS, _mm_xor_si128(x, constant));
+            _mm_store_si128(rk.add(r), permute(x, &SHIFT_ROWS[r % 4]));
+        }
+        _mm_store_si128(rk.add(rounds), _mm_loadu_si128(k));
+    }
+}
+
+/// Undo [`decryption_schedule`].
+///
+/// # Safety
+///
+/// As for [`restore_encryption`].
+#[cfg(feature = "hazmat")]
+#[target_feature(enable = "ssse3")]
+unsafe fn restore_decryption(rk: *const __m128i, canonical: &mut [u8]) {
+    let rounds = canonical.len() / BLOCK_SIZE - 1;
+    let k = canonical.as_mut_ptr().cast::<__m128i>();
+    // SAFETY: as for encryption_schedule.
+    unsafe {
+        let constant = _mm_set1_epi8(0x63);
+        let x = change(&INV_UNBASIS, _mm_load_si128(rk));
+        _mm_storeu_si128(k.add(rounds), _mm_xor_si128(x, constant));
+        for r in 1..rounds {
+            let x = _mm_load_si128(rk.add(r));
+            let x = permute(x, &SHIFT_ROWS[(4 - r % 4) % 4]);
+            let x = _mm_xor_si128(change(&INV_UNBASIS, x), constant);
+            _mm_storeu_si128(k.add(rounds - r), mix_columns(x));
+        }
+        _mm_storeu_si128(k, _mm_load_si128(rk.add(rounds)));
+    }
+}
+
+/// Clear the vector registers, and the stack beneath the caller, when the
+/// `scrub` feature asks for it.
+///
+/// Unlike the assembly kernels, these are compiled, and the compiler may
+/// spill state or round keys to the stack as well as leaving them in
+/// registers. Called straight after a kernel or schedule routine returns,
+/// from the function that called it, this clears both.
+#[inline(always)]
+fn scrub() {
+    aesni::scrub_registers();
+    #[cfg(feature = "scrub")]
+    crate::symmetric::aes::arch::scrub::stack();
+}
+
+macro_rules! define_aes {
+    (
+        $enc:ident, $dec:ident, $key_size:expr, $bytes:expr, $rounds:expr,
+        $bits:expr
+    ) => {
+        // Aligned so the round keys can be read with aligned loads.
+        #[doc = concat!("AES-", $bits, " encryption only, on SSSE3.")]
+        #[repr(align(16))]
+        pub struct $enc {
+            rk: [u8; $bytes],
+        }
+
+        #[doc = concat!("AES-", $bits, " decryption only, on SSSE3.")]
+        #[repr(align(16))]
+        pub struct $dec {
+            rk: [u8; $bytes],
+        }
+
+        impl $enc {
+            /// The key length in bytes.
+            pub const KEY_SIZE: usize = $key_size;
+            /// The block length in bytes.
+            pub const BLOCK_SIZE: usize = BLOCK_SIZE;
+            /// Blocks kept in flight.
+            pub const PARALLEL_BLOCKS: usize = WIDTH;
+
+            /// Expand `key` into an encryption schedule.
+            ///
+            /// # Panics
+            ///
+            /// If the CPU has no SSSE3. Naming this type asserts that it
+            /// has; the parent module's type of the same name checks and
+            /// falls back instead, and [`Self::try_new`] reports it.
+            pub fn new(key: &[u8; $key_size]) -> Self {
+                match Self::try_new(key) {
+                    Ok(this) => this,
+                    Err(e) => panic!("{e}"),
+                }
+            }
+
+            /// Expand `key` into an encryption schedule, or say which CPU
+            /// features that would need.
+            pub fn try_new(
+                key: &[u8; $key_size],
+            ) -> Result<Self, UnsupportedCpu> {
+                if !supported() {
+                    return Err(unsupported());
+                }
+                let mut canonical = [0u8; $bytes];
+                let mut this = Self { rk: [0u8; $bytes] };
+                // SAFETY: support was just checked; canonical is the
+                // schedule for this key size, and rk is as long and
+                // aligned by the type.
+                unsafe {
+                    expand(key, &mut canonical);
+                    encryption_schedule(
+                        &canonical,
+                        this.rk.as_mut_ptr().cast(),
+                    );
+                }
+                canonical.zeroize();
+                scrub();
+                Ok(this)
+            }
+
+            /// Encrypt whole blocks in place, returning bytes consumed.
+            pub fn encrypt(&self, data: &mut [u8]) -> usize {
+                let blocks = data.len() / BLOCK_SIZE;
+                if blocks == 0 {
+                    return 0;
+                }
+                // SAFETY: the schedule exists, so SSSE3 does, and the
+                // buffer holds that many whole blocks.
+                unsafe {
+                    encrypt_blocks::<$rounds>(
+                        self.rk.as_ptr().cast(),
+                        data.as_mut_ptr(),
+                        blocks,
+                    );
+                }
+                scrub();
+                blocks * BLOCK_SIZE
+            }
+
+            /// Encrypt exactly one block in place.
+            pub fn encrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+                self.encrypt(block);
+            }
+
+            /// Encrypt successive counter values and XOR them into
+            /// `data` in place, advancing `counter`.
+            ///
+            /// The counter is one block, big endian, wrapping at the
+            /// full block width, as SP 800-38A specifies. Whole blocks
+            /// only, like [`Self::encrypt`]; returns bytes consumed.
+            pub fn ctr(
+                &self,
+                counter: &mut [u8; BLOCK_SIZE],
+                data: &mut [u8],
+            ) -> usize {
+                let blocks = data.len() / BLOCK_SIZE;
+                if blocks == 0 {
+                    return 0;
+                }
+                // SAFETY: as for encrypt.
+                unsafe {
+                    ctr_blocks::<$rounds>(
+                        self.rk.as_ptr().cast(),
+                        data.as_mut_ptr(),
+                        blocks,
+                        counter,
+                    );
+                }
+                scrub();
+                blocks * BLOCK_SIZE
+            }
+        }
+
+        impl $dec {
+            /// The key length in bytes.
+            pub const KEY_SIZE: usize = $key_size;
+            /// The block length in bytes.
+            pub const BLOCK_SIZE: usize = BLOCK_SIZE;
+            /// Blocks kept in flight.
+            pub const PARALLEL_BLOCKS: usize = WIDTH;
+
+            /// Expand `key` into a decryption schedule.
+            ///
+            /// # Panics
+            ///
+            /// If the CPU has no SSSE3. [`Self::try_new`] reports that
+            /// instead.
+            pub fn new(key: &[u8; $key_size]) -> Self {
+                match Self::try_new(key) {
+                    Ok(this) => this,
+                    Err(e) => panic!("{e}"),
+                }
+            }
+
+            /// Expand `key` into a decryption schedule, or say which CPU
+            /// features that would need.
+            pub fn try_new(
+                key: &[u8; $key_size],
+            ) -> Result<Self, UnsupportedCpu> {
+                // The encryption schedule is wiped when it drops here.
+                Ok(Self::from(&$enc::try_new(key)?))
+            }
+
+            /// Decrypt whole blocks in place, returning bytes consumed.
+            pub fn decrypt(&self, data: &mut [u8]) -> usize {
+                let blocks = data.len() / BLOCK_SIZE;
+                if blocks == 0 {
+                    return 0;
+                }
+                // SAFETY: as for the encryption side.
+                unsafe {
+                    decrypt_blocks::<$rounds>(
+                        self.rk.as_ptr().cast(),
+                        data.as_mut_ptr(),
+                        blocks,
+                    );
+                }
+                scrub();
+                blocks * BLOCK_SIZE
+            }
+
+            /// Decrypt exactly one block in place.
+            pub fn decrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+                self.decrypt(block);
+            }
+        }
+
+        /// Invert an existing encryption schedule, so the key itself
+        /// need not be kept around in case decryption is wanted later.
+        impl From<&$enc> for $dec {
+            fn from(enc: &$enc) -> Self {
+                let mut canonical = [0u8; $bytes];
+                let mut this = Self { rk: [0u8; $bytes] };
+                // SAFETY: the encryption schedule exists, so SSSE3 does;
+                // all three schedules are $bytes long, and the two in
+                // this layout are aligned by their types.
+                unsafe {
+                    restore_encryption(enc.rk.as_ptr().cast(), &mut canonical);
+                    decryption_schedule(
+                        &canonical,
+                        this.rk.as_mut_ptr().cast(),
+                    );
+                }
+                canonical.zeroize();
+                scrub();
+                this
+            }
+        }
+
+        #[cfg(feature = "hazmat")]
+        impl $enc {
+            /// Copy out the round keys, in the [canonical layout].
+            ///
+            /// [canonical layout]: crate::symmetric::aes#round-keys
+            pub fn round_keys(&self) -> Zeroizing<[u8; $bytes]> {
+                let mut out = Zeroizing::new([0u8; $bytes]);
+                // SAFETY: the schedule exists, so SSSE3 does; both are
+                // $bytes long, and rk is aligned by the type.
+                unsafe {
+                    restore_encryption(self.rk.as_ptr().cast(), &mut out[..]);
+                }
+                scrub();
+                out
+            }
+
+            /// Take round keys in the canonical layout.
+            ///
+            /// Nothing checks that they came from a key expansion.
+            ///
+            /// # Panics
+            ///
+            /// As [`Self::new`]; [`Self::try_from_round_keys`] reports it
+            /// instead.
+            pub fn from_round_keys(round_keys: &[u8; $bytes]) -> Self {
+                match Self::try_from_round_keys(round_keys) {
+                    Ok(this) => this,
+                    Err(e) => panic!("{e}"),
+                }
+            }
+
+            /// Take round keys in the canonical layout, or say which CPU
+            /// features that would need.
+            pub fn try_from_round_keys(
+                round_keys: &[u8; $bytes],
+            ) -> Result<Self, UnsupportedCpu> {
+                if !supported() {
+                    return Err(unsupported());
+                }
+                let mut this = Self { rk: [0u8; $bytes] };
+                // SAFETY: support was just checked; both are $bytes long,
+                // and rk is aligned by the type.
+                unsafe {
+                    encryption_schedule(
+                        round_keys,
+                        this.rk.as_mut_ptr().cast(),
+                    );
+                }
+                scrub();
+                Ok(this)
+            }
+        }
+
+        #[cfg(feature = "hazmat")]
+        impl $dec {
+            /// Copy out the encryption round keys this schedule was
+            /// inverted from, in the canonical layout.
+            pub fn round_keys(&self) -> Zeroizing<[u8; $bytes]> {
+                let mut out = Zeroizing::new([0u8; $bytes]);
+                // SAFETY: as for the encryption side.
+                unsafe {
+                    restore_decryption(self.rk.as_ptr().cast(), &mut out[..]);
+                }
+                scrub();
+                out
+            }
+
+            /// Invert encryption round keys in the canonical layout.
+            ///
+            /// Nothing checks that they came from a key expansion.
+            ///
+            /// # Panics
+            ///
+            /// As [`Self::new`]; [`Self::try_from_round_keys`] reports it
+            /// instead.
+            pub fn from_round_keys(round_keys: &[u8; $bytes]) -> Self {
+                match Self::try_from_round_keys(round_keys) {
+                    Ok(this) => this,
+                    Err(e) => panic!("{e}"),
+                }
+            }
+
+            /// Invert encryption round keys in the canonical layout, or
+            /// say which CPU features that would need.
+            pub fn try_from_round_keys(
+                round_keys: &[u8; $bytes],
+            ) -> Result<Self, UnsupportedCpu> {
+                if !supported() {
+                    return Err(unsupported());
+                }
+                let mut this = Self { rk: [0u8; $bytes] };
+                // SAFETY: as for the encryption side.
+                unsafe {
+                    decryption_schedule(
+                        round_keys,
+                        this.rk.as_mut_ptr().cast(),
+                    );
+                }
+                scrub();
+                Ok(this)
+            }
+        }
+
+        impl KeyInit for $enc {
+            type Error = KeyInitError;
+
+            fn try_new(key: &[u8]) -> Result<Self, KeyInitError> {
+                let key: &[u8; $key_size] = key
+                    .try_into()
+                    .map_err(|_| InvalidKeyLength { got: key.len() })?;
+                Ok(Self::try_new(key)?)
+            }
+        }
+
+        impl KeyInit for $dec {
+            type Error = KeyInitError;
+
+            fn try_new(key: &[u8]) -> Result<Self, KeyInitError> {
+                let key: &[u8; $key_size] = key
+                    .try_into()
+                    .map_err(|_| InvalidKeyLength { got: key.len() })?;
+                Ok(Self::try_new(key)?)
+            }
+        }
+
+        impl BlockEncrypt for $enc {
+            const BLOCK_SIZE: usize = BLOCK_SIZE;
+            const PARALLEL_BLOCKS: usize = WIDTH;
+
+            fn encrypt(&self, data: &mut [u8]) -> usize {
+                $enc::encrypt(self, data)
+            }
+        }
+
+        impl BlockDecrypt for $dec {
+            const BLOCK_SIZE: usize = BLOCK_SIZE;
+            const PARALLEL_BLOCKS: usize = WIDTH;
+
+            fn decrypt(&self, data: &mut [u8]) -> usize {
+                $dec::decrypt(self, data)
+            }
+        }
+
+        impl Drop for $enc {
+            fn drop(&mut self) {
+                // SAFETY: rk is aligned by the type and whole round keys.
+                unsafe { aesni::wipe(&mut self.rk) };
+            }
+        }
+
+        impl Drop for $dec {
+            fn drop(&mut self) {
+                // SAFETY: as for the encryption schedule.
+                unsafe { aesni::wipe(&mut self.rk) };
+            }
+        }
+
+        impl core::fmt::Debug for $enc {
+            fn fmt(
+                &self,
+                f: &mut core::fmt::Formatter<'_>,
+            ) -> core::fmt::Result {
+                // Never format round keys.
+                f.write_str(concat!(stringify!($enc), " { .. }"))
+            }
+        }
+
+        impl core::fmt::Debug for $dec {
+            fn fmt(
+                &self,
+                f: &mut core::fmt::Formatter<'_>,
+            ) -> core::fmt::Result {
+                f.write_str(concat!(stringify!($dec), " { .. }"))
+            }
+        }
+    };
+}
+
+define_aes!(Aes128Enc, Aes128Dec, 16, 176, 10, "128");
+define_aes!(Aes192Enc, Aes192Dec, 24, 208, 12, "192");
+define_aes!(Aes256Enc, Aes256Dec, 32, 240, 14, "256");
+
+#[cfg(test)]
+mod tests {
+    use core::arch::asm;
+
+    use super::*;
+    use crate::symmetric::aes::arch::portable::ttable;
+    use crate::symmetric::aes::testing::{assert_inverting_matches_expanding, Rng};
+
+    /// Construction on the wrong CPU is an error naming what is missing,
+    /// not a panic, through both the inherent and the trait constructor.
+    #[test]
+    fn try_new_refuses_rather_than_panics() {
+        match Aes128Enc::try_new(&[0u8; 16]) {
+            Ok(_) => assert!(supported()),
+            Err(e) => assert!(!supported() && e.missing().count() > 0),
+        }
+        assert_eq!(Aes256Dec::try_new(&[0u8; 32]).is_ok(), supported());
+
+        // The length is checked first, so it is reported on any CPU.
+        assert!(matches!(
+            <Aes192Enc as KeyInit>::try_new(&[0u8; 16]),
+            Err(KeyInitError::Key(InvalidKeyLength { got: 16 }))
+        ));
+        match <Aes192Dec as KeyInit>::try_new(&[0u8; 24]) {
+            Ok(_) => assert!(supported()),
+            Err(e) => assert!(matches!(e, KeyInitError::Cpu(_))),
+        }
+    }
+
+    /// FIPS-197 Appendix C.1, C.2 and C.3.
+    #[test]
+    fn fips_197_all_key_sizes() {
+        if !supported() {
+            return;
+        }
+        let plaintext = [
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99,
+            0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff,
+        ];
+
+        let key: [u8; 16] = core::array::from_fn(|i| i as u8);
+        let expected = [
+            0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30, 0xd8, 0xcd,
+            0xb7, 0x80, 0x70, 0xb4, 0xc5, 0x5a,
+        ];
+        let mut block = plaintext;
+        Aes128Enc::new(&key).encrypt_block(&mut block);
+        assert_eq!(block, expected, "AES-128");
+        Aes128Dec::new(&key).decrypt_block(&mut block);
+        assert_eq!(block, plaintext);
+
+        let key: [u8; 24] = core::array::from_fn(|i| i as u8);
+        let expected = [
+            0xdd, 0xa9, 0x7c, 0xa4, 0x86, 0x4c, 0xdf, 0xe0, 0x6e, 0xaf,
+            0x70, 0xa0, 0xec, 0x0d, 0x71, 0x91,
+        ];
+        let mut block = plaintext;
+        Aes192Enc::new(&key).encrypt_block(&mut block);
+        assert_eq!(block, expected, "AES-192");
+        Aes192Dec::new(&key).decrypt_block(&mut block);
+        assert_eq!(block, plaintext);
+
+        let key: [u8; 32] = core::array::from_fn(|i| i as u8);
+        let expected = [
+            0x8e, 0xa2, 0xb7, 0xca, 0x51, 0x67, 0x45, 0xbf, 0xea, 0xfc,
+            0x49, 0x90, 0x4b, 0x49, 0x60, 0x89,
+        ];
+        let mut block = plaintext;
+        Aes256Enc::new(&key).encrypt_block(&mut block);
+        assert_eq!(block, expected, "AES-256");
+        Aes256Dec::new(&key).decrypt_block(&mut block);
+        assert_eq!(block, plaintext);
+    }
+
+    /// Every length here exercises the four block groups, the single
+    /// block tail, or the boundary between them.
+    macro_rules! check_against_portable {
+        ($enc:ident, $dec:ident, $pe:path, $len:expr, $seed:expr) => {{
+            let mut rng = Rng($seed);
+            for blocks in [0usize, 1, 2, 3, 4, 5, 7, 8, 9, 64, 101] {
+                let mut key = [0u8; $len];
+                rng.fill(&mut key);
+                let mut plaintext = vec![0u8; blocks * BLOCK_SIZE];
+                rng.fill(&mut plaintext);
+
+                let mut ours = plaintext.clone();
+                let mut theirs = plaintext.clone();
+                assert_eq!(
+                    $enc::new(&key).encrypt(&mut ours),
+                    blocks * BLOCK_SIZE
+                );
+                <$pe>::new(&key).encrypt(&mut theirs);
+                assert_eq!(
+                    ours, theirs,
+                    "{} bit encrypt differs at {} blocks",
+                    $len * 8, blocks
+                );
+
+                $dec::new(&key).decrypt(&mut ours);
+                assert_eq!(
+                    ours, plaintext,
+                    "{} bit decrypt failed at {} blocks",
+                    $len * 8, blocks
+                );
+            }
+        }};
+    }
+
+    #[test]
+    fn agrees_with_the_portable_implementation() {
+        if !supported() {
+            return;
+        }
+        check_against_portable!(
+            Aes128Enc, Aes128Dec, ttable::Aes128Enc, 16,
+            0x0123_4567_89ab_cdef
+        );
+        check_against_portable!(
+            Aes192Enc, Aes192Dec, ttable::Aes192Enc, 24,
+            0xfedc_ba98_7654_3210
+        );
+        check_against_portable!(
+            Aes256Enc, Aes256Dec, ttable::Aes256Enc, 32,
+            0x2468_ace0_1357_9bdf
+        );
+    }
+
+    #[test]
+    fn partial_trailing_block_is_left_alone() {
+        if !supported() {
+            return;
+        }
+        let aes = Aes128Enc::new(&[0u8; 16]);
+        let mut data = [0xccu8; WIDTH * BLOCK_SIZE + BLOCK_SIZE + 5];
+        assert_eq!(aes.encrypt(&mut data), (WIDTH + 1) * BLOCK_SIZE);
+        assert_eq!(&data[(WIDTH + 1) * BLOCK_SIZE..], &[0xcc; 5]);
+    }
+
+    /// The fused counter kernel against the portable scalar `ctr`,
+    /// including a counter that wraps the full block inside a group.
+    #[test]
+    fn ctr_agrees_with_the_portable_implementation() {
+        if !supported() {
+            return;
+        }
+        let mut rng = Rng(0x7777_8888_9999_aaaa);
+        macro_rules! check {
+            ($enc:ident, $pe:path, $len:expr) => {
+                for blocks in [0usize, 1, 3, 4, 5, 8, 11, 64] {
+                    let mut key = [0u8; $len];
+                    rng.fill(&mut key);
+                    let mut data = vec![0u8; blocks * BLOCK_SIZE];
+                    rng.fill(&mut data);
+                    let mut iv = [0u8; BLOCK_SIZE];
+                    rng.fill(&mut iv);
+                    for iv in [iv, [0xff; BLOCK_SIZE]] {
+                        let mut ours = data.clone();
+                        let mut ours_ctr = iv;
+                        assert_eq!(
+                            $enc::new(&key).ctr(&mut ours_ctr, &mut ours),
+                            blocks * BLOCK_SIZE
+                        );
+                        let mut theirs = data.clone();
+                        let mut theirs_ctr = iv;
+                        <$pe>::new(&key).ctr(&mut theirs_ctr, &mut theirs);
+                        assert_eq!(
+                            ours, theirs,
+                            "{} bit ctr differs at {} blocks",
+                            $len * 8, blocks
+                        );
+                        assert_eq!(ours_ctr, theirs_ctr);
+                    }
+                }
+            };
+        }
+        check!(Aes128Enc, ttable::Aes128Enc, 16);
+        check!(Aes192Enc, ttable::Aes192Enc, 24);
+        check!(Aes256Enc, ttable::Aes256Enc, 32);
+    }
+
+    /// The key schedule runs its own S-box, so every round key has to be
+    /// checked against an independent expansion, and one block under
+    /// each of many keys touches all of them.
+    #[test]
+    fn expansion_agrees_with_the_portable_implementation() {
+        if !supported() {
+            return;
+        }
+        let mut rng = Rng(0x5eed_0f6b_1c0d_e5a1);
+        macro_rules! check {
+            ($enc:ident, $dec:ident, $pe:path, $len:expr) => {
+                for _ in 0..1000 {
+                    let mut key = [0u8; $len];
+                    let mut ours = [0u8; BLOCK_SIZE];
+                    rng.fill(&mut key);
+                    rng.fill(&mut ours);
+                    let plaintext = ours;
+                    let mut theirs = ours;
+                    $enc::new(&key).encrypt_block(&mut ours);
+                    <$pe>::new(&key).encrypt_block(&mut theirs);
+                    assert_eq!(
+                        ours, theirs,
+                        "{} bit key {key:02x?}", $len * 8
+                    );
+                    $dec::new(&key).decrypt_block(&mut ours);
+                    assert_eq!(ours, plaintext, "{} bit key {key:02x?}", $len * 8);
+                }
+            };
+        }
+        check!(Aes128Enc, Aes128Dec, ttable::Aes128Enc, 16);
+        check!(Aes192Enc, Aes192Dec, ttable::Aes192Enc, 24);
+        check!(Aes256Enc, Aes256Dec, ttable::Aes256Enc, 32);
+    }
+
+    /// Inverting an encryption schedule must give exactly the schedule
+    /// `Dec::new` expands, at every key size.
+    #[test]
+    fn inverting_matches_expanding() {
+        if !supported() {
+            return;
+        }
+        assert_inverting_matches_expanding!();
+    }
+
+    /// The canonical layout is the portable one's, in both directions,
+    /// and loading a schedule gives back what expansion would build.
+    #[cfg(feature = "hazmat")]
+    #[test]
+    fn round_keys_match_the_portable_implementation() {
+        if !supported() {
+            return;
+        }
+        let mut rng = Rng(0x7f6e_5d4c_3b2a_1908);
+        macro_rules! check {
+            ($enc:ident, $dec:ident, $pe:path, $len:expr) => {
+                for _ in 0..16 {
+                    let mut key = [0u8; $len];
+                    rng.fill(&mut key);
+                    let rk = <$pe>::new(&key).round_keys();
+                    assert_eq!(*$enc::new(&key).round_keys(), *rk);
+                    assert_eq!(*$dec::new(&key).round_keys(), *rk);
+                    let enc = $enc::from_round_keys(&rk);
+                    assert_eq!(enc.rk, $enc::new(&key).rk);
+                    let dec = $dec::from_round_keys(&rk);
+                    assert_eq!(dec.rk, $dec::new(&key).rk);
+                }
+            };
+        }
+        check!(Aes128Enc, Aes128Dec, ttable::Aes128Enc, 16);
+        check!(Aes192Enc, Aes192Dec, ttable::Aes192Enc, 24);
+        check!(Aes256Enc, Aes256Dec, ttable::Aes256Enc, 32);
+    }
+
+    #[test]
+    fn schedules_are_wiped_on_drop() {
+        if !supported() {
+            return;
+        }
+        let mut slot =
+            core::mem::MaybeUninit::new(Aes192Dec::new(&[0xab; 24]));
+        let ptr = slot.as_mut_ptr();
+        let bytes = ptr.cast::<u8>();
+        let len = core::mem::size_of::<Aes192Dec>();
+        // SAFETY: slot holds an initialized value of exactly this size.
+        let live = unsafe { core::slice::from_raw_parts(bytes, len) };
+        assert!(live.iter().any(|&b| b != 0));
+        // SAFETY: the value is never read as a value again.
+        unsafe { core::ptr::drop_in_place(ptr) };
+        // SAFETY: the storage is ours and still allocated.
+        let dead = unsafe { core::slice::from_raw_parts(bytes, len) };
+        assert!(dead.iter().all(|&b| b == 0), "key material survived");
+    }
+
+    /// Run `f`, then read all sixteen xmm registers, as the AES-NI tests
+    /// do.
+    fn registers_after(mut f: impl FnMut()) -> [[u8; 16]; 16] {
+        extern "C" fn trampoline(f: &mut &mut dyn FnMut()) {
+            f();
+        }
+        let mut f: &mut dyn FnMut() = &mut f;
+        let mut regs = [[0u8; 16]; 16];
+        // SAFETY: the stack is call-aligned on entry to an asm block
+        // without nostack, trampoline has the C ABI that clobber_abi
+        // describes, and regs has room for every store.
+        unsafe {
+            asm!(
+                "call {t}",
+                "movdqu [r12 + 0x0], xmm0",
+                "movdqu [r12 + 0x10], xmm1",
+                "movdqu [r12 + 0x20], xmm2",
+                "movdqu [r12 + 0x30], xmm3",
+                "movdqu [r12 + 0x40], xmm4",
+                "movdqu [r12 + 0x50], xmm5",
+                "movdqu [r12 + 0x60], xmm6",
+                "movdqu [r12 + 0x70], xmm7",
+                "movdqu [r12 + 0x80], xmm8",
+                "movdqu [r12 + 0x90], xmm9",
+                "movdqu [r12 + 0xa0], xmm10",
+                "movdqu [r12 + 0xb0], xmm11",
+                "movdqu [r12 + 0xc0], xmm12",
+                "movdqu [r12 + 0xd0], xmm13",
+                "movdqu [r12 + 0xe0], xmm14",
+                "movdqu [r12 + 0xf0], xmm15",
+                t = sym trampoline,
+                in("rdi") &mut f,
+                in("r12") regs.as_mut_ptr(),
+                clobber_abi("C"),
+            );
+        }
+        regs
+    }
+
+    /// With `scrub`, every kernel and key schedule leaves the vector
+    /// registers empty. Without it, the same inspection finds state left
+    /// in them, which is what shows it can see them.
+    ///
+    /// Which register the compiler leaves a round key in, if any, is up
+    /// to it, so unlike the assembly kernels' test this one does not ask
+    /// for one.
+    #[test]
+    fn registers_are_scrubbed_after_every_kernel() {
+        if !supported() {
+            return;
+        }
+        let mut rng = Rng(0x5c2b_0e11_7d4a_9f63);
+        let mut key = [0u8; 32];
+        rng.fill(&mut key);
+        let enc = Aes256Enc::new(&key);
+        let dec = Aes256Dec::new(&key);
+        let mut data = [0u8; 3 * WIDTH * BLOCK_SIZE];
+        rng.fill(&mut data);
+
+        let check = |what: &str, regs: [[u8; 16]; 16]| {
+            if cfg!(feature = "scrub") {
+                assert!(
+                    regs.iter().all(|r| *r == [0; 16]),
+                    "{what} left {regs:02x?}"
+                );
+            } else {
+                assert!(
+                    regs.iter().any(|r| *r != [0; 16]),
+                    "{what} left nothing to find"
+                );
+            }
+        };
+        for blocks in [1, WIDTH, WIDTH + 1, 3 * WIDTH] {
+            let data = &mut data[..blocks * BLOCK_SIZE];
+            check("encrypt", registers_after(|| {
+                enc.encrypt(data);
+            }));
+            check("decrypt", registers_after(|| {
+                dec.decrypt(data);
+            }));
+            let mut counter = [0xff; BLOCK_SIZE];
+            check("ctr", registers_after(|| {
+                enc.ctr(&mut counter, data);
+            }));
+        }
+        check("expansion", registers_after(|| {
+            drop(Aes128Enc::new(&key[..16].try_into().unwrap()));
+        }));
+        check("inversion", registers_after(|| {
+            drop(Aes256Dec::from(&enc));
+        }));
+    }
+}
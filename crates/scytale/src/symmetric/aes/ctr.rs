@@ -15,19 +15,33 @@
 
 use zeroize::Zeroize;
 
-use super::arch::portable::ttable;
-use super::{BLOCK_SIZE, Backend, accel};
-use crate::symmetric::block_cipher::InvalidKeyLength;
+use super::{
+    BLOCK_SIZE, Backend, Implementation, KeySize, Report, accel, by_key_size,
+    fallback, refusal,
+};
+use crate::rand::{RandError, RandomSource};
+use crate::self_test;
+use crate::symmetric::block_cipher::{InvalidKeyLength, KeyInitError};
 use crate::symmetric::ctr::{CtrInitError, InvalidIvLength};
 
+/// Explain counter mode's dispatch on this machine.
+///
+/// As [`super::dispatch_report`], but for the fused counter kernels,
+/// which on some targets need more of the CPU than the block ciphers do.
+pub fn dispatch_report() -> Report {
+    Report::walk(accel::CTR_TIERS)
+}
+
 macro_rules! define_ctr {
     (
-        $name:ident, $vector:ty, $accel:ty, $portable:ty, $key_size:expr,
-        $doc:expr
+        $name:ident, $wide:ty, $vector:ty, $accel:ty, $simd:ty,
+        $portable:ty, $ct:ty, $key_size:expr, $doc:expr
     ) => {
         #[doc = $doc]
         pub struct $name {
-            cipher: Backend<$vector, $accel, $portable>,
+            cipher: Backend<$wide, $vector, $accel, $simd, $portable, $ct>,
+            /// The initial counter value, kept so the stream can seek.
+            iv: [u8; BLOCK_SIZE],
             /// The next block's counter value, big endian.
             counter: [u8; BLOCK_SIZE],
             /// The most recent keystream block; `used` bytes of it are
@@ -49,19 +63,100 @@ macro_rules! define_ctr {
             /// types choose. The accelerated arm additionally needs
             /// the counter kernels' own instructions, which is a
             /// separate check on some targets.
+            ///
+            /// # Panics
+            ///
+            /// If the library failed its [self tests](crate::self_test).
+            /// [`Self::try_new_constant_time`] reports that instead.
             pub fn new(
                 key: &[u8; $key_size],
                 iv: &[u8; BLOCK_SIZE],
             ) -> Self {
-                let cipher = if accel::vaes::supported() {
+                if let Err(e) = self_test::check() {
+                    panic!("{e}");
+                }
+                let cipher = Self::accelerated(key).unwrap_or_else(|| {
+                    Backend::Portable(<$portable>::new(key))
+                });
+                Self::with(cipher, iv)
+            }
+
+            /// Expand `key` on an implementation whose timing does not
+            /// depend on it and start the counter at `iv`, or say which
+            /// CPU features that would need.
+            ///
+            /// Chooses exactly as [`Self::new`] does, except that it
+            /// never falls back to the T-table cipher. Fails with
+            /// [`KeyInitError::Cpu`] for want of anything else, which
+            /// with the `constant-time` feature never happens, and with
+            /// [`KeyInitError::SelfTest`] if the library failed its self
+            /// tests.
+            pub fn try_new_constant_time(
+                key: &[u8; $key_size],
+                iv: &[u8; BLOCK_SIZE],
+            ) -> Result<Self, KeyInitError> {
+                self_test::check()?;
+                let cipher = if let Some(cipher) = Self::accelerated(key) {
+                    cipher
+                } else if fallback::IMPLEMENTATION.is_constant_time() {
+                    Backend::Portable(<$portable>::new(key))
+                } else {
+                    return Err(refusal(accel::CTR_TIERS));
+                };
+                Ok(Self::with(cipher, iv))
+            }
+
+            /// Expand `key` on an implementation whose timing does not
+            /// depend on it, whatever the CPU, and start the counter at
+            /// `iv`.
+            ///
+            /// Where [`Self::try_new_constant_time`] would fail for want
+            /// of CPU features, this uses the bitsliced cipher instead.
+            ///
+            /// # Panics
+            ///
+            /// If the library failed its [self tests](crate::self_test).
+            pub fn new_constant_time(
+                key: &[u8; $key_size],
+                iv: &[u8; BLOCK_SIZE],
+            ) -> Self {
+                match Self::try_new_constant_time(key, iv) {
+                    Ok(this) => this,
+                    Err(KeyInitError::SelfTest(e)) => panic!("{e}"),
+                    Err(_) => {
+                        Self::with(Backend::ConstantTime(<$ct>::new(key)), iv)
+                    }
+                }
+            }
+
+            /// The widest tier above the portable ciphers whose counter
+            /// kernels this CPU runs, if there is one.
+            #[allow(unreachable_code)]
+            fn accelerated(
+                key: &[u8; $key_size],
+            ) -> Option<Backend<$wide, $vector, $accel, $simd, $portable, $ct>>
+            {
+                Some(if accel::vaes512::supported() {
+                    Backend::Wide(<$wide>::new(key))
+                } else if accel::vaes::supported() {
                     Backend::Vector(<$vector>::new(key))
                 } else if accel::aesni::ctr_supported() {
                     Backend::Accelerated(<$accel>::new(key))
+                } else if accel::vpaes::supported() {
+                    Backend::Simd(<$simd>::new(key))
                 } else {
-                    Backend::Portable(<$portable>::new(key))
-                };
+                    return None;
+                })
+            }
+
+            /// A fresh stream over an already chosen backend.
+            fn with(
+                cipher: Backend<$wide, $vector, $accel, $simd, $portable, $ct>,
+                iv: &[u8; BLOCK_SIZE],
+            ) -> Self {
                 Self {
                     cipher,
+                    iv: *iv,
                     counter: *iv,
                     keystream: [0u8; BLOCK_SIZE],
                     used: BLOCK_SIZE,
@@ -79,9 +174,46 @@ macro_rules! define_ctr {
                 let iv: &[u8; BLOCK_SIZE] = iv
                     .try_into()
                     .map_err(|_| InvalidIvLength { got: iv.len() })?;
+                // Checked first, so that a failed self test is an error
+                // here rather than a panic in `new`.
+                self_test::check()?;
                 Ok(Self::new(key, iv))
             }
 
+            /// Expand `key` and start the counter at a fresh random
+            /// value from the operating system, returned alongside to
+            /// send with the ciphertext.
+            ///
+            /// A random 128-bit start keeps messages' counter ranges
+            /// apart short of an astronomical amount of data under one
+            /// key, so no state need be kept between messages.
+            ///
+            /// # Panics
+            ///
+            /// If the operating system cannot supply random bytes, or as
+            /// [`Self::new`] does.
+            #[cfg(target_os = "linux")]
+            pub fn with_random_iv(
+                key: &[u8; $key_size],
+            ) -> (Self, [u8; BLOCK_SIZE]) {
+                let mut os = crate::rand::OsRandom;
+                match Self::with_random_iv_from(key, &mut os) {
+                    Ok(pair) => pair,
+                    Err(e) => panic!("{e}"),
+                }
+            }
+
+            /// As [`Self::with_random_iv`], drawing the counter from
+            /// `source`.
+            pub fn with_random_iv_from(
+                key: &[u8; $key_size],
+                source: &mut impl RandomSource,
+            ) -> Result<(Self, [u8; BLOCK_SIZE]), RandError> {
+                let mut iv = [0u8; BLOCK_SIZE];
+                source.fill(&mut iv)?;
+                Ok((Self::new(key, &iv), iv))
+            }
+
             /// Whole blocks through the chosen backend's fused entry.
             fn ctr_blocks(
                 &self,
@@ -89,9 +221,12 @@ macro_rules! define_ctr {
                 data: &mut [u8],
             ) -> usize {
                 match &self.cipher {
+                    Backend::Wide(w) => w.ctr(counter, data),
                     Backend::Vector(v) => v.ctr(counter, data),
                     Backend::Accelerated(a) => a.ctr(counter, data),
+                    Backend::Simd(s) => s.ctr(counter, data),
                     Backend::Portable(p) => p.ctr(counter, data),
+                    Backend::ConstantTime(c) => c.ctr(counter, data),
                 }
             }
 
@@ -128,44 +263,79 @@ macro_rules! define_ctr {
                 }
 
                 // A trailing partial block takes what it needs from one
-                // fresh keystream block, made by running the fused
-                // entry over a zero block: E(counter) XOR 0.
+                // fresh keystream block and leaves the rest buffered.
                 if !data.is_empty() {
-                    let mut block = [0u8; BLOCK_SIZE];
-                    let mut counter = self.counter;
-                    self.ctr_blocks(&mut counter, &mut block);
-                    self.counter = counter;
+                    self.next_keystream_block();
                     let take = data.len();
-                    for (d, k) in data.iter_mut().zip(&block[..take]) {
+                    for (d, k) in data.iter_mut().zip(&self.keystream) {
                         *d ^= *k;
                     }
-                    self.keystream = block;
                     self.used = take;
                 }
             }
 
+            /// Move the stream to `pos` bytes from its start.
+            ///
+            /// The next byte of keystream is the one that byte `pos` of
+            /// the message was or will be XORed with, whatever has been
+            /// applied since construction.
+            pub fn seek(&mut self, pos: u64) {
+                let blocks = u128::from(pos / BLOCK_SIZE as u64);
+                let start = u128::from_be_bytes(self.iv);
+                self.counter = start.wrapping_add(blocks).to_be_bytes();
+                self.used = BLOCK_SIZE;
+                let skip = (pos % BLOCK_SIZE as u64) as usize;
+                if skip > 0 {
+                    self.next_keystream_block();
+                    self.used = skip;
+                }
+            }
+
+            /// Make one keystream block at the counter, by running the
+            /// fused entry over a zero block, and step the counter on.
+            fn next_keystream_block(&mut self) {
+                let mut block = [0u8; BLOCK_SIZE];
+                let mut counter = self.counter;
+                self.ctr_blocks(&mut counter, &mut block);
+                self.counter = counter;
+                self.keystream = block;
+            }
+
             /// How many blocks the chosen implementation keeps in
             /// flight.
             pub fn parallel_blocks(&self) -> usize {
                 match &self.cipher {
+                    Backend::Wide(_) => <$wide>::PARALLEL_BLOCKS,
                     Backend::Vector(_) => <$vector>::PARALLEL_BLOCKS,
                     Backend::Accelerated(_) => <$accel>::PARALLEL_BLOCKS,
+                    Backend::Simd(_) => <$simd>::PARALLEL_BLOCKS,
                     Backend::Portable(_) => <$portable>::PARALLEL_BLOCKS,
+                    Backend::ConstantTime(_) => <$ct>::PARALLEL_BLOCKS,
                 }
             }
 
             /// Whether this value is using an accelerated
             /// implementation.
             pub fn is_accelerated(&self) -> bool {
-                !matches!(self.cipher, Backend::Portable(_))
+                self.backend().is_accelerated()
             }
 
-            /// The name of the implementation this value chose.
+            /// The tier of the implementation this value chose.
+            ///
+            /// [`Self::backend`] names the implementation itself.
             pub fn implementation(&self) -> &'static str {
+                self.backend().tier()
+            }
+
+            /// The implementation this value chose.
+            pub fn backend(&self) -> Implementation {
                 match &self.cipher {
-                    Backend::Vector(_) => "vector",
-                    Backend::Accelerated(_) => "accelerated",
-                    Backend::Portable(_) => "portable",
+                    Backend::Wide(_) => accel::WIDE,
+                    Backend::Vector(_) => accel::VECTOR,
+                    Backend::Accelerated(_) => accel::ACCELERATED,
+                    Backend::Simd(_) => accel::SIMD,
+                    Backend::Portable(_) => fallback::IMPLEMENTATION,
+                    Backend::ConstantTime(_) => Implementation::Bitsliced,
                 }
             }
         }
@@ -175,6 +345,7 @@ macro_rules! define_ctr {
                 // Buffered keystream is as sensitive as the key while
                 // the counter position is knowable; the cipher wipes
                 // its own schedule.
+                self.iv.zeroize();
                 self.counter.zeroize();
                 self.keystream.zeroize();
             }
@@ -193,44 +364,120 @@ macro_rules! define_ctr {
 }
 
 define_ctr!(
-    Aes128Ctr, accel::vaes::Aes128Enc, accel::aesni::Aes128Enc,
-    ttable::Aes128Enc, 16, "AES-128 in CTR mode."
+    Aes128Ctr, accel::vaes512::Aes128Enc, accel::vaes::Aes128Enc,
+    accel::aesni::Aes128Enc, accel::vpaes::Aes128Enc, fallback::Aes128Enc,
+    fallback::constant_time::Aes128Enc, 16, "AES-128 in CTR mode."
 );
 define_ctr!(
-    Aes192Ctr, accel::vaes::Aes192Enc, accel::aesni::Aes192Enc,
-    ttable::Aes192Enc, 24, "AES-192 in CTR mode."
+    Aes192Ctr, accel::vaes512::Aes192Enc, accel::vaes::Aes192Enc,
+    accel::aesni::Aes192Enc, accel::vpaes::Aes192Enc, fallback::Aes192Enc,
+    fallback::constant_time::Aes192Enc, 24, "AES-192 in CTR mode."
 );
 define_ctr!(
-    Aes256Ctr, accel::vaes::Aes256Enc, accel::aesni::Aes256Enc,
-    ttable::Aes256Enc, 32, "AES-256 in CTR mode."
+    Aes256Ctr, accel::vaes512::Aes256Enc, accel::vaes::Aes256Enc,
+    accel::aesni::Aes256Enc, accel::vpaes::Aes256Enc, fallback::Aes256Enc,
+    fallback::constant_time::Aes256Enc, 32, "AES-256 in CTR mode."
 );
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::symmetric::ctr::Ctr;
+/// AES in CTR mode, at whichever key size it is given.
+///
+/// For callers who only learn the key length at run time. The length
+/// chooses the key size once, and from then on this is the sized type.
+pub struct AesCtr(KeySize<Aes128Ctr, Aes192Ctr, Aes256Ctr>);
+
+impl AesCtr {
+    /// The block length in bytes.
+    pub const BLOCK_SIZE: usize = BLOCK_SIZE;
+
+    /// Expand `key`, whose length picks the key size, and start the
+    /// counter at `iv`.
+    pub fn try_new(key: &[u8], iv: &[u8]) -> Result<Self, CtrInitError> {
+        Ok(Self(match key.len() {
+            16 => KeySize::Aes128(Aes128Ctr::try_new(key, iv)?),
+            24 => KeySize::Aes192(Aes192Ctr::try_new(key, iv)?),
+            32 => KeySize::Aes256(Aes256Ctr::try_new(key, iv)?),
+            got => return Err(InvalidKeyLength { got }.into()),
+        }))
+    }
 
-    /// xorshift64*, so a divergence is reproducible from the seed.
-    struct Rng(u64);
-
-    impl Rng {
-        fn next(&mut self) -> u64 {
-            let mut x = self.0;
-            x ^= x >> 12;
-            x ^= x << 25;
-            x ^= x >> 27;
-            self.0 = x;
-            x.wrapping_mul(0x2545_f491_4f6c_dd1d)
-        }
+    /// Expand `key`, whose length picks the key size, and start the
+    /// counter at a fresh random value from the operating system,
+    /// returned alongside.
+    ///
+    /// Fails with [`CtrInitError::Rand`] if the operating system cannot
+    /// supply random bytes, and otherwise as [`Self::try_new`] does.
+    #[cfg(target_os = "linux")]
+    pub fn with_random_iv(
+        key: &[u8],
+    ) -> Result<(Self, [u8; BLOCK_SIZE]), CtrInitError> {
+        Self::with_random_iv_from(key, &mut crate::rand::OsRandom)
+    }
 
-        fn fill(&mut self, buf: &mut [u8]) {
-            for chunk in buf.chunks_mut(8) {
-                let bytes = self.next().to_le_bytes();
-                chunk.copy_from_slice(&bytes[..chunk.len()]);
-            }
+    /// As [`Self::with_random_iv`], drawing the counter from `source`.
+    pub fn with_random_iv_from(
+        key: &[u8],
+        source: &mut impl RandomSource,
+    ) -> Result<(Self, [u8; BLOCK_SIZE]), CtrInitError> {
+        let mut iv = [0u8; BLOCK_SIZE];
+        source.fill(&mut iv)?;
+        Ok((Self::try_new(key, &iv)?, iv))
+    }
+
+    /// The key length in bytes: 16, 24 or 32.
+    pub fn key_size(&self) -> usize {
+        match &self.0 {
+            KeySize::Aes128(_) => Aes128Ctr::KEY_SIZE,
+            KeySize::Aes192(_) => Aes192Ctr::KEY_SIZE,
+            KeySize::Aes256(_) => Aes256Ctr::KEY_SIZE,
         }
     }
 
+    /// XOR the keystream into `data`, advancing the stream.
+    pub fn apply_keystream(&mut self, data: &mut [u8]) {
+        by_key_size!(&mut self.0, c => c.apply_keystream(data))
+    }
+
+    /// Move the stream to `pos` bytes from its start.
+    pub fn seek(&mut self, pos: u64) {
+        by_key_size!(&mut self.0, c => c.seek(pos))
+    }
+
+    /// How many blocks the chosen implementation keeps in flight.
+    pub fn parallel_blocks(&self) -> usize {
+        by_key_size!(&self.0, c => c.parallel_blocks())
+    }
+
+    /// Whether this value is using an accelerated implementation.
+    pub fn is_accelerated(&self) -> bool {
+        by_key_size!(&self.0, c => c.is_accelerated())
+    }
+
+    /// The tier of the implementation this value chose.
+    pub fn implementation(&self) -> &'static str {
+        by_key_size!(&self.0, c => c.implementation())
+    }
+
+    /// The implementation this value chose.
+    pub fn backend(&self) -> Implementation {
+        by_key_size!(&self.0, c => c.backend())
+    }
+}
+
+impl core::fmt::Debug for AesCtr {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("AesCtr")
+            .field("key_size", &self.key_size())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symmetric::aes::arch::portable::ttable;
+    use crate::symmetric::aes::testing::Rng;
+    use crate::symmetric::ctr::Ctr;
+
     /// The dispatching type against the generic mode over the portable
     /// cipher, on random lengths fed in random pieces, so the fused
     /// backends and the partial block state machine are both hit.
@@ -271,23 +518,101 @@ mod tests {
         let ctr = Aes128Ctr::new(&[0u8; 16], &[0u8; 16]);
         assert_eq!(
             ctr.is_accelerated(),
-            accel::aesni::ctr_supported() || accel::vaes::supported()
+            accel::aesni::ctr_supported()
+                || accel::vaes::supported()
+                || accel::vaes512::supported()
         );
     }
 
+    #[test]
+    fn constant_time_construction_needs_the_counter_kernels() {
+        let ctr = Aes128Ctr::try_new_constant_time(&[0u8; 16], &[0u8; 16]);
+        let expected = accel::aesni::ctr_supported()
+            || accel::vaes::supported()
+            || accel::vaes512::supported()
+            || accel::vpaes::supported()
+            || cfg!(feature = "constant-time");
+        assert_eq!(ctr.is_ok(), expected);
+        if let Ok(ctr) = ctr {
+            assert!(ctr.backend().is_constant_time());
+        }
+    }
+
+    #[test]
+    fn constant_time_construction_replaces_only_the_t_tables() {
+        let ctr = Aes128Ctr::new_constant_time(&[0u8; 16], &[0u8; 16]);
+        let chosen = Aes128Ctr::new(&[0u8; 16], &[0u8; 16]).backend();
+        if chosen.is_constant_time() {
+            assert_eq!(ctr.backend(), chosen);
+        } else {
+            assert_eq!(ctr.backend(), Implementation::Bitsliced);
+        }
+    }
+
     #[test]
     fn picks_the_widest_implementation_available() {
         let ctr = Aes256Ctr::new(&[0u8; 32], &[0u8; 16]);
-        let expected = if accel::vaes::supported() {
+        let expected = if accel::vaes512::supported() {
+            "wide"
+        } else if accel::vaes::supported() {
             "vector"
         } else if accel::aesni::ctr_supported() {
             "accelerated"
+        } else if accel::vpaes::supported() {
+            "simd"
         } else {
             "portable"
         };
         assert_eq!(ctr.implementation(), expected);
     }
 
+    #[test]
+    fn report_names_what_dispatch_chose() {
+        let ctr = Aes128Ctr::new(&[0u8; 16], &[0u8; 16]);
+        assert_eq!(dispatch_report().chosen(), Some(ctr.backend()));
+    }
+
+    /// The length picks the key size, and the stream is then exactly
+    /// the sized type's, partial blocks included.
+    #[test]
+    fn any_key_size_matches_the_sized_type() {
+        let iv = [0x42u8; 16];
+        let mut rng = Rng(0x5eed_0000_aaaa_5555);
+        for len in [16, 24, 32] {
+            let mut key = [0u8; 32];
+            rng.fill(&mut key);
+            let key = &key[..len];
+
+            let mut any = [0xa5u8; 77];
+            let mut ctr = AesCtr::try_new(key, &iv).unwrap();
+            assert_eq!(ctr.key_size(), len);
+            ctr.apply_keystream(&mut any[..5]);
+            ctr.apply_keystream(&mut any[5..]);
+
+            let mut sized = [0xa5u8; 77];
+            match len {
+                16 => Aes128Ctr::try_new(key, &iv)
+                    .unwrap()
+                    .apply_keystream(&mut sized),
+                24 => Aes192Ctr::try_new(key, &iv)
+                    .unwrap()
+                    .apply_keystream(&mut sized),
+                _ => Aes256Ctr::try_new(key, &iv)
+                    .unwrap()
+                    .apply_keystream(&mut sized),
+            }
+            assert_eq!(any, sized, "{len} byte key");
+        }
+        assert!(matches!(
+            AesCtr::try_new(&[0u8; 20], &iv),
+            Err(CtrInitError::Key(InvalidKeyLength { got: 20 }))
+        ));
+        assert!(matches!(
+            AesCtr::try_new(&[0u8; 16], &iv[..8]),
+            Err(CtrInitError::Iv(_))
+        ));
+    }
+
     #[test]
     fn resumes_across_a_partial_block() {
         let key = [0x2bu8; 24];
@@ -367,4 +692,96 @@ mod tests {
         let ctr = Aes128Ctr::new(&[0xab; 16], &[0xcd; 16]);
         assert_eq!(format!("{ctr:?}"), "Aes128Ctr { .. }");
     }
+
+    impl RandomSource for Rng {
+        fn fill(&mut self, buf: &mut [u8]) -> Result<(), RandError> {
+            Rng::fill(self, buf);
+            Ok(())
+        }
+    }
+
+    /// The returned IV is the one the stream started at, and it is
+    /// what the source produced.
+    #[test]
+    fn random_ivs_come_from_the_source() {
+        let key = [0x2bu8; 32];
+        let (mut ours, iv) =
+            Aes256Ctr::with_random_iv_from(&key, &mut Rng(7)).unwrap();
+        let mut expected = [0u8; BLOCK_SIZE];
+        Rng(7).fill(&mut expected);
+        assert_eq!(iv, expected);
+
+        let mut a = [0u8; 100];
+        let mut b = [0u8; 100];
+        ours.apply_keystream(&mut a);
+        Aes256Ctr::new(&key, &iv).apply_keystream(&mut b);
+        assert_eq!(a, b);
+    }
+
+    /// The run-time sized type draws its IV the same way, and says
+    /// whether the key or the source was at fault.
+    #[test]
+    fn any_size_random_ivs_report_the_source() {
+        struct Refusing;
+        impl RandomSource for Refusing {
+            fn fill(&mut self, _: &mut [u8]) -> Result<(), RandError> {
+                Err(RandError::from_raw_os_error(5))
+            }
+        }
+
+        let (_, iv) = AesCtr::with_random_iv_from(&[0u8; 24], &mut Rng(7))
+            .unwrap();
+        let mut expected = [0u8; BLOCK_SIZE];
+        Rng(7).fill(&mut expected);
+        assert_eq!(iv, expected);
+        assert!(matches!(
+            AesCtr::with_random_iv_from(&[0u8; 16], &mut Refusing),
+            Err(CtrInitError::Rand(e)) if e.raw_os_error() == 5
+        ));
+        assert!(matches!(
+            AesCtr::with_random_iv_from(&[0u8; 20], &mut Rng(7)),
+            Err(CtrInitError::Key(InvalidKeyLength { got: 20 }))
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn random_ivs_differ_and_decrypt() {
+        let key = [0x2bu8; 16];
+        let (mut enc, iv) = Aes128Ctr::with_random_iv(&key);
+        let (_, other) = Aes128Ctr::with_random_iv(&key);
+        assert_ne!(iv, other);
+
+        let mut data = *b"attack at dawn";
+        enc.apply_keystream(&mut data);
+        Aes128Ctr::new(&key, &iv).apply_keystream(&mut data);
+        assert_eq!(&data, b"attack at dawn");
+
+        let (_, iv) = AesCtr::with_random_iv(&[0u8; 24]).unwrap();
+        assert_ne!(iv, other);
+        assert_eq!(
+            AesCtr::with_random_iv(&[0u8; 20]).unwrap_err(),
+            CtrInitError::Key(InvalidKeyLength { got: 20 })
+        );
+    }
+
+    /// Seeking agrees with the generic mode's seek, including across
+    /// the counter's wrap.
+    #[test]
+    fn seek_matches_the_generic_mode() {
+        let key = [0x2bu8; 16];
+        let iv = [0xff; 16];
+        let mut ours = Aes128Ctr::new(&key, &iv);
+        let mut theirs =
+            Ctr::try_new(ttable::Aes128Enc::new(&key), &iv).unwrap();
+        for pos in [40u64, 0, 17, 1 << 40, 5, (1 << 33) + 9] {
+            ours.seek(pos);
+            theirs.seek(pos);
+            let mut a = [0u8; 70];
+            let mut b = [0u8; 70];
+            ours.apply_keystream(&mut a);
+            theirs.apply_keystream(&mut b);
+            assert_eq!(a, b, "position {pos}");
+        }
+    }
 }
@@ -0,0 +1,246 @@
+//! Which implementation dispatch chose, and why.
+//!
+//! [`Implementation`] names one exactly, where the dispatching types'
+//! `implementation()` gives only the tier. [`Report`] walks the same
+//! decisions dispatch makes and records, for every implementation it
+//! would try, whether it was chosen and if not what stood in its way.
+
+use core::fmt;
+
+use crate::cpu::{Feature, Features, UnsupportedCpu};
+
+/// One AES implementation, as dispatch would choose it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Implementation {
+    /// `arch::x86_64::vaes512`: the AES round instructions on 512 bit
+    /// registers.
+    Vaes512,
+    /// `arch::x86_64::vaes`: the AES round instructions on 256 bit
+    /// registers.
+    Vaes,
+    /// `arch::x86_64::aesni`, or on 32-bit x86 `arch::x86::aesni`: the AES
+    /// round instructions.
+    AesNi,
+    /// `arch::aarch64::armv8`: the ARMv8 Cryptographic Extension.
+    Armv8,
+    /// `arch::x86_64::vpaes`: table lookups by SSSE3's byte shuffle,
+    /// constant time without the AES instructions.
+    Vpaes,
+    /// `arch::portable::ttable`: table lookups, whose timing depends on
+    /// the key.
+    TTable,
+    /// `arch::portable::bitsliced`: a fixsliced circuit, constant time
+    /// without help from the CPU.
+    Bitsliced,
+}
+
+impl Implementation {
+    /// A short, stable name, as used in reports.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Implementation::Vaes512 => "vaes512",
+            Implementation::Vaes => "vaes",
+            Implementation::AesNi => "aes-ni",
+            Implementation::Armv8 => "armv8",
+            Implementation::Vpaes => "vpaes",
+            Implementation::TTable => "t-table",
+            Implementation::Bitsliced => "bitsliced",
+        }
+    }
+
+    /// The dispatch tier: `"wide"`, `"vector"`, `"accelerated"`,
+    /// `"simd"` or `"portable"`.
+    pub const fn tier(self) -> &'static str {
+        match self {
+            Implementation::Vaes512 => "wide",
+            Implementation::Vaes => "vector",
+            Implementation::AesNi | Implementation::Armv8 => "accelerated",
+            Implementation::Vpaes => "simd",
+            Implementation::TTable | Implementation::Bitsliced => "portable",
+        }
+    }
+
+    /// Whether this implementation uses AES instructions.
+    pub const fn is_accelerated(self) -> bool {
+        !matches!(
+            self,
+            Implementation::Vpaes
+                | Implementation::TTable
+                | Implementation::Bitsliced
+        )
+    }
+
+    /// Whether its timing is independent of the key and data.
+    pub const fn is_constant_time(self) -> bool {
+        !matches!(self, Implementation::TTable)
+    }
+}
+
+impl fmt::Display for Implementation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// What dispatch made of one implementation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Verdict {
+    /// This is the one dispatch uses.
+    Chosen,
+    /// It would have run, but a wider implementation was chosen first.
+    NotNeeded,
+    /// The CPU cannot run it; the error names what it lacks.
+    Unsupported(UnsupportedCpu),
+    /// The `constant-time` feature removed it from dispatch.
+    Refused,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Chosen => f.write_str("chosen"),
+            Verdict::NotNeeded => f.write_str("not needed"),
+            Verdict::Unsupported(e) => write!(f, "skipped, {e}"),
+            Verdict::Refused => f.write_str("refused, not constant-time"),
+        }
+    }
+}
+
+/// One implementation dispatch considered, and what it decided.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decision {
+    implementation: Implementation,
+    verdict: Verdict,
+}
+
+impl Decision {
+    /// The implementation considered.
+    pub fn implementation(&self) -> Implementation {
+        self.implementation
+    }
+
+    /// What dispatch made of it.
+    pub fn verdict(&self) -> Verdict {
+        self.verdict
+    }
+}
+
+/// Every decision dispatch makes, in the order it makes them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    features: Features,
+    decisions: Vec<Decision>,
+}
+
+impl Report {
+    /// Walk `tiers`, widest first, then the portable cipher dispatch
+    /// falls back to, deciding each exactly as dispatch does.
+    pub(super) fn walk(tiers: &[(Implementation, &[Feature])]) -> Self {
+        let portable = (super::fallback::IMPLEMENTATION, &[][..]);
+        let mut chosen = false;
+        let decisions = tiers
+            .iter()
+            .chain([&portable])
+            .map(|&(implementation, required)| {
+                let missing = UnsupportedCpu::lacking(required);
+                let verdict = if chosen {
+                    Verdict::NotNeeded
+                } else if cfg!(feature = "constant-time")
+                    && !implementation.is_constant_time()
+                {
+                    Verdict::Refused
+                } else if missing.missing().next().is_some() {
+                    Verdict::Unsupported(missing)
+                } else {
+                    chosen = true;
+                    Verdict::Chosen
+                };
+                Decision { implementation, verdict }
+            })
+            .collect();
+        Self { features: Features::detect(), decisions }
+    }
+
+    /// What the CPU offered when the report was made.
+    pub fn features(&self) -> Features {
+        self.features
+    }
+
+    /// Each implementation considered, widest first.
+    pub fn decisions(&self) -> &[Decision] {
+        &self.decisions
+    }
+
+    /// The implementation dispatch uses, or `None` if it has none and
+    /// construction would panic.
+    pub fn chosen(&self) -> Option<Implementation> {
+        self.decisions
+            .iter()
+            .find(|d| d.verdict == Verdict::Chosen)
+            .map(|d| d.implementation)
+    }
+}
+
+/// One line: each implementation and its verdict, separated by `; `.
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, d) in self.decisions.iter().enumerate() {
+            let sep = if i == 0 { "" } else { "; " };
+            write!(f, "{sep}{} {}", d.implementation, d.verdict)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Only the first runnable implementation is chosen, and everything
+    /// after it is reported as not needed, never as unsupported.
+    #[test]
+    fn chooses_the_first_runnable_tier() {
+        let report = Report::walk(&[
+            (Implementation::Vaes, &[Feature::Vaes]),
+            (Implementation::AesNi, &[]),
+        ]);
+        let verdicts: Vec<_> =
+            report.decisions().iter().map(|d| d.verdict()).collect();
+        if Feature::Vaes.detected() {
+            assert_eq!(
+                verdicts,
+                [Verdict::Chosen, Verdict::NotNeeded, Verdict::NotNeeded]
+            );
+            assert_eq!(report.chosen(), Some(Implementation::Vaes));
+        } else {
+            assert_eq!(verdicts[1..], [Verdict::Chosen, Verdict::NotNeeded]);
+            assert_eq!(report.chosen(), Some(Implementation::AesNi));
+            assert_eq!(
+                report.to_string(),
+                format!(
+                    "vaes skipped, CPU lacks vaes; aes-ni chosen; \
+                     {} not needed",
+                    super::super::fallback::IMPLEMENTATION
+                )
+            );
+        }
+    }
+
+    /// The T-table cipher, or with the `constant-time` feature the
+    /// bitsliced one, which is never refused.
+    #[test]
+    fn the_portable_cipher_ends_every_walk() {
+        let report = Report::walk(&[]);
+        assert_eq!(report.decisions().len(), 1);
+        let last = report.decisions()[0];
+        let expected = if cfg!(feature = "constant-time") {
+            Implementation::Bitsliced
+        } else {
+            Implementation::TTable
+        };
+        assert_eq!(last.implementation(), expected);
+        assert_eq!(last.verdict(), Verdict::Chosen);
+    }
+}
@@ -0,0 +1,218 @@
+//! The AES known-answer tests behind [`crate::self_test`].
+//!
+//! Each implementation the CPU can run is tested at every key size, on
+//! its pinned type, so that no test goes through the dispatch it guards.
+
+use super::arch::portable::{bitsliced, ttable};
+use super::{accel, Implementation, BLOCK_SIZE};
+use crate::self_test::{unhex, vectors, SelfTestError};
+
+/// The length of the SP 800-38A message.
+const MESSAGE: usize = 4 * BLOCK_SIZE;
+
+/// How many copies of the message each test runs through.
+///
+/// Sixty-eight blocks run the 512 bit loop twice, every other
+/// implementation's widest loop at least four times, and still leave each
+/// a tail to finish.
+const COPIES: usize = 17;
+
+const PLAINTEXT: [u8; MESSAGE] = unhex(vectors::PLAINTEXT);
+const IV: [u8; BLOCK_SIZE] = unhex(vectors::CTR_IV);
+
+/// One key size's key and answers.
+#[derive(Clone, Copy)]
+struct Vectors<const K: usize> {
+    algorithm: &'static str,
+    key: [u8; K],
+    ecb: [u8; MESSAGE],
+    ctr: [u8; MESSAGE],
+}
+
+const AES_128: Vectors<16> = Vectors {
+    algorithm: "AES-128",
+    key: unhex(vectors::KEY_128),
+    ecb: unhex(vectors::ECB_128),
+    ctr: unhex(vectors::CTR_128),
+};
+
+const AES_192: Vectors<24> = Vectors {
+    algorithm: "AES-192",
+    key: unhex(vectors::KEY_192),
+    ecb: unhex(vectors::ECB_192),
+    ctr: unhex(vectors::CTR_192),
+};
+
+const AES_256: Vectors<32> = Vectors {
+    algorithm: "AES-256",
+    key: unhex(vectors::KEY_256),
+    ecb: unhex(vectors::ECB_256),
+    ctr: unhex(vectors::CTR_256),
+};
+
+/// `COPIES` copies of a message, end to end.
+fn repeated(message: &[u8; MESSAGE]) -> [u8; MESSAGE * COPIES] {
+    let mut out = [0u8; MESSAGE * COPIES];
+    for chunk in out.chunks_exact_mut(MESSAGE) {
+        chunk.copy_from_slice(message);
+    }
+    out
+}
+
+fn first_block(message: &[u8; MESSAGE]) -> [u8; BLOCK_SIZE] {
+    let mut block = [0u8; BLOCK_SIZE];
+    block.copy_from_slice(&message[..BLOCK_SIZE]);
+    block
+}
+
+/// Test one implementation at one key size, as an expression of type
+/// `Result<(), SelfTestError>`.
+macro_rules! known_answers {
+    ($enc:ty, $dec:ty, $vectors:expr, $implementation:expr, $ctr:expr) => {
+        'test: {
+            let v = $vectors;
+            let name = $implementation.name();
+            let fail = |test| Err(SelfTestError::new(v.algorithm, name, test));
+            let enc = <$enc>::new(&v.key);
+
+            let mut data = repeated(&PLAINTEXT);
+            if enc.encrypt(&mut data) != data.len() || data != repeated(&v.ecb)
+            {
+                break 'test fail("ECB encrypt");
+            }
+            let mut block = first_block(&PLAINTEXT);
+            enc.encrypt_block(&mut block);
+            if block[..] != v.ecb[..BLOCK_SIZE] {
+                break 'test fail("ECB encrypt block");
+            }
+
+            let decryptors = [
+                (<$dec>::new(&v.key), "ECB decrypt"),
+                (<$dec>::from(&enc), "ECB decrypt, inverted schedule"),
+            ];
+            for (dec, test) in decryptors {
+                let mut data = repeated(&v.ecb);
+                if dec.decrypt(&mut data) != data.len()
+                    || data != repeated(&PLAINTEXT)
+                {
+                    break 'test fail(test);
+                }
+                let mut block = first_block(&v.ecb);
+                dec.decrypt_block(&mut block);
+                if block[..] != PLAINTEXT[..BLOCK_SIZE] {
+                    break 'test fail(test);
+                }
+            }
+
+            if $ctr {
+                // The vector covers the first four blocks. Past them the
+                // answer is the message under the encryptions of the
+                // counter values that follow, and the single block
+                // encryption those come from has just passed.
+                let mut expected = repeated(&PLAINTEXT);
+                let mut counter = IV;
+                for chunk in expected.chunks_exact_mut(BLOCK_SIZE) {
+                    let mut keystream = counter;
+                    enc.encrypt_block(&mut keystream);
+                    for (d, k) in chunk.iter_mut().zip(keystream) {
+                        *d ^= k;
+                    }
+                    counter = u128::from_be_bytes(counter)
+                        .wrapping_add(1)
+                        .to_be_bytes();
+                }
+                let mut data = repeated(&PLAINTEXT);
+                let mut ours = IV;
+                if expected[..MESSAGE] != v.ctr
+                    || enc.ctr(&mut ours, &mut data) != data.len()
+                    || data != expected
+                    || ours != counter
+                {
+                    break 'test fail("CTR");
+                }
+            }
+            Ok(())
+        }
+    };
+}
+
+/// Test one implementation at every key size.
+macro_rules! implementation {
+    ($($module:ident)::+, $implementation:expr, $ctr:expr) => {
+        known_answers!(
+            $($module)::+::Aes128Enc, $($module)::+::Aes128Dec, AES_128,
+            $implementation, $ctr
+        )?;
+        known_answers!(
+            $($module)::+::Aes192Enc, $($module)::+::Aes192Dec, AES_192,
+            $implementation, $ctr
+        )?;
+        known_answers!(
+            $($module)::+::Aes256Enc, $($module)::+::Aes256Dec, AES_256,
+            $implementation, $ctr
+        )?;
+    };
+}
+
+/// Test every implementation this CPU can run.
+///
+/// Both portable ciphers are tested even where dispatch never falls back
+/// to them: they cost little, and they are still reachable through
+/// [`super::arch`].
+// A tier the build rules out has a constructor that never returns, and
+// the compiler can see that, and that nothing after it is used.
+#[allow(unreachable_code, unused_variables)]
+pub(crate) fn run() -> Result<(), SelfTestError> {
+    if accel::vaes512::supported() {
+        implementation!(accel::vaes512, accel::WIDE, true);
+    }
+    if accel::vaes::supported() {
+        implementation!(accel::vaes, accel::VECTOR, true);
+    }
+    if accel::aesni::supported() {
+        let ctr = accel::aesni::ctr_supported();
+        implementation!(accel::aesni, accel::ACCELERATED, ctr);
+    }
+    if accel::vpaes::supported() {
+        implementation!(accel::vpaes, accel::SIMD, true);
+    }
+    implementation!(ttable, Implementation::TTable, true);
+    implementation!(bitsliced, Implementation::Bitsliced, true);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_implementation_passes() {
+        assert_eq!(run(), Ok(()));
+    }
+
+    /// A wrong answer is noticed, and named by the test that saw it.
+    #[test]
+    fn wrong_answers_are_caught() {
+        let mut ecb = AES_192;
+        ecb.ecb[MESSAGE - 1] ^= 0x01;
+        let mut ctr = AES_192;
+        ctr.ctr[MESSAGE - 1] ^= 0x80;
+        for (v, test) in [(ecb, "ECB encrypt"), (ctr, "CTR")] {
+            let result: Result<(), SelfTestError> = known_answers!(
+                ttable::Aes192Enc, ttable::Aes192Dec, v,
+                Implementation::TTable, true
+            );
+            let want = SelfTestError::new("AES-192", "t-table", test);
+            assert_eq!(result, Err(want));
+        }
+
+        // Without the counter kernels, only ECB is checked.
+        let mut v = AES_128;
+        v.ctr[0] ^= 1;
+        let result: Result<(), SelfTestError> = known_answers!(
+            ttable::Aes128Enc, ttable::Aes128Dec, v,
+            Implementation::TTable, false
+        );
+        assert_eq!(result, Ok(()));
+    }
+}
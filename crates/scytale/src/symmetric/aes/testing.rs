@@ -0,0 +1,55 @@
+//! What the implementations' unit tests share.
+
+/// xorshift64*, so a divergence is reproducible from the seed.
+pub(crate) struct Rng(pub(crate) u64);
+
+impl Rng {
+    pub(crate) fn next(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0 = x;
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    pub(crate) fn fill(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(8) {
+            let bytes = self.next().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+}
+
+/// Assert that inverting an encryption schedule gives the same `rk` as
+/// the decryption type expands itself, on random keys of every size.
+///
+/// Expanded inside an implementation's tests, where `Aes128Enc` and the
+/// rest name its own types and their `rk` field is visible. A target whose
+/// implementations all share one schedule has no use for it.
+#[allow(unused_macros)]
+macro_rules! assert_inverting_matches_expanding {
+    () => {{
+        let mut rng = $crate::symmetric::aes::testing::Rng(0x0f1e_2d3c_4b5a_6978);
+        $crate::symmetric::aes::testing::assert_inverting_matches_expanding!(
+            rng, Aes128Enc, Aes128Dec, 16
+        );
+        $crate::symmetric::aes::testing::assert_inverting_matches_expanding!(
+            rng, Aes192Enc, Aes192Dec, 24
+        );
+        $crate::symmetric::aes::testing::assert_inverting_matches_expanding!(
+            rng, Aes256Enc, Aes256Dec, 32
+        );
+    }};
+    ($rng:ident, $enc:ident, $dec:ident, $len:expr) => {
+        for _ in 0..16 {
+            let mut key = [0u8; $len];
+            $rng.fill(&mut key);
+            let inverted = $dec::from(&$enc::new(&key));
+            assert_eq!(inverted.rk, $dec::new(&key).rk);
+        }
+    };
+}
+
+#[allow(unused_imports)]
+pub(crate) use assert_inverting_matches_expanding;
@@ -6,6 +6,9 @@
 
 use core::fmt;
 
+use crate::cpu::UnsupportedCpu;
+use crate::self_test::SelfTestError;
+
 /// A key was rejected because its length is not one the cipher accepts.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct InvalidKeyLength {
@@ -21,15 +24,78 @@ impl fmt::Display for InvalidKeyLength {
 
 impl core::error::Error for InvalidKeyLength {}
 
+/// Why an implementation could not be keyed.
+///
+/// A type naming one exact implementation can be refused a key for either
+/// of two reasons; this says which. A dispatching type can also be refused
+/// one because the library is in its [self test](crate::self_test) error
+/// state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyInitError {
+    /// The key length is not one the cipher accepts.
+    Key(InvalidKeyLength),
+    /// The CPU cannot run this implementation.
+    Cpu(UnsupportedCpu),
+    /// The library failed its known-answer tests.
+    SelfTest(SelfTestError),
+}
+
+impl fmt::Display for KeyInitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Key(_) => f.write_str("key setup failed: wrong key length"),
+            Self::Cpu(_) => f.write_str("key setup failed: unsupported CPU"),
+            Self::SelfTest(_) => f.write_str("key setup failed: self test failure"),
+        }
+    }
+}
+
+// The cause's own message is left to source(), so a reporter walking the
+// chain prints each one once.
+impl core::error::Error for KeyInitError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Key(e) => Some(e),
+            Self::Cpu(e) => Some(e),
+            Self::SelfTest(e) => Some(e),
+        }
+    }
+}
+
+impl From<InvalidKeyLength> for KeyInitError {
+    fn from(e: InvalidKeyLength) -> Self {
+        Self::Key(e)
+    }
+}
+
+impl From<UnsupportedCpu> for KeyInitError {
+    fn from(e: UnsupportedCpu) -> Self {
+        Self::Cpu(e)
+    }
+}
+
+impl From<SelfTestError> for KeyInitError {
+    fn from(e: SelfTestError) -> Self {
+        Self::SelfTest(e)
+    }
+}
+
 /// Something that can be keyed from a byte string of run-time length.
 ///
 /// Key lengths are not always fixed: Blowfish accepts anything from 32 to
 /// 448 bits, so the interface cannot assume a compile-time size. Ciphers
 /// with a single fixed key size also offer an inherent `new` taking a
 /// fixed-size array, where a wrong length is a compile error instead.
+///
+/// Most implementations can only refuse a key for its length, and say so
+/// with [`InvalidKeyLength`]. Those pinned to CPU instructions can also
+/// refuse for want of them, and use [`KeyInitError`].
 pub trait KeyInit: Sized {
+    /// Why a key was refused.
+    type Error: core::error::Error + From<InvalidKeyLength>;
+
     /// Key the cipher, failing if it does not accept this key length.
-    fn try_new(key: &[u8]) -> Result<Self, InvalidKeyLength>;
+    fn try_new(key: &[u8]) -> Result<Self, Self::Error>;
 }
 
 /// A block cipher's encryption direction.
@@ -30,6 +30,8 @@
 
 use zeroize::Zeroize;
 
+use crate::rand::{RandError, RandomSource};
+use crate::self_test::SelfTestError;
 use crate::symmetric::block_cipher::{BlockEncrypt, InvalidKeyLength};
 
 use core::fmt;
@@ -52,13 +54,19 @@ impl core::error::Error for InvalidIvLength {}
 /// Why a CTR construction was rejected.
 ///
 /// Constructors that take both a key and an IV can fail on either; this
-/// says which one was wrong.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// says which one was wrong. The dispatching AES modes can also fail
+/// because the library is in its [self test](crate::self_test) error
+/// state, or when drawing a random IV, because the source failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum CtrInitError {
     /// The key length is not one the cipher accepts.
     Key(InvalidKeyLength),
     /// The IV length is not the cipher's block size.
     Iv(InvalidIvLength),
+    /// The library failed its known-answer tests.
+    SelfTest(SelfTestError),
+    /// The random source could not supply an IV.
+    Rand(RandError),
 }
 
 impl fmt::Display for CtrInitError {
@@ -66,6 +74,8 @@ impl fmt::Display for CtrInitError {
         match self {
             Self::Key(e) => e.fmt(f),
             Self::Iv(e) => e.fmt(f),
+            Self::SelfTest(e) => e.fmt(f),
+            Self::Rand(e) => e.fmt(f),
         }
     }
 }
@@ -75,6 +85,8 @@ impl core::error::Error for CtrInitError {
         match self {
             Self::Key(e) => Some(e),
             Self::Iv(e) => Some(e),
+            Self::SelfTest(e) => Some(e),
+            Self::Rand(e) => Some(e),
         }
     }
 }
@@ -91,6 +103,18 @@ impl From<InvalidIvLength> for CtrInitError {
     }
 }
 
+impl From<SelfTestError> for CtrInitError {
+    fn from(e: SelfTestError) -> Self {
+        Self::SelfTest(e)
+    }
+}
+
+impl From<RandError> for CtrInitError {
+    fn from(e: RandError) -> Self {
+        Self::Rand(e)
+    }
+}
+
 /// Advance a big-endian counter by one, wrapping at its full width.
 ///
 /// The early exit leaks how far the carry propagated, but the counter is
@@ -105,6 +129,21 @@ fn increment(counter: &mut [u8]) {
     }
 }
 
+/// Advance a big-endian counter by `blocks`, wrapping at its full width.
+fn advance(counter: &mut [u8], blocks: u64) {
+    // The carry holds what is still to be added, shifted down to the
+    // byte being worked on.
+    let mut carry = u128::from(blocks);
+    for byte in counter.iter_mut().rev() {
+        if carry == 0 {
+            return;
+        }
+        let sum = u128::from(*byte) + (carry & 0xff);
+        *byte = sum as u8;
+        carry = (carry >> 8) + (sum >> 8);
+    }
+}
+
 /// XOR `keystream` into `data`. The lengths must match.
 fn xor_into(data: &mut [u8], keystream: &[u8]) {
     for (d, k) in data.iter_mut().zip(keystream) {
@@ -123,6 +162,8 @@ fn xor_into(data: &mut [u8], keystream: &[u8]) {
 /// for the next call.
 pub struct Ctr<C: BlockEncrypt> {
     cipher: C,
+    /// The initial counter value, kept so the stream can seek.
+    iv: Vec<u8>,
     /// The next block's counter value, big endian, one block wide.
     counter: Vec<u8>,
     /// Keystream staging, sized to the cipher's full parallel width so
@@ -154,6 +195,7 @@ impl<C: BlockEncrypt> Ctr<C> {
         let width = C::PARALLEL_BLOCKS.max(1);
         Ok(Self {
             cipher,
+            iv: iv.to_vec(),
             counter: iv.to_vec(),
             scratch: vec![0u8; width * C::BLOCK_SIZE],
             keystream: vec![0u8; C::BLOCK_SIZE],
@@ -161,6 +203,39 @@ impl<C: BlockEncrypt> Ctr<C> {
         })
     }
 
+    /// Wrap `cipher`, starting the counter at a fresh random value from
+    /// the operating system, which is returned alongside to send with
+    /// the ciphertext.
+    ///
+    /// Random starting points only stay clear of one another when the
+    /// block is wide: over a 64-bit block, collisions become likely after
+    /// a few billion messages under one key.
+    ///
+    /// # Panics
+    ///
+    /// If the operating system cannot supply random bytes.
+    #[cfg(target_os = "linux")]
+    pub fn with_random_iv(cipher: C) -> (Self, Vec<u8>) {
+        let mut os = crate::rand::OsRandom;
+        match Self::with_random_iv_from(cipher, &mut os) {
+            Ok(pair) => pair,
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    /// As [`Self::with_random_iv`], drawing the counter from `source`.
+    pub fn with_random_iv_from(
+        cipher: C,
+        source: &mut impl RandomSource,
+    ) -> Result<(Self, Vec<u8>), RandError> {
+        let mut iv = vec![0u8; C::BLOCK_SIZE];
+        source.fill(&mut iv)?;
+        match Self::try_new(cipher, &iv) {
+            Ok(this) => Ok((this, iv)),
+            Err(_) => unreachable!("the IV is one block"),
+        }
+    }
+
     /// XOR the keystream into `data`, advancing the stream.
     ///
     /// Encrypting and decrypting are the same operation. Any length is
@@ -200,15 +275,38 @@ impl<C: BlockEncrypt> Ctr<C> {
         // A trailing partial block takes what it needs from one fresh
         // keystream block and leaves the rest buffered.
         if !data.is_empty() {
-            self.keystream.copy_from_slice(&self.counter);
-            increment(&mut self.counter);
-            let consumed = self.cipher.encrypt(&mut self.keystream);
-            assert_eq!(consumed, block, "cipher did not consume a block");
+            self.next_keystream_block();
             let take = data.len();
             xor_into(data, &self.keystream[..take]);
             self.used = take;
         }
     }
+
+    /// Move the stream to `pos` bytes from its start.
+    ///
+    /// The next byte of keystream is the one that byte `pos` of the
+    /// message was or will be XORed with, whatever has been applied
+    /// since construction. Past the end of the counter space the
+    /// counter wraps, as it does when running there.
+    pub fn seek(&mut self, pos: u64) {
+        let block = C::BLOCK_SIZE as u64;
+        self.counter.copy_from_slice(&self.iv);
+        advance(&mut self.counter, pos / block);
+        self.used = C::BLOCK_SIZE;
+        let skip = (pos % block) as usize;
+        if skip > 0 {
+            self.next_keystream_block();
+            self.used = skip;
+        }
+    }
+
+    /// Encrypt the counter into the keystream buffer and step it on.
+    fn next_keystream_block(&mut self) {
+        self.keystream.copy_from_slice(&self.counter);
+        increment(&mut self.counter);
+        let consumed = self.cipher.encrypt(&mut self.keystream);
+        assert_eq!(consumed, C::BLOCK_SIZE, "cipher did not consume a block");
+    }
 }
 
 impl<C: BlockEncrypt> Drop for Ctr<C> {
@@ -216,6 +314,7 @@ impl<C: BlockEncrypt> Drop for Ctr<C> {
         // The scratch and keystream buffers hold keystream, which is as
         // sensitive as the key while the counter position is knowable.
         // The cipher wipes its own schedule.
+        self.iv.zeroize();
         self.counter.zeroize();
         self.scratch.zeroize();
         self.keystream.zeroize();
@@ -488,4 +587,55 @@ mod tests {
             .expect("block-size IV");
         assert_eq!(format!("{ctr:?}"), "Ctr { .. }");
     }
+
+    /// A random IV is one block of whatever is wrapped, and the stream
+    /// starts there.
+    #[test]
+    fn random_ivs_are_one_wrapped_block() {
+        struct Sevens;
+        impl RandomSource for Sevens {
+            fn fill(&mut self, buf: &mut [u8]) -> Result<(), RandError> {
+                buf.fill(7);
+                Ok(())
+            }
+        }
+
+        let (mut ours, iv) =
+            Ctr::with_random_iv_from(stand(), &mut Sevens).unwrap();
+        assert_eq!(iv, [7u8; STAND_BLOCK]);
+        let mut a = [0u8; 20];
+        let mut b = [0u8; 20];
+        ours.apply_keystream(&mut a);
+        Ctr::try_new(stand(), &iv).unwrap().apply_keystream(&mut b);
+        assert_eq!(a, b);
+    }
+
+    /// Seeking lands where applying that many bytes would, from any
+    /// state, across the counter's wrap and within a block.
+    #[test]
+    fn seek_matches_skipping() {
+        let iv = [0xff; STAND_BLOCK];
+        let mut whole = [0u8; 100];
+        Ctr::try_new(stand(), &iv).unwrap().apply_keystream(&mut whole);
+
+        let mut ctr = Ctr::try_new(stand(), &iv).unwrap();
+        ctr.apply_keystream(&mut [0u8; 13]);
+        for pos in [0, 5, 8, 13, 64, 99, 3] {
+            ctr.seek(pos as u64);
+            let mut rest = vec![0u8; 100 - pos];
+            ctr.apply_keystream(&mut rest);
+            assert_eq!(rest, whole[pos..], "position {pos}");
+        }
+    }
+
+    #[test]
+    fn advance_carries_across_bytes() {
+        let mut counter = [0x00, 0x00, 0xff, 0xfe];
+        advance(&mut counter, 0x0103);
+        assert_eq!(counter, [0x00, 0x01, 0x01, 0x01]);
+
+        let mut counter = [0xff; 3];
+        advance(&mut counter, u64::MAX);
+        assert_eq!(counter, [0xff, 0xff, 0xfe], "wraps at the width");
+    }
 }
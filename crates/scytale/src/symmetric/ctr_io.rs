@@ -0,0 +1,620 @@
+//! [`std::io`] adapters that run counter mode over a stream.
+//!
+//! [`CtrReader`] decrypts, or encrypts, whatever it reads from the stream
+//! it wraps; [`CtrWriter`] does the same to whatever is written through
+//! it. Both drive any [`Keystream`], which covers the generic
+//! [`Ctr`] and the AES counter mode types, and both implement [`Seek`]
+//! where the wrapped stream does, moving the keystream along with it.
+//!
+//! Each keeps a buffer of a few kilobytes, a whole number of the
+//! keystream's parallel width, so small reads and writes are gathered
+//! into calls wide enough to keep every lane of the kernel busy. The
+//! buffer holds plaintext and is wiped when the adapter drops.
+//!
+//! The keystream starts at whatever position the wrapped stream is at
+//! when the adapter is made, so a file with a plaintext header can be
+//! wrapped after the header is read or written. Seeking is relative to
+//! the wrapped stream as a whole, and seeking before that starting
+//! position is an error.
+
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
+
+use zeroize::Zeroize;
+
+use crate::symmetric::aes::{Aes128Ctr, Aes192Ctr, Aes256Ctr, AesCtr};
+use crate::symmetric::block_cipher::BlockEncrypt;
+use crate::symmetric::ctr::Ctr;
+
+/// Roughly how many bytes the adapters buffer, before rounding to the
+/// keystream's width. The same as the standard library's buffered
+/// readers and writers.
+const BUFFER_TARGET: usize = 8 * 1024;
+
+/// A seekable keystream the adapters can drive.
+///
+/// Implemented for the generic [`Ctr`] over any block cipher, and for
+/// the AES counter mode types.
+pub trait Keystream {
+    /// The block length in bytes.
+    fn block_size(&self) -> usize;
+
+    /// How many blocks the implementation keeps in flight at once.
+    fn parallel_blocks(&self) -> usize;
+
+    /// XOR the keystream into `data`, advancing the stream.
+    fn apply_keystream(&mut self, data: &mut [u8]);
+
+    /// Move the stream to `pos` bytes from its start.
+    fn seek(&mut self, pos: u64);
+}
+
+impl<C: BlockEncrypt> Keystream for Ctr<C> {
+    fn block_size(&self) -> usize {
+        C::BLOCK_SIZE
+    }
+
+    fn parallel_blocks(&self) -> usize {
+        C::PARALLEL_BLOCKS
+    }
+
+    fn apply_keystream(&mut self, data: &mut [u8]) {
+        Ctr::apply_keystream(self, data);
+    }
+
+    fn seek(&mut self, pos: u64) {
+        Ctr::seek(self, pos);
+    }
+}
+
+macro_rules! keystream {
+    ($($name:ty),+) => {$(
+        impl Keystream for $name {
+            fn block_size(&self) -> usize {
+                <$name>::BLOCK_SIZE
+            }
+
+            fn parallel_blocks(&self) -> usize {
+                <$name>::parallel_blocks(self)
+            }
+
+            fn apply_keystream(&mut self, data: &mut [u8]) {
+                <$name>::apply_keystream(self, data);
+            }
+
+            fn seek(&mut self, pos: u64) {
+                <$name>::seek(self, pos);
+            }
+        }
+    )+};
+}
+
+keystream!(Aes128Ctr, Aes192Ctr, Aes256Ctr, AesCtr);
+
+/// A buffer a whole number of `stream`'s parallel width long, wiped on
+/// drop.
+struct Buffer(Vec<u8>);
+
+impl Buffer {
+    fn for_stream(stream: &impl Keystream) -> Self {
+        let width = stream.block_size() * stream.parallel_blocks().max(1);
+        Self(vec![0u8; (BUFFER_TARGET / width).max(1) * width])
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// Nothing can reach a [`CtrWriter`] after `into_inner` takes its stream.
+const TAKEN: &str = "the wrapped stream was taken";
+
+fn before_start() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "seek before the start of the keystream",
+    )
+}
+
+fn moved_underneath() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "wrapped stream is before the keystream's start",
+    )
+}
+
+/// Applies a keystream to everything read from `R`.
+pub struct CtrReader<R, K> {
+    inner: R,
+    stream: K,
+    buf: Buffer,
+    /// `buf[pos..filled]` has been through the keystream and not yet
+    /// been read.
+    pos: usize,
+    filled: usize,
+    /// How far into the keystream the next byte read is.
+    offset: u64,
+}
+
+impl<R: Read, K: Keystream> CtrReader<R, K> {
+    /// Read from `inner` through `stream`, starting the keystream at
+    /// `inner`'s current position.
+    pub fn new(inner: R, stream: K) -> Self {
+        let buf = Buffer::for_stream(&stream);
+        Self { inner, stream, buf, pos: 0, filled: 0, offset: 0 }
+    }
+
+    /// Borrow the wrapped stream.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Borrow the wrapped stream mutably. Reading from or seeking it
+    /// directly leaves the keystream out of step.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwrap, discarding anything buffered.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read, K: Keystream> Read for CtrReader<R, K> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        // A read at least as large as the buffer gains nothing from it,
+        // and is applied in place instead.
+        if self.pos == self.filled && out.len() >= self.buf.0.len() {
+            let n = self.inner.read(out)?;
+            self.stream.apply_keystream(&mut out[..n]);
+            self.offset += n as u64;
+            return Ok(n);
+        }
+        let available = self.fill_buf()?;
+        let n = available.len().min(out.len());
+        out[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: Read, K: Keystream> BufRead for CtrReader<R, K> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos == self.filled {
+            let n = self.inner.read(&mut self.buf.0)?;
+            self.stream.apply_keystream(&mut self.buf.0[..n]);
+            self.pos = 0;
+            self.filled = n;
+        }
+        Ok(&self.buf.0[self.pos..self.filled])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        let amount = amount.min(self.filled - self.pos);
+        self.pos += amount;
+        self.offset += amount as u64;
+    }
+}
+
+impl<R: Read + Seek, K: Keystream> Seek for CtrReader<R, K> {
+    fn seek(&mut self, from: SeekFrom) -> io::Result<u64> {
+        let was = self.inner.stream_position()?;
+        let unread = (self.filled - self.pos) as u64;
+        let here = was.checked_sub(unread).ok_or_else(moved_underneath)?;
+        let origin =
+            here.checked_sub(self.offset).ok_or_else(moved_underneath)?;
+
+        // Asking where the reader is need not cost it its buffer.
+        let from = match from {
+            SeekFrom::Current(0) => return Ok(here),
+            SeekFrom::Current(by) => SeekFrom::Start(
+                here.checked_add_signed(by).ok_or_else(before_start)?,
+            ),
+            from => from,
+        };
+        let to = self.inner.seek(from)?;
+        if to < origin {
+            self.inner.seek(SeekFrom::Start(was))?;
+            return Err(before_start());
+        }
+
+        self.offset = to - origin;
+        self.stream.seek(self.offset);
+        self.pos = 0;
+        self.filled = 0;
+        Ok(to)
+    }
+}
+
+impl<R, K> core::fmt::Debug for CtrReader<R, K> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        // The buffer holds plaintext.
+        f.write_str("CtrReader { .. }")
+    }
+}
+
+/// Applies a keystream to everything written to `W`.
+///
+/// Writes are gathered and go out when the buffer fills, on
+/// [`flush`](Write::flush), on a seek, and when the writer drops. Errors
+/// at drop are lost, so flush first where they matter.
+pub struct CtrWriter<W: Write, K: Keystream> {
+    /// `None` only once [`Self::into_inner`] has taken it, so that
+    /// dropping what is left has nothing to drain into.
+    inner: Option<W>,
+    stream: K,
+    buf: Buffer,
+    /// `buf[..written]` has gone out, `buf[written..sealed]` is through
+    /// the keystream and waiting to, and `buf[sealed..end]` is still as
+    /// it was written.
+    written: usize,
+    sealed: usize,
+    end: usize,
+    /// How far into the keystream the next byte written is.
+    offset: u64,
+}
+
+impl<W: Write, K: Keystream> CtrWriter<W, K> {
+    /// Write to `inner` through `stream`, starting the keystream at
+    /// `inner`'s current position.
+    pub fn new(inner: W, stream: K) -> Self {
+        let buf = Buffer::for_stream(&stream);
+        let inner = Some(inner);
+        Self { inner, stream, buf, written: 0, sealed: 0, end: 0, offset: 0 }
+    }
+
+    /// Borrow the wrapped stream.
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().expect(TAKEN)
+    }
+
+    /// Borrow the wrapped stream mutably. Writing to or seeking it
+    /// directly leaves the keystream out of step.
+    pub fn get_mut(&mut self) -> &mut W {
+        self.inner.as_mut().expect(TAKEN)
+    }
+
+    /// Write out anything buffered and unwrap.
+    ///
+    /// If writing fails, the error comes back with the writer, still
+    /// holding what it could not write, as [`io::BufWriter`] does.
+    pub fn into_inner(mut self) -> Result<W, IntoInnerError<Self>> {
+        match self.drain() {
+            Ok(()) => Ok(self.inner.take().expect(TAKEN)),
+            Err(error) => Err(IntoInnerError { writer: self, error }),
+        }
+    }
+
+    /// Apply the keystream to everything buffered and write it all out.
+    fn drain(&mut self) -> io::Result<()> {
+        let pending = &mut self.buf.0[self.sealed..self.end];
+        self.stream.apply_keystream(pending);
+        self.sealed = self.end;
+        let inner = self.inner.as_mut().expect(TAKEN);
+        while self.written < self.sealed {
+            match inner.write(&self.buf.0[self.written..self.sealed]) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => self.written += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        self.written = 0;
+        self.sealed = 0;
+        self.end = 0;
+        Ok(())
+    }
+}
+
+impl<W: Write, K: Keystream> Write for CtrWriter<W, K> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if self.end == self.buf.0.len() {
+            self.drain()?;
+        }
+        let n = data.len().min(self.buf.0.len() - self.end);
+        self.buf.0[self.end..self.end + n].copy_from_slice(&data[..n]);
+        self.end += n;
+        self.offset += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.drain()?;
+        self.get_mut().flush()
+    }
+}
+
+impl<W: Write + Seek, K: Keystream> Seek for CtrWriter<W, K> {
+    fn seek(&mut self, from: SeekFrom) -> io::Result<u64> {
+        self.drain()?;
+        let inner = self.inner.as_mut().expect(TAKEN);
+        let here = inner.stream_position()?;
+        let origin =
+            here.checked_sub(self.offset).ok_or_else(moved_underneath)?;
+
+        let to = inner.seek(from)?;
+        if to < origin {
+            inner.seek(SeekFrom::Start(here))?;
+            return Err(before_start());
+        }
+
+        self.offset = to - origin;
+        self.stream.seek(self.offset);
+        Ok(to)
+    }
+}
+
+impl<W: Write, K: Keystream> Drop for CtrWriter<W, K> {
+    fn drop(&mut self) {
+        // Unwinding past a failed write, the buffer may be what caused
+        // it; do not try again.
+        if self.inner.is_some() && !std::thread::panicking() {
+            let _ = self.drain();
+        }
+    }
+}
+
+impl<W: Write, K: Keystream> core::fmt::Debug for CtrWriter<W, K> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        // The buffer holds plaintext.
+        f.write_str("CtrWriter { .. }")
+    }
+}
+
+/// [`CtrWriter::into_inner`] could not write out what it had buffered.
+///
+/// Holds the writer, with the data still in it, alongside the error, so
+/// the caller can retry or recover the wrapped stream.
+#[derive(Debug)]
+pub struct IntoInnerError<W> {
+    writer: W,
+    error: io::Error,
+}
+
+impl<W> IntoInnerError<W> {
+    /// The error that stopped the write.
+    pub fn error(&self) -> &io::Error {
+        &self.error
+    }
+
+    /// Take back the writer, to retry or give up on.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Take the error, dropping the writer.
+    pub fn into_error(self) -> io::Error {
+        self.error
+    }
+
+    /// Take both the error and the writer.
+    pub fn into_parts(self) -> (io::Error, W) {
+        (self.error, self.writer)
+    }
+}
+
+impl<W> core::fmt::Display for IntoInnerError<W> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl<W: core::fmt::Debug> core::error::Error for IntoInnerError<W> {}
+
+impl<W> From<IntoInnerError<W>> for io::Error {
+    fn from(e: IntoInnerError<W>) -> Self {
+        e.error
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::symmetric::aes::{Aes128Enc, BLOCK_SIZE};
+
+    const KEY: [u8; 16] = [0x2b; 16];
+    const IV: [u8; 16] = [0xf0; 16];
+
+    fn message(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 + i / 251) as u8).collect()
+    }
+
+    fn encrypted(data: &[u8]) -> Vec<u8> {
+        let mut out = data.to_vec();
+        Aes128Ctr::new(&KEY, &IV).apply_keystream(&mut out);
+        out
+    }
+
+    /// Reads of every awkward size, through the buffer and around it,
+    /// give what one call over the whole message does.
+    #[test]
+    fn reading_in_pieces_matches_one_shot() {
+        let plain = message(50_000);
+        let fused = CtrReader::new(
+            Cursor::new(encrypted(&plain)),
+            Aes128Ctr::new(&KEY, &IV),
+        );
+        let generic = CtrReader::new(
+            Cursor::new(encrypted(&plain)),
+            Ctr::try_new(Aes128Enc::new(&KEY), &IV).unwrap(),
+        );
+        let readers: [Box<dyn Read>; 2] = [Box::new(fused), Box::new(generic)];
+        for mut reader in readers {
+            let mut got = Vec::new();
+            let mut sizes = [1, 15, 17, 4096, 9000, 20_000, 3].iter().cycle();
+            loop {
+                let mut chunk = vec![0u8; *sizes.next().unwrap()];
+                let n = reader.read(&mut chunk).unwrap();
+                if n == 0 {
+                    break;
+                }
+                got.extend_from_slice(&chunk[..n]);
+            }
+            assert_eq!(got, plain);
+        }
+    }
+
+    #[test]
+    fn writing_in_pieces_matches_one_shot() {
+        let plain = message(30_000);
+        let mut writer =
+            CtrWriter::new(Vec::new(), AesCtr::try_new(&KEY, &IV).unwrap());
+        for chunk in plain.chunks(333) {
+            writer.write_all(chunk).unwrap();
+        }
+        assert_eq!(writer.into_inner().unwrap(), encrypted(&plain));
+
+        // Dropping flushes too.
+        let mut out = Vec::new();
+        {
+            let stream = Aes128Ctr::new(&KEY, &IV);
+            let mut writer = CtrWriter::new(&mut out, stream);
+            writer.write_all(&plain[..100]).unwrap();
+        }
+        assert_eq!(out, encrypted(&plain[..100]));
+    }
+
+    /// A failed unwrap hands the writer back with nothing lost, and a
+    /// retry once the stream recovers writes it all out.
+    #[test]
+    fn failed_into_inner_returns_the_writer() {
+        #[derive(Debug)]
+        struct Flaky {
+            out: Vec<u8>,
+            fail: bool,
+        }
+        impl Write for Flaky {
+            fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+                if self.fail {
+                    return Err(io::ErrorKind::Other.into());
+                }
+                self.out.extend_from_slice(data);
+                Ok(data.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let plain = message(1000);
+        let sink = Flaky { out: Vec::new(), fail: true };
+        let mut writer = CtrWriter::new(sink, Aes128Ctr::new(&KEY, &IV));
+        writer.write_all(&plain).unwrap();
+        let e = writer.into_inner().unwrap_err();
+        assert_eq!(e.error().kind(), io::ErrorKind::Other);
+
+        let mut writer = e.into_inner();
+        writer.get_mut().fail = false;
+        let sink = writer.into_inner().unwrap();
+        assert_eq!(sink.out, encrypted(&plain));
+    }
+
+    /// The keystream starts where the wrapped stream was, and seeks are
+    /// in the wrapped stream's terms.
+    #[test]
+    fn seeking_past_a_header() {
+        let plain = message(20_000);
+        let mut file = b"header".to_vec();
+        file.extend(encrypted(&plain));
+
+        let mut inner = Cursor::new(file);
+        inner.seek(SeekFrom::Start(6)).unwrap();
+        let mut reader = CtrReader::new(inner, Aes128Ctr::new(&KEY, &IV));
+        let mut first = [0u8; 10];
+        reader.read_exact(&mut first).unwrap();
+        assert_eq!(first, plain[..10]);
+        assert_eq!(reader.stream_position().unwrap(), 16);
+
+        for (from, at) in [
+            (SeekFrom::Start(6 + 12_345), 12_345),
+            (SeekFrom::Current(-1007), 11_345),
+            (SeekFrom::End(-7), plain.len() - 7),
+            (SeekFrom::Start(6 + 3), 3),
+        ] {
+            reader.seek(from).unwrap();
+            let mut got = [0u8; 7];
+            reader.read_exact(&mut got).unwrap();
+            assert_eq!(got, plain[at..at + 7], "{from:?}");
+        }
+
+        let err = reader.seek(SeekFrom::Start(2)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let mut got = [0u8; 4];
+        reader.read_exact(&mut got).unwrap();
+        assert_eq!(got, plain[10..14], "a refused seek must not move");
+    }
+
+    /// Rewriting the middle of a file encrypts it at its own position.
+    #[test]
+    fn seeking_while_writing() {
+        let plain = message(5000);
+        let mut writer = CtrWriter::new(
+            Cursor::new(Vec::new()),
+            Ctr::try_new(Aes128Enc::new(&KEY), &IV).unwrap(),
+        );
+        writer.write_all(&[0u8; 5000]).unwrap();
+        writer.seek(SeekFrom::Start(1000)).unwrap();
+        writer.write_all(&plain[1000..4000]).unwrap();
+        writer.seek(SeekFrom::Start(0)).unwrap();
+        writer.write_all(&plain[..1000]).unwrap();
+        writer.seek(SeekFrom::End(-1000)).unwrap();
+        writer.write_all(&plain[4000..]).unwrap();
+        let file = writer.into_inner().unwrap().into_inner();
+        assert_eq!(file, encrypted(&plain));
+    }
+
+    /// A sink that takes a few bytes at a time and is sometimes
+    /// interrupted.
+    struct Trickle {
+        out: Vec<u8>,
+        calls: usize,
+    }
+
+    impl Write for Trickle {
+        fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+            self.calls += 1;
+            if self.calls.is_multiple_of(3) {
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+            let n = data.len().min(100);
+            self.out.extend_from_slice(&data[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn short_and_interrupted_writes_are_retried() {
+        let plain = message(20_000);
+        let mut writer = CtrWriter::new(
+            Trickle { out: Vec::new(), calls: 0 },
+            Aes128Ctr::new(&KEY, &IV),
+        );
+        writer.write_all(&plain).unwrap();
+        writer.flush().unwrap();
+        assert_eq!(writer.get_ref().out, encrypted(&plain));
+    }
+
+    #[test]
+    fn buffers_are_whole_parallel_widths() {
+        let stream = Aes128Ctr::new(&KEY, &IV);
+        let width = BLOCK_SIZE * stream.parallel_blocks();
+        let len = Buffer::for_stream(&stream).0.len();
+        assert!(len.is_multiple_of(width) && len >= BUFFER_TARGET / 2);
+    }
+
+    #[test]
+    fn debug_does_not_leak_state() {
+        let reader = CtrReader::new(&[][..], Aes128Ctr::new(&KEY, &IV));
+        assert_eq!(format!("{reader:?}"), "CtrReader { .. }");
+        let writer = CtrWriter::new(Vec::new(), Aes128Ctr::new(&KEY, &IV));
+        assert_eq!(format!("{writer:?}"), "CtrWriter { .. }");
+    }
+}
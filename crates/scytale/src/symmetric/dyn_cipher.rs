@@ -0,0 +1,134 @@
+//! Object-safe cipher interfaces, for choosing an algorithm at run time.
+//!
+//! [`BlockEncrypt`] and [`BlockDecrypt`] carry their sizes as associated
+//! constants, which is what lets a mode size its buffers at compile time
+//! and also what keeps them out of a `dyn` type. The traits here report
+//! the same figures through methods instead, and every type implementing
+//! the static traits gets them for free. Reach for these only where the
+//! algorithm really is data, such as a name in a configuration file; see
+//! [`Registry`](super::registry::Registry) for building one from a name.
+
+use crate::symmetric::aes::{Aes128Ctr, Aes192Ctr, Aes256Ctr, AesCtr};
+use crate::symmetric::block_cipher::{BlockDecrypt, BlockEncrypt};
+use crate::symmetric::ctr::Ctr;
+
+/// A block cipher keyed for both directions, behind a `dyn` pointer.
+///
+/// Implemented for everything that implements both [`BlockEncrypt`] and
+/// [`BlockDecrypt`].
+pub trait DynBlockCipher: Send + Sync {
+    /// The block length in bytes.
+    fn block_size(&self) -> usize;
+
+    /// How many blocks the implementation keeps in flight at once.
+    fn parallel_blocks(&self) -> usize;
+
+    /// Encrypt whole blocks in place, returning how many bytes were
+    /// consumed. As [`BlockEncrypt::encrypt`].
+    fn encrypt(&self, data: &mut [u8]) -> usize;
+
+    /// Decrypt whole blocks in place, returning how many bytes were
+    /// consumed. As [`BlockDecrypt::decrypt`].
+    fn decrypt(&self, data: &mut [u8]) -> usize;
+}
+
+impl<C> DynBlockCipher for C
+where
+    C: BlockEncrypt + BlockDecrypt + Send + Sync,
+{
+    fn block_size(&self) -> usize {
+        <C as BlockEncrypt>::BLOCK_SIZE
+    }
+
+    fn parallel_blocks(&self) -> usize {
+        <C as BlockEncrypt>::PARALLEL_BLOCKS
+    }
+
+    fn encrypt(&self, data: &mut [u8]) -> usize {
+        BlockEncrypt::encrypt(self, data)
+    }
+
+    fn decrypt(&self, data: &mut [u8]) -> usize {
+        BlockDecrypt::decrypt(self, data)
+    }
+}
+
+/// A keyed stream cipher, behind a `dyn` pointer.
+///
+/// Implemented for the generic [`Ctr`] over any block cipher, and for the
+/// AES counter mode types.
+pub trait DynStreamCipher: Send {
+    /// XOR the keystream into `data`, advancing the stream.
+    ///
+    /// Encrypting and decrypting are the same operation. Any length is
+    /// accepted, and successive calls continue where the last left off.
+    fn apply_keystream(&mut self, data: &mut [u8]);
+}
+
+impl<C: BlockEncrypt + Send> DynStreamCipher for Ctr<C> {
+    fn apply_keystream(&mut self, data: &mut [u8]) {
+        Ctr::apply_keystream(self, data);
+    }
+}
+
+macro_rules! dyn_stream {
+    ($($name:ty),+) => {$(
+        impl DynStreamCipher for $name {
+            fn apply_keystream(&mut self, data: &mut [u8]) {
+                <$name>::apply_keystream(self, data);
+            }
+        }
+    )+};
+}
+
+dyn_stream!(Aes128Ctr, Aes192Ctr, Aes256Ctr, AesCtr);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symmetric::aes::{Aes128, Aes256, Aes256Enc};
+
+    /// Through the pointer, a cipher behaves exactly as it does named.
+    #[test]
+    fn dyn_block_cipher_delegates() {
+        let key = [0x3cu8; 16];
+        let cipher: Box<dyn DynBlockCipher> = Box::new(Aes128::new(&key));
+        assert_eq!(cipher.block_size(), 16);
+        assert_eq!(
+            cipher.parallel_blocks(),
+            <Aes128 as BlockEncrypt>::PARALLEL_BLOCKS
+        );
+
+        let plaintext: Vec<u8> = (0..40u8).collect();
+        let mut data = plaintext.clone();
+        assert_eq!(cipher.encrypt(&mut data), 32);
+        let mut named = plaintext.clone();
+        Aes128::new(&key).encrypt(&mut named);
+        assert_eq!(data, named);
+        assert_eq!(cipher.decrypt(&mut data), 32);
+        assert_eq!(data, plaintext);
+    }
+
+    #[test]
+    fn dyn_stream_cipher_delegates() {
+        let key = [0x3cu8; 32];
+        let iv = [0x01u8; 16];
+        let mut streams: Vec<Box<dyn DynStreamCipher>> = vec![
+            Box::new(Aes256Ctr::new(&key, &iv)),
+            Box::new(Ctr::try_new(Aes256Enc::new(&key), &iv).unwrap()),
+            Box::new(AesCtr::try_new(&key, &iv).unwrap()),
+        ];
+        let mut outputs = Vec::new();
+        for stream in &mut streams {
+            let mut data = [0u8; 50];
+            stream.apply_keystream(&mut data[..7]);
+            stream.apply_keystream(&mut data[7..]);
+            outputs.push(data);
+        }
+        assert!(outputs.windows(2).all(|w| w[0] == w[1]));
+
+        // The combined type works as both a block and, wrapped, a stream.
+        let _: Box<dyn DynStreamCipher> =
+            Box::new(Ctr::try_new(Aes256::new(&key), &iv).unwrap());
+    }
+}
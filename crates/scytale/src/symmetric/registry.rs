@@ -0,0 +1,401 @@
+//! Building ciphers from algorithm names.
+//!
+//! Configuration names algorithms as strings, such as `"aes-256-ctr"`. A
+//! [`Registry`] maps each name to a constructor and hands back the cipher
+//! behind one of the [`dyn_cipher`](super::dyn_cipher) traits. The
+//! default registry knows every algorithm this crate implements; adding
+//! one means registering it, not editing a `match` here.
+//!
+//! Names are matched without regard to ASCII case. Block ciphers are
+//! registered under their ECB name, since a block cipher on its own is
+//! exactly that mode.
+
+use core::fmt;
+
+use crate::cpu::UnsupportedCpu;
+use crate::rand::RandError;
+use crate::self_test::SelfTestError;
+use crate::symmetric::aes::{
+    Aes128, Aes128Ctr, Aes192, Aes192Ctr, Aes256, Aes256Ctr,
+};
+use crate::symmetric::block_cipher::{
+    BlockDecrypt, BlockEncrypt, InvalidKeyLength, KeyInit, KeyInitError,
+};
+use crate::symmetric::ctr::{CtrInitError, InvalidIvLength};
+use crate::symmetric::dyn_cipher::{DynBlockCipher, DynStreamCipher};
+
+/// Builds a block cipher from a key.
+pub type BlockConstructor =
+    fn(key: &[u8]) -> Result<Box<dyn DynBlockCipher>, RegistryError>;
+
+/// Builds a stream cipher from a key and an IV.
+pub type StreamConstructor = fn(
+    key: &[u8],
+    iv: &[u8],
+) -> Result<Box<dyn DynStreamCipher>, RegistryError>;
+
+/// What kind of cipher a name refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// A block cipher, built with [`Registry::block`].
+    Block,
+    /// A stream cipher, built with [`Registry::stream`].
+    Stream,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Kind::Block => "block cipher",
+            Kind::Stream => "stream cipher",
+        })
+    }
+}
+
+/// Why a registry could not build a cipher.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RegistryError {
+    /// No algorithm is registered under this name.
+    Unknown {
+        /// The name that was asked for.
+        name: String,
+    },
+    /// The name is registered, but as the other kind of cipher.
+    WrongKind {
+        /// The name that was asked for.
+        name: String,
+        /// What it actually is.
+        is: Kind,
+    },
+    /// The key length is not one the algorithm accepts.
+    Key(InvalidKeyLength),
+    /// The IV length is not one the algorithm accepts.
+    Iv(InvalidIvLength),
+    /// The algorithm's implementation cannot run on this CPU.
+    Cpu(UnsupportedCpu),
+    /// The library failed its [self tests](crate::self_test).
+    SelfTest(SelfTestError),
+    /// The random source failed.
+    Rand(RandError),
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unknown { name } => write!(f, "unknown algorithm {name:?}"),
+            Self::WrongKind { name, is } => write!(f, "{name:?} is a {is}"),
+            Self::Key(e) => e.fmt(f),
+            Self::Iv(e) => e.fmt(f),
+            Self::Cpu(e) => e.fmt(f),
+            Self::SelfTest(e) => e.fmt(f),
+            Self::Rand(e) => e.fmt(f),
+        }
+    }
+}
+
+impl core::error::Error for RegistryError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Key(e) => Some(e),
+            Self::Iv(e) => Some(e),
+            Self::Cpu(e) => Some(e),
+            Self::SelfTest(e) => Some(e),
+            Self::Rand(e) => Some(e),
+            Self::Unknown { .. } | Self::WrongKind { .. } => None,
+        }
+    }
+}
+
+impl From<InvalidKeyLength> for RegistryError {
+    fn from(e: InvalidKeyLength) -> Self {
+        Self::Key(e)
+    }
+}
+
+impl From<InvalidIvLength> for RegistryError {
+    fn from(e: InvalidIvLength) -> Self {
+        Self::Iv(e)
+    }
+}
+
+impl From<UnsupportedCpu> for RegistryError {
+    fn from(e: UnsupportedCpu) -> Self {
+        Self::Cpu(e)
+    }
+}
+
+impl From<SelfTestError> for RegistryError {
+    fn from(e: SelfTestError) -> Self {
+        Self::SelfTest(e)
+    }
+}
+
+impl From<KeyInitError> for RegistryError {
+    fn from(e: KeyInitError) -> Self {
+        match e {
+            KeyInitError::Key(e) => Self::Key(e),
+            KeyInitError::Cpu(e) => Self::Cpu(e),
+            KeyInitError::SelfTest(e) => Self::SelfTest(e),
+        }
+    }
+}
+
+impl From<CtrInitError> for RegistryError {
+    fn from(e: CtrInitError) -> Self {
+        match e {
+            CtrInitError::Key(e) => Self::Key(e),
+            CtrInitError::Iv(e) => Self::Iv(e),
+            CtrInitError::SelfTest(e) => Self::SelfTest(e),
+            CtrInitError::Rand(e) => Self::Rand(e),
+        }
+    }
+}
+
+enum Constructor {
+    Block(BlockConstructor),
+    Stream(StreamConstructor),
+}
+
+impl Constructor {
+    fn kind(&self) -> Kind {
+        match self {
+            Constructor::Block(_) => Kind::Block,
+            Constructor::Stream(_) => Kind::Stream,
+        }
+    }
+}
+
+/// Algorithm names and how to build each.
+///
+/// [`Registry::default`] holds everything this crate implements;
+/// [`Registry::new`] starts empty, for a caller who wants to allow only
+/// a chosen few.
+pub struct Registry {
+    entries: Vec<(&'static str, Constructor)>,
+}
+
+impl Registry {
+    /// A registry with nothing in it.
+    pub fn new() -> Self {
+        Self { entries: Vec::new() }
+    }
+
+    /// Register the block cipher `C` under `name`.
+    ///
+    /// `C` is keyed through [`KeyInit`], so the key length is checked by
+    /// the cipher itself.
+    ///
+    /// # Panics
+    ///
+    /// If `name` is already registered. Quietly replacing an algorithm
+    /// would change what a configuration file means.
+    pub fn register_block<C>(&mut self, name: &'static str) -> &mut Self
+    where
+        C: KeyInit + BlockEncrypt + BlockDecrypt + Send + Sync + 'static,
+        RegistryError: From<C::Error>,
+    {
+        fn build<C>(
+            key: &[u8],
+        ) -> Result<Box<dyn DynBlockCipher>, RegistryError>
+        where
+            C: KeyInit + BlockEncrypt + BlockDecrypt + Send + Sync + 'static,
+            RegistryError: From<C::Error>,
+        {
+            Ok(Box::new(C::try_new(key)?))
+        }
+        self.insert(name, Constructor::Block(build::<C>))
+    }
+
+    /// Register a stream cipher under `name`, built by `constructor`.
+    ///
+    /// # Panics
+    ///
+    /// If `name` is already registered.
+    pub fn register_stream(
+        &mut self,
+        name: &'static str,
+        constructor: StreamConstructor,
+    ) -> &mut Self {
+        self.insert(name, Constructor::Stream(constructor))
+    }
+
+    fn insert(&mut self, name: &'static str, c: Constructor) -> &mut Self {
+        assert!(self.find(name).is_none(), "{name:?} is already registered");
+        self.entries.push((name, c));
+        self
+    }
+
+    fn find(&self, name: &str) -> Option<&Constructor> {
+        self.entries
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, c)| c)
+    }
+
+    /// Build the block cipher registered as `name`, keyed with `key`.
+    pub fn block(
+        &self,
+        name: &str,
+        key: &[u8],
+    ) -> Result<Box<dyn DynBlockCipher>, RegistryError> {
+        match self.find(name) {
+            Some(Constructor::Block(build)) => build(key),
+            Some(c) => Err(RegistryError::WrongKind {
+                name: name.to_string(),
+                is: c.kind(),
+            }),
+            None => Err(RegistryError::Unknown { name: name.to_string() }),
+        }
+    }
+
+    /// Build the stream cipher registered as `name`, keyed with `key` and
+    /// starting from `iv`.
+    pub fn stream(
+        &self,
+        name: &str,
+        key: &[u8],
+        iv: &[u8],
+    ) -> Result<Box<dyn DynStreamCipher>, RegistryError> {
+        match self.find(name) {
+            Some(Constructor::Stream(build)) => build(key, iv),
+            Some(c) => Err(RegistryError::WrongKind {
+                name: name.to_string(),
+                is: c.kind(),
+            }),
+            None => Err(RegistryError::Unknown { name: name.to_string() }),
+        }
+    }
+
+    /// What kind of cipher `name` is, if it is registered at all.
+    pub fn kind(&self, name: &str) -> Option<Kind> {
+        self.find(name).map(Constructor::kind)
+    }
+
+    /// Every registered name, in the order registered.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.entries.iter().map(|(n, _)| *n)
+    }
+}
+
+/// Every algorithm this crate implements.
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry
+            .register_block::<Aes128>("aes-128-ecb")
+            .register_block::<Aes192>("aes-192-ecb")
+            .register_block::<Aes256>("aes-256-ecb")
+            .register_stream("aes-128-ctr", |key, iv| {
+                Ok(Box::new(Aes128Ctr::try_new(key, iv)?))
+            })
+            .register_stream("aes-192-ctr", |key, iv| {
+                Ok(Box::new(Aes192Ctr::try_new(key, iv)?))
+            })
+            .register_stream("aes-256-ctr", |key, iv| {
+                Ok(Box::new(Aes256Ctr::try_new(key, iv)?))
+            });
+        registry
+    }
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symmetric::aes::Aes256Enc;
+    use crate::symmetric::ctr::Ctr;
+
+    #[test]
+    fn builds_every_aes_key_size_and_mode() {
+        let registry = Registry::default();
+        for bits in [128, 192, 256] {
+            let key = vec![0x42u8; bits / 8];
+            let ecb = format!("aes-{bits}-ecb");
+            let cipher = registry.block(&ecb, &key).unwrap();
+            let mut data = [7u8; 32];
+            assert_eq!(cipher.encrypt(&mut data), 32);
+            assert_ne!(data, [7u8; 32]);
+            cipher.decrypt(&mut data);
+            assert_eq!(data, [7u8; 32]);
+
+            let ctr = format!("aes-{bits}-ctr");
+            let mut stream = registry.stream(&ctr, &key, &[0u8; 16]).unwrap();
+            stream.apply_keystream(&mut data);
+            assert_ne!(data, [7u8; 32]);
+        }
+    }
+
+    /// The name selects exactly the algorithm it says.
+    #[test]
+    fn aes_256_ctr_is_aes_256_ctr() {
+        let key = [0x5au8; 32];
+        let iv = [0xa5u8; 16];
+        let mut named = [0u8; 40];
+        Registry::default()
+            .stream("AES-256-CTR", &key, &iv)
+            .unwrap()
+            .apply_keystream(&mut named);
+        let mut direct = [0u8; 40];
+        Ctr::try_new(Aes256Enc::new(&key), &iv)
+            .unwrap()
+            .apply_keystream(&mut direct);
+        assert_eq!(named, direct);
+    }
+
+    #[test]
+    fn errors_say_what_was_wrong() {
+        let registry = Registry::default();
+        assert_eq!(
+            registry.block("rot13", &[0u8; 16]).err(),
+            Some(RegistryError::Unknown { name: "rot13".into() })
+        );
+        assert_eq!(
+            registry.block("aes-128-ctr", &[0u8; 16]).err(),
+            Some(RegistryError::WrongKind {
+                name: "aes-128-ctr".into(),
+                is: Kind::Stream
+            })
+        );
+        assert_eq!(
+            registry.stream("aes-128-ecb", &[0u8; 16], &[0u8; 16]).err(),
+            Some(RegistryError::WrongKind {
+                name: "aes-128-ecb".into(),
+                is: Kind::Block
+            })
+        );
+        assert_eq!(
+            registry.block("aes-192-ecb", &[0u8; 16]).err(),
+            Some(RegistryError::Key(InvalidKeyLength { got: 16 }))
+        );
+        assert_eq!(
+            registry.stream("aes-256-ctr", &[0u8; 32], &[0u8; 12]).err(),
+            Some(RegistryError::Iv(InvalidIvLength { got: 12 }))
+        );
+        assert_eq!(
+            RegistryError::WrongKind { name: "x".into(), is: Kind::Block }
+                .to_string(),
+            "\"x\" is a block cipher"
+        );
+    }
+
+    #[test]
+    fn new_algorithms_only_register_themselves() {
+        let mut registry = Registry::new();
+        assert_eq!(registry.kind("aes-256-ecb"), None);
+        registry.register_block::<Aes256>("aes-256-ecb");
+        assert_eq!(registry.kind("aes-256-ecb"), Some(Kind::Block));
+        assert_eq!(registry.names().collect::<Vec<_>>(), ["aes-256-ecb"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "already registered")]
+    fn registering_a_name_twice_panics() {
+        Registry::default().register_block::<Aes128>("AES-128-ECB");
+    }
+}
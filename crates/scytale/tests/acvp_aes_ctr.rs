@@ -108,7 +108,17 @@ fn ttable_counter_kernel() {
     run(&acvp::ctr_fused_ttable());
 }
 
-#[cfg(target_arch = "x86_64")]
+#[test]
+fn bitsliced_counter_kernel() {
+    run(&acvp::ctr_fused_bitsliced());
+}
+
+#[test]
+fn compact_counter_kernel() {
+    run(&acvp::ctr_fused_compact());
+}
+
+#[cfg(any(target_arch = "x86_64", all(target_arch = "x86", target_feature = "sse2")))]
 #[test]
 fn aesni_counter_kernel() {
     run_if_available(acvp::ctr_fused_aesni());
@@ -120,6 +130,18 @@ fn vaes_counter_kernel() {
     run_if_available(acvp::ctr_fused_vaes());
 }
 
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn vaes512_counter_kernel() {
+    run_if_available(acvp::ctr_fused_vaes512());
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn vpaes_counter_kernel() {
+    run_if_available(acvp::ctr_fused_vpaes());
+}
+
 #[cfg(target_arch = "aarch64")]
 #[test]
 fn armv8_counter_kernel() {
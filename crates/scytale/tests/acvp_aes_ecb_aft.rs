@@ -75,12 +75,27 @@ fn ttable_kernel() {
     run(&acvp::ecb_ttable());
 }
 
+#[test]
+fn bitsliced_kernel() {
+    run(&acvp::ecb_bitsliced());
+}
+
+#[test]
+fn compact_kernel() {
+    run(&acvp::ecb_compact());
+}
+
+#[test]
+fn masked_kernel() {
+    run(&acvp::ecb_masked());
+}
+
 #[test]
 fn dispatching_type() {
     run(&acvp::ecb_dispatch());
 }
 
-#[cfg(target_arch = "x86_64")]
+#[cfg(any(target_arch = "x86_64", all(target_arch = "x86", target_feature = "sse2")))]
 #[test]
 fn aesni_kernel() {
     run_if_available(acvp::ecb_aesni());
@@ -92,6 +107,18 @@ fn vaes_kernel() {
     run_if_available(acvp::ecb_vaes());
 }
 
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn vaes512_kernel() {
+    run_if_available(acvp::ecb_vaes512());
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn vpaes_kernel() {
+    run_if_available(acvp::ecb_vpaes());
+}
+
 #[cfg(target_arch = "aarch64")]
 #[test]
 fn armv8_kernel() {
@@ -139,13 +139,31 @@ fn ttable_kernel() {
     run(&acvp::ecb_ttable());
 }
 
+#[test]
+#[ignore = "Monte Carlo: 600k chained blocks"]
+fn bitsliced_kernel() {
+    run(&acvp::ecb_bitsliced());
+}
+
+#[test]
+#[ignore = "Monte Carlo: 600k chained blocks"]
+fn compact_kernel() {
+    run(&acvp::ecb_compact());
+}
+
+#[test]
+#[ignore = "Monte Carlo: 600k chained blocks"]
+fn masked_kernel() {
+    run(&acvp::ecb_masked());
+}
+
 #[test]
 #[ignore = "Monte Carlo: 600k chained blocks"]
 fn dispatching_type() {
     run(&acvp::ecb_dispatch());
 }
 
-#[cfg(target_arch = "x86_64")]
+#[cfg(any(target_arch = "x86_64", all(target_arch = "x86", target_feature = "sse2")))]
 #[test]
 #[ignore = "Monte Carlo: 600k chained blocks"]
 fn aesni_kernel() {
@@ -159,6 +177,20 @@ fn vaes_kernel() {
     run_if_available(acvp::ecb_vaes());
 }
 
+#[cfg(target_arch = "x86_64")]
+#[test]
+#[ignore = "Monte Carlo: 600k chained blocks"]
+fn vaes512_kernel() {
+    run_if_available(acvp::ecb_vaes512());
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+#[ignore = "Monte Carlo: 600k chained blocks"]
+fn vpaes_kernel() {
+    run_if_available(acvp::ecb_vpaes());
+}
+
 #[cfg(target_arch = "aarch64")]
 #[test]
 #[ignore = "Monte Carlo: 600k chained blocks"]
@@ -0,0 +1,195 @@
+//! CTR_DRBG known-answer tests.
+//!
+//! The first case is the opening AES-128 vector of NIST's CAVP
+//! `CTR_DRBG.rsp`, use df, no prediction resistance. The others exercise
+//! what it does not: the other key sizes, the configuration without the
+//! derivation function, reseeding, additional input, outputs that end in
+//! a partial block, and prediction resistance. Their answers come from
+//! OpenSSL 3's CTR-DRBG, driven with the same inputs through its test
+//! entropy source.
+
+use scytale::rand::{Aes128CtrDrbg, Aes192CtrDrbg, Aes256CtrDrbg};
+
+fn unhex(s: &str) -> Vec<u8> {
+    assert!(s.len().is_multiple_of(2), "hex literal has an odd length");
+    (0..s.len() / 2)
+        .map(|i| {
+            u8::from_str_radix(&s[2 * i..2 * i + 2], 16)
+                .expect("test vector is not valid hex")
+        })
+        .collect()
+}
+
+/// CAVP reports the second of two generate calls.
+#[test]
+fn cavp_aes128_use_df() {
+    let mut drbg = Aes128CtrDrbg::instantiate(
+        &unhex("890eb067acf7382eff80b0c73bc872c6"),
+        &unhex("aad471ef3ef1d203"),
+        &[],
+    )
+    .unwrap();
+    let mut out = [0u8; 64];
+    drbg.generate(&mut out, &[]).unwrap();
+    drbg.generate(&mut out, &[]).unwrap();
+    assert_eq!(
+        out[..],
+        unhex(
+            "a5514ed7095f64f3d0d3a5760394ab42062f373a25072a6ea6bcfd8489e94af6\
+             cf18659fea22ed1ca0a9e33f718b115ee536b12809c31b72b08ddd8be1910fa3"
+        )
+    );
+}
+
+#[test]
+fn aes192_use_df_reseed_and_additional_input() {
+    let mut drbg = Aes192CtrDrbg::instantiate(
+        &unhex("872a87096eee983f9ceaca4c5dd9056e0afd9bd407555b5c"),
+        &unhex("b3ac5859338f66dafefce65c"),
+        &unhex("bf2811b768a3435e23097ae3323c90437acb0e951148b862"),
+    )
+    .unwrap();
+    let mut out = [0u8; 64];
+    drbg.generate(
+        &mut out,
+        &unhex("b1aa545fe926eadc6da7172a340181b1deef8dd9248096dc"),
+    )
+    .unwrap();
+    drbg.reseed(
+        &unhex("670f1b37ca9580a117f31285e4011e89ef12580ff729cb40"),
+        &unhex("ab2ff31208bb98903c800acdf895f89c468d47de422ffdda"),
+    )
+    .unwrap();
+    assert_eq!(drbg.reseed_counter(), 1);
+    drbg.generate(
+        &mut out,
+        &unhex("ed8c80e35225f72e9b5d0087ed9c42820d2cbdce30dc7a89"),
+    )
+    .unwrap();
+    assert_eq!(
+        out[..],
+        unhex(
+            "20e88bcd3f0b862cc4d9357cc71aa029cedbfb29ec04da36a635f5bd197a592d\
+             35d3567caf1558c248f5e6c8aaab9626898b16d779ddf5c77c9a7b910e3554c1"
+        )
+    );
+}
+
+/// Short personalization and additional input are zero padded, and a
+/// 37 byte request still spends three counter values.
+#[test]
+fn aes256_no_df_partial_blocks() {
+    let mut drbg = Aes256CtrDrbg::instantiate_without_df(
+        &unhex(
+            "f356bfd062c599e305f7f7afedc48330311357e2b0da4e53\
+             e953718b2e4bfee01e11bc8949d69c04fb52a8beca5a15e9",
+        ),
+        &unhex(
+            "5a564d150d3dfaaa5feda17a9114a41cd91f909806167e54be447fed580a",
+        ),
+    )
+    .unwrap();
+    let mut out = [0u8; 37];
+    drbg.generate(&mut out, &unhex("c3bcf80128503d69fe0df6f2c2d401e9e1c7f08d"))
+        .unwrap();
+    drbg.reseed(
+        &unhex(
+            "8bd65856adb3c0d25eb988f4f6a0cb2e4067913d1d3fc78b\
+             767cff48301659e83cf41a8af7f548b604c1e274059968e1",
+        ),
+        &[],
+    )
+    .unwrap();
+    drbg.generate(
+        &mut out,
+        &unhex(
+            "a1dc29a5d9a9c0114f1c64778e8a973de57dd389754880e1\
+             a41ef64e4c7fea8d42ebe38a7a469f90310ae6288a6cb2bf",
+        ),
+    )
+    .unwrap();
+    assert_eq!(
+        out[..],
+        unhex(
+            "630f840b8f750148dfb27f90a59fddb206825bc55fad6fe0182ce5bd0fa08058\
+             97a6e05f77"
+        )
+    );
+}
+
+#[test]
+fn aes256_use_df_prediction_resistance() {
+    let mut drbg = Aes256CtrDrbg::instantiate(
+        &unhex(
+            "1cc8b005f9a89a13675081ac4cb512b34330271ab42d9ce4901724901fa1c382",
+        ),
+        &unhex("0e9b0e3eaf185ccc93e721f8c8eabc23"),
+        &[],
+    )
+    .unwrap();
+    let mut out = [0u8; 64];
+    drbg.generate_with_prediction_resistance(
+        &mut out,
+        &unhex(
+            "4c42587b92321ec30119f78e713c09f735bfb9bb1dd7e1c14113ccbe9e3c235b",
+        ),
+        &unhex(
+            "2ac0a9c1113b30d59637953a1ee5c8e4fdfd595593e0d4b1d040d4d9bfe6dde5",
+        ),
+    )
+    .unwrap();
+    drbg.generate_with_prediction_resistance(
+        &mut out,
+        &unhex(
+            "25ab9e4aa3b5e25212bdb9bff2614356f2e448cdd3b257e908af78d3c512fc45",
+        ),
+        &unhex(
+            "d259b0183d0b0ed00c2c45dd2659515f2b712747af5612e836a4df274106ae08",
+        ),
+    )
+    .unwrap();
+    assert_eq!(
+        out[..],
+        unhex(
+            "6874ad0822dab56402a465495ab29bac4450c2b170b57ab44425fe5001742648\
+             0bc5566af48890bd7e760be719beb52f1cbcd49cccaa8de2a67884e47cd0ecb2"
+        )
+    );
+}
+
+#[test]
+fn aes128_no_df_prediction_resistance() {
+    let mut drbg = Aes128CtrDrbg::instantiate_without_df(
+        &unhex(
+            "71977f89e373231eb046e3d5f6ae5ae8a43cebe76f94eee7b93b8a9dfb596192",
+        ),
+        &[],
+    )
+    .unwrap();
+    let mut out = [0u8; 64];
+    drbg.generate_with_prediction_resistance(
+        &mut out,
+        &unhex(
+            "1f133f8dcd3bb78996fe537fbe8460a6d52488c7e102b926c79e7e1d95aa8472",
+        ),
+        &[],
+    )
+    .unwrap();
+    drbg.generate_with_prediction_resistance(
+        &mut out,
+        &unhex(
+            "047b2e7bba225b408b0766f13305b8767a45cbae862cb1c9fdce60887346b8c7",
+        ),
+        &unhex(
+            "407dbf6f869e5856a40f76b9c2905365cf4bb87c31529fb7af81c31ad1f080ce",
+        ),
+    )
+    .unwrap();
+    assert_eq!(
+        out[..],
+        unhex(
+            "074440110a194be1f9d063208ada3966051816bd042b9e1fc081f442a84880e4\
+             067aa6ac28fcf7aa00df48f1f847873cf2ed1387be01e947fa05c7757959f6ac"
+        )
+    );
+}